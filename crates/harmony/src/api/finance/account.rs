@@ -0,0 +1,350 @@
+use crate::api::http::state::StateInner;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{delete, get, post, put};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(post::PATH, post(post::handler))
+        .route(put::PATH, put(put::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .route(balances::PATH, get(balances::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/accounts";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::account::Account;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub id: Option<i64>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct AccountItem {
+        pub id: i64,
+        pub owner: i64,
+        pub name: String,
+        pub institution: Option<String>,
+        pub remark: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub accounts: Vec<AccountItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let total = Account::count_by_owner(&conn, owner)?;
+
+        if let Some(id) = params.id {
+            let account = Account::select_by_id_owner(&conn, id, owner)?.ok_or(
+                Response::not_found(format!("account {} does not exist", id)),
+            )?;
+
+            let account_item = AccountItem {
+                id: account.id(),
+                owner: account.owner,
+                name: account.name,
+                institution: account.institution,
+                remark: account.remark,
+                created_at: account.created_at,
+                updated_at: account.updated_at,
+            };
+
+            return Ok(Response::ok(ResponseBody {
+                accounts: vec![account_item],
+                total,
+            }));
+        }
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let accounts = Account::select_by_owner(&conn, owner, limit, offset)?;
+
+        let accounts = accounts
+            .into_iter()
+            .map(|account| AccountItem {
+                id: account.id(),
+                owner: account.owner,
+                name: account.name,
+                institution: account.institution,
+                remark: account.remark,
+                created_at: account.created_at,
+                updated_at: account.updated_at,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { accounts, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/accounts";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::account::Account;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        #[validate(length(min = 1, max = 1024))]
+        pub name: String,
+        #[validate(length(min = 1, max = 1024))]
+        pub institution: Option<String>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+        let id = Account::insert(
+            &conn,
+            owner,
+            payload.name,
+            payload.institution,
+            payload.remark,
+        )?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody { id, created_at }))
+    }
+}
+
+mod put {
+    pub const PATH: &str = "/finance/accounts/:id";
+
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::account::Account;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        #[validate(length(min = 1, max = 1024))]
+        pub name: Option<String>,
+        #[validate(length(min = 1, max = 1024))]
+        pub institution: Option<String>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(id): Path<i64>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let account = Account::select_by_id_owner(&conn, id, owner)?.ok_or(Response::not_found(
+            format!("account {} does not exist", id),
+        ))?;
+
+        let name = payload.name.unwrap_or(account.name);
+        let institution = payload.institution.or(account.institution);
+        let remark = payload.remark.or(account.remark);
+
+        Account::update_by_id_owner(&conn, id, owner, name, institution, remark)?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/accounts/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::account::Account;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct AccountItem {
+        pub id: i64,
+        pub owner: i64,
+        pub name: String,
+        pub institution: Option<String>,
+        pub remark: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<AccountItem> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let account = Account::select_by_id_owner(&conn, id, owner)?.ok_or(Response::not_found(
+            format!("account {} does not exist", id),
+        ))?;
+
+        if Account::is_referenced(&conn, id)? {
+            return Err(Response::conflict(format!(
+                "account {} still holds transactions, transfers, flows or plans",
+                id
+            )));
+        }
+
+        Account::delete_by_id_owner(&conn, id, owner)?;
+
+        let account_item = AccountItem {
+            id: account.id(),
+            owner: account.owner,
+            name: account.name,
+            institution: account.institution,
+            remark: account.remark,
+            created_at: account.created_at,
+            updated_at: account.updated_at,
+        };
+
+        Ok(Response::ok(account_item))
+    }
+}
+
+mod balances {
+    pub const PATH: &str = "/finance/accounts/balances";
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::account::Account;
+    use crate::portfolio::balance;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// Only the positions held in this account.
+        #[validate(range(min = 1))]
+        pub account_id: Option<i64>,
+        pub at: Option<DateTime<Utc>>,
+        /// Counts transactions that are executed but not settled yet, true
+        /// when omitted.
+        pub pending: Option<bool>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PositionItem {
+        pub account_id: i64,
+        pub object_id: i64,
+        pub quantity: Decimal,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct BalanceItem {
+        pub object_id: i64,
+        pub quantity: Decimal,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub at: DateTime<Utc>,
+        /// Holdings per account and object.
+        pub positions: Vec<PositionItem>,
+        /// Holdings per object, summed over the accounts above.
+        pub balances: Vec<BalanceItem>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+        let at = params.at.unwrap_or(Utc::now());
+
+        let mut movements = balance::load(&conn, owner, at, params.pending.unwrap_or(true))?;
+
+        if let Some(account_id) = params.account_id {
+            let account = Account::select_by_id_owner(&conn, account_id, owner)?.ok_or(
+                Response::not_found(format!("account {} does not exist", account_id)),
+            )?;
+
+            movements.retain(|movement| movement.account_id == account.id());
+        }
+
+        let positions = balance::account_balances(&movements)
+            .into_iter()
+            .map(|((account_id, object_id), quantity)| PositionItem {
+                account_id,
+                object_id,
+                quantity,
+            })
+            .collect();
+
+        let balances = balance::balances(&movements)
+            .into_iter()
+            .map(|(object_id, quantity)| BalanceItem {
+                object_id,
+                quantity,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            at,
+            positions,
+            balances,
+        }))
+    }
+}
@@ -0,0 +1,373 @@
+use rusqlite::Connection;
+
+use crate::api::http::prelude::Response;
+use crate::api::http::state::StateInner;
+use crate::model::finance::trade::transaction::Transaction;
+use crate::model::finance::trade::Trade;
+use crate::model::finance::Entity;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::extract::DefaultBodyLimit;
+    use axum::routing::{delete, get, post};
+
+    use crate::consts::attachment::MAX_SIZE;
+
+    // Room for the other fields and the multipart framing around the file
+    let body_limit = MAX_SIZE.saturating_add(64 * 1024);
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(
+            post::PATH,
+            post(post::handler).layer(DefaultBodyLimit::max(body_limit)),
+        )
+        .route(download::PATH, get(download::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .with_state(state)
+}
+
+/// Checks that a trade or transaction belongs to the owner, and returns it
+/// as the trade and transaction ids an attachment is stored with.
+fn check_entity(
+    conn: &Connection,
+    owner: i64,
+    entity: Entity,
+    entity_id: i64,
+) -> Result<(Option<i64>, Option<i64>), Response<()>> {
+    let exists = match entity {
+        Entity::Object => {
+            return Err(Response::bad_request(
+                "files can only be attached to trades and transactions".into(),
+            ))
+        }
+        Entity::Trade => Trade::select_by_id_owner(conn, entity_id, owner)?.is_some(),
+        Entity::Transaction => Transaction::select_by_id_owner(conn, entity_id, owner)?.is_some(),
+    };
+
+    if !exists {
+        return Err(Response::not_found(format!(
+            "{} {} does not exist",
+            entity, entity_id
+        )));
+    }
+
+    match entity {
+        Entity::Trade => Ok((Some(entity_id), None)),
+        _ => Ok((None, Some(entity_id))),
+    }
+}
+
+mod get {
+    pub const PATH: &str = "/finance/attachments";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::attachment::mime::Mime;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::attachment::Attachment;
+    use crate::model::finance::Entity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub id: Option<i64>,
+        /// Along with `entity_id`, the trade or transaction to list the
+        /// attachments of.
+        pub kind: Option<Entity>,
+        #[validate(range(min = 1))]
+        pub entity_id: Option<i64>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct AttachmentItem {
+        pub id: i64,
+        pub owner: i64,
+        pub trade_id: Option<i64>,
+        pub transaction_id: Option<i64>,
+        pub name: String,
+        pub mime: Mime,
+        pub size: i64,
+        pub sha256: String,
+        pub is_encrypted: bool,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub attachments: Vec<AttachmentItem>,
+        pub total: usize,
+    }
+
+    fn item(attachment: Attachment) -> AttachmentItem {
+        AttachmentItem {
+            id: attachment.id(),
+            owner: attachment.owner,
+            trade_id: attachment.trade_id,
+            transaction_id: attachment.transaction_id,
+            name: attachment.name,
+            mime: attachment.mime,
+            size: attachment.size,
+            sha256: attachment.sha256,
+            is_encrypted: attachment.is_encrypted,
+            created_at: attachment.created_at,
+        }
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        if let Some(id) = params.id {
+            let attachment = Attachment::select_by_id_owner(&conn, id, owner)?.ok_or(
+                Response::not_found(format!("attachment {} does not exist", id)),
+            )?;
+
+            return Ok(Response::ok(ResponseBody {
+                attachments: vec![item(attachment)],
+                total: 1,
+            }));
+        }
+
+        let (Some(kind), Some(entity_id)) = (params.kind, params.entity_id) else {
+            return Err(Response::bad_request(
+                "either id or both kind and entity_id are required".into(),
+            ));
+        };
+        let (trade_id, transaction_id) = super::check_entity(&conn, owner, kind, entity_id)?;
+
+        let total = Attachment::count_by_entity(&conn, trade_id, transaction_id)?;
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let attachments =
+            Attachment::select_by_entity(&conn, trade_id, transaction_id, limit, offset)?
+                .into_iter()
+                .map(item)
+                .collect();
+
+        Ok(Response::ok(ResponseBody { attachments, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/attachments";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::attachment;
+    use crate::attachment::mime::Mime;
+    use crate::consts::attachment::{MAX_SIZE, STORE};
+    use crate::model::database::prelude::*;
+    use crate::model::finance::Entity;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub name: String,
+        pub mime: Mime,
+        pub size: usize,
+        pub created_at: DateTime<Utc>,
+    }
+
+    /// Takes the fields `kind`, `entity_id` and `file`. What the file is
+    /// comes from its content rather than the type it was sent as.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Multipart(mut multipart): Multipart,
+    ) -> ResponseResult<ResponseBody> {
+        let mut file = None;
+        let mut kind = None;
+        let mut entity_id = None;
+
+        while let Some(mut field) = multipart
+            .next_field()
+            .await
+            .map_err(|error| Response::bad_request(error.body_text()))?
+        {
+            let name = field.name().unwrap_or_default().to_string();
+
+            if name == "file" {
+                let filename = field.file_name().and_then(attachment::sanitize).ok_or(
+                    Response::bad_request("file must be sent with a file name".to_string()),
+                )?;
+
+                let mut data = Vec::new();
+                while let Some(chunk) = field
+                    .chunk()
+                    .await
+                    .map_err(|error| Response::bad_request(error.body_text()))?
+                {
+                    if data.len() + chunk.len() > *MAX_SIZE {
+                        return Err(Response::bad_request(format!(
+                            "file must not be larger than {} bytes",
+                            *MAX_SIZE
+                        )));
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+
+                file = Some((filename, data));
+                continue;
+            }
+
+            let data = field
+                .bytes()
+                .await
+                .map_err(|error| Response::bad_request(error.body_text()))?;
+
+            match name.as_str() {
+                "kind" => {
+                    let value: Entity = String::from_utf8_lossy(&data)
+                        .trim()
+                        .parse()
+                        .map_err(Response::bad_request)?;
+                    kind = Some(value);
+                }
+                "entity_id" => {
+                    let value = String::from_utf8_lossy(&data)
+                        .trim()
+                        .parse::<i64>()
+                        .map_err(|_| {
+                            Response::bad_request("entity_id is not an integer".to_string())
+                        })?;
+                    entity_id = Some(value);
+                }
+                _ => {}
+            }
+        }
+
+        let (name, data) = file.ok_or(Response::bad_request("file is required".to_string()))?;
+        let kind = kind.ok_or(Response::bad_request("kind is required".to_string()))?;
+        let entity_id =
+            entity_id.ok_or(Response::bad_request("entity_id is required".to_string()))?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let (trade_id, transaction_id) = super::check_entity(&conn, owner, kind, entity_id)?;
+
+        let id = attachment::attach(
+            &conn,
+            &STORE,
+            owner,
+            trade_id,
+            transaction_id,
+            name.clone(),
+            &data,
+        )?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody {
+            id,
+            name,
+            mime: Mime::sniff(&data),
+            size: data.len(),
+            created_at,
+        }))
+    }
+}
+
+mod download {
+    pub const PATH: &str = "/finance/attachments/:id/download";
+
+    use axum::response::IntoResponse;
+
+    use crate::api::http::prelude::*;
+    use crate::attachment;
+    use crate::consts::attachment::STORE;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::attachment::Attachment;
+
+    /// The file as uploaded, served as what it was sniffed to be.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(id): Path<i64>,
+    ) -> Result<axum::response::Response, Response<()>> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let attachment = Attachment::select_by_id_owner(&conn, id, owner)?.ok_or(
+            Response::not_found(format!("attachment {} does not exist", id)),
+        )?;
+
+        let body = attachment::read(&STORE, &attachment)?;
+
+        Ok(Download::new(attachment.mime.content_type(), attachment.name, body).into_response())
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/attachments/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::attachment;
+    use crate::attachment::mime::Mime;
+    use crate::consts::attachment::STORE;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::attachment::Attachment;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct AttachmentItem {
+        pub id: i64,
+        pub owner: i64,
+        pub trade_id: Option<i64>,
+        pub transaction_id: Option<i64>,
+        pub name: String,
+        pub mime: Mime,
+        pub size: i64,
+        pub sha256: String,
+        pub is_encrypted: bool,
+        pub created_at: DateTime<Utc>,
+    }
+
+    /// Deletes an attachment, and its stored content once no other
+    /// attachment shares it.
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<AttachmentItem> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let attachment = Attachment::select_by_id_owner(&conn, id, owner)?.ok_or(
+            Response::not_found(format!("attachment {} does not exist", id)),
+        )?;
+
+        Attachment::delete_by_id_owner(&conn, id, owner)?;
+        attachment::release(&conn, &STORE, std::slice::from_ref(&attachment))?;
+
+        let attachment_item = AttachmentItem {
+            id: attachment.id(),
+            owner: attachment.owner,
+            trade_id: attachment.trade_id,
+            transaction_id: attachment.transaction_id,
+            name: attachment.name,
+            mime: attachment.mime,
+            size: attachment.size,
+            sha256: attachment.sha256,
+            is_encrypted: attachment.is_encrypted,
+            created_at: attachment.created_at,
+        };
+
+        Ok(Response::ok(attachment_item))
+    }
+}
@@ -0,0 +1,258 @@
+use crate::api::http::state::StateInner;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::get;
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(report::PATH, get(report::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/basis";
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::Object;
+    use crate::portfolio::basis;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// The object costs and proceeds are measured in.
+        #[validate(range(min = 1))]
+        pub object_id: i64,
+        pub at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct LotItem {
+        pub acquired_at: DateTime<Utc>,
+        pub quantity: Decimal,
+        pub cost: Option<Decimal>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PositionItem {
+        pub object_id: i64,
+        pub quantity: Decimal,
+        /// `None` when any lot could not be priced.
+        pub cost: Option<Decimal>,
+        pub average_cost: Option<Decimal>,
+        /// Open lots, oldest first.
+        pub lots: Vec<LotItem>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub object_id: i64,
+        pub at: DateTime<Utc>,
+        pub positions: Vec<PositionItem>,
+        /// Gains of every disposal so far that could be priced.
+        pub realized: Decimal,
+        /// Disposals that could not be priced and are missing from `realized`.
+        pub unpriced: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+        let at = params.at.unwrap_or(Utc::now());
+
+        let object = Object::select_by_id_owner(&conn, params.object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", params.object_id)),
+        )?;
+
+        let basis = basis::load(&conn, owner, object.id(), at)?;
+
+        let positions = basis
+            .positions()
+            .into_iter()
+            .map(|position| PositionItem {
+                object_id: position.object_id,
+                quantity: position.quantity,
+                cost: position.cost,
+                average_cost: position
+                    .cost
+                    .and_then(|cost| cost.checked_div(position.quantity)),
+                lots: basis
+                    .lots
+                    .iter()
+                    .filter(|lot| lot.object_id == position.object_id)
+                    .map(|lot| LotItem {
+                        acquired_at: lot.acquired_at,
+                        quantity: lot.quantity,
+                        cost: lot.cost,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            object_id: object.id(),
+            at,
+            positions,
+            realized: basis.realized(),
+            unpriced: basis
+                .disposals
+                .iter()
+                .filter(|disposal| disposal.gain().is_none())
+                .count(),
+        }))
+    }
+}
+
+mod report {
+    pub const PATH: &str = "/finance/basis/report";
+
+    use std::collections::HashMap;
+
+    use axum::response::IntoResponse;
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::Object;
+    use crate::portfolio::basis;
+    use crate::portfolio::tax::{self, FiscalYear, Term};
+
+    #[derive(Debug, Clone, Copy, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Format {
+        Json,
+        Csv,
+    }
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// The object costs and proceeds are measured in.
+        #[validate(range(min = 1))]
+        pub object_id: i64,
+        /// The calendar year the fiscal year starts in.
+        #[validate(range(min = 1900, max = 9999))]
+        pub year: i32,
+        /// `MM-DD`, `FINANCE_FISCAL_YEAR_START` when omitted.
+        pub fiscal_year_start: Option<FiscalYear>,
+        /// Whole days a lot must be held beyond to be long-term,
+        /// `FINANCE_LONG_TERM_DAYS` when omitted.
+        #[validate(range(min = 0, max = 36500))]
+        pub long_term_days: Option<i64>,
+        /// JSON when omitted.
+        pub format: Option<Format>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct RowItem {
+        pub object_id: i64,
+        pub symbol: Option<String>,
+        pub quantity: Decimal,
+        pub acquired_at: Option<DateTime<Utc>>,
+        pub disposed_at: DateTime<Utc>,
+        pub proceeds: Option<Decimal>,
+        pub cost: Option<Decimal>,
+        pub gain: Option<Decimal>,
+        pub holding_days: Option<i64>,
+        pub term: Option<Term>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub object_id: i64,
+        pub start: DateTime<Utc>,
+        pub end: DateTime<Utc>,
+        pub rows: Vec<RowItem>,
+        pub proceeds: Decimal,
+        pub cost: Decimal,
+        pub short_term_gain: Decimal,
+        pub long_term_gain: Decimal,
+        /// Rows whose gain is unknown and missing from the sums.
+        pub unpriced: usize,
+    }
+
+    /// Capital gains of the lots closed within one fiscal year, as JSON or
+    /// as a CSV download.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> Result<axum::response::Response, Response<()>> {
+        use crate::consts::finance::{FISCAL_YEAR_START, LONG_TERM_DAYS};
+
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object = Object::select_by_id_owner(&conn, params.object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", params.object_id)),
+        )?;
+
+        let fiscal_year = params.fiscal_year_start.unwrap_or(*FISCAL_YEAR_START);
+        let (start, end) = fiscal_year
+            .bounds(params.year)
+            .ok_or(Response::bad_request(format!(
+                "fiscal year {} cannot start on {}",
+                params.year, fiscal_year
+            )))?;
+        let long_term_days = params.long_term_days.unwrap_or(*LONG_TERM_DAYS);
+
+        let basis = basis::load(&conn, owner, object.id(), end)?;
+        let report = tax::report(&basis.disposals, start, end, long_term_days);
+
+        let symbols: HashMap<i64, String> = Object::select_all_by_owner(&conn, owner)?
+            .into_iter()
+            .map(|object| (object.id(), object.symbol))
+            .collect();
+
+        if let Some(Format::Csv) = params.format {
+            let body = tax::to_csv(&report, &symbols)?;
+            let filename = format!("capital-gains-{}.csv", params.year);
+
+            return Ok(Download::new("text/csv", filename, body).into_response());
+        }
+
+        let rows = report
+            .rows
+            .into_iter()
+            .map(|row| RowItem {
+                object_id: row.object_id,
+                symbol: symbols.get(&row.object_id).cloned(),
+                quantity: row.quantity,
+                acquired_at: row.acquired_at,
+                disposed_at: row.disposed_at,
+                proceeds: row.proceeds,
+                cost: row.cost,
+                gain: row.gain,
+                holding_days: row.holding_days,
+                term: row.term,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            object_id: object.id(),
+            start: report.start,
+            end: report.end,
+            rows,
+            proceeds: report.proceeds,
+            cost: report.cost,
+            short_term_gain: report.short_term_gain,
+            long_term_gain: report.long_term_gain,
+            unpriced: report.unpriced,
+        })
+        .into_response())
+    }
+}
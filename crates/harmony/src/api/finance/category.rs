@@ -0,0 +1,377 @@
+use rusqlite::Connection;
+
+use crate::api::http::prelude::Response;
+use crate::api::http::state::StateInner;
+use crate::model::finance::category::Category;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{delete, get, post, put};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(post::PATH, post(post::handler))
+        .route(put::PATH, put(put::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .route(summary::PATH, get(summary::handler))
+        .with_state(state)
+}
+
+/// Checks that a name has no `/`, that the parent belongs to the owner and
+/// is not the category itself or below it, and that no sibling goes by the
+/// name.
+fn check(
+    conn: &Connection,
+    owner: i64,
+    parent_id: Option<i64>,
+    name: &str,
+    category: Option<&Category>,
+) -> Result<(), Response<()>> {
+    if name.contains('/') {
+        return Err(Response::bad_request("name must not contain /".into()));
+    }
+
+    if let Some(parent_id) = parent_id {
+        let parent = Category::select_by_id_owner(conn, parent_id, owner)?.ok_or(
+            Response::not_found(format!("category {} does not exist", parent_id)),
+        )?;
+
+        if let Some(category) = category {
+            if parent.is_within(category) {
+                return Err(Response::bad_request(format!(
+                    "category {} cannot move below itself",
+                    category.id()
+                )));
+            }
+        }
+    }
+
+    match Category::select_by_owner_parent_id_name(conn, owner, parent_id, name)? {
+        Some(sibling) if Some(sibling.id()) != category.map(|category| category.id()) => Err(
+            Response::conflict(format!("category {} already exists", sibling.path)),
+        ),
+        _ => Ok(()),
+    }
+}
+
+mod get {
+    pub const PATH: &str = "/finance/categories";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::category::Category;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub id: Option<i64>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct CategoryItem {
+        pub id: i64,
+        pub owner: i64,
+        pub parent_id: Option<i64>,
+        pub name: String,
+        pub path: String,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub categories: Vec<CategoryItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let total = Category::count_by_owner(&conn, owner)?;
+
+        if let Some(id) = params.id {
+            let category = Category::select_by_id_owner(&conn, id, owner)?.ok_or(
+                Response::not_found(format!("category {} does not exist", id)),
+            )?;
+
+            let category_item = CategoryItem {
+                id: category.id(),
+                owner: category.owner,
+                parent_id: category.parent_id,
+                name: category.name,
+                path: category.path,
+                created_at: category.created_at,
+                updated_at: category.updated_at,
+            };
+
+            return Ok(Response::ok(ResponseBody {
+                categories: vec![category_item],
+                total,
+            }));
+        }
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let categories = Category::select_by_owner(&conn, owner, limit, offset)?;
+
+        let categories = categories
+            .into_iter()
+            .map(|category| CategoryItem {
+                id: category.id(),
+                owner: category.owner,
+                parent_id: category.parent_id,
+                name: category.name,
+                path: category.path,
+                created_at: category.created_at,
+                updated_at: category.updated_at,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { categories, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/categories";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::category::Category;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        /// The category to sit in, or none to sit at the top.
+        #[validate(range(min = 1))]
+        pub parent_id: Option<i64>,
+        #[validate(length(min = 1, max = 64))]
+        pub name: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        super::check(&conn, owner, payload.parent_id, &payload.name, None)?;
+
+        let id = Category::insert(&conn, owner, payload.parent_id, payload.name)?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody { id, created_at }))
+    }
+}
+
+mod put {
+    pub const PATH: &str = "/finance/categories/:id";
+
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::category::Category;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        /// Moves the category into another.
+        #[validate(range(min = 1))]
+        pub parent_id: Option<i64>,
+        /// Moves the category to the top when true.
+        pub is_top: Option<bool>,
+        #[validate(length(min = 1, max = 64))]
+        pub name: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(id): Path<i64>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let category = Category::select_by_id_owner(&conn, id, owner)?.ok_or(
+            Response::not_found(format!("category {} does not exist", id)),
+        )?;
+
+        let parent_id = match (payload.is_top, payload.parent_id) {
+            (Some(true), Some(_)) => {
+                return Err(Response::bad_request(
+                    "parent_id must not be given with is_top".into(),
+                ))
+            }
+            (Some(true), None) => None,
+            (_, parent_id) => parent_id.or(category.parent_id),
+        };
+        let name = payload.name.unwrap_or(category.name.clone());
+
+        super::check(&conn, owner, parent_id, &name, Some(&category))?;
+
+        Category::update_by_id_owner(&conn, id, owner, parent_id, name)?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/categories/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::category::Category;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct CategoryItem {
+        pub id: i64,
+        pub owner: i64,
+        pub parent_id: Option<i64>,
+        pub name: String,
+        pub path: String,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    /// Deletes the categories below as well, and leaves their transactions
+    /// without a category.
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<CategoryItem> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let category = Category::select_by_id_owner(&conn, id, owner)?.ok_or(
+            Response::not_found(format!("category {} does not exist", id)),
+        )?;
+
+        Category::delete_by_id_owner(&conn, id, owner)?;
+
+        let category_item = CategoryItem {
+            id: category.id(),
+            owner: category.owner,
+            parent_id: category.parent_id,
+            name: category.name,
+            path: category.path,
+            created_at: category.created_at,
+            updated_at: category.updated_at,
+        };
+
+        Ok(Response::ok(category_item))
+    }
+}
+
+mod summary {
+    pub const PATH: &str = "/finance/categories/summary";
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::portfolio::category::summarize;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub from: Option<DateTime<Utc>>,
+        pub to: Option<DateTime<Utc>>,
+        /// Counts categories further down towards their ancestor this many
+        /// levels from the top.
+        #[validate(range(min = 1, max = 64))]
+        pub depth: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct CategoryItem {
+        /// None for transactions without a category.
+        pub path: Option<String>,
+        pub quote_object_id: i64,
+        pub count: usize,
+        pub spent: Decimal,
+        pub received: Decimal,
+        pub fee: Decimal,
+        pub unpriced: usize,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub from: Option<DateTime<Utc>>,
+        pub to: DateTime<Utc>,
+        pub categories: Vec<CategoryItem>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let to = params.to.unwrap_or(Utc::now());
+        if params.from.is_some_and(|from| from > to) {
+            return Err(Response::bad_request("from must not be after to".into()));
+        }
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let categories = summarize(&conn, owner, params.from, to, params.depth)?
+            .into_iter()
+            .map(|summary| CategoryItem {
+                path: summary.path,
+                quote_object_id: summary.quote_object_id,
+                count: summary.count,
+                spent: summary.spent,
+                received: summary.received,
+                fee: summary.fee,
+                unpriced: summary.unpriced,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            from: params.from,
+            to,
+            categories,
+        }))
+    }
+}
@@ -0,0 +1,93 @@
+pub fn router(state: std::sync::Arc<crate::api::http::state::StateInner>) -> axum::Router {
+    use axum::routing::get;
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(statement::PATH, get(statement::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/export";
+
+    use axum::response::IntoResponse;
+    use chrono::{DateTime, Utc};
+    use serde::Deserialize;
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::export::{Format, Journal};
+    use crate::model::database::prelude::*;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub format: Format,
+        /// Now when omitted.
+        pub at: Option<DateTime<Utc>>,
+    }
+
+    /// The objects, accounts, prices and transactions of the owner as a
+    /// Beancount or Ledger file.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> Result<axum::response::Response, Response<()>> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let journal = Journal::load(&conn, owner, params.at.unwrap_or(Utc::now()))?;
+        let body = params.format.render(&journal).into_bytes();
+        let filename = format!("harmony.{}", params.format);
+
+        Ok(Download::new("text/plain; charset=utf-8", filename, body).into_response())
+    }
+}
+
+mod statement {
+    pub const PATH: &str = "/finance/export/statement";
+
+    use axum::response::IntoResponse;
+    use chrono::{DateTime, Utc};
+    use serde::Deserialize;
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::export::statement::{Format, Statement};
+    use crate::model::database::prelude::*;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub format: Format,
+        /// The first transaction when omitted.
+        pub from: Option<DateTime<Utc>>,
+        /// Now when omitted.
+        pub to: Option<DateTime<Utc>>,
+    }
+
+    /// The settled trade transactions of the owner within a date range as
+    /// an OFX or QIF file.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> Result<axum::response::Response, Response<()>> {
+        params.validate()?;
+
+        let to = params.to.unwrap_or(Utc::now());
+        if params.from.is_some_and(|from| from > to) {
+            return Err(Response::bad_request("from must not be after to".into()));
+        }
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let statement = Statement::load(&conn, owner, params.from, to)?;
+        let body = params.format.render(&statement).into_bytes();
+        let filename = format!("harmony.{}", params.format);
+
+        Ok(Download::new(params.format.content_type(), filename, body).into_response())
+    }
+}
@@ -0,0 +1,514 @@
+use rusqlite::Connection;
+
+use crate::api::http::prelude::Response;
+use crate::api::http::state::StateInner;
+use crate::model::finance::account::Account;
+use crate::model::finance::flow::Kind;
+use crate::model::finance::object::Object;
+use crate::model::finance::{Quantity, Rounding};
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{delete, get, post, put};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(post::PATH, post(post::handler))
+        .route(put::PATH, put(put::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .route(summary::PATH, get(summary::handler))
+        .with_state(state)
+}
+
+/// Checks the objects and account of a flow and brings its amounts to the
+/// precision of its object, returning the quantity and tax withheld to store.
+#[allow(clippy::too_many_arguments)]
+fn check(
+    conn: &Connection,
+    owner: i64,
+    kind: Kind,
+    object_id: i64,
+    account_id: i64,
+    counterparty_object_id: Option<i64>,
+    quantity: Quantity,
+    tax_withheld: Option<Quantity>,
+    rounding: Option<Rounding>,
+) -> Result<(Quantity, Option<Quantity>), Response<()>> {
+    let object = Object::select_by_id_owner(conn, object_id, owner)?.ok_or(Response::not_found(
+        format!("object {} does not exist", object_id),
+    ))?;
+
+    Account::select_by_id_owner(conn, account_id, owner)?.ok_or(Response::not_found(format!(
+        "account {} does not exist",
+        account_id
+    )))?;
+
+    if let Some(counterparty_object_id) = counterparty_object_id {
+        Object::select_by_id_owner(conn, counterparty_object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", counterparty_object_id)),
+        )?;
+    }
+
+    let precision = object.classification.precision;
+    let quantity = quantity.with_precision(precision, rounding)?;
+
+    let tax_withheld = match tax_withheld {
+        Some(_) if !kind.is_income() => {
+            return Err(Response::bad_request(format!(
+                "tax_withheld does not apply to {} flows",
+                kind
+            )))
+        }
+        Some(tax) => Some(tax.with_precision(precision, rounding)?),
+        None => None,
+    };
+
+    Ok((quantity, tax_withheld))
+}
+
+mod get {
+    pub const PATH: &str = "/finance/flows";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::flow::{Flow, Kind};
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub id: Option<i64>,
+        pub kind: Option<Kind>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct FlowItem {
+        pub id: i64,
+        pub owner: i64,
+        pub kind: Kind,
+        pub object_id: i64,
+        pub account_id: i64,
+        pub quantity: Quantity,
+        pub counterparty_object_id: Option<i64>,
+        pub tax_withheld: Option<Quantity>,
+        pub tax_category: Option<String>,
+        pub remark: Option<String>,
+        pub occurrence_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub flows: Vec<FlowItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        if let Some(id) = params.id {
+            let flow = Flow::select_by_id_owner(&conn, id, owner)?
+                .ok_or(Response::not_found(format!("flow {} does not exist", id)))?;
+
+            let flow_item = FlowItem {
+                id: flow.id(),
+                owner: flow.owner,
+                kind: flow.kind,
+                object_id: flow.object_id,
+                account_id: flow.account_id,
+                quantity: flow.quantity,
+                counterparty_object_id: flow.counterparty_object_id,
+                tax_withheld: flow.tax_withheld,
+                tax_category: flow.tax_category,
+                remark: flow.remark,
+                occurrence_at: flow.occurrence_at,
+                created_at: flow.created_at,
+                updated_at: flow.updated_at,
+            };
+
+            return Ok(Response::ok(ResponseBody {
+                flows: vec![flow_item],
+                total: 1,
+            }));
+        }
+
+        let total = Flow::count_by_owner(&conn, owner, params.kind)?;
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let flows = Flow::select_by_owner(&conn, owner, params.kind, limit, offset)?
+            .into_iter()
+            .map(|flow| FlowItem {
+                id: flow.id(),
+                owner: flow.owner,
+                kind: flow.kind,
+                object_id: flow.object_id,
+                account_id: flow.account_id,
+                quantity: flow.quantity,
+                counterparty_object_id: flow.counterparty_object_id,
+                tax_withheld: flow.tax_withheld,
+                tax_category: flow.tax_category,
+                remark: flow.remark,
+                occurrence_at: flow.occurrence_at,
+                created_at: flow.created_at,
+                updated_at: flow.updated_at,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { flows, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/flows";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::atomically;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::flow::{Flow, Kind};
+    use crate::model::finance::{positive, Quantity, Rounding};
+    use crate::portfolio::ledger;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        pub kind: Kind,
+        #[validate(range(min = 1))]
+        pub object_id: i64,
+        #[validate(range(min = 1))]
+        pub account_id: i64,
+        #[serde(deserialize_with = "positive::deserialize")]
+        pub quantity: Quantity,
+        #[validate(range(min = 1))]
+        pub counterparty_object_id: Option<i64>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub tax_withheld: Option<Quantity>,
+        #[validate(length(min = 1, max = 64))]
+        pub tax_category: Option<String>,
+        /// Rounds amounts finer than the object's precision instead of
+        /// rejecting them.
+        pub rounding: Option<Rounding>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+        pub occurrence_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let (quantity, tax_withheld) = super::check(
+            &conn,
+            owner,
+            payload.kind,
+            payload.object_id,
+            payload.account_id,
+            payload.counterparty_object_id,
+            payload.quantity,
+            payload.tax_withheld,
+            payload.rounding,
+        )?;
+
+        // The flow is not kept when its entry cannot be recorded
+        let id = atomically(&conn, || {
+            let id = Flow::insert(
+                &conn,
+                owner,
+                payload.kind,
+                payload.object_id,
+                payload.account_id,
+                quantity,
+                payload.counterparty_object_id,
+                tax_withheld,
+                payload.tax_category,
+                payload.remark,
+                payload.occurrence_at.unwrap_or(Utc::now()),
+            )?;
+
+            let flow = Flow::select_by_id_owner(&conn, id, owner)?
+                .ok_or(format!("flow {} does not exist", id))?;
+            ledger::record_flow(&conn, &flow)?;
+
+            Ok(id)
+        })?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody { id, created_at }))
+    }
+}
+
+mod put {
+    pub const PATH: &str = "/finance/flows/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::atomically;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::flow::{Flow, Kind};
+    use crate::model::finance::{positive, Quantity, Rounding};
+    use crate::portfolio::ledger;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        pub kind: Option<Kind>,
+        #[validate(range(min = 1))]
+        pub object_id: Option<i64>,
+        #[validate(range(min = 1))]
+        pub account_id: Option<i64>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub quantity: Option<Quantity>,
+        #[validate(range(min = 1))]
+        pub counterparty_object_id: Option<i64>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub tax_withheld: Option<Quantity>,
+        #[validate(length(min = 1, max = 64))]
+        pub tax_category: Option<String>,
+        /// Rounds amounts finer than the object's precision instead of
+        /// rejecting them.
+        pub rounding: Option<Rounding>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+        pub occurrence_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(id): Path<i64>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let flow = Flow::select_by_id_owner(&conn, id, owner)?
+            .ok_or(Response::not_found(format!("flow {} does not exist", id)))?;
+
+        let kind = payload.kind.unwrap_or(flow.kind);
+        let object_id = payload.object_id.unwrap_or(flow.object_id);
+        let account_id = payload.account_id.unwrap_or(flow.account_id);
+        let counterparty_object_id = payload
+            .counterparty_object_id
+            .or(flow.counterparty_object_id);
+        // Tax withheld only stays with income
+        let tax_withheld = match payload.tax_withheld {
+            Some(tax) => Some(tax),
+            None if kind.is_income() => flow.tax_withheld,
+            None => None,
+        };
+
+        let (quantity, tax_withheld) = super::check(
+            &conn,
+            owner,
+            kind,
+            object_id,
+            account_id,
+            counterparty_object_id,
+            payload.quantity.unwrap_or(flow.quantity),
+            tax_withheld,
+            payload.rounding,
+        )?;
+
+        // The change is undone when its entry cannot be recorded
+        atomically(&conn, || {
+            Flow::update_by_id_owner(
+                &conn,
+                id,
+                owner,
+                kind,
+                object_id,
+                account_id,
+                quantity,
+                counterparty_object_id,
+                tax_withheld,
+                payload.tax_category.or(flow.tax_category),
+                payload.remark.or(flow.remark),
+                payload.occurrence_at.unwrap_or(flow.occurrence_at),
+            )?;
+
+            let flow = Flow::select_by_id_owner(&conn, id, owner)?
+                .ok_or(format!("flow {} does not exist", id))?;
+            ledger::record_flow(&conn, &flow)?;
+
+            Ok(())
+        })?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/flows/:id";
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::flow::Flow;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<ResponseBody> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let flow = Flow::select_by_id_owner(&conn, id, owner)?
+            .ok_or(Response::not_found(format!("flow {} does not exist", id)))?;
+
+        // The journal entry goes with it
+        Flow::delete_by_id_owner(&conn, flow.id(), owner)?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
+
+mod summary {
+    pub const PATH: &str = "/finance/flows/summary";
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::flow::{Flow, Kind};
+    use crate::model::finance::object::Object;
+    use crate::model::finance::price::Price;
+    use crate::portfolio::cashflow::summarize;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// The object every flow is valued in.
+        #[validate(range(min = 1))]
+        pub object_id: i64,
+        pub from: Option<DateTime<Utc>>,
+        pub to: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct KindItem {
+        pub kind: Kind,
+        pub count: usize,
+        pub value: Decimal,
+        pub tax_withheld: Decimal,
+        pub unpriced: usize,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub object_id: i64,
+        pub from: Option<DateTime<Utc>>,
+        pub to: DateTime<Utc>,
+        pub kinds: Vec<KindItem>,
+        /// Dividends, interest, staking rewards and airdrops.
+        pub income: Decimal,
+        pub expense: Decimal,
+        /// Deposits minus withdrawals.
+        pub net_deposit: Decimal,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let to = params.to.unwrap_or(Utc::now());
+        if params.from.is_some_and(|from| from > to) {
+            return Err(Response::bad_request("from must not be after to".into()));
+        }
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object = Object::select_by_id_owner(&conn, params.object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", params.object_id)),
+        )?;
+
+        let mut flows = Flow::select_by_owner_until(&conn, owner, to)?;
+        if let Some(from) = params.from {
+            flows.retain(|flow| flow.occurrence_at >= from);
+        }
+        let prices = Price::select_by_owner_until(&conn, owner, to)?;
+
+        let summaries = summarize(&flows, &prices, object.id());
+
+        let sum = |filter: fn(Kind) -> bool| -> Decimal {
+            summaries
+                .iter()
+                .filter(|summary| filter(summary.kind))
+                .map(|summary| summary.value)
+                .sum()
+        };
+        let income = sum(|kind| kind.is_income());
+        let expense = sum(|kind| kind == Kind::Expense);
+        let net_deposit = sum(|kind| kind == Kind::Deposit) - sum(|kind| kind == Kind::Withdrawal);
+
+        let kinds = summaries
+            .into_iter()
+            .map(|summary| KindItem {
+                kind: summary.kind,
+                count: summary.count,
+                value: summary.value,
+                tax_withheld: summary.tax_withheld,
+                unpriced: summary.unpriced,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            object_id: object.id(),
+            from: params.from,
+            to,
+            kinds,
+            income,
+            expense,
+            net_deposit,
+        }))
+    }
+}
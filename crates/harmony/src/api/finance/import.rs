@@ -0,0 +1,271 @@
+pub fn router(state: std::sync::Arc<crate::api::http::state::StateInner>) -> axum::Router {
+    use axum::routing::{get, post};
+
+    axum::Router::new()
+        .route(post::PATH, post(post::handler))
+        .route(beancount::PATH, post(beancount::handler))
+        .route(presets::PATH, get(presets::handler))
+        .with_state(state)
+}
+
+mod post {
+    pub const PATH: &str = "/finance/import";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::import;
+    use crate::import::csv::Mapping;
+    use crate::import::preset::Preset;
+    use crate::import::{Status, Summary};
+    use crate::model::database::prelude::*;
+    use crate::model::finance::account::Account;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct RowItem {
+        pub line: u64,
+        pub status: Status,
+        pub hash: Option<String>,
+        pub trade_id: Option<i64>,
+        pub transaction_id: Option<i64>,
+        pub imported_at: Option<DateTime<Utc>>,
+        pub message: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub committed: bool,
+        pub rows: Vec<RowItem>,
+        /// Symbols of the objects created, or that would be on a dry run.
+        pub objects: Vec<String>,
+        pub accounts: Vec<String>,
+        pub trades: Vec<i64>,
+        pub created: usize,
+        pub duplicates: usize,
+        pub invalid: usize,
+    }
+
+    /// Takes the fields `file`, either `mapping` as JSON or the name of a
+    /// `preset`, `account_id` and optionally `dry_run`. Nothing is stored on a
+    /// dry run, nor when any row is invalid.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Multipart(mut multipart): Multipart,
+    ) -> ResponseResult<ResponseBody> {
+        let mut file = None;
+        let mut mapping = None;
+        let mut preset = None;
+        let mut account_id = None;
+        let mut dry_run = false;
+
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|error| Response::bad_request(error.body_text()))?
+        {
+            let name = field.name().unwrap_or_default().to_string();
+            let data = field
+                .bytes()
+                .await
+                .map_err(|error| Response::bad_request(error.body_text()))?;
+
+            match name.as_str() {
+                "file" => file = Some(data),
+                "mapping" => {
+                    let value: Mapping = serde_json::from_slice(&data).map_err(|error| {
+                        Response::bad_request(format!("mapping is invalid: {}", error))
+                    })?;
+                    mapping = Some(value);
+                }
+                "preset" => {
+                    let value: Preset = String::from_utf8_lossy(&data)
+                        .trim()
+                        .parse()
+                        .map_err(Response::bad_request)?;
+                    preset = Some(value);
+                }
+                "account_id" => {
+                    let value = String::from_utf8_lossy(&data)
+                        .trim()
+                        .parse::<i64>()
+                        .map_err(|_| {
+                            Response::bad_request("account_id is not an integer".to_string())
+                        })?;
+                    account_id = Some(value);
+                }
+                "dry_run" => {
+                    dry_run = String::from_utf8_lossy(&data)
+                        .trim()
+                        .parse::<bool>()
+                        .map_err(|_| {
+                            Response::bad_request("dry_run is not a boolean".to_string())
+                        })?;
+                }
+                _ => {}
+            }
+        }
+
+        let file = file.ok_or(Response::bad_request("file is required".to_string()))?;
+        let mapping = match (mapping, preset) {
+            (Some(mapping), None) => mapping,
+            (None, Some(preset)) => preset.mapping(),
+            _ => {
+                return Err(Response::bad_request(
+                    "exactly one of mapping and preset is required".to_string(),
+                ))
+            }
+        };
+        let account_id =
+            account_id.ok_or(Response::bad_request("account_id is required".to_string()))?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let account = Account::select_by_id_owner(&conn, account_id, owner)?.ok_or(
+            Response::not_found(format!("account {} does not exist", account_id)),
+        )?;
+
+        let lines = mapping
+            .parse(&file)
+            .map_err(|error| Response::bad_request(error.to_string()))?;
+
+        let summary = import::import(&conn, owner, Some(account.id()), lines, dry_run)?;
+
+        Ok(Response::ok(body(summary)))
+    }
+
+    pub fn body(summary: Summary) -> ResponseBody {
+        let count = |status: Status| {
+            summary
+                .outcomes
+                .iter()
+                .filter(|outcome| outcome.status == status)
+                .count()
+        };
+        let created = count(Status::Created);
+        let duplicates = count(Status::Duplicate);
+        let invalid = count(Status::Invalid);
+
+        let rows = summary
+            .outcomes
+            .into_iter()
+            .map(|outcome| RowItem {
+                line: outcome.line,
+                status: outcome.status,
+                hash: outcome.hash,
+                trade_id: outcome.trade_id,
+                transaction_id: outcome.transaction_id,
+                imported_at: outcome.imported_at,
+                message: outcome.message,
+            })
+            .collect();
+
+        ResponseBody {
+            committed: summary.committed,
+            rows,
+            objects: summary.objects,
+            accounts: summary.accounts,
+            trades: summary.trades,
+            created,
+            duplicates,
+            invalid,
+        }
+    }
+}
+
+mod beancount {
+    pub const PATH: &str = "/finance/import/beancount";
+
+    use crate::api::http::prelude::*;
+    use crate::import;
+    use crate::model::database::prelude::*;
+
+    use super::post::{body, ResponseBody};
+
+    /// Takes the fields `file` and optionally `dry_run`. Trades are booked to
+    /// the accounts the file names, which are created when missing.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Multipart(mut multipart): Multipart,
+    ) -> ResponseResult<ResponseBody> {
+        let mut file = None;
+        let mut dry_run = false;
+
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|error| Response::bad_request(error.body_text()))?
+        {
+            let name = field.name().unwrap_or_default().to_string();
+            let data = field
+                .bytes()
+                .await
+                .map_err(|error| Response::bad_request(error.body_text()))?;
+
+            match name.as_str() {
+                "file" => file = Some(data),
+                "dry_run" => {
+                    dry_run = String::from_utf8_lossy(&data)
+                        .trim()
+                        .parse::<bool>()
+                        .map_err(|_| {
+                            Response::bad_request("dry_run is not a boolean".to_string())
+                        })?;
+                }
+                _ => {}
+            }
+        }
+
+        let file = file.ok_or(Response::bad_request("file is required".to_string()))?;
+        let data = String::from_utf8(file.to_vec())
+            .map_err(|_| Response::bad_request("file is not UTF-8".to_string()))?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let lines = import::beancount::parse(&data)
+            .map_err(|error| Response::bad_request(error.to_string()))?;
+
+        let summary = import::import(&conn, owner, None, lines, dry_run)?;
+
+        Ok(Response::ok(body(summary)))
+    }
+}
+
+mod presets {
+    pub const PATH: &str = "/finance/import/presets";
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::import::csv::Mapping;
+    use crate::import::preset::Preset;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PresetItem {
+        pub preset: Preset,
+        pub mapping: Mapping,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub presets: Vec<PresetItem>,
+    }
+
+    /// The built-in mappings, to use as they are or as a start for one's own.
+    #[tracing::instrument()]
+    pub async fn handler(_claim: Claim) -> ResponseResult<ResponseBody> {
+        let presets = Preset::ALL
+            .into_iter()
+            .map(|preset| PresetItem {
+                preset,
+                mapping: preset.mapping(),
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { presets }))
+    }
+}
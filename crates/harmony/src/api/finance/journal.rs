@@ -0,0 +1,168 @@
+use crate::api::http::state::StateInner;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{get, post};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(check::PATH, get(check::handler))
+        .route(rebuild::PATH, post(rebuild::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/journal";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::journal::{Entry, Kind, Posting};
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PostingItem {
+        pub id: i64,
+        pub kind: Kind,
+        pub account_id: Option<i64>,
+        pub object_id: i64,
+        pub amount: Quantity,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct EntryItem {
+        pub id: i64,
+        pub owner: i64,
+        pub transaction_id: Option<i64>,
+        pub flow_id: Option<i64>,
+        pub description: Option<String>,
+        pub postings: Vec<PostingItem>,
+        pub occurrence_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub entries: Vec<EntryItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let total = Entry::count_by_owner(&conn, owner)?;
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let mut entries = Vec::new();
+
+        for entry in Entry::select_by_owner(&conn, owner, limit, offset)? {
+            let postings = Posting::select_by_entry_id(&conn, entry.id())?
+                .into_iter()
+                .map(|posting| PostingItem {
+                    id: posting.id(),
+                    kind: posting.kind,
+                    account_id: posting.account_id,
+                    object_id: posting.object_id,
+                    amount: posting.amount,
+                })
+                .collect();
+
+            entries.push(EntryItem {
+                id: entry.id(),
+                owner: entry.owner,
+                transaction_id: entry.transaction_id,
+                flow_id: entry.flow_id,
+                description: entry.description,
+                postings,
+                occurrence_at: entry.occurrence_at,
+                created_at: entry.created_at,
+            });
+        }
+
+        Ok(Response::ok(ResponseBody { entries, total }))
+    }
+}
+
+mod check {
+    pub const PATH: &str = "/finance/journal/check";
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::portfolio::ledger::{self, Imbalance};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        /// Whether every entry balances and every transaction and flow is
+        /// recorded.
+        pub consistent: bool,
+        pub imbalances: Vec<Imbalance>,
+        /// Transactions without a journal entry.
+        pub unrecorded: Vec<i64>,
+        /// Flows without a journal entry.
+        pub unrecorded_flows: Vec<i64>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim) -> ResponseResult<ResponseBody> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let report = ledger::check(&conn, owner)?;
+
+        Ok(Response::ok(ResponseBody {
+            consistent: report.imbalances.is_empty()
+                && report.unrecorded.is_empty()
+                && report.unrecorded_flows.is_empty(),
+            imbalances: report.imbalances,
+            unrecorded: report.unrecorded,
+            unrecorded_flows: report.unrecorded_flows,
+        }))
+    }
+}
+
+mod rebuild {
+    pub const PATH: &str = "/finance/journal/rebuild";
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::portfolio::ledger;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        /// How many entries were written.
+        pub recorded: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim) -> ResponseResult<ResponseBody> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let recorded = ledger::rebuild(&conn, owner)?;
+
+        Ok(Response::ok(ResponseBody { recorded }))
+    }
+}
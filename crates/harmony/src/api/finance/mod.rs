@@ -1,5 +1,21 @@
+mod account;
+mod attachment;
+mod basis;
+mod category;
+mod export;
+mod flow;
+mod import;
+mod journal;
 mod object;
+mod performance;
+mod price;
+mod search;
+mod tag;
+mod target;
 mod trade;
+mod transaction;
+mod transfer;
+mod valuation;
 
 use std::sync::Arc;
 
@@ -10,8 +26,24 @@ use crate::api::http::state::StateInner;
 pub fn router(state: Arc<StateInner>) -> Router {
     let mut router = Router::new().with_state(state.clone());
 
+    router = router.merge(account::router(state.clone()));
+    router = router.merge(attachment::router(state.clone()));
+    router = router.merge(basis::router(state.clone()));
+    router = router.merge(category::router(state.clone()));
+    router = router.merge(export::router(state.clone()));
+    router = router.merge(flow::router(state.clone()));
+    router = router.merge(import::router(state.clone()));
+    router = router.merge(journal::router(state.clone()));
     router = router.merge(object::router(state.clone()));
+    router = router.merge(performance::router(state.clone()));
+    router = router.merge(price::router(state.clone()));
+    router = router.merge(search::router(state.clone()));
+    router = router.merge(tag::router(state.clone()));
+    router = router.merge(target::router(state.clone()));
     router = router.merge(trade::router(state.clone()));
+    router = router.merge(transaction::router(state.clone()));
+    router = router.merge(transfer::router(state.clone()));
+    router = router.merge(valuation::router(state.clone()));
 
     router
 }
@@ -0,0 +1,382 @@
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+
+use crate::api::http::prelude::Response;
+use crate::api::http::state::StateInner;
+use crate::model::finance::object::action::Kind;
+use crate::model::finance::object::Object;
+use crate::model::finance::Quantity;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{delete, get, post, put};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(post::PATH, post(post::handler))
+        .route(put::PATH, put(put::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .with_state(state)
+}
+
+/// Checks that the ratio fits the kind of action and that a successor is
+/// given exactly when the action replaces the object, returning the
+/// successor.
+fn check(
+    conn: &Connection,
+    owner: i64,
+    object_id: i64,
+    kind: Kind,
+    ratio: &Quantity,
+    successor_object_id: Option<i64>,
+) -> Result<Option<i64>, Response<()>> {
+    let ratio = ratio.value();
+    let fits = match kind {
+        Kind::Split => ratio > Decimal::ONE,
+        Kind::ReverseSplit => ratio < Decimal::ONE,
+        Kind::SymbolChange => ratio == Decimal::ONE,
+        Kind::Merger => true,
+    };
+    if !fits {
+        return Err(Response::bad_request(format!(
+            "ratio {} does not fit a {}",
+            ratio, kind
+        )));
+    }
+
+    match successor_object_id {
+        None if kind.is_replacement() => Err(Response::bad_request(format!(
+            "a {} needs a successor object",
+            kind
+        ))),
+        Some(_) if !kind.is_replacement() => Err(Response::bad_request(format!(
+            "a {} keeps its object and takes no successor",
+            kind
+        ))),
+        Some(successor_object_id) if successor_object_id == object_id => Err(
+            Response::bad_request("an object cannot succeed itself".into()),
+        ),
+        Some(successor_object_id) => {
+            let successor = Object::select_by_id_owner(conn, successor_object_id, owner)?.ok_or(
+                Response::not_found(format!("object {} does not exist", successor_object_id)),
+            )?;
+
+            Ok(Some(successor.id()))
+        }
+        None => Ok(None),
+    }
+}
+
+mod get {
+    pub const PATH: &str = "/finance/objects/:object_id/actions";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::action::{CorporateAction, Kind};
+    use crate::model::finance::object::Object;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub id: Option<i64>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ActionItem {
+        pub id: i64,
+        pub object_id: i64,
+        pub kind: Kind,
+        pub ratio: Quantity,
+        pub successor_object_id: Option<i64>,
+        pub remark: Option<String>,
+        pub effective_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub actions: Vec<ActionItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(object_id): Path<i64>,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object = Object::select_by_id_owner(&conn, object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", object_id)),
+        )?;
+
+        let total = CorporateAction::count_by_object_id(&conn, object.id())?;
+
+        if let Some(id) = params.id {
+            let action = CorporateAction::select_by_id_object_id(&conn, id, object.id())?.ok_or(
+                Response::not_found(format!("corporate action {} does not exist", id)),
+            )?;
+
+            let action_item = ActionItem {
+                id: action.id(),
+                object_id: action.object_id,
+                kind: action.kind,
+                ratio: action.ratio,
+                successor_object_id: action.successor_object_id,
+                remark: action.remark,
+                effective_at: action.effective_at,
+                created_at: action.created_at,
+                updated_at: action.updated_at,
+            };
+
+            return Ok(Response::ok(ResponseBody {
+                actions: vec![action_item],
+                total,
+            }));
+        }
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let actions = CorporateAction::select_by_object_id(&conn, object.id(), limit, offset)?;
+
+        let actions = actions
+            .into_iter()
+            .map(|action| ActionItem {
+                id: action.id(),
+                object_id: action.object_id,
+                kind: action.kind,
+                ratio: action.ratio,
+                successor_object_id: action.successor_object_id,
+                remark: action.remark,
+                effective_at: action.effective_at,
+                created_at: action.created_at,
+                updated_at: action.updated_at,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { actions, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/objects/:object_id/actions";
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::action::{CorporateAction, Kind};
+    use crate::model::finance::object::Object;
+    use crate::model::finance::{positive, Quantity};
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        pub kind: Kind,
+        /// Units received per unit held, 1 when omitted.
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub ratio: Option<Quantity>,
+        #[validate(range(min = 1))]
+        pub successor_object_id: Option<i64>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+        pub effective_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(object_id): Path<i64>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object = Object::select_by_id_owner(&conn, object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", object_id)),
+        )?;
+
+        let ratio = payload.ratio.unwrap_or(Decimal::ONE.into());
+        let successor_object_id = super::check(
+            &conn,
+            owner,
+            object.id(),
+            payload.kind,
+            &ratio,
+            payload.successor_object_id,
+        )?;
+
+        let id = CorporateAction::insert(
+            &conn,
+            object.id(),
+            payload.kind,
+            ratio,
+            successor_object_id,
+            payload.remark,
+            payload.effective_at,
+        )?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody { id, created_at }))
+    }
+}
+
+mod put {
+    pub const PATH: &str = "/finance/objects/:object_id/actions/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::action::{CorporateAction, Kind};
+    use crate::model::finance::object::Object;
+    use crate::model::finance::{positive, Quantity};
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        pub kind: Option<Kind>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub ratio: Option<Quantity>,
+        #[validate(range(min = 1))]
+        pub successor_object_id: Option<i64>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+        pub effective_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((object_id, id)): Path<(i64, i64)>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object = Object::select_by_id_owner(&conn, object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", object_id)),
+        )?;
+
+        let action = CorporateAction::select_by_id_object_id(&conn, id, object.id())?.ok_or(
+            Response::not_found(format!("corporate action {} does not exist", id)),
+        )?;
+
+        // A kind that keeps the object drops the stored successor
+        let kind = payload.kind.unwrap_or(action.kind);
+        let ratio = payload.ratio.unwrap_or(action.ratio);
+        let successor_object_id = match payload.successor_object_id {
+            Some(successor_object_id) => Some(successor_object_id),
+            None if kind.is_replacement() => action.successor_object_id,
+            None => None,
+        };
+        let successor_object_id =
+            super::check(&conn, owner, object.id(), kind, &ratio, successor_object_id)?;
+        let remark = payload.remark.or(action.remark);
+        let effective_at = payload.effective_at.unwrap_or(action.effective_at);
+
+        CorporateAction::update_by_id_object_id(
+            &conn,
+            id,
+            object.id(),
+            kind,
+            ratio,
+            successor_object_id,
+            remark,
+            effective_at,
+        )?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/objects/:object_id/actions/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::action::{CorporateAction, Kind};
+    use crate::model::finance::object::Object;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ActionItem {
+        pub id: i64,
+        pub object_id: i64,
+        pub kind: Kind,
+        pub ratio: Quantity,
+        pub successor_object_id: Option<i64>,
+        pub remark: Option<String>,
+        pub effective_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((object_id, id)): Path<(i64, i64)>,
+    ) -> ResponseResult<ActionItem> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object = Object::select_by_id_owner(&conn, object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", object_id)),
+        )?;
+
+        let action = CorporateAction::select_by_id_object_id(&conn, id, object.id())?.ok_or(
+            Response::not_found(format!("corporate action {} does not exist", id)),
+        )?;
+
+        CorporateAction::delete_by_id_object_id(&conn, id, object.id())?;
+
+        let action_item = ActionItem {
+            id: action.id(),
+            object_id: action.object_id,
+            kind: action.kind,
+            ratio: action.ratio,
+            successor_object_id: action.successor_object_id,
+            remark: action.remark,
+            effective_at: action.effective_at,
+            created_at: action.created_at,
+            updated_at: action.updated_at,
+        };
+
+        Ok(Response::ok(action_item))
+    }
+}
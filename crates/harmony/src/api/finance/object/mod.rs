@@ -1,14 +1,20 @@
+mod action;
+
 use crate::api::http::state::StateInner;
 
 pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
     use axum::routing::{delete, get, post, put};
 
-    axum::Router::new()
+    let mut router = axum::Router::new()
         .route(get::PATH, get(get::handler))
         .route(post::PATH, post(post::handler))
         .route(put::PATH, put(put::handler))
         .route(delete::PATH, delete(delete::handler))
-        .with_state(state)
+        .with_state(state.clone());
+
+    router = router.merge(action::router(state));
+
+    router
 }
 
 mod get {
@@ -21,11 +27,27 @@ mod get {
     use crate::api::http::paginate;
     use crate::api::http::prelude::*;
     use crate::model::database::prelude::*;
-    use crate::model::finance::object::Object;
+    use crate::model::database::Order;
+    use crate::model::finance::object::{AssetType, Filter, Object, Sort};
 
     #[derive(Debug, Validate, Deserialize)]
     pub struct Params {
         pub id: Option<i64>,
+        pub asset_type: Option<AssetType>,
+        #[validate(length(min = 1, max = 64))]
+        pub exchange: Option<String>,
+        /// Objects created at or after.
+        pub from: Option<DateTime<Utc>>,
+        /// Objects created at or before.
+        pub to: Option<DateTime<Utc>>,
+        /// Found in the symbol, the alias or the remark, ignoring case.
+        #[validate(length(min = 1, max = 256))]
+        pub text: Option<String>,
+        /// Objects the tag is attached to.
+        #[validate(range(min = 1))]
+        pub tag_id: Option<i64>,
+        pub sort: Option<Sort>,
+        pub order: Option<Order>,
         #[validate(range(min = 1))]
         pub page: Option<usize>,
         #[validate(range(min = 1, max = 1024))]
@@ -39,6 +61,10 @@ mod get {
         pub symbol: String,
         pub alias: Option<String>,
         pub remark: Option<String>,
+        pub asset_type: AssetType,
+        pub precision: u32,
+        pub identifier: Option<String>,
+        pub exchange: Option<String>,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
     }
@@ -59,7 +85,22 @@ mod get {
         let owner = claim.subject();
         let conn = connection()?;
 
-        let total = Object::count_by_owner(&conn, owner)?;
+        if let (Some(from), Some(to)) = (params.from, params.to) {
+            if from > to {
+                return Err(Response::bad_request("from must not be after to".into()));
+            }
+        }
+
+        let filter = Filter {
+            asset_type: params.asset_type,
+            exchange: params.exchange,
+            from: params.from,
+            to: params.to,
+            text: params.text,
+            tag_id: params.tag_id,
+        };
+
+        let total = Object::count_by_owner(&conn, owner, &filter)?;
 
         if let Some(id) = params.id {
             let object = Object::select_by_id_owner(&conn, id, owner)?
@@ -71,6 +112,10 @@ mod get {
                 symbol: object.symbol,
                 alias: object.alias,
                 remark: object.remark,
+                asset_type: object.classification.asset_type,
+                precision: object.classification.precision,
+                identifier: object.classification.identifier,
+                exchange: object.classification.exchange,
                 created_at: object.created_at,
                 updated_at: object.updated_at,
             };
@@ -83,7 +128,9 @@ mod get {
 
         let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
 
-        let objects = Object::select_by_owner(&conn, owner, limit, offset)?;
+        let sort = params.sort.unwrap_or_default();
+        let order = params.order.unwrap_or_default();
+        let objects = Object::select_by_owner(&conn, owner, &filter, sort, order, limit, offset)?;
 
         let objects = objects
             .into_iter()
@@ -93,6 +140,10 @@ mod get {
                 symbol: obj.symbol,
                 alias: obj.alias,
                 remark: obj.remark,
+                asset_type: obj.classification.asset_type,
+                precision: obj.classification.precision,
+                identifier: obj.classification.identifier,
+                exchange: obj.classification.exchange,
                 created_at: obj.created_at,
                 updated_at: obj.updated_at,
             })
@@ -111,7 +162,7 @@ mod post {
 
     use crate::api::http::prelude::*;
     use crate::model::database::prelude::*;
-    use crate::model::finance::object::Object;
+    use crate::model::finance::object::{AssetType, Classification, Object};
 
     #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
     pub struct RequestBody {
@@ -121,6 +172,10 @@ mod post {
         pub alias: Option<String>,
         #[validate(length(min = 1, max = 4096))]
         pub remark: Option<String>,
+        pub asset_type: Option<AssetType>,
+        pub precision: Option<u32>,
+        pub identifier: Option<String>,
+        pub exchange: Option<String>,
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,8 +192,24 @@ mod post {
         payload.validate()?;
 
         let owner = claim.subject();
+        let default = Classification::default();
+        let classification = Classification {
+            asset_type: payload.asset_type.unwrap_or(default.asset_type),
+            precision: payload.precision.unwrap_or(default.precision),
+            identifier: payload.identifier,
+            exchange: payload.exchange,
+        };
+        classification.validate()?;
+
         let conn = connection()?;
-        let id = Object::insert(&conn, owner, payload.symbol, payload.alias, payload.remark)?;
+        let id = Object::insert(
+            &conn,
+            owner,
+            payload.symbol,
+            payload.alias,
+            payload.remark,
+            classification,
+        )?;
 
         let created_at = Utc::now();
 
@@ -154,7 +225,7 @@ mod put {
 
     use crate::api::http::prelude::*;
     use crate::model::database::prelude::*;
-    use crate::model::finance::object::Object;
+    use crate::model::finance::object::{AssetType, Classification, Object};
 
     #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
     pub struct RequestBody {
@@ -164,6 +235,10 @@ mod put {
         pub alias: Option<String>,
         #[validate(length(min = 2, max = 4096))]
         pub remark: Option<String>,
+        pub asset_type: Option<AssetType>,
+        pub precision: Option<u32>,
+        pub identifier: Option<String>,
+        pub exchange: Option<String>,
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -188,8 +263,17 @@ mod put {
         let symbol = payload.symbol.unwrap_or(object.symbol);
         let alias = payload.alias.or(object.alias);
         let remark = payload.remark.or(object.remark);
+        let classification = Classification {
+            asset_type: payload
+                .asset_type
+                .unwrap_or(object.classification.asset_type),
+            precision: payload.precision.unwrap_or(object.classification.precision),
+            identifier: payload.identifier.or(object.classification.identifier),
+            exchange: payload.exchange.or(object.classification.exchange),
+        };
+        classification.validate()?;
 
-        Object::update_by_id_owner(&conn, id, owner, symbol, alias, remark)?;
+        Object::update_by_id_owner(&conn, id, owner, symbol, alias, remark, classification)?;
 
         Ok(Response::ok(ResponseBody { id }))
     }
@@ -203,7 +287,7 @@ mod delete {
 
     use crate::api::http::prelude::*;
     use crate::model::database::prelude::*;
-    use crate::model::finance::object::Object;
+    use crate::model::finance::object::{AssetType, Object};
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct ObjectItem {
@@ -212,15 +296,14 @@ mod delete {
         pub symbol: String,
         pub alias: Option<String>,
         pub remark: Option<String>,
+        pub asset_type: AssetType,
+        pub precision: u32,
+        pub identifier: Option<String>,
+        pub exchange: Option<String>,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
     }
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
-    pub struct ResponseBody {
-        pub id: i64,
-    }
-
     #[tracing::instrument()]
     pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<ObjectItem> {
         let owner = claim.subject();
@@ -237,6 +320,10 @@ mod delete {
             symbol: object.symbol,
             alias: object.alias,
             remark: object.remark,
+            asset_type: object.classification.asset_type,
+            precision: object.classification.precision,
+            identifier: object.classification.identifier,
+            exchange: object.classification.exchange,
             created_at: object.created_at,
             updated_at: object.updated_at,
         };
@@ -0,0 +1,132 @@
+use crate::api::http::state::StateInner;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::get;
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/performance";
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::Object;
+    use crate::model::finance::trade::Trade;
+    use crate::portfolio::performance::analyze;
+    use crate::portfolio::series::{Interval, Scope};
+
+    const MAX_POINTS: usize = 10000;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// The object every holding is valued in.
+        #[validate(range(min = 1))]
+        pub object_id: i64,
+        pub from: DateTime<Utc>,
+        pub to: Option<DateTime<Utc>>,
+        pub interval: Option<Interval>,
+        /// Limits the report to the holding of one object.
+        #[validate(range(min = 1))]
+        pub scope_object_id: Option<i64>,
+        /// Limits the report to the position built up by one trade.
+        #[validate(range(min = 1))]
+        pub scope_trade_id: Option<i64>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub object_id: i64,
+        pub from: DateTime<Utc>,
+        pub to: DateTime<Utc>,
+        pub interval: Interval,
+        pub start_value: Decimal,
+        pub end_value: Decimal,
+        pub net_flow: Decimal,
+        pub time_weighted_return: Option<Decimal>,
+        pub money_weighted_return: Option<Decimal>,
+        pub max_drawdown: Option<Decimal>,
+        pub volatility: Option<Decimal>,
+        pub annualized_volatility: Option<Decimal>,
+        pub unpriced: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let to = params.to.unwrap_or(Utc::now());
+        if params.from > to {
+            return Err(Response::bad_request("from must not be after to".into()));
+        }
+
+        let interval = params.interval.unwrap_or(Interval::Day);
+        let instants = interval.instants(params.from, to);
+        if instants.len() < 2 {
+            return Err(Response::bad_request(
+                "the window must span at least one interval".into(),
+            ));
+        }
+        if instants.len() > MAX_POINTS {
+            return Err(Response::bad_request(format!(
+                "too many points (maximum {})",
+                MAX_POINTS
+            )));
+        }
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object = Object::select_by_id_owner(&conn, params.object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", params.object_id)),
+        )?;
+
+        let scope = match (params.scope_object_id, params.scope_trade_id) {
+            (None, None) => Scope::Person,
+            (Some(id), None) => {
+                let object = Object::select_by_id_owner(&conn, id, owner)?
+                    .ok_or(Response::not_found(format!("object {} does not exist", id)))?;
+                Scope::Object(object.id())
+            }
+            (None, Some(id)) => {
+                let trade = Trade::select_by_id_owner(&conn, id, owner)?
+                    .ok_or(Response::not_found(format!("trade {} does not exist", id)))?;
+                Scope::Trade(trade.id())
+            }
+            (Some(_), Some(_)) => {
+                return Err(Response::bad_request(
+                    "scope_object_id and scope_trade_id are mutually exclusive".into(),
+                ))
+            }
+        };
+
+        let report = analyze(&conn, owner, object.id(), scope, &instants, interval)?
+            .ok_or(Response::bad_request("nothing to report".into()))?;
+
+        Ok(Response::ok(ResponseBody {
+            object_id: object.id(),
+            from: params.from,
+            to,
+            interval,
+            start_value: report.start_value,
+            end_value: report.end_value,
+            net_flow: report.net_flow,
+            time_weighted_return: report.time_weighted_return,
+            money_weighted_return: report.money_weighted_return,
+            max_drawdown: report.max_drawdown,
+            volatility: report.volatility,
+            annualized_volatility: report.annualized_volatility,
+            unpriced: report.unpriced,
+        }))
+    }
+}
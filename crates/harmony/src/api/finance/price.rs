@@ -0,0 +1,259 @@
+use crate::api::http::state::StateInner;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{delete, get, post};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(post::PATH, post(post::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .route(backfill::PATH, post(backfill::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/prices";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::price::Price;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PriceItem {
+        pub id: i64,
+        pub owner: i64,
+        pub base_object_id: i64,
+        pub quote_object_id: i64,
+        pub price: Quantity,
+        pub source: Option<String>,
+        pub occurrence_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub prices: Vec<PriceItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let total = Price::count_by_owner(&conn, owner)?;
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let prices = Price::select_by_owner(&conn, owner, limit, offset)?;
+
+        let prices = prices
+            .into_iter()
+            .map(|price| PriceItem {
+                id: price.id(),
+                owner: price.owner,
+                base_object_id: price.base_object_id,
+                quote_object_id: price.quote_object_id,
+                price: price.price,
+                source: price.source,
+                occurrence_at: price.occurrence_at,
+                created_at: price.created_at,
+                updated_at: price.updated_at,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { prices, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/prices";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::Object;
+    use crate::model::finance::price::Price;
+    use crate::model::finance::{positive, Quantity};
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        #[validate(range(min = 1))]
+        pub base_object_id: i64,
+        #[validate(range(min = 1))]
+        pub quote_object_id: i64,
+        #[serde(deserialize_with = "positive::deserialize")]
+        pub price: Quantity,
+        #[validate(length(min = 1, max = 1024))]
+        pub source: Option<String>,
+        pub occurrence_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let base_object = Object::select_by_id_owner(&conn, payload.base_object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", payload.base_object_id)),
+        )?;
+
+        let quote_object =
+            Object::select_by_id_owner(&conn, payload.quote_object_id, owner)?.ok_or(
+                Response::not_found(format!("object {} does not exist", payload.quote_object_id)),
+            )?;
+
+        let id = Price::upsert(
+            &conn,
+            owner,
+            base_object.id(),
+            quote_object.id(),
+            payload.price,
+            payload.source,
+            payload.occurrence_at.unwrap_or(Utc::now()),
+        )?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody { id, created_at }))
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/prices/:id";
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::price::Price;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<ResponseBody> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        if Price::delete_by_id_owner(&conn, id, owner)? == 0 {
+            return Err(Response::not_found(format!("price {} does not exist", id)));
+        }
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
+
+mod backfill {
+    pub const PATH: &str = "/finance/prices/backfill";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::consts::market::PROVIDER;
+    use crate::market::refresher::{self, Backfill};
+    use crate::model::database::prelude::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct RequestBody {
+        pub from: DateTime<Utc>,
+        pub to: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct FailureItem {
+        pub base_symbol: String,
+        pub quote_symbol: String,
+        pub message: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub stored: usize,
+        /// Pairs the provider failed on, skipped without ending the backfill.
+        pub failures: Vec<FailureItem>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        if PROVIDER.is_none() {
+            return Err(Response::bad_request(
+                "market data provider is not configured".into(),
+            ));
+        }
+
+        let to = payload.to.unwrap_or(Utc::now());
+        if payload.from > to {
+            return Err(Response::bad_request("from must not be after to".into()));
+        }
+
+        let owner = claim.subject();
+        let from = payload.from;
+
+        // The provider blocks on its requests, so it is kept off the runtime
+        let result = tokio::task::spawn_blocking(move || -> Result<Backfill, String> {
+            let provider = PROVIDER.as_deref().ok_or("provider is not configured")?;
+            let conn = connection().map_err(|e| e.to_string())?;
+
+            refresher::backfill(&conn, provider, owner, from, to).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| Response::internal_error(format!("backfill panicked: {}", e)))?;
+        let backfill = result.map_err(Response::bad_request)?;
+
+        let failures = backfill
+            .failures
+            .into_iter()
+            .map(|failure| FailureItem {
+                base_symbol: failure.base_symbol,
+                quote_symbol: failure.quote_symbol,
+                message: failure.message,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            stored: backfill.stored,
+            failures,
+        }))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::api::http::state::StateInner;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::get;
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/search";
+
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::search::{self, Hit};
+    use crate::model::finance::Entity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// Words found in the symbol, the alias or the remark, each as a
+        /// whole word or the start of one.
+        #[validate(length(min = 1, max = 256))]
+        pub text: String,
+        pub kind: Option<Entity>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct HitItem {
+        pub kind: Entity,
+        pub id: i64,
+        pub symbol: String,
+        pub alias: Option<String>,
+        pub remark: Option<String>,
+        pub snippet: String,
+        pub rank: f64,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub hits: Vec<HitItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let query = search::query(&params.text)
+            .ok_or(Response::bad_request("text must have a word".into()))?;
+
+        let total = Hit::count(&conn, owner, &query, params.kind)?;
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let hits = Hit::search(&conn, owner, &query, params.kind, limit, offset)?
+            .into_iter()
+            .map(|hit| HitItem {
+                kind: hit.kind,
+                id: hit.id,
+                symbol: hit.symbol,
+                alias: hit.alias,
+                remark: hit.remark,
+                snippet: hit.snippet,
+                rank: hit.rank,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { hits, total }))
+    }
+}
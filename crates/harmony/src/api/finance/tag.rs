@@ -0,0 +1,391 @@
+use rusqlite::Connection;
+
+use crate::api::http::prelude::Response;
+use crate::api::http::state::StateInner;
+use crate::model::finance::object::Object;
+use crate::model::finance::tag::{self, Tag};
+use crate::model::finance::trade::transaction::Transaction;
+use crate::model::finance::trade::Trade;
+use crate::model::finance::Entity;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{delete, get, post, put};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(post::PATH, post(post::handler))
+        .route(put::PATH, put(put::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .route(attach::PATH, put(attach::handler))
+        .route(detach::PATH, delete(detach::handler))
+        .with_state(state)
+}
+
+/// Checks that a color is written as `#rrggbb` and that no other tag of
+/// the owner goes by the name.
+fn check(
+    conn: &Connection,
+    owner: i64,
+    name: &str,
+    color: &str,
+    tag_id: Option<i64>,
+) -> Result<(), Response<()>> {
+    if !tag::is_color(color) {
+        return Err(Response::bad_request(format!(
+            "color {} is not written as #rrggbb",
+            color
+        )));
+    }
+
+    match Tag::select_by_owner_name(conn, owner, name)? {
+        Some(tag) if Some(tag.id()) != tag_id => Err(Response::conflict(format!(
+            "tag {} already goes by {}",
+            tag.id(),
+            name
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that an object, trade or transaction belongs to the owner.
+fn check_entity(
+    conn: &Connection,
+    owner: i64,
+    entity: Entity,
+    entity_id: i64,
+) -> Result<(), Response<()>> {
+    let exists = match entity {
+        Entity::Object => Object::select_by_id_owner(conn, entity_id, owner)?.is_some(),
+        Entity::Trade => Trade::select_by_id_owner(conn, entity_id, owner)?.is_some(),
+        Entity::Transaction => Transaction::select_by_id_owner(conn, entity_id, owner)?.is_some(),
+    };
+
+    if !exists {
+        return Err(Response::not_found(format!(
+            "{} {} does not exist",
+            entity, entity_id
+        )));
+    }
+
+    Ok(())
+}
+
+mod get {
+    pub const PATH: &str = "/finance/tags";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::tag::Tag;
+    use crate::model::finance::Entity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub id: Option<i64>,
+        /// Along with `entity_id`, only the tags attached to that object,
+        /// trade or transaction.
+        pub kind: Option<Entity>,
+        #[validate(range(min = 1))]
+        pub entity_id: Option<i64>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TagItem {
+        pub id: i64,
+        pub owner: i64,
+        pub name: String,
+        pub color: String,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub tags: Vec<TagItem>,
+        pub total: usize,
+    }
+
+    fn item(tag: Tag) -> TagItem {
+        TagItem {
+            id: tag.id(),
+            owner: tag.owner,
+            name: tag.name,
+            color: tag.color,
+            created_at: tag.created_at,
+            updated_at: tag.updated_at,
+        }
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        if let Some(id) = params.id {
+            let tag = Tag::select_by_id_owner(&conn, id, owner)?
+                .ok_or(Response::not_found(format!("tag {} does not exist", id)))?;
+
+            return Ok(Response::ok(ResponseBody {
+                tags: vec![item(tag)],
+                total: 1,
+            }));
+        }
+
+        match (params.kind, params.entity_id) {
+            (Some(kind), Some(entity_id)) => {
+                super::check_entity(&conn, owner, kind, entity_id)?;
+
+                let tags: Vec<TagItem> = Tag::select_by_entity(&conn, kind, entity_id)?
+                    .into_iter()
+                    .map(item)
+                    .collect();
+                let total = tags.len();
+
+                return Ok(Response::ok(ResponseBody { tags, total }));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(Response::bad_request(
+                    "kind and entity_id must be given together".into(),
+                ))
+            }
+        }
+
+        let total = Tag::count_by_owner(&conn, owner)?;
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let tags = Tag::select_by_owner(&conn, owner, limit, offset)?
+            .into_iter()
+            .map(item)
+            .collect();
+
+        Ok(Response::ok(ResponseBody { tags, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/tags";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::tag::Tag;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        #[validate(length(min = 1, max = 64))]
+        pub name: String,
+        /// As `#rrggbb`.
+        pub color: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        super::check(&conn, owner, &payload.name, &payload.color, None)?;
+
+        let id = Tag::insert(&conn, owner, payload.name, payload.color)?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody { id, created_at }))
+    }
+}
+
+mod put {
+    pub const PATH: &str = "/finance/tags/:id";
+
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::tag::Tag;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        #[validate(length(min = 1, max = 64))]
+        pub name: Option<String>,
+        /// As `#rrggbb`.
+        pub color: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(id): Path<i64>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let tag = Tag::select_by_id_owner(&conn, id, owner)?
+            .ok_or(Response::not_found(format!("tag {} does not exist", id)))?;
+
+        let name = payload.name.unwrap_or(tag.name);
+        let color = payload.color.unwrap_or(tag.color);
+
+        super::check(&conn, owner, &name, &color, Some(id))?;
+
+        Tag::update_by_id_owner(&conn, id, owner, name, color)?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/tags/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::tag::Tag;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TagItem {
+        pub id: i64,
+        pub owner: i64,
+        pub name: String,
+        pub color: String,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<TagItem> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let tag = Tag::select_by_id_owner(&conn, id, owner)?
+            .ok_or(Response::not_found(format!("tag {} does not exist", id)))?;
+
+        Tag::delete_by_id_owner(&conn, id, owner)?;
+
+        let tag_item = TagItem {
+            id: tag.id(),
+            owner: tag.owner,
+            name: tag.name,
+            color: tag.color,
+            created_at: tag.created_at,
+            updated_at: tag.updated_at,
+        };
+
+        Ok(Response::ok(tag_item))
+    }
+}
+
+mod attach {
+    pub const PATH: &str = "/finance/tags/:id/:kind/:entity_id";
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::tag::Tag;
+    use crate::model::finance::Entity;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub kind: Entity,
+        pub entity_id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((id, kind, entity_id)): Path<(i64, Entity, i64)>,
+    ) -> ResponseResult<ResponseBody> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let tag = Tag::select_by_id_owner(&conn, id, owner)?
+            .ok_or(Response::not_found(format!("tag {} does not exist", id)))?;
+        super::check_entity(&conn, owner, kind, entity_id)?;
+
+        Tag::attach(&conn, tag.id(), kind, entity_id)?;
+
+        Ok(Response::ok(ResponseBody {
+            id,
+            kind,
+            entity_id,
+        }))
+    }
+}
+
+mod detach {
+    pub const PATH: &str = "/finance/tags/:id/:kind/:entity_id";
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::tag::Tag;
+    use crate::model::finance::Entity;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub kind: Entity,
+        pub entity_id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((id, kind, entity_id)): Path<(i64, Entity, i64)>,
+    ) -> ResponseResult<ResponseBody> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let tag = Tag::select_by_id_owner(&conn, id, owner)?
+            .ok_or(Response::not_found(format!("tag {} does not exist", id)))?;
+
+        Tag::detach(&conn, tag.id(), kind, entity_id)?;
+
+        Ok(Response::ok(ResponseBody {
+            id,
+            kind,
+            entity_id,
+        }))
+    }
+}
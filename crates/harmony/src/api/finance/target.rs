@@ -0,0 +1,571 @@
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+
+use crate::api::http::prelude::Response;
+use crate::api::http::state::StateInner;
+use crate::model::finance::target::Target;
+use crate::model::finance::Quantity;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{delete, get, post, put};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(post::PATH, post(post::handler))
+        .route(put::PATH, put(put::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .route(rebalance::PATH, get(rebalance::handler))
+        .with_state(state)
+}
+
+/// Checks that the weight and tolerance are shares and that the weights of
+/// all targets of the owner, other than target `id`, leave room for this one.
+fn check(
+    conn: &Connection,
+    owner: i64,
+    id: Option<i64>,
+    weight: &Quantity,
+    tolerance: &Quantity,
+) -> Result<(), Response<()>> {
+    for (name, value) in [("weight", weight), ("tolerance", tolerance)] {
+        let value = value.value();
+        if value.is_sign_negative() || value > Decimal::ONE {
+            return Err(Response::bad_request(format!(
+                "{} must be between 0 and 1",
+                name
+            )));
+        }
+    }
+
+    let others: Decimal = Target::select_all_by_owner(conn, owner)?
+        .into_iter()
+        .filter(|target| Some(target.id()) != id)
+        .map(|target| target.weight.value())
+        .sum();
+    if others + weight.value() > Decimal::ONE {
+        return Err(Response::bad_request(format!(
+            "weights would add up to {}, more than 1",
+            others + weight.value()
+        )));
+    }
+
+    Ok(())
+}
+
+mod get {
+    pub const PATH: &str = "/finance/targets";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::AssetType;
+    use crate::model::finance::target::Target;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub id: Option<i64>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TargetItem {
+        pub id: i64,
+        pub owner: i64,
+        pub object_id: Option<i64>,
+        pub asset_type: Option<AssetType>,
+        pub weight: Quantity,
+        pub tolerance: Quantity,
+        pub remark: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub targets: Vec<TargetItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let total = Target::count_by_owner(&conn, owner)?;
+
+        if let Some(id) = params.id {
+            let target = Target::select_by_id_owner(&conn, id, owner)?
+                .ok_or(Response::not_found(format!("target {} does not exist", id)))?;
+
+            let target_item = TargetItem {
+                id: target.id(),
+                owner: target.owner,
+                object_id: target.object_id,
+                asset_type: target.asset_type,
+                weight: target.weight,
+                tolerance: target.tolerance,
+                remark: target.remark,
+                created_at: target.created_at,
+                updated_at: target.updated_at,
+            };
+
+            return Ok(Response::ok(ResponseBody {
+                targets: vec![target_item],
+                total,
+            }));
+        }
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let targets = Target::select_by_owner(&conn, owner, limit, offset)?;
+
+        let targets = targets
+            .into_iter()
+            .map(|target| TargetItem {
+                id: target.id(),
+                owner: target.owner,
+                object_id: target.object_id,
+                asset_type: target.asset_type,
+                weight: target.weight,
+                tolerance: target.tolerance,
+                remark: target.remark,
+                created_at: target.created_at,
+                updated_at: target.updated_at,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { targets, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/targets";
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::{AssetType, Object};
+    use crate::model::finance::target::Target;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        /// The object aimed at, exclusive with `asset_type`.
+        #[validate(range(min = 1))]
+        pub object_id: Option<i64>,
+        /// The asset type aimed at, covering its objects without a target.
+        pub asset_type: Option<AssetType>,
+        pub weight: Quantity,
+        /// 0.05 when omitted.
+        pub tolerance: Option<Quantity>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object_id = match (payload.object_id, payload.asset_type) {
+            (Some(object_id), None) => {
+                let object = Object::select_by_id_owner(&conn, object_id, owner)?.ok_or(
+                    Response::not_found(format!("object {} does not exist", object_id)),
+                )?;
+
+                Some(object.id())
+            }
+            (None, Some(_)) => None,
+            _ => {
+                return Err(Response::bad_request(
+                    "exactly one of object_id and asset_type is required".into(),
+                ))
+            }
+        };
+
+        let exists = Target::select_all_by_owner(&conn, owner)?
+            .into_iter()
+            .any(|target| {
+                (object_id.is_some() && target.object_id == object_id)
+                    || (payload.asset_type.is_some() && target.asset_type == payload.asset_type)
+            });
+        if exists {
+            return Err(Response::conflict("target already exists".into()));
+        }
+
+        let tolerance = payload.tolerance.unwrap_or(Decimal::new(5, 2).into());
+        super::check(&conn, owner, None, &payload.weight, &tolerance)?;
+
+        let id = Target::insert(
+            &conn,
+            owner,
+            object_id,
+            payload.asset_type,
+            payload.weight,
+            tolerance,
+            payload.remark,
+        )?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody { id, created_at }))
+    }
+}
+
+mod put {
+    pub const PATH: &str = "/finance/targets/:id";
+
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::target::Target;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        pub weight: Option<Quantity>,
+        pub tolerance: Option<Quantity>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(id): Path<i64>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let target = Target::select_by_id_owner(&conn, id, owner)?
+            .ok_or(Response::not_found(format!("target {} does not exist", id)))?;
+
+        let weight = payload.weight.unwrap_or(target.weight);
+        let tolerance = payload.tolerance.unwrap_or(target.tolerance);
+        super::check(&conn, owner, Some(id), &weight, &tolerance)?;
+        let remark = payload.remark.or(target.remark);
+
+        Target::update_by_id_owner(&conn, id, owner, weight, tolerance, remark)?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/targets/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::AssetType;
+    use crate::model::finance::target::Target;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TargetItem {
+        pub id: i64,
+        pub owner: i64,
+        pub object_id: Option<i64>,
+        pub asset_type: Option<AssetType>,
+        pub weight: Quantity,
+        pub tolerance: Quantity,
+        pub remark: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<TargetItem> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let target = Target::select_by_id_owner(&conn, id, owner)?
+            .ok_or(Response::not_found(format!("target {} does not exist", id)))?;
+
+        Target::delete_by_id_owner(&conn, id, owner)?;
+
+        let target_item = TargetItem {
+            id: target.id(),
+            owner: target.owner,
+            object_id: target.object_id,
+            asset_type: target.asset_type,
+            weight: target.weight,
+            tolerance: target.tolerance,
+            remark: target.remark,
+            created_at: target.created_at,
+            updated_at: target.updated_at,
+        };
+
+        Ok(Response::ok(target_item))
+    }
+}
+
+mod rebalance {
+    pub const PATH: &str = "/finance/targets/rebalance";
+
+    use std::collections::HashMap;
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::{AssetType, Classification, Object};
+    use crate::model::finance::price::Price;
+    use crate::model::finance::target::Target;
+    use crate::model::finance::trade::Trade;
+    use crate::model::finance::{positive, Quantity};
+    use crate::portfolio::balance;
+    use crate::portfolio::rebalance::{rebalance, Band, Mode, Pair, Sleeve};
+    use crate::portfolio::valuation::{valuate, PriceGraph};
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// The object every holding is valued in.
+        #[validate(range(min = 1))]
+        pub object_id: i64,
+        pub at: Option<DateTime<Utc>>,
+        /// Counts transactions that are executed but not settled yet, true
+        /// when omitted.
+        pub pending: Option<bool>,
+        /// Only directs new money of `cash_amount` in this object, never
+        /// selling anything.
+        #[validate(range(min = 1))]
+        pub cash_object_id: Option<i64>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub cash_amount: Option<Quantity>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct DriftItem {
+        /// The object of the target, `None` with `asset_type` for holdings
+        /// no target covers.
+        pub object_id: Option<i64>,
+        pub asset_type: Option<AssetType>,
+        pub value: Decimal,
+        pub weight: Option<Decimal>,
+        pub target_weight: Decimal,
+        pub target_value: Option<Decimal>,
+        pub drift: Option<Decimal>,
+        pub is_outside: bool,
+        pub object_ids: Vec<i64>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct OrderItem {
+        pub trade_id: i64,
+        pub is_base_to_quote: bool,
+        pub quantity: Decimal,
+        pub price: Decimal,
+        pub value: Decimal,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResidualItem {
+        pub object_id: Option<i64>,
+        pub asset_type: Option<AssetType>,
+        pub value: Decimal,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub object_id: i64,
+        pub at: DateTime<Utc>,
+        pub total: Decimal,
+        pub drifts: Vec<DriftItem>,
+        pub orders: Vec<OrderItem>,
+        pub residuals: Vec<ResidualItem>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+        let at = params.at.unwrap_or(Utc::now());
+
+        let object = Object::select_by_id_owner(&conn, params.object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", params.object_id)),
+        )?;
+
+        let targets = Target::select_all_by_owner(&conn, owner)?;
+        let weights: Decimal = targets.iter().map(|target| target.weight.value()).sum();
+        if weights != Decimal::ONE {
+            return Err(Response::bad_request(format!(
+                "weights add up to {}, not 1",
+                weights
+            )));
+        }
+
+        let bands: Vec<Band> = targets
+            .into_iter()
+            .filter_map(|target| {
+                let sleeve = match (target.object_id, target.asset_type) {
+                    (Some(object_id), _) => Sleeve::Object(object_id),
+                    (None, Some(asset_type)) => Sleeve::AssetType(asset_type),
+                    (None, None) => return None,
+                };
+
+                Some(Band {
+                    sleeve,
+                    weight: target.weight.value(),
+                    tolerance: target.tolerance.value(),
+                })
+            })
+            .collect();
+
+        let movements = balance::load(&conn, owner, at, params.pending.unwrap_or(true))?;
+        let balances = balance::balances(&movements);
+        let graph = PriceGraph::from_prices(&Price::select_latest_by_owner(&conn, owner, at)?);
+        let valuation = valuate(&balances, &graph, object.id());
+
+        let mode = match (params.cash_object_id, params.cash_amount) {
+            (None, None) => Mode::Full,
+            (Some(cash_object_id), Some(cash_amount)) => {
+                let cash = Object::select_by_id_owner(&conn, cash_object_id, owner)?.ok_or(
+                    Response::not_found(format!("object {} does not exist", cash_object_id)),
+                )?;
+                let conversion =
+                    graph
+                        .conversion(cash.id(), object.id())
+                        .ok_or(Response::bad_request(format!(
+                            "object {} has no price",
+                            cash.id()
+                        )))?;
+
+                let value = cash_amount.value().checked_mul(conversion.rate).ok_or(
+                    Response::bad_request(format!("cash_amount {} is too large", cash_amount)),
+                )?;
+
+                Mode::Cash {
+                    object_id: cash.id(),
+                    value,
+                }
+            }
+            _ => {
+                return Err(Response::bad_request(
+                    "cash_object_id and cash_amount go together".into(),
+                ))
+            }
+        };
+
+        let classifications: HashMap<i64, Classification> =
+            Object::select_all_by_owner(&conn, owner)?
+                .into_iter()
+                .map(|object| (object.id(), object.classification))
+                .collect();
+        let pairs: Vec<Pair> = Trade::select_all_by_owner(&conn, owner)?
+            .into_iter()
+            .map(|trade| Pair {
+                trade_id: trade.id(),
+                base_object_id: trade.base_object_id,
+                quote_object_id: trade.quote_object_id,
+            })
+            .collect();
+
+        let result = rebalance(&valuation, &bands, &classifications, &pairs, &graph, mode);
+
+        let split = |sleeve: Sleeve| match sleeve {
+            Sleeve::Object(object_id) => (Some(object_id), None),
+            Sleeve::AssetType(asset_type) => (None, Some(asset_type)),
+            Sleeve::Untargeted => (None, None),
+        };
+
+        let drifts = result
+            .drifts
+            .into_iter()
+            .map(|drift| {
+                let (object_id, asset_type) = split(drift.sleeve);
+
+                DriftItem {
+                    object_id,
+                    asset_type,
+                    value: drift.value,
+                    weight: drift.weight,
+                    target_weight: drift.target_weight,
+                    target_value: drift.target_value,
+                    drift: drift.drift,
+                    is_outside: drift.is_outside,
+                    object_ids: drift.object_ids,
+                }
+            })
+            .collect();
+
+        let orders = result
+            .orders
+            .into_iter()
+            .map(|order| OrderItem {
+                trade_id: order.trade_id,
+                is_base_to_quote: order.is_base_to_quote,
+                quantity: order.quantity,
+                price: order.price,
+                value: order.value,
+            })
+            .collect();
+
+        let residuals = result
+            .residuals
+            .into_iter()
+            .map(|residual| ResidualItem {
+                object_id: residual.object_id,
+                asset_type: split(residual.sleeve).1,
+                value: residual.value,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            object_id: object.id(),
+            at,
+            total: valuation.total,
+            drifts,
+            orders,
+            residuals,
+        }))
+    }
+}
@@ -1,6 +1,15 @@
+mod plan;
 mod transaction;
 
+use std::error::Error;
+
+use rusqlite::Connection;
+
+use crate::api::http::prelude::Response;
 use crate::api::http::state::StateInner;
+use crate::model::database::is_unique_violation;
+use crate::model::finance::object::Object;
+use crate::model::finance::trade::Trade;
 
 pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
     use axum::routing::{delete, get, post, put};
@@ -8,15 +17,73 @@ pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
     let mut router = axum::Router::new()
         .route(get::PATH, get(get::handler))
         .route(post::PATH, post(post::handler))
+        .route(pair::PATH, post(pair::handler))
         .route(put::PATH, put(put::handler))
         .route(delete::PATH, delete(delete::handler))
         .with_state(state.clone());
 
+    router = router.merge(plan::router(state.clone()));
     router = router.merge(transaction::router(state));
 
     router
 }
 
+/// Checks that both sides of a pair are distinct objects of the owner.
+fn check_objects(
+    conn: &Connection,
+    owner: i64,
+    base_object_id: i64,
+    quote_object_id: i64,
+) -> Result<(), Response<()>> {
+    if base_object_id == quote_object_id {
+        return Err(Response::bad_request(
+            "base and quote objects must differ".into(),
+        ));
+    }
+
+    for object_id in [base_object_id, quote_object_id] {
+        Object::select_by_id_owner(conn, object_id, owner)?.ok_or(Response::not_found(format!(
+            "object {} does not exist",
+            object_id
+        )))?;
+    }
+
+    Ok(())
+}
+
+/// When pairs are unique, rejects a pair already used by another trade.
+/// The database refuses it too, for a trade that takes the pair meanwhile.
+fn check_unique(
+    conn: &Connection,
+    owner: i64,
+    base_object_id: i64,
+    quote_object_id: i64,
+    trade_id: Option<i64>,
+    is_unique: bool,
+) -> Result<(), Response<()>> {
+    if !is_unique {
+        return Ok(());
+    }
+
+    match Trade::select_by_owner_pair(conn, owner, base_object_id, quote_object_id)? {
+        Some(trade) if Some(trade.id()) != trade_id => Err(Response::conflict(format!(
+            "trade {} already exists for this pair",
+            trade.id()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Turns the database refusing a pair taken meanwhile into a conflict.
+fn pair_taken(error: Box<dyn Error>) -> Response<()> {
+    match error.downcast_ref::<rusqlite::Error>() {
+        Some(error) if is_unique_violation(error) => {
+            Response::conflict("another trade already exists for this pair".into())
+        }
+        _ => error.into(),
+    }
+}
+
 mod get {
     pub const PATH: &str = "/finance/trades";
 
@@ -27,12 +94,29 @@ mod get {
     use crate::api::http::paginate;
     use crate::api::http::prelude::*;
     use crate::model::database::prelude::*;
-    use crate::model::finance::trade::Trade;
+    use crate::model::database::Order;
+    use crate::model::finance::trade::{Filter, Sort, Trade};
 
     #[derive(Debug, Validate, Deserialize)]
     pub struct Params {
         pub id: Option<i64>,
         #[validate(range(min = 1))]
+        pub base_object_id: Option<i64>,
+        #[validate(range(min = 1))]
+        pub quote_object_id: Option<i64>,
+        /// Trades created at or after.
+        pub from: Option<DateTime<Utc>>,
+        /// Trades created at or before.
+        pub to: Option<DateTime<Utc>>,
+        /// Found in the alias or the remark, ignoring case.
+        #[validate(length(min = 1, max = 256))]
+        pub text: Option<String>,
+        /// Trades the tag is attached to.
+        #[validate(range(min = 1))]
+        pub tag_id: Option<i64>,
+        pub sort: Option<Sort>,
+        pub order: Option<Order>,
+        #[validate(range(min = 1))]
         pub page: Option<usize>,
         #[validate(range(min = 1, max = 1024))]
         pub page_size: Option<usize>,
@@ -66,7 +150,22 @@ mod get {
         let owner = claim.subject();
         let conn = connection()?;
 
-        let total = Trade::count_by_owner(&conn, owner)?;
+        if let (Some(from), Some(to)) = (params.from, params.to) {
+            if from > to {
+                return Err(Response::bad_request("from must not be after to".into()));
+            }
+        }
+
+        let filter = Filter {
+            base_object_id: params.base_object_id,
+            quote_object_id: params.quote_object_id,
+            from: params.from,
+            to: params.to,
+            text: params.text,
+            tag_id: params.tag_id,
+        };
+
+        let total = Trade::count_by_owner(&conn, owner, &filter)?;
 
         if let Some(id) = params.id {
             let trade = Trade::select_by_id_owner(&conn, id, owner)?
@@ -91,7 +190,9 @@ mod get {
 
         let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
 
-        let trades = Trade::select_by_owner(&conn, owner, limit, offset)?;
+        let sort = params.sort.unwrap_or_default();
+        let order = params.order.unwrap_or_default();
+        let trades = Trade::select_by_owner(&conn, owner, &filter, sort, order, limit, offset)?;
 
         let trades = trades
             .into_iter()
@@ -120,7 +221,6 @@ mod post {
 
     use crate::api::http::prelude::*;
     use crate::model::database::prelude::*;
-    use crate::model::finance::object::Object;
     use crate::model::finance::trade::Trade;
 
     #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
@@ -151,23 +251,30 @@ mod post {
         let owner = claim.subject();
         let conn = connection()?;
 
-        let base_object = Object::select_by_id_owner(&conn, payload.base_object_id, owner)?.ok_or(
-            Response::not_found(format!("object {} does not exist", payload.base_object_id)),
+        super::check_objects(
+            &conn,
+            owner,
+            payload.base_object_id,
+            payload.quote_object_id,
+        )?;
+        super::check_unique(
+            &conn,
+            owner,
+            payload.base_object_id,
+            payload.quote_object_id,
+            None,
+            *crate::consts::finance::UNIQUE_TRADE_PAIR,
         )?;
-
-        let quote_object =
-            Object::select_by_id_owner(&conn, payload.quote_object_id, owner)?.ok_or(
-                Response::not_found(format!("object {} does not exist", payload.quote_object_id)),
-            )?;
 
         let id = Trade::insert(
             &conn,
             owner,
-            base_object.id(),
-            quote_object.id(),
+            payload.base_object_id,
+            payload.quote_object_id,
             payload.alias,
             payload.remark,
-        )?;
+        )
+        .map_err(|error| super::pair_taken(error.into()))?;
 
         let created_at = Utc::now();
 
@@ -175,6 +282,67 @@ mod post {
     }
 }
 
+mod pair {
+    pub const PATH: &str = "/finance/trades/pair";
+
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::Trade;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        #[validate(range(min = 1))]
+        pub base_object_id: i64,
+        #[validate(range(min = 1))]
+        pub quote_object_id: i64,
+        /// Only used when the trade has to be created.
+        #[validate(length(min = 1, max = 4096))]
+        pub alias: Option<String>,
+        /// Only used when the trade has to be created.
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub created: bool,
+    }
+
+    /// Finds the trade of the caller for a pair, creating it if there is none.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        super::check_objects(
+            &conn,
+            owner,
+            payload.base_object_id,
+            payload.quote_object_id,
+        )?;
+
+        let (id, created) = Trade::insert_or_select_by_owner_pair(
+            &conn,
+            owner,
+            payload.base_object_id,
+            payload.quote_object_id,
+            payload.alias,
+            payload.remark,
+        )?;
+
+        Ok(Response::ok(ResponseBody { id, created }))
+    }
+}
+
 mod put {
     pub const PATH: &str = "/finance/trades/:id";
 
@@ -182,8 +350,10 @@ mod put {
     use validator::Validate;
 
     use crate::api::http::prelude::*;
+    use crate::model::database::atomically;
     use crate::model::database::prelude::*;
     use crate::model::finance::trade::Trade;
+    use crate::portfolio::ledger;
 
     #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
     pub struct RequestBody {
@@ -218,19 +388,42 @@ mod put {
 
         let base_object_id = payload.base_object_id.unwrap_or(trade.base_object_id);
         let quote_object_id = payload.quote_object_id.unwrap_or(trade.quote_object_id);
-        let alias = payload.alias.or(trade.alias);
-        let remark = payload.remark.or(trade.remark);
 
-        Trade::update_by_id_owner(
+        super::check_objects(&conn, owner, base_object_id, quote_object_id)?;
+        super::check_unique(
             &conn,
-            id,
             owner,
             base_object_id,
             quote_object_id,
-            alias,
-            remark,
+            Some(id),
+            *crate::consts::finance::UNIQUE_TRADE_PAIR,
         )?;
 
+        let alias = payload.alias.or(trade.alias);
+        let remark = payload.remark.or(trade.remark);
+
+        atomically(&conn, || {
+            Trade::update_by_id_owner(
+                &conn,
+                id,
+                owner,
+                base_object_id,
+                quote_object_id,
+                alias,
+                remark,
+            )?;
+
+            // Entries name the trade's objects, so they follow a changed pair
+            if (base_object_id, quote_object_id) != (trade.base_object_id, trade.quote_object_id) {
+                let trade = Trade::select_by_id_owner(&conn, id, owner)?
+                    .ok_or(format!("trade {} does not exist", id))?;
+                ledger::record_trade(&conn, owner, &trade)?;
+            }
+
+            Ok(())
+        })
+        .map_err(super::pair_taken)?;
+
         Ok(Response::ok(ResponseBody { id }))
     }
 }
@@ -242,7 +435,10 @@ mod delete {
     use serde::{Deserialize, Serialize};
 
     use crate::api::http::prelude::*;
+    use crate::attachment;
+    use crate::consts::attachment::STORE;
     use crate::model::database::prelude::*;
+    use crate::model::finance::attachment::Attachment;
     use crate::model::finance::trade::Trade;
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -257,11 +453,6 @@ mod delete {
         pub updated_at: DateTime<Utc>,
     }
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
-    pub struct ResponseBody {
-        pub id: i64,
-    }
-
     #[tracing::instrument()]
     pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<TradeItem> {
         let owner = claim.subject();
@@ -270,7 +461,10 @@ mod delete {
         let trade = Trade::select_by_id_owner(&conn, id, owner)?
             .ok_or(Response::not_found(format!("trade {} does not exist", id)))?;
 
+        let attachments = Attachment::select_by_trade_id(&conn, id)?;
+
         Trade::delete_by_id_owner(&conn, id, owner)?;
+        attachment::release(&conn, &STORE, &attachments)?;
 
         let trade_item = TradeItem {
             id: trade.id(),
@@ -286,3 +480,64 @@ mod delete {
         Ok(Response::ok(trade_item))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::model::finance::trade::Trade;
+    use crate::model::fixture;
+
+    use super::{check_objects, check_unique, pair_taken};
+
+    #[test]
+    fn test_check_pair() {
+        let conn = fixture::connection();
+        let owner = fixture::person(&conn, "test_user");
+        let other = fixture::person(&conn, "other_user");
+        let (btc, usd, eur) = (
+            fixture::object(&conn, owner, "BTC"),
+            fixture::object(&conn, owner, "USD"),
+            fixture::object(&conn, other, "EUR"),
+        );
+
+        let code =
+            |result: Result<(), _>| result.err().map(|error: super::Response<()>| error.code);
+        assert_eq!(code(check_objects(&conn, owner, btc, btc)), Some(400));
+        assert_eq!(code(check_objects(&conn, owner, btc, eur)), Some(404));
+        assert_eq!(code(check_objects(&conn, owner, btc, i64::MAX)), Some(404));
+        assert_eq!(code(check_objects(&conn, owner, btc, usd)), None);
+
+        let trade = fixture::trade(&conn, owner, btc, usd);
+        assert_eq!(
+            code(check_unique(&conn, owner, btc, usd, None, false)),
+            None
+        );
+        assert_eq!(
+            code(check_unique(&conn, owner, btc, usd, None, true)),
+            Some(409)
+        );
+        assert_eq!(
+            code(check_unique(&conn, owner, btc, usd, Some(trade), true)),
+            None
+        );
+        assert_eq!(code(check_unique(&conn, owner, usd, btc, None, true)), None);
+
+        // The pair is found rather than created twice
+        assert_eq!(
+            Trade::insert_or_select_by_owner_pair(&conn, owner, btc, usd, None, None).unwrap(),
+            (trade, false)
+        );
+        let (reverse, created) =
+            Trade::insert_or_select_by_owner_pair(&conn, owner, usd, btc, None, None).unwrap();
+        assert!(created && reverse != trade);
+
+        // Unique pairs are refused by the database, and cannot be turned on
+        // while an owner has several trades of a pair
+        Trade::set_unique_pair(&conn, true).unwrap();
+        let error = Trade::insert(&conn, owner, btc, usd, None, None).unwrap_err();
+        assert_eq!(pair_taken(error.into()).code, 409);
+
+        Trade::set_unique_pair(&conn, false).unwrap();
+        Trade::insert(&conn, owner, btc, usd, None, None).unwrap();
+        assert!(Trade::set_unique_pair(&conn, true).is_err());
+    }
+}
@@ -0,0 +1,663 @@
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+use crate::api::http::prelude::Response;
+use crate::api::http::state::StateInner;
+use crate::common::cron::Cron;
+use crate::model::finance::account::Account;
+use crate::portfolio::plan::first_run;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{delete, get, post, put};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(post::PATH, post(post::handler))
+        .route(put::PATH, put(put::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .route(pause::PATH, post(pause::handler))
+        .route(resume::PATH, post(resume::handler))
+        .route(skip::PATH, post(skip::handler))
+        .route(runs::PATH, get(runs::handler))
+        .with_state(state)
+}
+
+/// Checks that the account belongs to the owner, returning that account.
+fn check(conn: &Connection, owner: i64, account_id: i64) -> Result<i64, Response<()>> {
+    let account = Account::select_by_id_owner(conn, account_id, owner)?.ok_or(
+        Response::not_found(format!("account {} does not exist", account_id)),
+    )?;
+
+    Ok(account.id())
+}
+
+/// The first run of a schedule from `from` on, refusing a schedule that
+/// never runs before `end_at`.
+fn next_run(
+    schedule: &Cron,
+    from: DateTime<Utc>,
+    end_at: Option<DateTime<Utc>>,
+) -> Result<DateTime<Utc>, Response<()>> {
+    first_run(schedule, from, end_at).ok_or(Response::bad_request(format!(
+        "schedule {} has no run left before the plan ends",
+        schedule
+    )))
+}
+
+mod get {
+    pub const PATH: &str = "/finance/trades/:trade_id/plans";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::common::cron::Cron;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::plan::Plan;
+    use crate::model::finance::trade::Trade;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        pub id: Option<i64>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PlanItem {
+        pub id: i64,
+        pub trade_id: i64,
+        pub account_id: i64,
+        pub schedule: Cron,
+        pub amount: Quantity,
+        pub is_base_amount: bool,
+        pub is_base_to_quote: bool,
+        pub needs_confirmation: bool,
+        pub is_paused: bool,
+        pub start_at: DateTime<Utc>,
+        pub end_at: Option<DateTime<Utc>>,
+        pub next_run_at: Option<DateTime<Utc>>,
+        pub remark: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub plans: Vec<PlanItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(trade_id): Path<i64>,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let total = Plan::count_by_trade_id(&conn, trade.id())?;
+
+        if let Some(id) = params.id {
+            let plan = Plan::select_by_id_trade_id(&conn, id, trade.id())?
+                .ok_or(Response::not_found(format!("plan {} does not exist", id)))?;
+
+            let plan_item = PlanItem {
+                id: plan.id(),
+                trade_id: plan.trade_id,
+                account_id: plan.account_id,
+                schedule: plan.schedule,
+                amount: plan.amount,
+                is_base_amount: plan.is_base_amount,
+                is_base_to_quote: plan.is_base_to_quote,
+                needs_confirmation: plan.needs_confirmation,
+                is_paused: plan.is_paused,
+                start_at: plan.start_at,
+                end_at: plan.end_at,
+                next_run_at: plan.next_run_at,
+                remark: plan.remark,
+                created_at: plan.created_at,
+                updated_at: plan.updated_at,
+            };
+
+            return Ok(Response::ok(ResponseBody {
+                plans: vec![plan_item],
+                total,
+            }));
+        }
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let plans = Plan::select_by_trade_id(&conn, trade.id(), limit, offset)?;
+
+        let plans = plans
+            .into_iter()
+            .map(|plan| PlanItem {
+                id: plan.id(),
+                trade_id: plan.trade_id,
+                account_id: plan.account_id,
+                schedule: plan.schedule,
+                amount: plan.amount,
+                is_base_amount: plan.is_base_amount,
+                is_base_to_quote: plan.is_base_to_quote,
+                needs_confirmation: plan.needs_confirmation,
+                is_paused: plan.is_paused,
+                start_at: plan.start_at,
+                end_at: plan.end_at,
+                next_run_at: plan.next_run_at,
+                remark: plan.remark,
+                created_at: plan.created_at,
+                updated_at: plan.updated_at,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { plans, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/trades/:trade_id/plans";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::common::cron::Cron;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::plan::Plan;
+    use crate::model::finance::trade::Trade;
+    use crate::model::finance::{positive, Quantity};
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        #[validate(range(min = 1))]
+        pub account_id: i64,
+        /// A five-field cron expression in UTC, such as `0 9 * * 1`.
+        pub schedule: Cron,
+        /// Given up per run, in the quote object when buying and in the base
+        /// object when selling.
+        #[serde(deserialize_with = "positive::deserialize")]
+        pub amount: Quantity,
+        /// Takes `amount` in the base object when buying too.
+        pub is_base_amount: Option<bool>,
+        pub is_base_to_quote: bool,
+        /// Creates planned transactions to be confirmed instead of settled
+        /// ones.
+        pub needs_confirmation: Option<bool>,
+        /// Now when omitted. Runs since a past start are caught up on a few
+        /// dozen at a time.
+        pub start_at: Option<DateTime<Utc>>,
+        pub end_at: Option<DateTime<Utc>>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub next_run_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path(trade_id): Path<i64>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let account_id = super::check(&conn, owner, payload.account_id)?;
+        let start_at = payload.start_at.unwrap_or(Utc::now());
+        let next_run_at = super::next_run(&payload.schedule, start_at, payload.end_at)?;
+
+        let id = Plan::insert(
+            &conn,
+            trade.id(),
+            account_id,
+            &payload.schedule,
+            payload.amount,
+            payload.is_base_amount.unwrap_or(false),
+            payload.is_base_to_quote,
+            payload.needs_confirmation.unwrap_or(false),
+            start_at,
+            payload.end_at,
+            Some(next_run_at),
+            payload.remark,
+        )?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody {
+            id,
+            next_run_at,
+            created_at,
+        }))
+    }
+}
+
+mod put {
+    pub const PATH: &str = "/finance/trades/:trade_id/plans/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::common::cron::Cron;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::plan::Plan;
+    use crate::model::finance::trade::Trade;
+    use crate::model::finance::{positive, Quantity};
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        #[validate(range(min = 1))]
+        pub account_id: Option<i64>,
+        pub schedule: Option<Cron>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub amount: Option<Quantity>,
+        pub is_base_amount: Option<bool>,
+        pub is_base_to_quote: Option<bool>,
+        pub needs_confirmation: Option<bool>,
+        pub start_at: Option<DateTime<Utc>>,
+        pub end_at: Option<DateTime<Utc>>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub next_run_at: Option<DateTime<Utc>>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((trade_id, id)): Path<(i64, i64)>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let plan = Plan::select_by_id_trade_id(&conn, id, trade.id())?
+            .ok_or(Response::not_found(format!("plan {} does not exist", id)))?;
+
+        let amount = payload.amount.unwrap_or(plan.amount);
+        let account_id = super::check(&conn, owner, payload.account_id.unwrap_or(plan.account_id))?;
+
+        // A new timing starts over from now, runs already due are left alone
+        let reschedules =
+            payload.schedule.is_some() || payload.start_at.is_some() || payload.end_at.is_some();
+        let schedule = payload.schedule.unwrap_or(plan.schedule);
+        let start_at = payload.start_at.unwrap_or(plan.start_at);
+        let end_at = payload.end_at.or(plan.end_at);
+        let next_run_at = if reschedules {
+            Some(super::next_run(
+                &schedule,
+                start_at.max(Utc::now()),
+                end_at,
+            )?)
+        } else {
+            plan.next_run_at
+        };
+
+        Plan::update_by_id_trade_id(
+            &conn,
+            id,
+            trade.id(),
+            account_id,
+            &schedule,
+            amount,
+            payload.is_base_amount.unwrap_or(plan.is_base_amount),
+            payload.is_base_to_quote.unwrap_or(plan.is_base_to_quote),
+            payload
+                .needs_confirmation
+                .unwrap_or(plan.needs_confirmation),
+            start_at,
+            end_at,
+            next_run_at,
+            payload.remark.or(plan.remark),
+        )?;
+
+        Ok(Response::ok(ResponseBody { id, next_run_at }))
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/trades/:trade_id/plans/:id";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::common::cron::Cron;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::plan::Plan;
+    use crate::model::finance::trade::Trade;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PlanItem {
+        pub id: i64,
+        pub trade_id: i64,
+        pub account_id: i64,
+        pub schedule: Cron,
+        pub amount: Quantity,
+        pub is_base_amount: bool,
+        pub is_base_to_quote: bool,
+        pub needs_confirmation: bool,
+        pub is_paused: bool,
+        pub start_at: DateTime<Utc>,
+        pub end_at: Option<DateTime<Utc>>,
+        pub next_run_at: Option<DateTime<Utc>>,
+        pub remark: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    /// Transactions the plan already created are kept.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((trade_id, id)): Path<(i64, i64)>,
+    ) -> ResponseResult<PlanItem> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let plan = Plan::select_by_id_trade_id(&conn, id, trade.id())?
+            .ok_or(Response::not_found(format!("plan {} does not exist", id)))?;
+
+        Plan::delete_by_id_trade_id(&conn, id, trade.id())?;
+
+        let plan_item = PlanItem {
+            id: plan.id(),
+            trade_id: plan.trade_id,
+            account_id: plan.account_id,
+            schedule: plan.schedule,
+            amount: plan.amount,
+            is_base_amount: plan.is_base_amount,
+            is_base_to_quote: plan.is_base_to_quote,
+            needs_confirmation: plan.needs_confirmation,
+            is_paused: plan.is_paused,
+            start_at: plan.start_at,
+            end_at: plan.end_at,
+            next_run_at: plan.next_run_at,
+            remark: plan.remark,
+            created_at: plan.created_at,
+            updated_at: plan.updated_at,
+        };
+
+        Ok(Response::ok(plan_item))
+    }
+}
+
+mod pause {
+    pub const PATH: &str = "/finance/trades/:trade_id/plans/:id/pause";
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::plan::Plan;
+    use crate::model::finance::trade::Trade;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((trade_id, id)): Path<(i64, i64)>,
+    ) -> ResponseResult<ResponseBody> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let plan = Plan::select_by_id_trade_id(&conn, id, trade.id())?
+            .ok_or(Response::not_found(format!("plan {} does not exist", id)))?;
+
+        if plan.is_paused {
+            return Err(Response::conflict(format!("plan {} is already paused", id)));
+        }
+
+        Plan::update_paused_by_id_trade_id(&conn, id, trade.id(), true, plan.next_run_at)?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
+
+mod resume {
+    pub const PATH: &str = "/finance/trades/:trade_id/plans/:id/resume";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::plan::Plan;
+    use crate::model::finance::trade::Trade;
+    use crate::portfolio::plan::first_run;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        /// `None` when the plan ended while paused.
+        pub next_run_at: Option<DateTime<Utc>>,
+    }
+
+    /// Runs that fell due while the plan was paused are not caught up on.
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((trade_id, id)): Path<(i64, i64)>,
+    ) -> ResponseResult<ResponseBody> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let plan = Plan::select_by_id_trade_id(&conn, id, trade.id())?
+            .ok_or(Response::not_found(format!("plan {} does not exist", id)))?;
+
+        if !plan.is_paused {
+            return Err(Response::conflict(format!("plan {} is not paused", id)));
+        }
+
+        let from = plan.start_at.max(Utc::now());
+        let next_run_at = plan
+            .next_run_at
+            .and_then(|_| first_run(&plan.schedule, from, plan.end_at));
+
+        Plan::update_paused_by_id_trade_id(&conn, id, trade.id(), false, next_run_at)?;
+
+        Ok(Response::ok(ResponseBody { id, next_run_at }))
+    }
+}
+
+mod skip {
+    pub const PATH: &str = "/finance/trades/:trade_id/plans/:id/skip";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::plan::{Outcome, Plan, Run};
+    use crate::model::finance::trade::Trade;
+    use crate::portfolio::plan::first_run;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        /// The run to skip, the next one when omitted.
+        pub due_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub due_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((trade_id, id)): Path<(i64, i64)>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let plan = Plan::select_by_id_trade_id(&conn, id, trade.id())?
+            .ok_or(Response::not_found(format!("plan {} does not exist", id)))?;
+
+        let next_run_at = plan
+            .next_run_at
+            .ok_or(Response::conflict(format!("plan {} has ended", id)))?;
+        let due_at = payload.due_at.unwrap_or(next_run_at);
+
+        if due_at < next_run_at {
+            return Err(Response::conflict(format!(
+                "run at {} was already dealt with",
+                due_at
+            )));
+        }
+        if first_run(&plan.schedule, due_at, plan.end_at) != Some(due_at) {
+            return Err(Response::bad_request(format!(
+                "plan {} has no run at {}",
+                id, due_at
+            )));
+        }
+        if Run::select_by_plan_id_due_at(&conn, id, due_at)?.is_some() {
+            return Err(Response::conflict(format!(
+                "run at {} is already skipped",
+                due_at
+            )));
+        }
+
+        Run::insert(&conn, id, due_at, Outcome::Skipped, None)?;
+
+        Ok(Response::ok(ResponseBody { id, due_at }))
+    }
+}
+
+mod runs {
+    pub const PATH: &str = "/finance/trades/:trade_id/plans/:id/runs";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::plan::{Outcome, Plan, Run};
+    use crate::model::finance::trade::Trade;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct RunItem {
+        pub id: i64,
+        pub plan_id: i64,
+        pub due_at: DateTime<Utc>,
+        pub outcome: Outcome,
+        pub transaction_id: Option<i64>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        /// The latest first, including runs skipped ahead of time.
+        pub runs: Vec<RunItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((trade_id, id)): Path<(i64, i64)>,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let plan = Plan::select_by_id_trade_id(&conn, id, trade.id())?
+            .ok_or(Response::not_found(format!("plan {} does not exist", id)))?;
+
+        let total = Run::count_by_plan_id(&conn, plan.id())?;
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let runs = Run::select_by_plan_id(&conn, plan.id(), limit, offset)?
+            .into_iter()
+            .map(|run| RunItem {
+                id: run.id(),
+                plan_id: run.plan_id,
+                due_at: run.due_at,
+                outcome: run.outcome,
+                transaction_id: run.transaction_id,
+                created_at: run.created_at,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { runs, total }))
+    }
+}
@@ -1,35 +1,83 @@
+use rusqlite::Connection;
+
+use crate::api::http::prelude::Response;
 use crate::api::http::state::StateInner;
+use crate::model::database::savepoint;
+use crate::model::finance::object::Object;
 
 pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
     use axum::routing::{delete, get, post, put};
 
-    let router = axum::Router::new()
+    axum::Router::new()
         .route(get::PATH, get(get::handler))
         .route(post::PATH, post(post::handler))
         .route(put::PATH, put(put::handler))
         .route(delete::PATH, delete(delete::handler))
-        .with_state(state);
+        .route(status::PATH, post(status::handler))
+        .route(category::PATH, put(category::handler))
+        .route(batch::PATH, post(batch::create))
+        .route(batch::PATH, put(batch::update))
+        .route(batch::PATH, delete(batch::delete))
+        .with_state(state)
+}
+
+/// Checks that a fee is paid in an object of the owner, returning that
+/// object.
+fn check_fee(conn: &Connection, owner: i64, object_id: i64) -> Result<i64, Response<()>> {
+    let object = Object::select_by_id_owner(conn, object_id, owner)?.ok_or(Response::not_found(
+        format!("object {} does not exist", object_id),
+    ))?;
+
+    Ok(object.id())
+}
 
-    router
+/// Runs `f` inside a savepoint, so that whatever it wrote is undone when it
+/// fails, and hands back the response it failed with. The savepoint itself
+/// failing is an internal error, not a fault of the request.
+fn atomically<T>(
+    conn: &Connection,
+    f: impl FnOnce() -> Result<T, Response<()>>,
+) -> Result<T, Response<()>> {
+    savepoint(conn, || Ok(f()), |result| result.is_ok())
+        .map_err(|error: rusqlite::Error| Response::internal_error(error.to_string()))?
 }
 
 mod get {
     pub const PATH: &str = "/finance/trades/:trade_id/transactions";
 
     use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
     use serde::{Deserialize, Serialize};
     use validator::Validate;
 
     use crate::api::http::paginate;
     use crate::api::http::prelude::*;
     use crate::model::database::prelude::*;
-    use crate::model::finance::trade::transaction::Transaction;
+    use crate::model::database::Order;
+    use crate::model::finance::trade::transaction::{Filter, Sort, Status, Transaction};
     use crate::model::finance::trade::Trade;
     use crate::model::finance::Quantity;
 
     #[derive(Debug, Validate, Deserialize)]
     pub struct Params {
         pub id: Option<i64>,
+        /// Transactions that occurred at or after.
+        pub from: Option<DateTime<Utc>>,
+        /// Transactions that occurred at or before.
+        pub to: Option<DateTime<Utc>>,
+        pub is_base_to_quote: Option<bool>,
+        pub min_quantity: Option<Decimal>,
+        pub max_quantity: Option<Decimal>,
+        /// Found in the alias or the remark, ignoring case.
+        #[validate(length(min = 1, max = 256))]
+        pub text: Option<String>,
+        pub status: Option<Status>,
+        /// Transactions the tag is attached to.
+        #[validate(range(min = 1))]
+        pub tag_id: Option<i64>,
+        /// The time the transactions occurred when omitted.
+        pub sort: Option<Sort>,
+        pub order: Option<Order>,
         #[validate(range(min = 1))]
         pub page: Option<usize>,
         #[validate(range(min = 1, max = 1024))]
@@ -40,8 +88,14 @@ mod get {
     pub struct TransactionItem {
         pub id: i64,
         pub trade_id: i64,
+        pub account_id: i64,
         pub quantity: Quantity,
+        pub price: Option<Quantity>,
+        pub fee: Option<Quantity>,
+        pub fee_object_id: Option<i64>,
         pub is_base_to_quote: bool,
+        pub status: Status,
+        pub settled_at: Option<DateTime<Utc>>,
         pub alias: Option<String>,
         pub remark: Option<String>,
         pub occurrence_at: DateTime<Utc>,
@@ -70,7 +124,31 @@ mod get {
             Response::not_found(format!("trade {} does not exist", trade_id)),
         )?;
 
-        let total = Transaction::count_by_trade_id(&conn, trade.id())?;
+        if let (Some(from), Some(to)) = (params.from, params.to) {
+            if from > to {
+                return Err(Response::bad_request("from must not be after to".into()));
+            }
+        }
+        if let (Some(min), Some(max)) = (params.min_quantity, params.max_quantity) {
+            if min > max {
+                return Err(Response::bad_request(
+                    "min_quantity must not be above max_quantity".into(),
+                ));
+            }
+        }
+
+        let filter = Filter {
+            from: params.from,
+            to: params.to,
+            is_base_to_quote: params.is_base_to_quote,
+            min_quantity: params.min_quantity,
+            max_quantity: params.max_quantity,
+            text: params.text,
+            status: params.status,
+            tag_id: params.tag_id,
+        };
+
+        let total = Transaction::count_by_trade_id(&conn, trade.id(), &filter)?;
 
         if let Some(id) = params.id {
             let transaction = Transaction::select_by_id_trade_id(&conn, id, trade_id)?.ok_or(
@@ -80,8 +158,14 @@ mod get {
             let transaction_item = TransactionItem {
                 id: transaction.id(),
                 trade_id: transaction.trade_id,
+                account_id: transaction.account_id,
                 quantity: transaction.quantity,
+                price: transaction.price,
+                fee: transaction.fee,
+                fee_object_id: transaction.fee_object_id,
                 is_base_to_quote: transaction.is_base_to_quote,
+                status: transaction.status,
+                settled_at: transaction.settled_at,
                 alias: transaction.alias,
                 remark: transaction.remark,
                 occurrence_at: transaction.occurrence_at,
@@ -97,15 +181,24 @@ mod get {
 
         let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
 
-        let transactions = Transaction::select_by_trade_id(&conn, trade_id, limit, offset)?;
+        let sort = params.sort.unwrap_or_default();
+        let order = params.order.unwrap_or_default();
+        let transactions =
+            Transaction::select_by_trade_id(&conn, trade_id, &filter, sort, order, limit, offset)?;
 
         let transactions = transactions
             .into_iter()
             .map(|tx| TransactionItem {
                 id: tx.id(),
                 trade_id: tx.trade_id,
+                account_id: tx.account_id,
                 quantity: tx.quantity,
+                price: tx.price,
+                fee: tx.fee,
+                fee_object_id: tx.fee_object_id,
                 is_base_to_quote: tx.is_base_to_quote,
+                status: tx.status,
+                settled_at: tx.settled_at,
                 alias: tx.alias,
                 remark: tx.remark,
                 occurrence_at: tx.occurrence_at,
@@ -125,19 +218,41 @@ mod post {
     pub const PATH: &str = "/finance/trades/:trade_id/transactions";
 
     use chrono::{DateTime, Utc};
+    use rusqlite::Connection;
     use serde::{Deserialize, Serialize};
     use validator::Validate;
 
     use crate::api::http::prelude::*;
     use crate::model::database::prelude::*;
-    use crate::model::finance::trade::transaction::Transaction;
+    use crate::model::finance::account::Account;
+    use crate::model::finance::object::Object;
+    use crate::model::finance::trade::transaction::{Status, Transaction};
     use crate::model::finance::trade::Trade;
-    use crate::model::finance::Quantity;
+    use crate::model::finance::{positive, Quantity, Rounding};
+    use crate::portfolio::ledger;
 
     #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
     pub struct RequestBody {
+        #[validate(range(min = 1))]
+        pub account_id: i64,
+        #[serde(deserialize_with = "positive::deserialize")]
         pub quantity: Quantity,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub price: Option<Quantity>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub fee: Option<Quantity>,
+        /// The object the fee is paid in, the quote object when omitted.
+        #[validate(range(min = 1))]
+        pub fee_object_id: Option<i64>,
+        /// Rounds a quantity finer than the base object's precision instead
+        /// of rejecting it.
+        pub rounding: Option<Rounding>,
         pub is_base_to_quote: bool,
+        /// Settled when omitted.
+        pub status: Option<Status>,
+        /// The occurrence when a settled transaction omits it, refused for a
+        /// cancelled one.
+        pub settled_at: Option<DateTime<Utc>>,
         #[validate(length(min = 1, max = 4096))]
         pub alias: Option<String>,
         #[validate(length(min = 1, max = 4096))]
@@ -157,8 +272,6 @@ mod post {
         Path(trade_id): Path<i64>,
         Json(payload): Json<RequestBody>,
     ) -> ResponseResult<ResponseBody> {
-        payload.validate()?;
-
         let owner = claim.subject();
         let conn = connection()?;
 
@@ -166,19 +279,78 @@ mod post {
             Response::not_found(format!("trade {} does not exist", trade_id)),
         )?;
 
+        let id = super::atomically(&conn, || create(&conn, owner, &trade, payload))?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody { id, created_at }))
+    }
+
+    /// Adds a transaction to a trade of the owner and records it in the
+    /// ledger, returning its id.
+    pub fn create(
+        conn: &Connection,
+        owner: i64,
+        trade: &Trade,
+        payload: RequestBody,
+    ) -> Result<i64, Response<()>> {
+        payload.validate()?;
+
+        let occurrence_at = payload.occurrence_at.unwrap_or(Utc::now());
+        let status = payload.status.unwrap_or(Status::Settled);
+        let settled_at = match status {
+            Status::Settled => Some(payload.settled_at.unwrap_or(occurrence_at)),
+            // A cancelled transaction never settles
+            Status::Cancelled if payload.settled_at.is_some() => {
+                return Err(Response::bad_request(
+                    "a cancelled transaction has no settled_at".to_string(),
+                ));
+            }
+            _ => payload.settled_at,
+        };
+
+        let base = Object::select_by_id_owner(conn, trade.base_object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", trade.base_object_id)),
+        )?;
+        let quantity = payload
+            .quantity
+            .with_precision(base.classification.precision, payload.rounding)?;
+
+        let account = Account::select_by_id_owner(conn, payload.account_id, owner)?.ok_or(
+            Response::not_found(format!("account {} does not exist", payload.account_id)),
+        )?;
+
+        let (fee, fee_object_id) = match payload.fee {
+            Some(fee) => {
+                let object_id = payload.fee_object_id.unwrap_or(trade.quote_object_id);
+                let object_id = super::check_fee(conn, owner, object_id)?;
+                (Some(fee), Some(object_id))
+            }
+            None => (None, None),
+        };
+
         let id = Transaction::insert(
-            &conn,
+            conn,
             trade.id(),
-            payload.quantity,
+            account.id(),
+            quantity,
+            payload.price,
+            fee,
+            fee_object_id,
             payload.is_base_to_quote,
+            status,
+            settled_at,
             payload.alias,
             payload.remark,
-            payload.occurrence_at,
+            Some(occurrence_at),
         )?;
 
-        let created_at = Utc::now();
+        let transaction = Transaction::select_by_id_trade_id(conn, id, trade.id())?.ok_or(
+            Response::not_found(format!("transaction {} does not exist", id)),
+        )?;
+        ledger::record_transaction(conn, owner, trade, &transaction)?;
 
-        Ok(Response::ok(ResponseBody { id, created_at }))
+        Ok(id)
     }
 }
 
@@ -186,18 +358,34 @@ mod put {
     pub const PATH: &str = "/finance/trades/:trade_id/transactions/:id";
 
     use chrono::{DateTime, Utc};
+    use rusqlite::Connection;
     use serde::{Deserialize, Serialize};
     use validator::Validate;
 
     use crate::api::http::prelude::*;
     use crate::model::database::prelude::*;
+    use crate::model::finance::account::Account;
+    use crate::model::finance::object::Object;
     use crate::model::finance::trade::transaction::Transaction;
     use crate::model::finance::trade::Trade;
-    use crate::model::finance::Quantity;
+    use crate::model::finance::{positive, Quantity, Rounding};
+    use crate::portfolio::ledger;
 
     #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
     pub struct RequestBody {
+        #[validate(range(min = 1))]
+        pub account_id: Option<i64>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
         pub quantity: Option<Quantity>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub price: Option<Quantity>,
+        #[serde(default, deserialize_with = "positive::option::deserialize")]
+        pub fee: Option<Quantity>,
+        #[validate(range(min = 1))]
+        pub fee_object_id: Option<i64>,
+        /// Rounds a quantity finer than the base object's precision instead
+        /// of rejecting it.
+        pub rounding: Option<Rounding>,
         pub is_base_to_quote: Option<bool>,
         #[validate(length(min = 1, max = 4096))]
         pub alias: Option<String>,
@@ -217,8 +405,6 @@ mod put {
         Path((trade_id, id)): Path<(i64, i64)>,
         Json(payload): Json<RequestBody>,
     ) -> ResponseResult<ResponseBody> {
-        payload.validate()?;
-
         let owner = claim.subject();
         let conn = connection()?;
 
@@ -226,11 +412,63 @@ mod put {
             Response::not_found(format!("trade {} does not exist", trade_id)),
         )?;
 
-        let transaction = Transaction::select_by_id_trade_id(&conn, id, trade.id())?.ok_or(
+        super::atomically(&conn, || update(&conn, owner, &trade, id, payload))?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+
+    /// Changes what is given of a transaction of a trade of the owner and
+    /// records it in the ledger anew.
+    pub fn update(
+        conn: &Connection,
+        owner: i64,
+        trade: &Trade,
+        id: i64,
+        payload: RequestBody,
+    ) -> Result<(), Response<()>> {
+        payload.validate()?;
+
+        let transaction = Transaction::select_by_id_trade_id(conn, id, trade.id())?.ok_or(
             Response::not_found(format!("transaction {} does not exist", id)),
         )?;
 
-        let quantity = payload.quantity.unwrap_or(transaction.quantity);
+        // Stored values are kept as they are, only new ones are checked
+        let quantity = match payload.quantity {
+            Some(quantity) => {
+                let base = Object::select_by_id_owner(conn, trade.base_object_id, owner)?.ok_or(
+                    Response::not_found(format!("object {} does not exist", trade.base_object_id)),
+                )?;
+                quantity.with_precision(base.classification.precision, payload.rounding)?
+            }
+            None => transaction.quantity,
+        };
+        let price = payload.price.or(transaction.price);
+        let account_id = match payload.account_id {
+            Some(account_id) => Account::select_by_id_owner(conn, account_id, owner)?
+                .ok_or(Response::not_found(format!(
+                    "account {} does not exist",
+                    account_id
+                )))?
+                .id(),
+            None => transaction.account_id,
+        };
+        let (fee, fee_object_id) = match payload.fee {
+            Some(fee) => {
+                let object_id = payload
+                    .fee_object_id
+                    .or(transaction.fee_object_id)
+                    .unwrap_or(trade.quote_object_id);
+                let object_id = super::check_fee(conn, owner, object_id)?;
+                (Some(fee), Some(object_id))
+            }
+            None => match (transaction.fee, payload.fee_object_id) {
+                (Some(fee), Some(object_id)) => {
+                    let object_id = super::check_fee(conn, owner, object_id)?;
+                    (Some(fee), Some(object_id))
+                }
+                (fee, _) => (fee, transaction.fee_object_id),
+            },
+        };
         let is_base_to_quote = payload
             .is_base_to_quote
             .unwrap_or(transaction.is_base_to_quote);
@@ -239,17 +477,26 @@ mod put {
         let occurrence_at = payload.occurrence_at.unwrap_or(transaction.occurrence_at);
 
         Transaction::update_by_id_trade_id(
-            &conn,
+            conn,
             id,
             trade.id(),
+            account_id,
             quantity,
+            price,
+            fee,
+            fee_object_id,
             is_base_to_quote,
             occurrence_at,
             alias,
             remark,
         )?;
 
-        Ok(Response::ok(ResponseBody { id }))
+        let transaction = Transaction::select_by_id_trade_id(conn, id, trade.id())?.ok_or(
+            Response::not_found(format!("transaction {} does not exist", id)),
+        )?;
+        ledger::record_transaction(conn, owner, trade, &transaction)?;
+
+        Ok(())
     }
 }
 
@@ -257,11 +504,15 @@ mod delete {
     pub const PATH: &str = "/finance/trades/:trade_id/transactions/:id";
 
     use chrono::{DateTime, Utc};
+    use rusqlite::Connection;
     use serde::{Deserialize, Serialize};
 
     use crate::api::http::prelude::*;
+    use crate::attachment;
+    use crate::consts::attachment::STORE;
     use crate::model::database::prelude::*;
-    use crate::model::finance::trade::transaction::Transaction;
+    use crate::model::finance::attachment::Attachment;
+    use crate::model::finance::trade::transaction::{Status, Transaction};
     use crate::model::finance::trade::Trade;
     use crate::model::finance::Quantity;
 
@@ -269,8 +520,14 @@ mod delete {
     pub struct TransactionItem {
         pub id: i64,
         pub trade_id: i64,
+        pub account_id: i64,
         pub quantity: Quantity,
+        pub price: Option<Quantity>,
+        pub fee: Option<Quantity>,
+        pub fee_object_id: Option<i64>,
         pub is_base_to_quote: bool,
+        pub status: Status,
+        pub settled_at: Option<DateTime<Utc>>,
         pub alias: Option<String>,
         pub remark: Option<String>,
         pub occurrence_at: DateTime<Utc>,
@@ -290,17 +547,20 @@ mod delete {
             Response::not_found(format!("trade {} does not exist", trade_id)),
         )?;
 
-        let transaction = Transaction::select_by_id_trade_id(&conn, id, trade.id())?.ok_or(
-            Response::not_found(format!("transaction {} does not exist", id)),
-        )?;
-
-        Transaction::delete_by_id_trade_id(&conn, id, trade.id())?;
+        let (transaction, attachments) = super::atomically(&conn, || remove(&conn, &trade, id))?;
+        attachment::release(&conn, &STORE, &attachments)?;
 
         let transaction_item = TransactionItem {
             id: transaction.id(),
             trade_id: transaction.trade_id,
+            account_id: transaction.account_id,
             quantity: transaction.quantity,
+            price: transaction.price,
+            fee: transaction.fee,
+            fee_object_id: transaction.fee_object_id,
             is_base_to_quote: transaction.is_base_to_quote,
+            status: transaction.status,
+            settled_at: transaction.settled_at,
             alias: transaction.alias,
             remark: transaction.remark,
             occurrence_at: transaction.occurrence_at,
@@ -310,4 +570,566 @@ mod delete {
 
         Ok(Response::ok(transaction_item))
     }
+
+    /// Deletes a transaction of a trade along with its ledger entry, and
+    /// returns it with the attachments that went with it. Their stored
+    /// content is left for the caller to release once the deletion holds.
+    pub fn remove(
+        conn: &Connection,
+        trade: &Trade,
+        id: i64,
+    ) -> Result<(Transaction, Vec<Attachment>), Response<()>> {
+        let transaction = Transaction::select_by_id_trade_id(conn, id, trade.id())?.ok_or(
+            Response::not_found(format!("transaction {} does not exist", id)),
+        )?;
+
+        let attachments = Attachment::select_by_transaction_id(conn, id)?;
+
+        Transaction::delete_by_id_trade_id(conn, id, trade.id())?;
+
+        Ok((transaction, attachments))
+    }
+}
+
+mod status {
+    pub const PATH: &str = "/finance/trades/:trade_id/transactions/:id/status";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::portfolio::ledger;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        pub status: Status,
+        /// When the transaction settled, now when it becomes settled without
+        /// one being given or known before. Cleared when it is cancelled.
+        pub settled_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub status: Status,
+        pub settled_at: Option<DateTime<Utc>>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((trade_id, id)): Path<(i64, i64)>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let transaction = Transaction::select_by_id_trade_id(&conn, id, trade.id())?.ok_or(
+            Response::not_found(format!("transaction {} does not exist", id)),
+        )?;
+
+        if !transaction.status.can_become(payload.status) {
+            return Err(Response::conflict(format!(
+                "transaction {} cannot go from {} to {}",
+                id, transaction.status, payload.status
+            )));
+        }
+
+        let settled_at = match payload.status {
+            Status::Settled => Some(
+                payload
+                    .settled_at
+                    .or(transaction.settled_at)
+                    .unwrap_or(Utc::now()),
+            ),
+            // A cancelled transaction never settles
+            Status::Cancelled => None,
+            _ => payload.settled_at.or(transaction.settled_at),
+        };
+
+        let transaction = super::atomically(&conn, || {
+            Transaction::update_status_by_id_trade_id(
+                &conn,
+                id,
+                trade.id(),
+                payload.status,
+                settled_at,
+            )?;
+
+            let transaction = Transaction::select_by_id_trade_id(&conn, id, trade.id())?.ok_or(
+                Response::not_found(format!("transaction {} does not exist", id)),
+            )?;
+            ledger::record_transaction(&conn, owner, &trade, &transaction)?;
+
+            Ok(transaction)
+        })?;
+
+        Ok(Response::ok(ResponseBody {
+            id,
+            status: transaction.status,
+            settled_at: transaction.settled_at,
+        }))
+    }
+}
+
+mod category {
+    pub const PATH: &str = "/finance/trades/:trade_id/transactions/:id/category";
+
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::category::Category;
+    use crate::model::finance::trade::transaction::Transaction;
+    use crate::model::finance::trade::Trade;
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        /// The category to put the transaction in, or none to take it out
+        /// of its category.
+        #[validate(range(min = 1))]
+        pub category_id: Option<i64>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub category_id: Option<i64>,
+        pub path: Option<String>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Path((trade_id, id)): Path<(i64, i64)>,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let transaction = Transaction::select_by_id_trade_id(&conn, id, trade.id())?.ok_or(
+            Response::not_found(format!("transaction {} does not exist", id)),
+        )?;
+
+        match payload.category_id {
+            Some(category_id) => {
+                let category = Category::select_by_id_owner(&conn, category_id, owner)?.ok_or(
+                    Response::not_found(format!("category {} does not exist", category_id)),
+                )?;
+
+                Category::assign(&conn, category.id(), transaction.id())?;
+            }
+            None => Category::unassign(&conn, transaction.id())?,
+        }
+
+        let category = Category::select_by_transaction_id(&conn, transaction.id(), owner)?;
+
+        Ok(Response::ok(ResponseBody {
+            id,
+            category_id: category.as_ref().map(|category| category.id()),
+            path: category.map(|category| category.path),
+        }))
+    }
+}
+
+mod batch {
+    pub const PATH: &str = "/finance/trades/:trade_id/transactions/batch";
+
+    use rusqlite::Connection;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::attachment;
+    use crate::consts::attachment::STORE;
+    use crate::model::database::prelude::*;
+    use crate::model::database::savepoint;
+    use crate::model::finance::trade::Trade;
+
+    use super::{post, put};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Status {
+        Applied,
+        Failed,
+        /// Applied, then undone since another item of an all-or-nothing
+        /// batch failed.
+        RolledBack,
+    }
+
+    /// What became of one item of a batch.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ItemResult {
+        /// Where the item stands in the request.
+        pub index: usize,
+        pub status: Status,
+        /// The transaction created, changed or deleted. None for one that
+        /// was not created in the end.
+        pub id: Option<i64>,
+        /// The code the item would have failed with on its own.
+        pub code: Option<u16>,
+        pub message: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        /// Whether anything was stored, false when an item of an
+        /// all-or-nothing batch failed.
+        pub committed: bool,
+        pub items: Vec<ItemResult>,
+        pub applied: usize,
+        pub failed: usize,
+    }
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct CreateBody {
+        #[validate(length(min = 1, max = 1000))]
+        pub transactions: Vec<post::RequestBody>,
+        /// Stores nothing when any item fails, rather than every item that
+        /// succeeds.
+        pub all_or_nothing: Option<bool>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Change {
+        pub id: i64,
+        #[serde(flatten)]
+        pub changes: put::RequestBody,
+    }
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct UpdateBody {
+        #[validate(length(min = 1, max = 1000))]
+        pub transactions: Vec<Change>,
+        /// Stores nothing when any item fails, rather than every item that
+        /// succeeds.
+        pub all_or_nothing: Option<bool>,
+    }
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct DeleteBody {
+        #[validate(length(min = 1, max = 1000))]
+        pub ids: Vec<i64>,
+        /// Deletes nothing when any item fails, rather than every item that
+        /// succeeds.
+        pub all_or_nothing: Option<bool>,
+    }
+
+    /// Applies each item in a savepoint of its own, so a failing item
+    /// leaves nothing behind, within one for the whole batch that is rolled
+    /// back when an item fails and `all_or_nothing` is set. Items are given
+    /// with the transaction they are about, when known beforehand.
+    pub fn run<T>(
+        conn: &Connection,
+        items: Vec<(Option<i64>, T)>,
+        all_or_nothing: bool,
+        mut apply: impl FnMut(T) -> Result<i64, Response<()>>,
+    ) -> Result<ResponseBody, Response<()>> {
+        let mut failed = 0;
+        let results = savepoint(
+            conn,
+            || {
+                let mut results = Vec::with_capacity(items.len());
+                for (index, (id, item)) in items.into_iter().enumerate() {
+                    let result = match super::atomically(conn, || apply(item)) {
+                        Ok(id) => ItemResult {
+                            index,
+                            status: Status::Applied,
+                            id: Some(id),
+                            code: None,
+                            message: None,
+                        },
+                        Err(response) => ItemResult {
+                            index,
+                            status: Status::Failed,
+                            id,
+                            code: Some(response.code),
+                            message: response.message,
+                        },
+                    };
+                    results.push((id, result));
+                }
+
+                Ok::<_, rusqlite::Error>(results)
+            },
+            |results| {
+                failed = results
+                    .iter()
+                    .filter(|(_, result)| result.status == Status::Failed)
+                    .count();
+                !all_or_nothing || failed == 0
+            },
+        )
+        .map_err(|error| Response::internal_error(error.to_string()))?;
+        let committed = !all_or_nothing || failed == 0;
+
+        let items: Vec<ItemResult> = results
+            .into_iter()
+            .map(|(id, mut result)| {
+                if !committed && result.status == Status::Applied {
+                    result.status = Status::RolledBack;
+                    result.id = id;
+                }
+                result
+            })
+            .collect();
+        let applied = items
+            .iter()
+            .filter(|result| result.status == Status::Applied)
+            .count();
+
+        Ok(ResponseBody {
+            committed,
+            items,
+            applied,
+            failed,
+        })
+    }
+
+    /// Adds up to 1000 transactions to a trade at once.
+    #[tracing::instrument()]
+    pub async fn create(
+        claim: Claim,
+        Path(trade_id): Path<i64>,
+        Json(payload): Json<CreateBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let items = payload
+            .transactions
+            .into_iter()
+            .map(|transaction| (None, transaction))
+            .collect();
+        let all_or_nothing = payload.all_or_nothing.unwrap_or(false);
+
+        let body = run(&conn, items, all_or_nothing, |transaction| {
+            post::create(&conn, owner, &trade, transaction)
+        })?;
+
+        Ok(Response::ok(body))
+    }
+
+    /// Changes up to 1000 transactions of a trade at once, each given with
+    /// its id and the fields to change.
+    #[tracing::instrument()]
+    pub async fn update(
+        claim: Claim,
+        Path(trade_id): Path<i64>,
+        Json(payload): Json<UpdateBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let items = payload
+            .transactions
+            .into_iter()
+            .map(|change| (Some(change.id), change))
+            .collect();
+        let all_or_nothing = payload.all_or_nothing.unwrap_or(false);
+
+        let body = run(&conn, items, all_or_nothing, |change| {
+            put::update(&conn, owner, &trade, change.id, change.changes)?;
+            Ok(change.id)
+        })?;
+
+        Ok(Response::ok(body))
+    }
+
+    /// Deletes up to 1000 transactions of a trade at once. Attachments go
+    /// with them, their stored content once the batch holds.
+    #[tracing::instrument()]
+    pub async fn delete(
+        claim: Claim,
+        Path(trade_id): Path<i64>,
+        Json(payload): Json<DeleteBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let trade = Trade::select_by_id_owner(&conn, trade_id, owner)?.ok_or(
+            Response::not_found(format!("trade {} does not exist", trade_id)),
+        )?;
+
+        let items = payload.ids.into_iter().map(|id| (Some(id), id)).collect();
+        let all_or_nothing = payload.all_or_nothing.unwrap_or(false);
+
+        let mut attachments = Vec::new();
+        let body = run(&conn, items, all_or_nothing, |id| {
+            let (transaction, gone) = super::delete::remove(&conn, &trade, id)?;
+            attachments.extend(gone);
+            Ok(transaction.id())
+        })?;
+
+        if body.committed {
+            attachment::release(&conn, &STORE, &attachments)?;
+        }
+
+        Ok(Response::ok(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::model::finance::trade::transaction::{Filter, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::fixture;
+
+    use super::batch::{run, Status};
+    use super::post;
+
+    #[test]
+    fn test_create_settles() {
+        let conn = fixture::connection();
+        let owner = fixture::person(&conn, "test_user");
+        let (btc, usd) = (
+            fixture::object(&conn, owner, "BTC"),
+            fixture::object(&conn, owner, "USD"),
+        );
+        let account = fixture::account(&conn, owner, "exchange");
+        let trade = fixture::trade(&conn, owner, btc, usd);
+        let trade = Trade::select_by_id_owner(&conn, trade, owner)
+            .unwrap()
+            .unwrap();
+
+        let create = |status: &str, settled_at: Option<&str>| {
+            let body = json!({
+                "account_id": account,
+                "quantity": "1",
+                "is_base_to_quote": false,
+                "status": status,
+                "settled_at": settled_at,
+                "occurrence_at": "2024-01-01T00:00:00Z",
+            });
+            post::create(&conn, owner, &trade, serde_json::from_value(body).unwrap())
+                .map(|id| {
+                    Transaction::select_by_id_trade_id(&conn, id, trade.id())
+                        .unwrap()
+                        .unwrap()
+                        .settled_at
+                        .map(|settled_at| settled_at.to_rfc3339())
+                })
+                .map_err(|error| error.code)
+        };
+
+        // A settled transaction settles when it occurs unless told otherwise
+        assert_eq!(
+            create("settled", None),
+            Ok(Some("2024-01-01T00:00:00+00:00".to_string()))
+        );
+        assert_eq!(
+            create("settled", Some("2024-01-03T00:00:00Z")),
+            Ok(Some("2024-01-03T00:00:00+00:00".to_string()))
+        );
+        assert_eq!(
+            create("pending", Some("2024-01-03T00:00:00Z")),
+            Ok(Some("2024-01-03T00:00:00+00:00".to_string()))
+        );
+        assert_eq!(create("planned", None), Ok(None));
+        assert_eq!(create("cancelled", None), Ok(None));
+        assert_eq!(create("cancelled", Some("2024-01-03T00:00:00Z")), Err(400));
+    }
+
+    #[test]
+    fn test_batch_run() {
+        let conn = fixture::connection();
+        let owner = fixture::person(&conn, "test_user");
+        let (btc, usd) = (
+            fixture::object(&conn, owner, "BTC"),
+            fixture::object(&conn, owner, "USD"),
+        );
+        let account = fixture::account(&conn, owner, "exchange");
+        let trade = fixture::trade(&conn, owner, btc, usd);
+        let trade = Trade::select_by_id_owner(&conn, trade, owner)
+            .unwrap()
+            .unwrap();
+
+        let items = |quantities: &[&str]| -> Vec<(Option<i64>, post::RequestBody)> {
+            quantities
+                .iter()
+                .map(|quantity| {
+                    let body = json!({
+                        "account_id": account,
+                        "quantity": quantity,
+                        "price": "40000",
+                        "is_base_to_quote": false,
+                    });
+                    (None, serde_json::from_value(body).unwrap())
+                })
+                .collect()
+        };
+        let count = || Transaction::count_by_trade_id(&conn, trade.id(), &Filter::default());
+
+        // A failing item is left out while the others are stored
+        let body = run(
+            &conn,
+            items(&["0.1", "0.000000001", "0.2"]),
+            false,
+            |transaction| post::create(&conn, owner, &trade, transaction),
+        )
+        .ok()
+        .unwrap();
+        assert!(body.committed);
+        assert_eq!((body.applied, body.failed), (2, 1));
+        assert_eq!(body.items[1].status, Status::Failed);
+        assert_eq!(body.items[1].code, Some(400));
+        assert!(body.items[0].id.is_some());
+        assert_eq!(count().unwrap(), 2);
+
+        // All or nothing stores nothing
+        let body = run(&conn, items(&["0.3", "0.000000001"]), true, |transaction| {
+            post::create(&conn, owner, &trade, transaction)
+        })
+        .ok()
+        .unwrap();
+        assert!(!body.committed);
+        assert_eq!((body.applied, body.failed), (0, 1));
+        assert_eq!(body.items[0].status, Status::RolledBack);
+        assert_eq!(body.items[0].id, None);
+        assert_eq!(count().unwrap(), 2);
+
+        // Amounts that are not positive do not make it into a batch at all
+        let body = json!({
+            "transactions": [{ "account_id": account, "quantity": "0", "is_base_to_quote": false }],
+        });
+        assert!(serde_json::from_value::<super::batch::CreateBody>(body).is_err());
+
+        // Every stored transaction made it into the ledger
+        let report = crate::portfolio::ledger::check(&conn, owner).unwrap();
+        assert!(report.unrecorded.is_empty());
+    }
 }
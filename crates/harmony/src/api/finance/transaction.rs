@@ -0,0 +1,160 @@
+use crate::api::http::state::StateInner;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::get;
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/transactions";
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::database::Order;
+    use crate::model::finance::trade::transaction::{Filter, Sort, Status, Transaction};
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// Transactions that occurred at or after.
+        pub from: Option<DateTime<Utc>>,
+        /// Transactions that occurred at or before.
+        pub to: Option<DateTime<Utc>>,
+        pub is_base_to_quote: Option<bool>,
+        pub min_quantity: Option<Decimal>,
+        pub max_quantity: Option<Decimal>,
+        /// Found in the alias or the remark, ignoring case.
+        #[validate(length(min = 1, max = 256))]
+        pub text: Option<String>,
+        pub status: Option<Status>,
+        /// Transactions the tag is attached to.
+        #[validate(range(min = 1))]
+        pub tag_id: Option<i64>,
+        /// The time the transactions occurred when omitted.
+        pub sort: Option<Sort>,
+        pub order: Option<Order>,
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TransactionItem {
+        pub id: i64,
+        pub trade_id: i64,
+        pub account_id: i64,
+        pub base_object_id: i64,
+        pub base_symbol: String,
+        pub quote_object_id: i64,
+        pub quote_symbol: String,
+        pub quantity: Quantity,
+        pub price: Option<Quantity>,
+        pub fee: Option<Quantity>,
+        pub fee_object_id: Option<i64>,
+        pub fee_symbol: Option<String>,
+        pub is_base_to_quote: bool,
+        pub status: Status,
+        pub settled_at: Option<DateTime<Utc>>,
+        pub alias: Option<String>,
+        pub remark: Option<String>,
+        pub occurrence_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub transactions: Vec<TransactionItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        if let (Some(from), Some(to)) = (params.from, params.to) {
+            if from > to {
+                return Err(Response::bad_request("from must not be after to".into()));
+            }
+        }
+        if let (Some(min), Some(max)) = (params.min_quantity, params.max_quantity) {
+            if min > max {
+                return Err(Response::bad_request(
+                    "min_quantity must not be above max_quantity".into(),
+                ));
+            }
+        }
+
+        let filter = Filter {
+            from: params.from,
+            to: params.to,
+            is_base_to_quote: params.is_base_to_quote,
+            min_quantity: params.min_quantity,
+            max_quantity: params.max_quantity,
+            text: params.text,
+            status: params.status,
+            tag_id: params.tag_id,
+        };
+
+        let total = Transaction::count_by_owner(&conn, owner, &filter)?;
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let sort = params.sort.unwrap_or_default();
+        let order = params.order.unwrap_or_default();
+        let listings = Transaction::select_listings_by_owner(
+            &conn, owner, &filter, sort, order, limit, offset,
+        )?;
+
+        let transactions = listings
+            .into_iter()
+            .map(|listing| {
+                let tx = listing.transaction;
+
+                TransactionItem {
+                    id: tx.id(),
+                    trade_id: tx.trade_id,
+                    account_id: tx.account_id,
+                    base_object_id: listing.base_object_id,
+                    base_symbol: listing.base_symbol,
+                    quote_object_id: listing.quote_object_id,
+                    quote_symbol: listing.quote_symbol,
+                    quantity: tx.quantity,
+                    price: tx.price,
+                    fee: tx.fee,
+                    fee_object_id: tx.fee_object_id,
+                    fee_symbol: listing.fee_symbol,
+                    is_base_to_quote: tx.is_base_to_quote,
+                    status: tx.status,
+                    settled_at: tx.settled_at,
+                    alias: tx.alias,
+                    remark: tx.remark,
+                    occurrence_at: tx.occurrence_at,
+                    created_at: tx.created_at,
+                    updated_at: tx.updated_at,
+                }
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            transactions,
+            total,
+        }))
+    }
+}
@@ -0,0 +1,202 @@
+use crate::api::http::state::StateInner;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::{delete, get, post};
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(post::PATH, post(post::handler))
+        .route(delete::PATH, delete(delete::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/transfers";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::paginate;
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::transfer::Transfer;
+    use crate::model::finance::Quantity;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        #[validate(range(min = 1))]
+        pub page: Option<usize>,
+        #[validate(range(min = 1, max = 1024))]
+        pub page_size: Option<usize>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct TransferItem {
+        pub id: i64,
+        pub owner: i64,
+        pub object_id: i64,
+        pub from_account_id: i64,
+        pub to_account_id: i64,
+        pub quantity: Quantity,
+        pub remark: Option<String>,
+        pub occurrence_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub transfers: Vec<TransferItem>,
+        pub total: usize,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let total = Transfer::count_by_owner(&conn, owner)?;
+
+        let (limit, offset) = paginate(params.page.unwrap_or(1), params.page_size.unwrap_or(256));
+
+        let transfers = Transfer::select_by_owner(&conn, owner, limit, offset)?;
+
+        let transfers = transfers
+            .into_iter()
+            .map(|transfer| TransferItem {
+                id: transfer.id(),
+                owner: transfer.owner,
+                object_id: transfer.object_id,
+                from_account_id: transfer.from_account_id,
+                to_account_id: transfer.to_account_id,
+                quantity: transfer.quantity,
+                remark: transfer.remark,
+                occurrence_at: transfer.occurrence_at,
+                created_at: transfer.created_at,
+                updated_at: transfer.updated_at,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody { transfers, total }))
+    }
+}
+
+mod post {
+    pub const PATH: &str = "/finance/transfers";
+
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::account::Account;
+    use crate::model::finance::object::Object;
+    use crate::model::finance::transfer::Transfer;
+    use crate::model::finance::{positive, Quantity, Rounding};
+
+    #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+    pub struct RequestBody {
+        #[validate(range(min = 1))]
+        pub object_id: i64,
+        #[validate(range(min = 1))]
+        pub from_account_id: i64,
+        #[validate(range(min = 1))]
+        pub to_account_id: i64,
+        #[serde(deserialize_with = "positive::deserialize")]
+        pub quantity: Quantity,
+        /// Rounds a quantity finer than the object's precision instead of
+        /// rejecting it.
+        pub rounding: Option<Rounding>,
+        #[validate(length(min = 1, max = 4096))]
+        pub remark: Option<String>,
+        pub occurrence_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Json(payload): Json<RequestBody>,
+    ) -> ResponseResult<ResponseBody> {
+        payload.validate()?;
+
+        if payload.from_account_id == payload.to_account_id {
+            return Err(Response::bad_request(
+                "from and to accounts must differ".into(),
+            ));
+        }
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object = Object::select_by_id_owner(&conn, payload.object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", payload.object_id)),
+        )?;
+
+        for account_id in [payload.from_account_id, payload.to_account_id] {
+            Account::select_by_id_owner(&conn, account_id, owner)?.ok_or(Response::not_found(
+                format!("account {} does not exist", account_id),
+            ))?;
+        }
+
+        let quantity = payload
+            .quantity
+            .with_precision(object.classification.precision, payload.rounding)?;
+
+        let id = Transfer::insert(
+            &conn,
+            owner,
+            object.id(),
+            payload.from_account_id,
+            payload.to_account_id,
+            quantity,
+            payload.remark,
+            payload.occurrence_at.unwrap_or(Utc::now()),
+        )?;
+
+        let created_at = Utc::now();
+
+        Ok(Response::ok(ResponseBody { id, created_at }))
+    }
+}
+
+mod delete {
+    pub const PATH: &str = "/finance/transfers/:id";
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::transfer::Transfer;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub id: i64,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(claim: Claim, Path(id): Path<i64>) -> ResponseResult<ResponseBody> {
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let transfer = Transfer::select_by_id_owner(&conn, id, owner)?.ok_or(
+            Response::not_found(format!("transfer {} does not exist", id)),
+        )?;
+
+        Transfer::delete_by_id_owner(&conn, transfer.id(), owner)?;
+
+        Ok(Response::ok(ResponseBody { id }))
+    }
+}
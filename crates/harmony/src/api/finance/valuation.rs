@@ -0,0 +1,253 @@
+use crate::api::http::state::StateInner;
+
+pub fn router(state: std::sync::Arc<StateInner>) -> axum::Router {
+    use axum::routing::get;
+
+    axum::Router::new()
+        .route(get::PATH, get(get::handler))
+        .route(series::PATH, get(series::handler))
+        .with_state(state)
+}
+
+mod get {
+    pub const PATH: &str = "/finance/valuation";
+
+    use std::collections::HashMap;
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::price::Price;
+    use crate::portfolio::balance;
+    use crate::portfolio::valuation::{group, valuate, PriceGraph};
+
+    #[derive(Debug, Clone, Copy, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum GroupBy {
+        AssetType,
+        Exchange,
+    }
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// The object every holding is valued in.
+        #[validate(range(min = 1))]
+        pub object_id: i64,
+        pub at: Option<DateTime<Utc>>,
+        /// Sums holdings up into an allocation by this classification.
+        pub group_by: Option<GroupBy>,
+        /// Counts transactions that are executed but not settled yet, true
+        /// when omitted.
+        pub pending: Option<bool>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct HoldingItem {
+        pub object_id: i64,
+        pub quantity: Decimal,
+        pub price: Decimal,
+        pub value: Decimal,
+        pub weight: Option<Decimal>,
+        pub path: Vec<i64>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct UnpricedItem {
+        pub object_id: i64,
+        pub quantity: Decimal,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct GroupItem {
+        /// An asset type or an exchange, `None` for holdings without one.
+        pub key: Option<String>,
+        pub value: Decimal,
+        pub weight: Option<Decimal>,
+        pub object_ids: Vec<i64>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub object_id: i64,
+        pub at: DateTime<Utc>,
+        pub holdings: Vec<HoldingItem>,
+        pub unpriced: Vec<UnpricedItem>,
+        pub groups: Option<Vec<GroupItem>>,
+        pub total: Decimal,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let owner = claim.subject();
+        let conn = connection()?;
+        let at = params.at.unwrap_or(Utc::now());
+
+        let object = Object::select_by_id_owner(&conn, params.object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", params.object_id)),
+        )?;
+
+        let movements = balance::load(&conn, owner, at, params.pending.unwrap_or(true))?;
+        let balances = balance::balances(&movements);
+        let graph = PriceGraph::from_prices(&Price::select_latest_by_owner(&conn, owner, at)?);
+
+        let valuation = valuate(&balances, &graph, object.id());
+
+        let groups = match params.group_by {
+            Some(group_by) => {
+                let classifications: HashMap<i64, Classification> =
+                    Object::select_all_by_owner(&conn, owner)?
+                        .into_iter()
+                        .map(|object| (object.id(), object.classification))
+                        .collect();
+
+                let key = |object_id: i64| {
+                    let classification = classifications.get(&object_id)?;
+                    match group_by {
+                        GroupBy::AssetType => Some(classification.asset_type.to_string()),
+                        GroupBy::Exchange => classification.exchange.clone(),
+                    }
+                };
+
+                let groups = group(&valuation, key)
+                    .into_iter()
+                    .map(|group| GroupItem {
+                        key: group.key,
+                        value: group.value,
+                        weight: group.weight,
+                        object_ids: group.object_ids,
+                    })
+                    .collect();
+
+                Some(groups)
+            }
+            None => None,
+        };
+
+        let holdings = valuation
+            .holdings
+            .into_iter()
+            .map(|holding| HoldingItem {
+                object_id: holding.object_id,
+                quantity: holding.quantity,
+                price: holding.price,
+                value: holding.value,
+                weight: holding.weight,
+                path: holding.path,
+            })
+            .collect();
+
+        let unpriced = valuation
+            .unpriced
+            .into_iter()
+            .map(|item| UnpricedItem {
+                object_id: item.object_id,
+                quantity: item.quantity,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            object_id: valuation.object_id,
+            at,
+            holdings,
+            unpriced,
+            groups,
+            total: valuation.total,
+        }))
+    }
+}
+
+mod series {
+    pub const PATH: &str = "/finance/valuation/series";
+
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    use crate::api::http::prelude::*;
+    use crate::model::database::prelude::*;
+    use crate::model::finance::object::Object;
+    use crate::portfolio::series::{net_worth, Interval, Scope};
+
+    const MAX_POINTS: usize = 10000;
+
+    #[derive(Debug, Validate, Deserialize)]
+    pub struct Params {
+        /// The object every holding is valued in.
+        #[validate(range(min = 1))]
+        pub object_id: i64,
+        pub from: DateTime<Utc>,
+        pub to: Option<DateTime<Utc>>,
+        pub interval: Option<Interval>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct PointItem {
+        pub at: DateTime<Utc>,
+        pub total: Decimal,
+        pub unpriced: usize,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ResponseBody {
+        pub object_id: i64,
+        pub interval: Interval,
+        pub points: Vec<PointItem>,
+    }
+
+    #[tracing::instrument()]
+    pub async fn handler(
+        claim: Claim,
+        Query(params): Query<Params>,
+    ) -> ResponseResult<ResponseBody> {
+        params.validate()?;
+
+        let to = params.to.unwrap_or(Utc::now());
+        if params.from > to {
+            return Err(Response::bad_request("from must not be after to".into()));
+        }
+
+        let interval = params.interval.unwrap_or(Interval::Day);
+        let instants = interval.instants(params.from, to);
+        if instants.len() > MAX_POINTS {
+            return Err(Response::bad_request(format!(
+                "too many points (maximum {})",
+                MAX_POINTS
+            )));
+        }
+
+        let owner = claim.subject();
+        let conn = connection()?;
+
+        let object = Object::select_by_id_owner(&conn, params.object_id, owner)?.ok_or(
+            Response::not_found(format!("object {} does not exist", params.object_id)),
+        )?;
+
+        // Only whole days are snapshotted, hourly points are always replayed
+        let cache = interval == Interval::Day;
+        let points = net_worth(&conn, owner, object.id(), Scope::Person, &instants, cache)?
+            .into_iter()
+            .map(|point| PointItem {
+                at: point.at,
+                total: point.total,
+                unpriced: point.unpriced,
+            })
+            .collect();
+
+        Ok(Response::ok(ResponseBody {
+            object_id: object.id(),
+            interval,
+            points,
+        }))
+    }
+}
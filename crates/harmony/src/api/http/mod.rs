@@ -4,10 +4,10 @@ pub mod response;
 pub mod prelude {
     use super::*;
 
-    pub use request::body::Json;
+    pub use request::body::{Json, Multipart};
     pub use request::headers::{Claim, Path, Query};
 
-    pub use response::{Response, ResponseResult};
+    pub use response::{Download, Response, ResponseResult};
 
     pub use state::State;
 }
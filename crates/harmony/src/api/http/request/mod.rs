@@ -20,6 +20,7 @@ pub mod headers {
 
 pub mod body {
     pub use super::Json;
+    pub use super::Multipart;
 }
 
 // ===== Query =====
@@ -95,25 +96,25 @@ where
 }
 
 // ===== Multipart =====
-// use axum::extract::Multipart as AxumMultipart;
-// #[derive(Debug)]
-// pub struct Multipart(pub AxumMultipart);
-
-// #[async_trait]
-// impl<S> FromRequest<S> for Multipart
-// where
-//     S: Send + Sync,
-// {
-//     type Rejection = Response<()>;
-
-//     async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
-//         match AxumMultipart::from_request(req, state).await {
-//             Ok(value) => Ok(Self(value)),
-//             Err(rejection) => {
-//                 let response = Response::bad_request(rejection.body_text());
-
-//                 Err(response)
-//             }
-//         }
-//     }
-// }
+use axum::extract::Multipart as AxumMultipart;
+#[derive(Debug)]
+pub struct Multipart(pub AxumMultipart);
+
+#[async_trait]
+impl<S> FromRequest<S> for Multipart
+where
+    S: Send + Sync,
+{
+    type Rejection = Response<()>;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match AxumMultipart::from_request(req, state).await {
+            Ok(value) => Ok(Self(value)),
+            Err(rejection) => {
+                let response = Response::bad_request(rejection.body_text());
+
+                Err(response)
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::http::{header, StatusCode};
+use axum::{response::IntoResponse, Json};
 use serde::Serialize;
 use serde_json::json;
 
@@ -52,6 +53,15 @@ where
         response
     }
 
+    pub fn conflict(message: String) -> Self {
+        let mut response = Self::new();
+        response.ok = false;
+        response.code = 409;
+        response.message = Some(message);
+
+        response
+    }
+
     pub fn bad_request(message: String) -> Self {
         let mut response = Self::new();
         response.ok = false;
@@ -61,14 +71,14 @@ where
         response
     }
 
-    // pub fn internal_error(message: String) -> Self {
-    //     let mut response = Self::new();
-    //     response.ok = false;
-    //     response.code = 500;
-    //     response.message = Some(message);
+    pub fn internal_error(message: String) -> Self {
+        let mut response = Self::new();
+        response.ok = false;
+        response.code = 500;
+        response.message = Some(message);
 
-    //     response
-    // }
+        response
+    }
 }
 
 impl<T> IntoResponse for Response<T>
@@ -83,6 +93,39 @@ where
     }
 }
 
+/// A file handed over as it is rather than wrapped in JSON.
+pub struct Download {
+    content_type: &'static str,
+    filename: String,
+    body: Vec<u8>,
+}
+
+impl Download {
+    pub fn new(content_type: &'static str, filename: String, body: Vec<u8>) -> Self {
+        Self {
+            content_type,
+            filename,
+            body,
+        }
+    }
+}
+
+impl IntoResponse for Download {
+    fn into_response(self) -> axum::response::Response {
+        let disposition = format!("attachment; filename=\"{}\"", self.filename);
+
+        (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, self.content_type.to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            self.body,
+        )
+            .into_response()
+    }
+}
+
 mod from_general_error {
     use std::error::Error;
 
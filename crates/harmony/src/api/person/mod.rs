@@ -165,7 +165,7 @@ mod put {
         if let Some(nickname) = payload.nickname {
             let nickname = validate_value::nickname(nickname)?;
 
-            if &person.nickname != &nickname {
+            if person.nickname != nickname {
                 is_update = true;
                 person.nickname = nickname.clone();
                 result.nickname = Some(nickname);
@@ -175,7 +175,7 @@ mod put {
         if let Some(password) = payload.password {
             let password = validate_value::password(password)?;
 
-            if &person.password != &password {
+            if person.password != password {
                 is_update = true;
                 person.password = password;
             }
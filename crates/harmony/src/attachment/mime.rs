@@ -0,0 +1,181 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// The kinds of files told apart by their leading bytes. Whatever the
+/// uploader claims is ignored, so a file is always served as what it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mime {
+    Pdf,
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Tiff,
+    Zip,
+    Xml,
+    Text,
+    Binary,
+}
+
+impl Mime {
+    /// Magic bytes at the start of a file and what they mark.
+    const SIGNATURES: [(&'static [u8], Self); 8] = [
+        (b"%PDF-", Self::Pdf),
+        (b"\x89PNG\r\n\x1a\n", Self::Png),
+        (b"\xff\xd8\xff", Self::Jpeg),
+        (b"GIF87a", Self::Gif),
+        (b"GIF89a", Self::Gif),
+        (b"II*\x00", Self::Tiff),
+        (b"MM\x00*", Self::Tiff),
+        (b"PK\x03\x04", Self::Zip),
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pdf => "pdf",
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+            Self::Tiff => "tiff",
+            Self::Zip => "zip",
+            Self::Xml => "xml",
+            Self::Text => "text",
+            Self::Binary => "binary",
+        }
+    }
+
+    /// The media type a file is served with.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Pdf => "application/pdf",
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+            Self::Tiff => "image/tiff",
+            Self::Zip => "application/zip",
+            Self::Xml => "application/xml",
+            Self::Text => "text/plain; charset=utf-8",
+            Self::Binary => "application/octet-stream",
+        }
+    }
+
+    /// Tells what a file is from its content. UTF-8 without NUL bytes is
+    /// text, or XML when it opens with a declaration, and anything else
+    /// unrecognized is binary.
+    pub fn sniff(data: &[u8]) -> Self {
+        if let Some((_, mime)) = Self::SIGNATURES
+            .iter()
+            .find(|(signature, _)| data.starts_with(signature))
+        {
+            return *mime;
+        }
+
+        if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            return Self::Webp;
+        }
+
+        match std::str::from_utf8(data) {
+            Ok(text) if !text.contains('\0') => {
+                if text
+                    .trim_start_matches('\u{feff}')
+                    .trim_start()
+                    .starts_with("<?xml")
+                {
+                    Self::Xml
+                } else {
+                    Self::Text
+                }
+            }
+            _ => Self::Binary,
+        }
+    }
+}
+
+impl fmt::Display for Mime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Mime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pdf" => Ok(Self::Pdf),
+            "png" => Ok(Self::Png),
+            "jpeg" => Ok(Self::Jpeg),
+            "gif" => Ok(Self::Gif),
+            "webp" => Ok(Self::Webp),
+            "tiff" => Ok(Self::Tiff),
+            "zip" => Ok(Self::Zip),
+            "xml" => Ok(Self::Xml),
+            "text" => Ok(Self::Text),
+            "binary" => Ok(Self::Binary),
+            _ => Err(format!("unknown mime {}", s)),
+        }
+    }
+}
+
+mod database {
+    use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+    use super::Mime;
+
+    impl FromSql for Mime {
+        fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+            value
+                .as_str()?
+                .parse()
+                .map_err(|error: String| FromSqlError::Other(error.into()))
+        }
+    }
+
+    impl ToSql for Mime {
+        fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+            Ok(ToSqlOutput::from(self.as_str()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mime;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(Mime::sniff(b"%PDF-1.7\n%\xe2\xe3\xcf\xd3"), Mime::Pdf);
+        assert_eq!(
+            Mime::sniff(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"),
+            Mime::Png
+        );
+        assert_eq!(Mime::sniff(b"\xff\xd8\xff\xe0\x00\x10JFIF"), Mime::Jpeg);
+        assert_eq!(Mime::sniff(b"GIF89a\x01\x00"), Mime::Gif);
+        assert_eq!(Mime::sniff(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Mime::Webp);
+        assert_eq!(Mime::sniff(b"II*\x00\x08\x00"), Mime::Tiff);
+        assert_eq!(Mime::sniff(b"PK\x03\x04\x14\x00"), Mime::Zip);
+        assert_eq!(
+            Mime::sniff(b"\xef\xbb\xbf<?xml version=\"1.0\"?>"),
+            Mime::Xml
+        );
+        assert_eq!(
+            Mime::sniff("date,amount\n2024-01-01,12.50 €\n".as_bytes()),
+            Mime::Text
+        );
+        assert_eq!(Mime::sniff(b""), Mime::Text);
+        assert_eq!(Mime::sniff(b"text\x00with nul"), Mime::Binary);
+        assert_eq!(Mime::sniff(b"\xde\xad\xbe\xef"), Mime::Binary);
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("pdf".parse::<Mime>(), Ok(Mime::Pdf));
+        assert_eq!(Mime::Jpeg.to_string().parse::<Mime>(), Ok(Mime::Jpeg));
+        assert!("image/png".parse::<Mime>().is_err());
+    }
+}
@@ -0,0 +1,212 @@
+pub mod mime;
+pub mod store;
+
+use std::error::Error;
+
+use rusqlite::Connection;
+
+use crate::model::finance::attachment::Attachment;
+
+use mime::Mime;
+use store::BlobStore;
+
+/// Keeps the last component of an uploaded file name without control
+/// characters, quotes or backslashes, so it is safe to hand back in a
+/// `Content-Disposition` header. `None` when nothing is left.
+pub fn sanitize(name: &str) -> Option<String> {
+    let name: String = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(255)
+        .collect();
+    let name = name.trim();
+
+    match name {
+        "" | "." | ".." => None,
+        _ => Some(name.to_string()),
+    }
+}
+
+/// Stores a file and attaches it to a trade, or to a transaction when
+/// `trade_id` is none. Returns the id of the attachment.
+pub fn attach(
+    conn: &Connection,
+    store: &BlobStore,
+    owner: i64,
+    trade_id: Option<i64>,
+    transaction_id: Option<i64>,
+    name: String,
+    data: &[u8],
+) -> Result<i64, Box<dyn Error>> {
+    let mime = Mime::sniff(data);
+    let sha256 = store.put(data)?;
+    let is_encrypted = store.is_encrypted();
+
+    let result = Attachment::insert(
+        conn,
+        owner,
+        trade_id,
+        transaction_id,
+        name,
+        mime,
+        data.len() as i64,
+        sha256.clone(),
+        is_encrypted,
+    );
+
+    // Content stored only for this attachment must not stay behind
+    if result.is_err() && Attachment::count_by_sha256(conn, &sha256, is_encrypted)? == 0 {
+        store.delete(&sha256, is_encrypted)?;
+    }
+
+    Ok(result?)
+}
+
+/// Reads the content of an attachment back.
+pub fn read(store: &BlobStore, attachment: &Attachment) -> Result<Vec<u8>, Box<dyn Error>> {
+    store.get(&attachment.sha256, attachment.is_encrypted)
+}
+
+/// Removes the stored content of attachments that are gone, unless other
+/// attachments still share it. Called once their rows are deleted.
+pub fn release(
+    conn: &Connection,
+    store: &BlobStore,
+    attachments: &[Attachment],
+) -> Result<(), Box<dyn Error>> {
+    for attachment in attachments {
+        if Attachment::count_by_sha256(conn, &attachment.sha256, attachment.is_encrypted)? == 0 {
+            store.delete(&attachment.sha256, attachment.is_encrypted)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::attachment::Attachment;
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::fixture;
+
+    use super::mime::Mime;
+    use super::store::BlobStore;
+    use super::{attach, read, release, sanitize};
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("receipt.pdf").as_deref(), Some("receipt.pdf"));
+        assert_eq!(
+            sanitize("C:\\Users\\me\\receipt.pdf").as_deref(),
+            Some("receipt.pdf")
+        );
+        assert_eq!(sanitize("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(sanitize("a\"b\r\n.pdf").as_deref(), Some("ab.pdf"));
+        assert_eq!(sanitize(&"x".repeat(300)).map(|name| name.len()), Some(255));
+        assert_eq!(sanitize("dir/"), None);
+        assert_eq!(sanitize(".."), None);
+        assert_eq!(sanitize(" "), None);
+    }
+
+    #[test]
+    fn test_attach_release() {
+        let conn = fixture::connection();
+
+        let root = std::env::temp_dir().join("harmony-attachment");
+        std::fs::remove_dir_all(&root).ok();
+        let store = BlobStore::new(root, None);
+
+        let owner = fixture::person(&conn, "test_user");
+        let (btc, usd) = (
+            fixture::object(&conn, owner, "BTC"),
+            fixture::object(&conn, owner, "USD"),
+        );
+        let account = fixture::account(&conn, owner, "exchange");
+        let trade = fixture::trade(&conn, owner, btc, usd);
+        let transaction = Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::ONE.into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let pdf = b"%PDF-1.7 receipt";
+        let on_trade = attach(
+            &conn,
+            &store,
+            owner,
+            Some(trade),
+            None,
+            "a.pdf".to_string(),
+            pdf,
+        )
+        .unwrap();
+        let on_transaction = attach(
+            &conn,
+            &store,
+            owner,
+            None,
+            Some(transaction),
+            "b.pdf".to_string(),
+            pdf,
+        )
+        .unwrap();
+        assert!(attach(
+            &conn,
+            &store,
+            owner,
+            Some(trade),
+            Some(transaction),
+            "c".into(),
+            pdf
+        )
+        .is_err());
+        assert!(attach(&conn, &store, owner, None, None, "d".into(), pdf).is_err());
+
+        let attachment = Attachment::select_by_id_owner(&conn, on_trade, owner)
+            .unwrap()
+            .unwrap();
+        assert_eq!(attachment.mime, Mime::Pdf);
+        assert_eq!(attachment.size, pdf.len() as i64);
+        assert_eq!(read(&store, &attachment).unwrap(), pdf);
+        assert_eq!(
+            Attachment::count_by_entity(&conn, Some(trade), None).unwrap(),
+            1
+        );
+        assert_eq!(
+            Attachment::count_by_entity(&conn, None, Some(transaction)).unwrap(),
+            1
+        );
+
+        // Both go with the trade, and the content is shared until then
+        let attachments = Attachment::select_by_trade_id(&conn, trade).unwrap();
+        assert_eq!(attachments.len(), 2);
+
+        Attachment::delete_by_id_owner(&conn, on_transaction, owner).unwrap();
+        release(&conn, &store, &attachments[1..]).unwrap();
+        assert_eq!(read(&store, &attachment).unwrap(), pdf);
+
+        Trade::delete_by_id_owner(&conn, trade, owner).unwrap();
+        assert!(Attachment::select_by_id_owner(&conn, on_trade, owner)
+            .unwrap()
+            .is_none());
+        release(&conn, &store, &attachments).unwrap();
+        assert!(read(&store, &attachment).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::common::cipher::{ChaCha20Poly1305, Cryptographer};
+use crate::common::hash::{digest_to_hex, sha256_digest};
+
+/// Tells temporary files of concurrent writes apart.
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Files on the local filesystem, addressed by the SHA-256 digest of their
+/// content, so that the same file uploaded twice is stored once.
+///
+/// With a key, files are encrypted at rest. The nonce is taken from the
+/// digest, so equal content still encrypts to one file, and a key must not
+/// change once files were stored with it.
+pub struct BlobStore {
+    root: PathBuf,
+    key: Option<[u8; 32]>,
+}
+
+impl BlobStore {
+    pub fn new(root: PathBuf, key: Option<[u8; 32]>) -> Self {
+        Self { root, key }
+    }
+
+    /// Whether files put from now on are encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Where a file lives, fanned out by the first two digits of its digest.
+    /// Encrypted files keep apart from plain ones of the same content.
+    fn path(&self, digest: &str, is_encrypted: bool) -> Result<PathBuf, Box<dyn Error>> {
+        let is_digest = digest.len() == 64
+            && digest
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+        if !is_digest {
+            return Err(format!("{} is not a SHA-256 digest", digest).into());
+        }
+
+        let name = match is_encrypted {
+            true => format!("{}.enc", digest),
+            false => digest.to_string(),
+        };
+
+        Ok(self.root.join(&digest[..2]).join(name))
+    }
+
+    fn cryptographer(&self, digest: &str) -> Result<Box<dyn Cryptographer>, Box<dyn Error>> {
+        let key = self
+            .key
+            .ok_or("no key to encrypt attachments with is set")?;
+
+        let mut nonce = [0u8; 12];
+        for (index, byte) in nonce.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&digest[index * 2..index * 2 + 2], 16)?;
+        }
+
+        Ok(Box::new(ChaCha20Poly1305::new(key, nonce)?))
+    }
+
+    /// Stores a file unless it is stored already, and returns its digest as
+    /// hex. The file is written aside and renamed into place, so a file is
+    /// never seen half written.
+    pub fn put(&self, data: &[u8]) -> Result<String, Box<dyn Error>> {
+        let digest = digest_to_hex(&sha256_digest(data)).ok_or("digest cannot be written")?;
+        let path = self.path(&digest, self.is_encrypted())?;
+
+        if path.exists() {
+            return Ok(digest);
+        }
+
+        let content = match self.is_encrypted() {
+            true => self.cryptographer(&digest)?.encrypt(data.to_vec())?,
+            false => data.to_vec(),
+        };
+
+        let directory = path.parent().ok_or("blob has no directory")?;
+        fs::create_dir_all(directory)?;
+
+        let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        let temporary = directory.join(format!(".{}.{}.{}", digest, std::process::id(), sequence));
+        fs::write(&temporary, content)?;
+        if let Err(error) = fs::rename(&temporary, &path) {
+            fs::remove_file(&temporary).ok();
+            return Err(error.into());
+        }
+
+        Ok(digest)
+    }
+
+    /// Reads a file back, checking that it still matches its digest.
+    pub fn get(&self, digest: &str, is_encrypted: bool) -> Result<Vec<u8>, Box<dyn Error>> {
+        let content = fs::read(self.path(digest, is_encrypted)?)?;
+
+        let data = match is_encrypted {
+            true => self.cryptographer(digest)?.decrypt(content)?,
+            false => content,
+        };
+
+        if digest_to_hex(&sha256_digest(&data)).as_deref() != Some(digest) {
+            return Err(format!("blob {} is corrupted", digest).into());
+        }
+
+        Ok(data)
+    }
+
+    /// Removes a file, doing nothing when it is gone already.
+    pub fn delete(&self, digest: &str, is_encrypted: bool) -> Result<(), Box<dyn Error>> {
+        match fs::remove_file(self.path(digest, is_encrypted)?) {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::BlobStore;
+
+    fn setup(name: &str, key: Option<[u8; 32]>) -> BlobStore {
+        let root = std::env::temp_dir().join(format!("harmony-blob-store-{}", name));
+        fs::remove_dir_all(&root).ok();
+
+        BlobStore::new(root, key)
+    }
+
+    #[test]
+    fn test_put_get_delete() {
+        let store = setup("plain", None);
+
+        let digest = store.put(b"hello, world").unwrap();
+        assert_eq!(
+            digest,
+            "09ca7e4eaa6e8ae9c7d261167129184883644d07dfba7cbfbc4c8a2e08360d5b"
+        );
+        assert_eq!(store.put(b"hello, world").unwrap(), digest);
+
+        let path = store.path(&digest, false).unwrap();
+        assert!(path.starts_with(store.root.join("09")));
+        assert!(path.ends_with(&digest));
+        assert_eq!(fs::read(&path).unwrap(), b"hello, world");
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        assert_eq!(store.get(&digest, false).unwrap(), b"hello, world");
+        assert!(store.get(&digest, true).is_err());
+
+        // A file changed on disk is refused
+        fs::write(&path, b"hello, there").unwrap();
+        assert!(store.get(&digest, false).is_err());
+
+        store.delete(&digest, false).unwrap();
+        store.delete(&digest, false).unwrap();
+        assert!(store.get(&digest, false).is_err());
+
+        // Digests never reach outside the root
+        assert!(store.get("../../etc/passwd", false).is_err());
+        assert!(store.get(&digest.to_uppercase(), false).is_err());
+    }
+
+    #[test]
+    fn test_encrypted() {
+        let key = *b"12345678901234567890123456789012";
+        let store = setup("encrypted", Some(key));
+
+        let digest = store.put(b"hello, world").unwrap();
+        assert_eq!(store.put(b"hello, world").unwrap(), digest);
+
+        let path = store.path(&digest, true).unwrap();
+        assert!(path.to_string_lossy().ends_with(".enc"));
+        assert_ne!(fs::read(&path).unwrap(), b"hello, world");
+        assert_eq!(store.get(&digest, true).unwrap(), b"hello, world");
+
+        // Another key cannot read it back
+        let other = BlobStore::new(
+            store.root.clone(),
+            Some(*b"abcdefghijklmnopqrstuvwxyzabcdef"),
+        );
+        assert!(other.get(&digest, true).is_err());
+
+        // Nor can a store without a key
+        let plain = BlobStore::new(store.root.clone(), None);
+        assert!(plain.get(&digest, true).is_err());
+
+        store.delete(&digest, true).unwrap();
+        assert!(!path.exists());
+    }
+}
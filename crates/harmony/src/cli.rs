@@ -0,0 +1,62 @@
+use std::error::Error;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::export::statement::{self, Statement};
+use crate::export::{Format, Journal};
+use crate::model::database::connection;
+use crate::model::person::Person;
+
+const USAGE: &str = "usage: harmony export <nickname> <beancount|ledger>\n       \
+                     harmony export <nickname> <ofx|qif> [from] [to]";
+
+/// Runs a command given on the command line instead of serving.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args {
+        [command, nickname, format, dates @ ..] if command == "export" && dates.len() <= 2 => {
+            export(nickname, format, dates)
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+/// Reads a date as `YYYY-MM-DD`, at the start of the day or, for `is_end`,
+/// at its end.
+fn date(text: &str, is_end: bool) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a date as YYYY-MM-DD", text))?;
+    let time = if is_end {
+        date.and_hms_milli_opt(23, 59, 59, 999)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+
+    Ok(time.ok_or(format!("{} is not a date", text))?.and_utc())
+}
+
+/// Writes the journal of a person, or for the statement formats the
+/// transactions between the dates given, to standard output.
+fn export(nickname: &String, format: &str, dates: &[String]) -> Result<(), Box<dyn Error>> {
+    let conn = connection()?;
+    let person = Person::select_one_by_nickname(&conn, nickname)?
+        .ok_or(format!("person {} does not exist", nickname))?;
+
+    if let Ok(format) = format.parse::<statement::Format>() {
+        let from = dates.first().map(|text| date(text, false)).transpose()?;
+        let to = dates.get(1).map(|text| date(text, true)).transpose()?;
+        let statement = Statement::load(&conn, person.id(), from, to.unwrap_or(Utc::now()))?;
+
+        print!("{}", format.render(&statement));
+        return Ok(());
+    }
+
+    if !dates.is_empty() {
+        return Err(USAGE.into());
+    }
+    let format: Format = format.parse()?;
+    let journal = Journal::load(&conn, person.id(), Utc::now())?;
+
+    print!("{}", format.render(&journal));
+
+    Ok(())
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A five-field cron expression of minute, hour, day of month, month and day
+/// of week, evaluated in UTC.
+///
+/// Fields take `*`, single values, `a-b` ranges, `,` lists and `/n` steps.
+/// Days of week run from 0 to 7, both being Sunday. As in cron, a day
+/// matches when either a restricted day of month or day of week does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cron {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// Parses one field into a bit per allowed value.
+fn field(text: &str, min: u32, max: u32) -> Result<u64, String> {
+    let number = |text: &str| {
+        text.parse::<u32>()
+            .map_err(|_| format!("{} is not a number", text))
+    };
+    let mut bits = 0;
+
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, number(step)?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(format!("step of {} must be positive", part));
+        }
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (number(start)?, number(end)?),
+            // A stepped single value runs to the end, as in `5/15`
+            None if step > 1 => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if start < min || end > max || start > end {
+            return Err(format!("{} is out of {}-{}", part, min, max));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+impl Cron {
+    fn matches(&self, date: NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+
+        has(self.months, date.month())
+            && match (self.any_day, self.any_weekday) {
+                (true, true) => true,
+                (false, true) => day,
+                (true, false) => weekday,
+                (false, false) => day || weekday,
+            }
+    }
+
+    /// The first instant at or after `at` the expression matches, `None`
+    /// when it never does.
+    pub fn first_from(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = at.with_second(0)?.with_nanosecond(0)?;
+        let start = if start < at {
+            start + Duration::minutes(1)
+        } else {
+            start
+        };
+
+        let mut date = start.date_naive();
+        let (mut earliest_hour, mut earliest_minute) = (start.hour(), start.minute());
+
+        // Long enough to reach any day that exists, such as a Monday 29 February
+        for _ in 0..366 * 28 {
+            if self.matches(date) {
+                for hour in (earliest_hour..24).filter(|&hour| has(self.hours, hour)) {
+                    let from = if hour == earliest_hour {
+                        earliest_minute
+                    } else {
+                        0
+                    };
+
+                    if let Some(minute) = (from..60).find(|&minute| has(self.minutes, minute)) {
+                        return Some(Utc.from_utc_datetime(&date.and_hms_opt(hour, minute, 0)?));
+                    }
+                }
+            }
+
+            date = date.succ_opt()?;
+            (earliest_hour, earliest_minute) = (0, 0);
+        }
+
+        None
+    }
+
+    /// The first instant strictly after `at` the expression matches.
+    pub fn after(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.first_from(at + Duration::nanoseconds(1))
+    }
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("{} does not have five fields", s));
+        };
+
+        let mut weekday_bits = field(weekdays, 0, 7)?;
+        if has(weekday_bits, 7) {
+            weekday_bits |= 1;
+        }
+
+        Ok(Self {
+            source: fields.join(" "),
+            minutes: field(minutes, 0, 59)?,
+            hours: field(hours, 0, 23)?,
+            days: field(days, 1, 31)?,
+            months: field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+impl TryFrom<String> for Cron {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Cron> for String {
+    fn from(value: Cron) -> Self {
+        value.source
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::Cron;
+
+    #[test]
+    fn test_every_monday() {
+        let cron: Cron = "0 9 * * 1".parse().unwrap();
+        // A Wednesday
+        let at = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+
+        let next = cron.first_from(at).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 1, 8, 9, 0, 0).unwrap());
+        assert_eq!(cron.first_from(next), Some(next));
+        assert_eq!(
+            cron.after(next),
+            Some(Utc.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_lists_ranges_and_steps() {
+        let cron: Cron = "*/15 8-9 1,15 * *".parse().unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 9, 46, 30).unwrap();
+
+        assert_eq!(
+            cron.first_from(at),
+            Some(Utc.with_ymd_and_hms(2024, 1, 15, 8, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_day_of_month_or_weekday() {
+        // The 13th or any Friday
+        let cron: Cron = "0 0 13 * 5".parse().unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            cron.first_from(at),
+            Some(Utc.with_ymd_and_hms(2024, 1, 5, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            cron.after(Utc.with_ymd_and_hms(2024, 1, 12, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2024, 1, 13, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_rare_and_impossible_days() {
+        let cron: Cron = "0 0 29 2 *".parse().unwrap();
+        let at = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            cron.first_from(at),
+            Some(Utc.with_ymd_and_hms(2028, 2, 29, 0, 0, 0).unwrap())
+        );
+
+        let cron: Cron = "0 0 30 2 *".parse().unwrap();
+        assert_eq!(cron.first_from(at), None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        for source in [
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "a * * * *",
+        ] {
+            assert!(source.parse::<Cron>().is_err(), "{}", source);
+        }
+
+        let cron: Cron = "0 0 * * 7".parse().unwrap();
+        assert_eq!(cron.to_string(), "0 0 * * 7");
+        assert_eq!(
+            cron.first_from(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2024, 1, 7, 0, 0, 0).unwrap())
+        );
+    }
+}
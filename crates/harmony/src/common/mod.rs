@@ -1,3 +1,4 @@
 pub mod cipher;
+pub mod cron;
 pub mod encode;
 pub mod hash;
@@ -14,7 +14,15 @@ pub mod database {
         let pool = r2d2::Pool::new(database).unwrap();
 
         let conn = pool.get().unwrap();
+        crate::model::migration::migrate(&conn).unwrap();
         conn.execute_batch(&crate::model::initialize()).unwrap();
+        crate::model::finance::trade::Trade::set_unique_pair(
+            &conn,
+            *super::finance::UNIQUE_TRADE_PAIR,
+        )
+        .expect(
+            "FINANCE_UNIQUE_TRADE_PAIR cannot be set while an owner has several trades of a pair",
+        );
 
         pool
     });
@@ -43,3 +51,124 @@ pub mod claim_encrypt {
         .unwrap()
     });
 }
+
+pub mod attachment {
+    use std::path::PathBuf;
+
+    use crate::attachment::store::BlobStore;
+
+    use super::LazyLock;
+
+    /// Where attachments are kept, encrypted at rest when `ATTACHMENT_KEY`
+    /// is set to 32 bytes.
+    pub static STORE: LazyLock<BlobStore> = LazyLock::new(|| {
+        dotenvy::dotenv().ok();
+
+        let root = std::env::var("ATTACHMENT_DIR").unwrap_or("attachments".to_string());
+        let key = std::env::var("ATTACHMENT_KEY").ok().map(|key| {
+            key.as_bytes()
+                .try_into()
+                .expect("ATTACHMENT_KEY must be 32 bytes")
+        });
+
+        BlobStore::new(PathBuf::from(root), key)
+    });
+
+    /// The largest file accepted, in bytes.
+    pub static MAX_SIZE: LazyLock<usize> = LazyLock::new(|| {
+        dotenvy::dotenv().ok();
+
+        std::env::var("ATTACHMENT_MAX_SIZE")
+            .map(|value| value.parse().expect("ATTACHMENT_MAX_SIZE must be bytes"))
+            .unwrap_or(10 * 1024 * 1024)
+    });
+}
+
+pub mod market {
+    use std::time::Duration;
+
+    use crate::market::provider::{from_config, PriceProvider};
+
+    use super::LazyLock;
+
+    pub static PROVIDER: LazyLock<Option<Box<dyn PriceProvider>>> = LazyLock::new(|| {
+        dotenvy::dotenv().ok();
+
+        let kind = std::env::var("MARKET_PROVIDER").ok()?;
+        let source = std::env::var("MARKET_SOURCE").expect("MARKET_SOURCE must be set");
+
+        Some(from_config(&kind, &source).expect("MARKET_PROVIDER must be a known provider"))
+    });
+
+    pub static REFRESH_INTERVAL: LazyLock<Duration> = LazyLock::new(|| {
+        dotenvy::dotenv().ok();
+
+        let seconds = std::env::var("MARKET_REFRESH_INTERVAL")
+            .map(|value| {
+                value
+                    .parse()
+                    .expect("MARKET_REFRESH_INTERVAL must be seconds")
+            })
+            .unwrap_or(3600);
+
+        Duration::from_secs(seconds)
+    });
+}
+
+pub mod finance {
+    use std::time::Duration;
+
+    use crate::portfolio::tax::FiscalYear;
+
+    use super::LazyLock;
+
+    /// Whether an owner may keep only one trade per base and quote pair.
+    pub static UNIQUE_TRADE_PAIR: LazyLock<bool> = LazyLock::new(|| {
+        dotenvy::dotenv().ok();
+
+        std::env::var("FINANCE_UNIQUE_TRADE_PAIR")
+            .map(|value| {
+                value
+                    .parse()
+                    .expect("FINANCE_UNIQUE_TRADE_PAIR must be a boolean")
+            })
+            .unwrap_or(false)
+    });
+
+    /// How often due plan runs are turned into transactions.
+    pub static PLAN_INTERVAL: LazyLock<Duration> = LazyLock::new(|| {
+        dotenvy::dotenv().ok();
+
+        let seconds = std::env::var("FINANCE_PLAN_INTERVAL")
+            .map(|value| {
+                value
+                    .parse()
+                    .expect("FINANCE_PLAN_INTERVAL must be seconds")
+            })
+            .unwrap_or(60);
+
+        Duration::from_secs(seconds)
+    });
+
+    /// When fiscal years begin, as `MM-DD`.
+    pub static FISCAL_YEAR_START: LazyLock<FiscalYear> = LazyLock::new(|| {
+        dotenvy::dotenv().ok();
+
+        std::env::var("FINANCE_FISCAL_YEAR_START")
+            .map(|value| {
+                value
+                    .parse()
+                    .expect("FINANCE_FISCAL_YEAR_START must be MM-DD")
+            })
+            .unwrap_or_default()
+    });
+
+    /// Whole days a lot must be held beyond to be long-term.
+    pub static LONG_TERM_DAYS: LazyLock<i64> = LazyLock::new(|| {
+        dotenvy::dotenv().ok();
+
+        std::env::var("FINANCE_LONG_TERM_DAYS")
+            .map(|value| value.parse().expect("FINANCE_LONG_TERM_DAYS must be days"))
+            .unwrap_or(365)
+    });
+}
@@ -0,0 +1,168 @@
+use std::fmt::Write;
+
+use chrono::NaiveDate;
+
+use super::{number, Amount, Journal, Open, Posting, CAPITAL_GAINS};
+
+/// Quotes a string, escaping what would end it.
+pub fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn amount(amount: &Amount) -> String {
+    format!("{} {}", number(amount.number), amount.commodity)
+}
+
+fn posting(posting: &Posting) -> String {
+    let mut line = format!("  {}", posting.account);
+
+    if let Some(units) = &posting.units {
+        write!(line, "  {}", amount(units)).ok();
+    }
+    if let Some(lot) = &posting.lot {
+        write!(line, " {{{}, {}}}", amount(&lot.cost), lot.date).ok();
+    }
+    if let Some(price) = &posting.price {
+        write!(line, " @ {}", amount(price)).ok();
+    }
+
+    line
+}
+
+/// Renders a journal as a Beancount file. Lots given up at a price leave
+/// their gain or loss to `Income:Capital-Gains`. Metadata keeps the symbols
+/// and account names that don't fit the syntax, and the time of day, for
+/// the journal to be read back.
+pub fn render(journal: &Journal) -> String {
+    let mut output = String::from("option \"booking_method\" \"FIFO\"\n\n");
+
+    for commodity in &journal.commodities {
+        writeln!(output, "{} commodity {}", commodity.date, commodity.name).ok();
+        if commodity.symbol != commodity.name {
+            writeln!(output, "  symbol: {}", quote(&commodity.symbol)).ok();
+        }
+        if let Some(alias) = &commodity.alias {
+            writeln!(output, "  name: {}", quote(alias)).ok();
+        }
+    }
+    if !journal.commodities.is_empty() {
+        output.push('\n');
+    }
+
+    let mut accounts = journal.accounts.clone();
+    let realized = journal
+        .transactions
+        .iter()
+        .find(|transaction| transaction.realizes())
+        .map(|transaction| transaction.occurrence_at.date_naive());
+    if let Some(date) = realized {
+        if accounts.iter().all(|open| open.name != CAPITAL_GAINS) {
+            accounts.push(Open {
+                name: CAPITAL_GAINS.to_string(),
+                date,
+                account: None,
+            });
+            accounts.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+    }
+
+    for open in &accounts {
+        writeln!(output, "{} open {}", open.date, open.name).ok();
+        if let Some(account) = &open.account {
+            writeln!(output, "  name: {}", quote(account)).ok();
+        }
+    }
+    if !accounts.is_empty() {
+        output.push('\n');
+    }
+
+    // Prices come before the transactions of the same day
+    let mut entries: Vec<(NaiveDate, u8, String)> = journal
+        .prices
+        .iter()
+        .map(|price| {
+            let text = format!(
+                "{} price {} {}\n",
+                price.date,
+                price.commodity,
+                amount(&price.price)
+            );
+            (price.date, 0, text)
+        })
+        .collect();
+
+    for transaction in &journal.transactions {
+        let date = transaction.occurrence_at.date_naive();
+        let flag = if transaction.pending { '!' } else { '*' };
+
+        let mut text = format!("{} {} {}\n", date, flag, quote(&transaction.narration));
+        writeln!(
+            text,
+            "  time: {}",
+            quote(&transaction.occurrence_at.to_rfc3339())
+        )
+        .ok();
+        for item in &transaction.postings {
+            writeln!(text, "{}", posting(item)).ok();
+        }
+
+        let is_elided = transaction.postings.iter().any(|item| item.units.is_none());
+        if transaction.realizes() && !is_elided {
+            writeln!(text, "  {}", CAPITAL_GAINS).ok();
+        }
+
+        entries.push((date, 1, text));
+    }
+
+    entries.sort_by_key(|(date, rank, _)| (*date, *rank));
+
+    let entries: Vec<String> = entries.into_iter().map(|(_, _, text)| text).collect();
+    output.push_str(&entries.join("\n"));
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::export::tests::{setup, Setup};
+    use crate::export::Journal;
+
+    use super::{quote, render};
+
+    #[test]
+    fn test_render() {
+        let Setup { conn, owner } = setup();
+        let journal = Journal::load(&conn, owner, Utc::now()).unwrap();
+
+        let text = render(&journal);
+
+        assert!(text.starts_with("option \"booking_method\" \"FIFO\"\n"));
+        assert!(text.contains(" commodity BTC\n"));
+        assert!(text.contains(" open Assets:Cold-Wallet\n  name: \"cold wallet\"\n"));
+        assert!(text.contains(" open Income:Capital-Gains\n"));
+        assert!(text.contains("2024-01-04 price BTC 310 USD\n"));
+        assert!(text.contains(
+            "2024-01-01 * \"Buy BTC with USD\"\n\
+             \x20 time: \"2024-01-01T12:00:00+00:00\"\n\
+             \x20 Assets:My-Exchange  2 BTC {100 USD, 2024-01-01} @ 100 USD\n\
+             \x20 Assets:My-Exchange  -200 USD\n"
+        ));
+        assert!(text.contains(
+            "\x20 Assets:Cold-Wallet  -2 BTC {100 USD, 2024-01-01} @ 300 USD\n\
+             \x20 Assets:Cold-Wallet  -0.5 BTC {200 USD, 2024-01-02} @ 300 USD\n"
+        ));
+        assert!(text.contains(
+            "\x20 Assets:Cold-Wallet  750 USD\n\
+             \x20 Assets:Cold-Wallet  -1 USD\n\
+             \x20 Expenses:Fees  1 USD\n\
+             \x20 Income:Capital-Gains\n"
+        ));
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt::Write;
+
+use chrono::NaiveDate;
+
+use super::{number, Amount, Journal, Posting};
+
+/// Quotes commodities other than plain words, as Ledger reads digits as
+/// part of the amount.
+fn commodity(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_alphabetic()) {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name)
+    }
+}
+
+fn amount(amount: &Amount) -> String {
+    format!("{} {}", number(amount.number), commodity(&amount.commodity))
+}
+
+fn date(date: NaiveDate) -> String {
+    date.format("%Y/%m/%d").to_string()
+}
+
+fn posting(posting: &Posting) -> String {
+    let mut line = format!("    {}", posting.account);
+
+    if let Some(units) = &posting.units {
+        write!(line, "  {}", amount(units)).ok();
+    }
+    if let Some(lot) = &posting.lot {
+        write!(line, " {{{}}} [{}]", amount(&lot.cost), date(lot.date)).ok();
+    }
+    if let Some(price) = &posting.price {
+        write!(line, " @ {}", amount(price)).ok();
+    }
+
+    line
+}
+
+/// Renders a journal as a Ledger file, which hledger reads as well. Sales
+/// balance at the price they were made at, with the lots they give up
+/// annotated for Ledger to work out gains.
+pub fn render(journal: &Journal) -> String {
+    let mut output = String::new();
+
+    for item in &journal.commodities {
+        writeln!(output, "commodity {}", commodity(&item.name)).ok();
+        if let Some(alias) = &item.alias {
+            writeln!(output, "    note {}", alias).ok();
+        }
+        if item.symbol != item.name {
+            writeln!(output, "    ; symbol: {}", item.symbol).ok();
+        }
+    }
+    if !journal.commodities.is_empty() {
+        output.push('\n');
+    }
+
+    for open in &journal.accounts {
+        writeln!(output, "account {}", open.name).ok();
+        if let Some(account) = &open.account {
+            writeln!(output, "    ; name: {}", account).ok();
+        }
+    }
+    if !journal.accounts.is_empty() {
+        output.push('\n');
+    }
+
+    // Prices come before the transactions of the same day
+    let mut entries: Vec<(NaiveDate, u8, String)> = journal
+        .prices
+        .iter()
+        .map(|price| {
+            let text = format!(
+                "P {} 00:00:00 {} {}\n",
+                date(price.date),
+                commodity(&price.commodity),
+                amount(&price.price)
+            );
+            (price.date, 0, text)
+        })
+        .collect();
+
+    for transaction in &journal.transactions {
+        let day = transaction.occurrence_at.date_naive();
+        let flag = if transaction.pending { '!' } else { '*' };
+
+        let mut text = format!("{} {} {}\n", date(day), flag, transaction.narration);
+        writeln!(
+            text,
+            "    ; time: {}",
+            transaction.occurrence_at.to_rfc3339()
+        )
+        .ok();
+        for item in &transaction.postings {
+            writeln!(text, "{}", posting(item)).ok();
+        }
+
+        entries.push((day, 1, text));
+    }
+
+    entries.sort_by_key(|(day, rank, _)| (*day, *rank));
+
+    let entries: Vec<String> = entries.into_iter().map(|(_, _, text)| text).collect();
+    output.push_str(&entries.join("\n"));
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::export::tests::{setup, Setup};
+    use crate::export::Journal;
+
+    use super::{commodity, render};
+
+    #[test]
+    fn test_render() {
+        let Setup { conn, owner } = setup();
+        let journal = Journal::load(&conn, owner, Utc::now()).unwrap();
+
+        let text = render(&journal);
+
+        assert!(text.starts_with("commodity BTC\n"));
+        assert!(text.contains("account Assets:Cold-Wallet\n    ; name: cold wallet\n"));
+        assert!(text.contains("P 2024/01/04 00:00:00 BTC 310 USD\n"));
+        assert!(text.contains(
+            "2024/01/04 * Sell BTC for USD\n\
+             \x20   ; time: 2024-01-04T12:00:00+00:00\n\
+             \x20   Assets:Cold-Wallet  -2 BTC {100 USD} [2024/01/01] @ 300 USD\n\
+             \x20   Assets:Cold-Wallet  -0.5 BTC {200 USD} [2024/01/02] @ 300 USD\n\
+             \x20   Assets:Cold-Wallet  750 USD\n"
+        ));
+        assert!(!text.contains("Capital-Gains"));
+    }
+
+    #[test]
+    fn test_commodity() {
+        assert_eq!(commodity("BTC"), "BTC");
+        assert_eq!(commodity("X1INCH"), "\"X1INCH\"");
+    }
+}
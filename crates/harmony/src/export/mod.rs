@@ -0,0 +1,945 @@
+pub mod beancount;
+pub mod ledger;
+pub mod ofx;
+pub mod qif;
+pub mod statement;
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::model::finance::account::Account;
+use crate::model::finance::flow::{self, Flow};
+use crate::model::finance::object::action::{CorporateAction, Kind as ActionKind};
+use crate::model::finance::object::{AssetType, Object};
+use crate::model::finance::price::Price as PriceRow;
+use crate::model::finance::trade::transaction::{Status, Transaction as TransactionRow};
+use crate::model::finance::trade::Trade;
+use crate::model::finance::transfer::Transfer;
+use crate::portfolio::balance::{self, Source};
+
+pub const FEES: &str = "Expenses:Fees";
+pub const TAXES: &str = "Expenses:Taxes";
+pub const SPENDING: &str = "Expenses:Spending";
+pub const CONTRIBUTIONS: &str = "Equity:Contributions";
+/// Takes the other side of trades without a price.
+pub const UNPRICED: &str = "Equity:Unpriced";
+/// Takes the other side of corporate actions on units held without a cost.
+pub const CONVERSIONS: &str = "Equity:Conversions";
+pub const CAPITAL_GAINS: &str = "Income:Capital-Gains";
+
+/// The plain-text accounting formats a journal renders to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Beancount,
+    Ledger,
+}
+
+impl Format {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Beancount => "beancount",
+            Self::Ledger => "ledger",
+        }
+    }
+
+    pub fn render(&self, journal: &Journal) -> String {
+        match self {
+            Self::Beancount => beancount::render(journal),
+            Self::Ledger => ledger::render(journal),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "beancount" => Ok(Self::Beancount),
+            "ledger" => Ok(Self::Ledger),
+            _ => Err(format!("unknown export format {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Amount {
+    pub number: Decimal,
+    pub commodity: String,
+}
+
+/// What a lot of a commodity cost per unit and when it was acquired.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lot {
+    pub cost: Amount,
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Posting {
+    pub account: String,
+    /// `None` for the posting that takes whatever the others leave over.
+    pub units: Option<Amount>,
+    /// The lot added to or taken from.
+    pub lot: Option<Lot>,
+    /// Per unit.
+    pub price: Option<Amount>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transaction {
+    pub occurrence_at: DateTime<Utc>,
+    pub pending: bool,
+    pub narration: String,
+    pub postings: Vec<Posting>,
+}
+
+impl Transaction {
+    /// Whether lots are given up at a price, leaving a gain or loss for the
+    /// cost-based formats to book.
+    pub fn realizes(&self) -> bool {
+        self.postings.iter().any(|posting| {
+            posting.lot.is_some()
+                && posting.price.is_some()
+                && posting
+                    .units
+                    .as_ref()
+                    .is_some_and(|units| units.number.is_sign_negative())
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commodity {
+    pub name: String,
+    pub date: NaiveDate,
+    /// The symbol of the object, which the name only approximates when the
+    /// symbol has characters the formats don't allow.
+    pub symbol: String,
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Open {
+    pub name: String,
+    pub date: NaiveDate,
+    /// The name of the account of the owner, `None` for the income, expense
+    /// and equity accounts.
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Price {
+    pub date: NaiveDate,
+    pub commodity: String,
+    pub price: Amount,
+}
+
+/// Everything of an owner in terms of double-entry bookkeeping, ready to be
+/// rendered as plain text.
+///
+/// Accounts of the owner become `Assets:` accounts. Trades keep lots of
+/// objects other than fiat, acquired at the price paid and given up oldest
+/// first, transfers carry the lots along and corporate actions convert
+/// them at the same total cost, so that every lot given up is named by its
+/// cost and date. Other movements carry no cost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Journal {
+    /// By name.
+    pub commodities: Vec<Commodity>,
+    /// By name.
+    pub accounts: Vec<Open>,
+    /// In time order.
+    pub prices: Vec<Price>,
+    /// In time order.
+    pub transactions: Vec<Transaction>,
+}
+
+/// Turns `text` into a name made of ASCII letters, digits and dashes, words
+/// capitalized, that starts with a letter.
+fn sanitize(text: &str, capitalize: fn(&str) -> String) -> String {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(capitalize)
+        .collect();
+    let name = words.join("-");
+
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        _ => format!("X{}", name),
+    }
+}
+
+/// Gives each id a distinct name, suffixing the id to names taken already.
+fn names<'a>(
+    items: impl IntoIterator<Item = (i64, &'a str)>,
+    name: impl Fn(&str) -> String,
+) -> HashMap<i64, String> {
+    let mut taken = HashSet::new();
+    let mut result = HashMap::new();
+
+    for (id, text) in items {
+        let mut candidate = name(text);
+        if !taken.insert(candidate.clone()) {
+            candidate = format!("{}-{}", candidate, id);
+            taken.insert(candidate.clone());
+        }
+        result.insert(id, candidate);
+    }
+
+    result
+}
+
+fn commodity_name(symbol: &str) -> String {
+    let name = sanitize(symbol, |word| word.to_ascii_uppercase());
+
+    name.chars().take(24).collect()
+}
+
+fn account_name(name: &str) -> String {
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+            .unwrap_or_default()
+    };
+
+    format!("Assets:{}", sanitize(name, capitalize))
+}
+
+/// Open lots by account and object, oldest first. A quantity without a lot
+/// stands for units held without a cost.
+#[derive(Default)]
+struct Lots(HashMap<(i64, i64), VecDeque<(Decimal, Lot)>>);
+
+impl Lots {
+    fn add(&mut self, account_id: i64, object_id: i64, quantity: Decimal, lot: Lot) {
+        self.0
+            .entry((account_id, object_id))
+            .or_default()
+            .push_back((quantity, lot));
+    }
+
+    /// Takes `quantity` from the oldest lots, the rest from units without
+    /// a cost.
+    fn take(
+        &mut self,
+        account_id: i64,
+        object_id: i64,
+        quantity: Decimal,
+    ) -> Vec<(Decimal, Option<Lot>)> {
+        let mut taken = Vec::new();
+        let mut left = quantity;
+        let lots = self.0.entry((account_id, object_id)).or_default();
+
+        while left > Decimal::ZERO {
+            let Some((held, lot)) = lots.front_mut() else {
+                break;
+            };
+
+            let part = left.min(*held);
+            taken.push((part, Some(lot.clone())));
+            *held -= part;
+            left -= part;
+
+            if held.is_zero() {
+                lots.pop_front();
+            }
+        }
+
+        if left > Decimal::ZERO {
+            taken.push((left, None));
+        }
+
+        taken
+    }
+}
+
+fn amount(number: Decimal, commodity: &str) -> Option<Amount> {
+    Some(Amount {
+        number,
+        commodity: commodity.to_string(),
+    })
+}
+
+fn posting(account: &str, number: Decimal, commodity: &str) -> Posting {
+    Posting {
+        account: account.to_string(),
+        units: amount(number, commodity),
+        lot: None,
+        price: None,
+    }
+}
+
+fn income_account(kind: flow::Kind) -> String {
+    let name = kind.as_str();
+
+    format!("Income:{}{}", name[..1].to_ascii_uppercase(), &name[1..])
+}
+
+/// What happened at one time, so trades, flows, transfers and corporate
+/// actions keep their order across kinds.
+enum Event {
+    Trade(TransactionRow),
+    Flow(Flow),
+    Transfer(Transfer),
+    Action(CorporateAction),
+}
+
+impl Event {
+    fn occurrence_at(&self) -> DateTime<Utc> {
+        match self {
+            Self::Trade(transaction) => transaction.occurrence_at,
+            Self::Flow(flow) => flow.occurrence_at,
+            Self::Transfer(transfer) => transfer.occurrence_at,
+            Self::Action(action) => action.effective_at,
+        }
+    }
+}
+
+impl Journal {
+    /// Loads the objects, accounts, prices and the executed trades, flows
+    /// and transfers of the owner up to `until`. Pending transactions are
+    /// marked as such.
+    pub fn load(
+        conn: &Connection,
+        owner: i64,
+        until: DateTime<Utc>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let objects = Object::select_all_by_owner(conn, owner)?;
+        let accounts = Account::select_all_by_owner(conn, owner)?;
+        let trades: HashMap<i64, Trade> = Trade::select_all_by_owner(conn, owner)?
+            .into_iter()
+            .map(|trade| (trade.id(), trade))
+            .collect();
+
+        let commodities = names(
+            objects
+                .iter()
+                .map(|object| (object.id(), object.symbol.as_str())),
+            commodity_name,
+        );
+        let account_names = names(
+            accounts
+                .iter()
+                .map(|account| (account.id(), account.name.as_str())),
+            account_name,
+        );
+        let is_fiat: HashMap<i64, bool> = objects
+            .iter()
+            .map(|object| {
+                (
+                    object.id(),
+                    object.classification.asset_type == AssetType::Fiat,
+                )
+            })
+            .collect();
+        let symbols: HashMap<i64, &str> = objects
+            .iter()
+            .map(|object| (object.id(), object.symbol.as_str()))
+            .collect();
+
+        let commodity = |id: i64| -> Result<&str, Box<dyn Error>> {
+            Ok(commodities
+                .get(&id)
+                .ok_or(format!("object {} does not exist", id))?
+                .as_str())
+        };
+        let account = |id: i64| -> Result<&str, Box<dyn Error>> {
+            Ok(account_names
+                .get(&id)
+                .ok_or(format!("account {} does not exist", id))?
+                .as_str())
+        };
+        let symbol = |id: i64| symbols.get(&id).copied().unwrap_or_default();
+
+        let actions = CorporateAction::select_by_owner_until(conn, owner, until)?;
+
+        // What each account held of an object when an action converted it
+        let action_objects: HashMap<i64, i64> = actions
+            .iter()
+            .map(|action| (action.id(), action.object_id))
+            .collect();
+        let mut held: HashMap<(i64, i64), Decimal> = HashMap::new();
+        let mut holdings: HashMap<i64, Vec<(i64, Decimal)>> = HashMap::new();
+        for movement in balance::load(conn, owner, until, true)? {
+            let key = (movement.account_id, movement.object_id);
+
+            if let Source::Action { action_id, .. } = movement.source {
+                if action_objects.get(&action_id) == Some(&movement.object_id) {
+                    let quantity = held.get(&key).copied().unwrap_or_default();
+                    holdings
+                        .entry(action_id)
+                        .or_default()
+                        .push((movement.account_id, quantity));
+                }
+            }
+
+            *held.entry(key).or_default() += movement.quantity;
+        }
+
+        // Actions go first, as what happens at the instant they take effect
+        // is already in the new units
+        let rows = TransactionRow::select_by_owner_until(conn, owner, until, true)?;
+        let mut events: Vec<Event> = actions
+            .into_iter()
+            .map(Event::Action)
+            .chain(rows.into_iter().map(Event::Trade))
+            .chain(
+                Flow::select_by_owner_until(conn, owner, until)?
+                    .into_iter()
+                    .map(Event::Flow),
+            )
+            .chain(
+                Transfer::select_by_owner_until(conn, owner, until)?
+                    .into_iter()
+                    .map(Event::Transfer),
+            )
+            .collect();
+        events.sort_by_key(Event::occurrence_at);
+
+        let mut lots = Lots::default();
+        let mut transactions = Vec::new();
+
+        for event in events {
+            let occurrence_at = event.occurrence_at();
+            let date = occurrence_at.date_naive();
+
+            let (pending, narration, postings) = match event {
+                Event::Trade(transaction) => {
+                    let trade = trades
+                        .get(&transaction.trade_id)
+                        .ok_or(format!("trade {} does not exist", transaction.trade_id))?;
+                    let base = commodity(trade.base_object_id)?;
+                    let quote = commodity(trade.quote_object_id)?;
+                    let holding = account(transaction.account_id)?;
+                    let has_lots = !is_fiat.get(&trade.base_object_id).copied().unwrap_or(true);
+                    let quantity = transaction.quantity.value();
+                    let price = transaction.price.as_ref().map(|price| price.value());
+
+                    let mut postings = Vec::new();
+
+                    match price {
+                        Some(price) => {
+                            let total = quantity.checked_mul(price).ok_or(format!(
+                                "transaction {} overflows its quote amount",
+                                transaction.id()
+                            ))?;
+
+                            if transaction.is_base_to_quote {
+                                let taken = if has_lots {
+                                    lots.take(
+                                        transaction.account_id,
+                                        trade.base_object_id,
+                                        quantity,
+                                    )
+                                } else {
+                                    vec![(quantity, None)]
+                                };
+                                for (part, lot) in taken {
+                                    postings.push(Posting {
+                                        lot,
+                                        price: amount(price, quote),
+                                        ..posting(holding, -part, base)
+                                    });
+                                }
+                                postings.push(posting(holding, total, quote));
+                            } else {
+                                let lot = has_lots.then(|| Lot {
+                                    cost: Amount {
+                                        number: price,
+                                        commodity: quote.to_string(),
+                                    },
+                                    date,
+                                });
+                                if let Some(lot) = &lot {
+                                    lots.add(
+                                        transaction.account_id,
+                                        trade.base_object_id,
+                                        quantity,
+                                        lot.clone(),
+                                    );
+                                }
+                                postings.push(Posting {
+                                    lot,
+                                    price: amount(price, quote),
+                                    ..posting(holding, quantity, base)
+                                });
+                                postings.push(posting(holding, -total, quote));
+                            }
+                        }
+                        None => {
+                            let sign = if transaction.is_base_to_quote {
+                                Decimal::NEGATIVE_ONE
+                            } else {
+                                Decimal::ONE
+                            };
+                            postings.push(posting(holding, sign * quantity, base));
+                            postings.push(Posting {
+                                units: None,
+                                ..posting(UNPRICED, Decimal::ZERO, base)
+                            });
+                        }
+                    }
+
+                    if let (Some(fee), Some(fee_object_id)) =
+                        (&transaction.fee, transaction.fee_object_id)
+                    {
+                        let fee_commodity = commodity(fee_object_id)?;
+                        postings.push(posting(holding, -fee.value(), fee_commodity));
+                        postings.push(posting(FEES, fee.value(), fee_commodity));
+                    }
+
+                    let narration = transaction
+                        .remark
+                        .clone()
+                        .or(transaction.alias.clone())
+                        .unwrap_or_else(|| {
+                            if transaction.is_base_to_quote {
+                                format!(
+                                    "Sell {} for {}",
+                                    symbol(trade.base_object_id),
+                                    symbol(trade.quote_object_id)
+                                )
+                            } else {
+                                format!(
+                                    "Buy {} with {}",
+                                    symbol(trade.base_object_id),
+                                    symbol(trade.quote_object_id)
+                                )
+                            }
+                        });
+
+                    (transaction.status == Status::Pending, narration, postings)
+                }
+                Event::Flow(flow) => {
+                    let object = commodity(flow.object_id)?;
+                    let holding = account(flow.account_id)?;
+                    let quantity = flow.quantity.value();
+
+                    let postings = match flow.kind {
+                        flow::Kind::Deposit => vec![
+                            posting(holding, quantity, object),
+                            posting(CONTRIBUTIONS, -quantity, object),
+                        ],
+                        flow::Kind::Withdrawal => vec![
+                            posting(holding, -quantity, object),
+                            posting(CONTRIBUTIONS, quantity, object),
+                        ],
+                        flow::Kind::Expense => vec![
+                            posting(holding, -quantity, object),
+                            posting(SPENDING, quantity, object),
+                        ],
+                        flow::Kind::Dividend
+                        | flow::Kind::Interest
+                        | flow::Kind::Staking
+                        | flow::Kind::Airdrop => {
+                            let tax = flow
+                                .tax_withheld
+                                .as_ref()
+                                .map(|tax| tax.value())
+                                .unwrap_or_default();
+
+                            let mut postings = vec![
+                                posting(holding, quantity, object),
+                                posting(&income_account(flow.kind), -quantity - tax, object),
+                            ];
+                            if !tax.is_zero() {
+                                postings.push(posting(TAXES, tax, object));
+                            }
+
+                            postings
+                        }
+                    };
+
+                    let narration = flow.remark.clone().unwrap_or_else(|| {
+                        let kind = income_account(flow.kind);
+                        format!("{} of {}", &kind["Income:".len()..], symbol(flow.object_id))
+                    });
+
+                    (false, narration, postings)
+                }
+                Event::Transfer(transfer) => {
+                    let object = commodity(transfer.object_id)?;
+                    let from = account(transfer.from_account_id)?;
+                    let to = account(transfer.to_account_id)?;
+                    let quantity = transfer.quantity.value();
+
+                    let mut postings = Vec::new();
+                    let taken = lots.take(transfer.from_account_id, transfer.object_id, quantity);
+                    for (part, lot) in taken {
+                        if let Some(lot) = &lot {
+                            lots.add(
+                                transfer.to_account_id,
+                                transfer.object_id,
+                                part,
+                                lot.clone(),
+                            );
+                        }
+                        postings.push(Posting {
+                            lot: lot.clone(),
+                            ..posting(from, -part, object)
+                        });
+                        postings.push(Posting {
+                            lot,
+                            ..posting(to, part, object)
+                        });
+                    }
+
+                    let narration = transfer
+                        .remark
+                        .clone()
+                        .unwrap_or_else(|| format!("Transfer {}", symbol(transfer.object_id)));
+
+                    (false, narration, postings)
+                }
+                Event::Action(action) => {
+                    let Some(holdings) = holdings.get(&action.id()) else {
+                        continue;
+                    };
+                    let object = commodity(action.object_id)?;
+                    let target_object_id = action.target_object_id();
+                    let target = commodity(target_object_id)?;
+                    let ratio = action.ratio.value();
+                    let overflow = || format!("corporate action {} overflows", action.id());
+
+                    let mut postings = Vec::new();
+                    for &(account_id, quantity) in holdings {
+                        let holding = account(account_id)?;
+
+                        for (part, lot) in lots.take(account_id, action.object_id, quantity) {
+                            let into = part.checked_mul(ratio).ok_or_else(overflow)?;
+                            let converted = match &lot {
+                                Some(lot) => Some(Lot {
+                                    cost: Amount {
+                                        number: lot
+                                            .cost
+                                            .number
+                                            .checked_div(ratio)
+                                            .ok_or_else(overflow)?,
+                                        commodity: lot.cost.commodity.clone(),
+                                    },
+                                    date: lot.date,
+                                }),
+                                None => None,
+                            };
+                            if let Some(converted) = &converted {
+                                lots.add(account_id, target_object_id, into, converted.clone());
+                            }
+
+                            postings.push(Posting {
+                                lot,
+                                ..posting(holding, -part, object)
+                            });
+                            postings.push(Posting {
+                                lot: converted,
+                                ..posting(holding, into, target)
+                            });
+                        }
+                    }
+
+                    // Lots keep their total cost, units without one don't balance
+                    if postings.iter().any(|posting| posting.lot.is_none()) {
+                        postings.push(Posting {
+                            units: None,
+                            ..posting(CONVERSIONS, Decimal::ZERO, target)
+                        });
+                    }
+
+                    let narration = action.remark.clone().unwrap_or_else(|| match action.kind {
+                        ActionKind::Split => format!("Split {}", symbol(action.object_id)),
+                        ActionKind::ReverseSplit => {
+                            format!("Reverse split {}", symbol(action.object_id))
+                        }
+                        ActionKind::SymbolChange | ActionKind::Merger => format!(
+                            "Convert {} into {}",
+                            symbol(action.object_id),
+                            symbol(target_object_id)
+                        ),
+                    });
+
+                    (false, narration, postings)
+                }
+            };
+
+            transactions.push(Transaction {
+                occurrence_at,
+                pending,
+                narration,
+                postings,
+            });
+        }
+
+        let prices = PriceRow::select_by_owner_until(conn, owner, until)?
+            .into_iter()
+            .map(|price| {
+                Ok(Price {
+                    date: price.occurrence_at.date_naive(),
+                    commodity: commodity(price.base_object_id)?.to_string(),
+                    price: Amount {
+                        number: price.price.value(),
+                        commodity: commodity(price.quote_object_id)?.to_string(),
+                    },
+                })
+            })
+            .collect::<Result<Vec<Price>, Box<dyn Error>>>()?;
+
+        // Accounts open on the day they are first used, or created if earlier
+        let mut opened: BTreeMap<String, NaiveDate> = BTreeMap::new();
+        for transaction in &transactions {
+            for posting in &transaction.postings {
+                let date = transaction.occurrence_at.date_naive();
+                opened
+                    .entry(posting.account.clone())
+                    .and_modify(|opened| *opened = (*opened).min(date))
+                    .or_insert(date);
+            }
+        }
+        let mut owned = HashMap::new();
+        for account in &accounts {
+            let name = account_names[&account.id()].clone();
+            let date = account.created_at.date_naive();
+            opened
+                .entry(name.clone())
+                .and_modify(|opened| *opened = (*opened).min(date))
+                .or_insert(date);
+            owned.insert(name, account.name.clone());
+        }
+
+        let mut commodities: Vec<Commodity> = objects
+            .iter()
+            .map(|object| Commodity {
+                name: commodities[&object.id()].clone(),
+                date: object.created_at.date_naive(),
+                symbol: object.symbol.clone(),
+                alias: object.alias.clone(),
+            })
+            .collect();
+        commodities.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self {
+            commodities,
+            accounts: opened
+                .into_iter()
+                .map(|(name, date)| Open {
+                    account: owned.get(&name).cloned(),
+                    name,
+                    date,
+                })
+                .collect(),
+            prices,
+            transactions,
+        })
+    }
+}
+
+/// Writes a number without trailing zeros.
+pub fn number(value: Decimal) -> String {
+    value.normalize().to_string()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::account::Account;
+    use crate::model::finance::object::action::{CorporateAction, Kind};
+    use crate::model::finance::object::{AssetType, Classification, Object};
+    use crate::model::finance::price::Price;
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::finance::transfer::Transfer;
+    use crate::model::person::Person;
+
+    use super::{Amount, Journal, Lot};
+
+    pub struct Setup {
+        pub conn: PooledConnection<SqliteConnectionManager>,
+        pub owner: i64,
+    }
+
+    /// Two accounts, one buying 2 BTC at 100 and 1 BTC at 200 USD, moving
+    /// 2.5 BTC to the other which sells them at 300 USD with a fee.
+    pub fn setup() -> Setup {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+
+        let object = |symbol: &str, asset_type| {
+            let classification = Classification {
+                asset_type,
+                ..Classification::default()
+            };
+            Object::insert(&conn, owner, symbol.to_string(), None, None, classification).unwrap()
+        };
+        let btc = object("BTC", AssetType::Crypto);
+        let usd = object("USD", AssetType::Fiat);
+
+        let account = |name: &str| Account::insert(&conn, owner, name.to_string(), None, None);
+        let exchange = account("My exchange").unwrap();
+        let wallet = account("cold wallet").unwrap();
+        let trade = Trade::insert(&conn, owner, btc, usd, None, None).unwrap();
+
+        let at = |day| Some(Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap());
+        let transaction = |account, quantity: i64, price: i64, fee: Option<i64>, day| {
+            Transaction::insert(
+                &conn,
+                trade,
+                account,
+                Decimal::new(quantity, 1).into(),
+                Some(Decimal::from(price).into()),
+                fee.map(|fee| Decimal::from(fee).into()),
+                fee.map(|_| usd),
+                price == 300,
+                Status::Settled,
+                None,
+                None,
+                None,
+                at(day),
+            )
+            .unwrap();
+        };
+        transaction(exchange, 20, 100, None, 1);
+        transaction(exchange, 10, 200, None, 2);
+        let quantity = Decimal::new(25, 1).into();
+        Transfer::insert(
+            &conn,
+            owner,
+            btc,
+            exchange,
+            wallet,
+            quantity,
+            None,
+            at(3).unwrap(),
+        )
+        .unwrap();
+        transaction(wallet, 25, 300, Some(1), 4);
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usd,
+            Decimal::from(310).into(),
+            None,
+            at(4).unwrap(),
+        )
+        .unwrap();
+
+        Setup { conn, owner }
+    }
+
+    #[test]
+    fn test_lots_follow_transfers_and_sales() {
+        let Setup { conn, owner } = setup();
+
+        // What is left of the second lot splits in two afterwards
+        let btc = Object::select_all_by_owner(&conn, owner).unwrap()[0].id();
+        CorporateAction::insert(
+            &conn,
+            btc,
+            Kind::Split,
+            Decimal::from(2).into(),
+            None,
+            None,
+            Utc.with_ymd_and_hms(2024, 1, 5, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+
+        let journal = Journal::load(&conn, owner, Utc::now()).unwrap();
+
+        let names: Vec<&str> = journal
+            .accounts
+            .iter()
+            .map(|open| open.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["Assets:Cold-Wallet", "Assets:My-Exchange", "Expenses:Fees"]
+        );
+        assert_eq!(journal.accounts[0].account.as_deref(), Some("cold wallet"));
+
+        let lot = |cost, day| {
+            Some(Lot {
+                cost: Amount {
+                    number: Decimal::from(cost),
+                    commodity: "USD".to_string(),
+                },
+                date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            })
+        };
+
+        // The transfer moves all of the first lot and half of the second
+        let transfer = &journal.transactions[2];
+        assert_eq!(transfer.postings.len(), 4);
+        assert_eq!(transfer.postings[1].lot, lot(100, 1));
+        assert_eq!(transfer.postings[3].lot, lot(200, 2));
+        assert_eq!(
+            transfer.postings[3].units.as_ref().unwrap().number,
+            Decimal::new(5, 1)
+        );
+        assert!(!transfer.realizes());
+
+        let sale = &journal.transactions[3];
+        assert!(sale.realizes());
+        assert_eq!(sale.postings[0].lot, lot(100, 1));
+        assert_eq!(
+            sale.postings[0].units.as_ref().unwrap().number,
+            Decimal::from(-2)
+        );
+        assert_eq!(sale.postings[1].lot, lot(200, 2));
+        assert_eq!(
+            sale.postings[1].units.as_ref().unwrap().number,
+            Decimal::new(-5, 1)
+        );
+        assert_eq!(
+            sale.postings[2].units.as_ref().unwrap().number,
+            Decimal::from(750)
+        );
+        assert_eq!(sale.postings[4].account, "Expenses:Fees");
+
+        // The split gives up the lot for twice the units at half the cost
+        let split = &journal.transactions[4];
+        assert_eq!(split.narration, "Split BTC");
+        assert_eq!(split.postings.len(), 2);
+        assert_eq!(split.postings[0].lot, lot(200, 2));
+        assert_eq!(
+            split.postings[0].units.as_ref().unwrap().number,
+            Decimal::new(-5, 1)
+        );
+        assert_eq!(split.postings[1].lot, lot(100, 2));
+        assert_eq!(
+            split.postings[1].units.as_ref().unwrap().number,
+            Decimal::ONE
+        );
+        assert!(!split.realizes());
+    }
+}
@@ -0,0 +1,633 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::model::finance::object::AssetType;
+
+use super::number;
+use super::statement::{Entry, Security, Statement};
+
+const BROKER_ID: &str = "harmony";
+
+/// Writes elements one per line, indented by depth.
+#[derive(Default)]
+struct Writer {
+    output: String,
+    open: Vec<&'static str>,
+}
+
+impl Writer {
+    fn indent(&mut self) {
+        self.output.push_str(&"  ".repeat(self.open.len()));
+    }
+
+    fn open(&mut self, name: &'static str) {
+        self.indent();
+        self.output.push_str(&format!("<{}>\n", name));
+        self.open.push(name);
+    }
+
+    fn close(&mut self) {
+        if let Some(name) = self.open.pop() {
+            self.indent();
+            self.output.push_str(&format!("</{}>\n", name));
+        }
+    }
+
+    fn element(&mut self, name: &'static str, value: &str) {
+        self.indent();
+        self.output
+            .push_str(&format!("<{}>{}</{}>\n", name, escape(value), name));
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Cuts `text` to the length an element allows.
+fn truncate(text: &str, length: usize) -> String {
+    text.chars().take(length).collect()
+}
+
+fn date(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%d%H%M%S%.3f[0:GMT]").to_string()
+}
+
+/// Whether the entry is paid in a currency OFX knows, which an ISO 4217
+/// code stands for.
+fn is_currency(entry: &Entry) -> bool {
+    entry.is_cash
+        && entry.currency.len() == 3
+        && entry.currency.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// The aggregates a security is bought, sold and described with.
+fn kind(security: &Security) -> (&'static str, &'static str, &'static str) {
+    match security.asset_type {
+        AssetType::Equity => ("BUYSTOCK", "SELLSTOCK", "STOCKINFO"),
+        AssetType::Fund => ("BUYMF", "SELLMF", "MFINFO"),
+        _ => ("BUYOTHER", "SELLOTHER", "OTHERINFO"),
+    }
+}
+
+fn security_id(writer: &mut Writer, security: &Security) {
+    let is_listed = matches!(
+        security.asset_type,
+        AssetType::Equity | AssetType::Fund | AssetType::Bond
+    );
+    let (id, kind) = match &security.identifier {
+        Some(id) if is_listed && id.len() == 12 => (id.clone(), "ISIN"),
+        Some(id) if is_listed && id.len() == 9 => (id.clone(), "CUSIP"),
+        _ => (truncate(&security.symbol, 32), "TICKER"),
+    };
+
+    writer.open("SECID");
+    writer.element("UNIQUEID", &id);
+    writer.element("UNIQUEIDTYPE", kind);
+    writer.close();
+}
+
+fn status(writer: &mut Writer) {
+    writer.open("STATUS");
+    writer.element("CODE", "0");
+    writer.element("SEVERITY", "INFO");
+    writer.close();
+}
+
+fn entry(writer: &mut Writer, entry: &Entry, security: &Security) {
+    let (buy, sell, _) = kind(security);
+    let units = if entry.is_sell {
+        -entry.units
+    } else {
+        entry.units
+    };
+
+    writer.open(if entry.is_sell { sell } else { buy });
+    writer.open(if entry.is_sell { "INVSELL" } else { "INVBUY" });
+
+    writer.open("INVTRAN");
+    writer.element("FITID", &entry.transaction_id.to_string());
+    writer.element("DTTRADE", &date(entry.occurrence_at));
+    if let Some(settled_at) = entry.settled_at {
+        writer.element("DTSETTLE", &date(settled_at));
+    }
+    if let Some(memo) = &entry.memo {
+        writer.element("MEMO", &truncate(memo, 255));
+    }
+    writer.close();
+
+    security_id(writer, security);
+    writer.element("UNITS", &number(units));
+    writer.element("UNITPRICE", &number(entry.unit_price));
+    if !entry.fee.is_zero() {
+        writer.element("FEES", &number(entry.fee));
+    }
+    writer.element("TOTAL", &number(entry.total));
+    writer.element("SUBACCTSEC", "CASH");
+    writer.element("SUBACCTFUND", "CASH");
+    writer.close();
+
+    match security.asset_type {
+        AssetType::Equity | AssetType::Fund if entry.is_sell => writer.element("SELLTYPE", "SELL"),
+        AssetType::Equity | AssetType::Fund => writer.element("BUYTYPE", "BUY"),
+        _ => {}
+    }
+    writer.close();
+}
+
+/// Renders a statement as an OFX 2.2 file of investment statements, one
+/// for each account and currency. An account traded in more than one
+/// currency gets the currency appended to its id. Entries paid in other
+/// than fiat money, which OFX has no currency for, are left out.
+pub fn render(statement: &Statement) -> String {
+    let mut writer = Writer::default();
+    writer
+        .output
+        .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    writer.output.push_str(
+        "<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" \
+         NEWFILEUID=\"NONE\"?>\n",
+    );
+
+    writer.open("OFX");
+    writer.open("SIGNONMSGSRSV1");
+    writer.open("SONRS");
+    status(&mut writer);
+    writer.element("DTSERVER", &date(statement.to));
+    writer.element("LANGUAGE", "ENG");
+    writer.close();
+    writer.close();
+
+    let mut traded = Vec::new();
+    let mut trnuid = 0;
+
+    writer.open("INVSTMTMSGSRSV1");
+    for account in &statement.accounts {
+        let mut currencies: BTreeMap<&str, Vec<&Entry>> = BTreeMap::new();
+        for entry in account.entries.iter().filter(|entry| is_currency(entry)) {
+            currencies.entry(&entry.currency).or_default().push(entry);
+        }
+        let is_mixed = currencies.len() > 1;
+
+        for (currency, entries) in currencies {
+            trnuid += 1;
+            let account_id = if is_mixed {
+                format!("{}-{}", account.account_id, currency)
+            } else {
+                account.account_id.to_string()
+            };
+
+            writer.open("INVSTMTTRNRS");
+            writer.element("TRNUID", &trnuid.to_string());
+            status(&mut writer);
+            writer.open("INVSTMTRS");
+            writer.element("DTASOF", &date(statement.to));
+            writer.element("CURDEF", currency);
+            writer.open("INVACCTFROM");
+            writer.element("BROKERID", BROKER_ID);
+            writer.element("ACCTID", &account_id);
+            writer.close();
+
+            writer.open("INVTRANLIST");
+            writer.element("DTSTART", &date(statement.from));
+            writer.element("DTEND", &date(statement.to));
+            for item in entries {
+                let Some(security) = statement.security(item.object_id) else {
+                    continue;
+                };
+                entry(&mut writer, item, security);
+                if !traded.contains(&security) {
+                    traded.push(security);
+                }
+            }
+            writer.close();
+
+            writer.close();
+            writer.close();
+        }
+    }
+    writer.close();
+
+    if !traded.is_empty() {
+        traded.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        writer.open("SECLISTMSGSRSV1");
+        writer.open("SECLIST");
+        for security in traded {
+            let (_, _, info) = kind(security);
+
+            writer.open(info);
+            writer.open("SECINFO");
+            security_id(&mut writer, security);
+            writer.element("SECNAME", &truncate(&security.name, 120));
+            writer.element("TICKER", &truncate(&security.symbol, 32));
+            writer.close();
+            writer.close();
+        }
+        writer.close();
+        writer.close();
+    }
+
+    writer.close();
+
+    writer.output
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::export::statement::Statement;
+    use crate::export::tests::{setup, Setup};
+
+    use super::render;
+
+    /// An element and what is in it.
+    #[derive(Debug)]
+    struct Element {
+        name: String,
+        children: Vec<Element>,
+        text: Option<String>,
+    }
+
+    /// Reads elements without attributes, which is all OFX 2 uses.
+    fn parse_element(input: &mut &str) -> Result<Element, String> {
+        *input = input.trim_start();
+        let rest = input.strip_prefix('<').ok_or("expected an element")?;
+        let end = rest.find('>').ok_or("unclosed tag")?;
+        let name = rest[..end].to_string();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.') {
+            return Err(format!("{} is not an element name", name));
+        }
+        *input = &rest[end + 1..];
+
+        let closing = format!("</{}>", name);
+        let mut children = Vec::new();
+        let mut text = None;
+
+        if !input.trim_start().starts_with('<') {
+            let end = input.find('<').ok_or("unclosed element")?;
+            let value = &input[..end];
+            let entities = ["&amp;", "&lt;", "&gt;"];
+            let bare = entities
+                .iter()
+                .fold(value.to_string(), |text, entity| text.replace(entity, ""));
+            if value.contains('>') || bare.contains('&') {
+                return Err(format!("{} is not escaped", value));
+            }
+            text = Some(value.to_string());
+            *input = &input[end..];
+        } else {
+            while !input.trim_start().starts_with(&closing) {
+                children.push(parse_element(input)?);
+            }
+            *input = input.trim_start();
+        }
+
+        *input = input
+            .strip_prefix(&closing)
+            .ok_or(format!("{} is not closed", name))?;
+
+        Ok(Element {
+            name,
+            children,
+            text,
+        })
+    }
+
+    enum Value {
+        Text(usize),
+        Amount,
+        Date,
+        Currency,
+        Of(&'static [&'static str]),
+    }
+
+    enum Content {
+        /// Children in order, each of the names given with `|` and how
+        /// often it may occur.
+        Sequence(&'static [(&'static str, usize, usize)]),
+        Value(Value),
+    }
+
+    const MANY: usize = usize::MAX;
+
+    /// The elements the export writes, transcribed by hand from the OFX 2.2
+    /// specification. The published XSD is not vendored, so this holds only
+    /// as far as the transcription does: the order, occurrence and value
+    /// formats of these elements, nothing the export doesn't write.
+    fn schema(name: &str) -> Option<Content> {
+        use Content::{Sequence, Value as V};
+        use Value::*;
+
+        let subaccount = V(Of(&["CASH", "MARGIN", "SHORT", "OTHER"]));
+        let content = match name {
+            "OFX" => Sequence(&[
+                ("SIGNONMSGSRSV1", 1, 1),
+                ("INVSTMTMSGSRSV1", 0, 1),
+                ("SECLISTMSGSRSV1", 0, 1),
+            ]),
+            "SIGNONMSGSRSV1" => Sequence(&[("SONRS", 1, 1)]),
+            "SONRS" => Sequence(&[
+                ("STATUS", 1, 1),
+                ("DTSERVER", 1, 1),
+                ("USERKEY", 0, 1),
+                ("TSKEYEXPIRE", 0, 1),
+                ("LANGUAGE", 1, 1),
+                ("DTPROFUP", 0, 1),
+                ("DTACCTUP", 0, 1),
+                ("FI", 0, 1),
+                ("SESSCOOKIE", 0, 1),
+                ("ACCESSKEY", 0, 1),
+            ]),
+            "STATUS" => Sequence(&[("CODE", 1, 1), ("SEVERITY", 1, 1), ("MESSAGE", 0, 1)]),
+            "CODE" => V(Text(6)),
+            "SEVERITY" => V(Of(&["INFO", "WARN", "ERROR"])),
+            "LANGUAGE" => V(Text(3)),
+            "INVSTMTMSGSRSV1" => Sequence(&[
+                ("INVSTMTTRNRS", 0, MANY),
+                ("INVMAILTRNRS", 0, MANY),
+                ("INVMAILSYNCRS", 0, MANY),
+            ]),
+            "INVSTMTTRNRS" => Sequence(&[
+                ("TRNUID", 1, 1),
+                ("STATUS", 1, 1),
+                ("CLTCOOKIE", 0, 1),
+                ("INVSTMTRS", 0, 1),
+            ]),
+            "TRNUID" => V(Text(36)),
+            "INVSTMTRS" => Sequence(&[
+                ("DTASOF", 1, 1),
+                ("CURDEF", 1, 1),
+                ("INVACCTFROM", 1, 1),
+                ("INVTRANLIST", 0, 1),
+                ("INVPOSLIST", 0, 1),
+                ("INVBAL", 0, 1),
+                ("INVOOLIST", 0, 1),
+                ("MKTGINFO", 0, 1),
+                ("INV401K", 0, 1),
+                ("INV401KBAL", 0, 1),
+            ]),
+            "CURDEF" => V(Currency),
+            "INVACCTFROM" => Sequence(&[("BROKERID", 1, 1), ("ACCTID", 1, 1)]),
+            "BROKERID" | "ACCTID" => V(Text(22)),
+            "INVTRANLIST" => Sequence(&[
+                ("DTSTART", 1, 1),
+                ("DTEND", 1, 1),
+                (
+                    "BUYDEBT|BUYMF|BUYOPT|BUYOTHER|BUYSTOCK|CLOSUREOPT|INCOME|INVEXPENSE|\
+                     JRNLFUND|JRNLSEC|MARGININTEREST|REINVEST|RETOFCAP|SELLDEBT|SELLMF|\
+                     SELLOPT|SELLOTHER|SELLSTOCK|SPLIT|TRANSFER",
+                    0,
+                    MANY,
+                ),
+                ("INVBANKTRAN", 0, MANY),
+            ]),
+            "BUYSTOCK" => Sequence(&[("INVBUY", 1, 1), ("BUYTYPE", 1, 1)]),
+            "BUYMF" => Sequence(&[("INVBUY", 1, 1), ("BUYTYPE", 1, 1), ("RELFITID", 0, 1)]),
+            "BUYOTHER" => Sequence(&[("INVBUY", 1, 1)]),
+            "SELLSTOCK" => Sequence(&[("INVSELL", 1, 1), ("SELLTYPE", 1, 1)]),
+            "SELLMF" => Sequence(&[
+                ("INVSELL", 1, 1),
+                ("SELLTYPE", 1, 1),
+                ("AVGCOSTBASIS", 0, 1),
+                ("RELFITID", 0, 1),
+            ]),
+            "SELLOTHER" => Sequence(&[("INVSELL", 1, 1)]),
+            "BUYTYPE" => V(Of(&["BUY", "BUYTOCOVER"])),
+            "SELLTYPE" => V(Of(&["SELL", "SELLSHORT"])),
+            "INVBUY" => Sequence(&[
+                ("INVTRAN", 1, 1),
+                ("SECID", 1, 1),
+                ("UNITS", 1, 1),
+                ("UNITPRICE", 1, 1),
+                ("MARKUP", 0, 1),
+                ("COMMISSION", 0, 1),
+                ("TAXES", 0, 1),
+                ("FEES", 0, 1),
+                ("LOAD", 0, 1),
+                ("TOTAL", 1, 1),
+                ("CURRENCY|ORIGCURRENCY", 0, 1),
+                ("SUBACCTSEC", 1, 1),
+                ("SUBACCTFUND", 1, 1),
+            ]),
+            "INVSELL" => Sequence(&[
+                ("INVTRAN", 1, 1),
+                ("SECID", 1, 1),
+                ("UNITS", 1, 1),
+                ("UNITPRICE", 1, 1),
+                ("MARKDOWN", 0, 1),
+                ("COMMISSION", 0, 1),
+                ("TAXES", 0, 1),
+                ("FEES", 0, 1),
+                ("LOAD", 0, 1),
+                ("WITHHOLDING", 0, 1),
+                ("TAXEXEMPT", 0, 1),
+                ("TOTAL", 1, 1),
+                ("GAIN", 0, 1),
+                ("CURRENCY|ORIGCURRENCY", 0, 1),
+                ("SUBACCTSEC", 1, 1),
+                ("SUBACCTFUND", 1, 1),
+            ]),
+            "INVTRAN" => Sequence(&[
+                ("FITID", 1, 1),
+                ("SRVRTID", 0, 1),
+                ("DTTRADE", 1, 1),
+                ("DTSETTLE", 0, 1),
+                ("REVERSALFITID", 0, 1),
+                ("MEMO", 0, 1),
+            ]),
+            "FITID" | "MEMO" => V(Text(255)),
+            "SECID" => Sequence(&[("UNIQUEID", 1, 1), ("UNIQUEIDTYPE", 1, 1)]),
+            "UNIQUEID" | "TICKER" => V(Text(32)),
+            "UNIQUEIDTYPE" => V(Text(10)),
+            "SECNAME" => V(Text(120)),
+            "UNITS" | "UNITPRICE" | "FEES" | "TOTAL" => V(Amount),
+            "SUBACCTSEC" | "SUBACCTFUND" => subaccount,
+            "DTSERVER" | "DTASOF" | "DTSTART" | "DTEND" | "DTTRADE" | "DTSETTLE" => V(Date),
+            "SECLISTMSGSRSV1" => Sequence(&[("SECLISTTRNRS", 0, MANY), ("SECLIST", 0, 1)]),
+            "SECLIST" => Sequence(&[("DEBTINFO|MFINFO|OPTINFO|OTHERINFO|STOCKINFO", 0, MANY)]),
+            "STOCKINFO" => Sequence(&[
+                ("SECINFO", 1, 1),
+                ("STOCKTYPE", 0, 1),
+                ("YIELD", 0, 1),
+                ("DTYIELDASOF", 0, 1),
+                ("ASSETCLASS", 0, 1),
+                ("FIASSETCLASS", 0, 1),
+            ]),
+            "MFINFO" => Sequence(&[
+                ("SECINFO", 1, 1),
+                ("MFTYPE", 0, 1),
+                ("YIELD", 0, 1),
+                ("DTYIELDASOF", 0, 1),
+                ("MFASSETCLASS", 0, 1),
+                ("FIMFASSETCLASS", 0, 1),
+            ]),
+            "OTHERINFO" => Sequence(&[
+                ("SECINFO", 1, 1),
+                ("TYPEDESC", 0, 1),
+                ("ASSETCLASS", 0, 1),
+                ("FIASSETCLASS", 0, 1),
+            ]),
+            "SECINFO" => Sequence(&[
+                ("SECID", 1, 1),
+                ("SECNAME", 1, 1),
+                ("TICKER", 0, 1),
+                ("FIID", 0, 1),
+                ("RATING", 0, 1),
+                ("UNITPRICE", 0, 1),
+                ("DTASOF", 0, 1),
+                ("CURRENCY", 0, 1),
+                ("MEMO", 0, 1),
+            ]),
+            _ => return None,
+        };
+
+        Some(content)
+    }
+
+    fn is_date(value: &str) -> bool {
+        let (stamp, zone) = value.split_once('[').unwrap_or((value, ""));
+        let (digits, fraction) = stamp.split_once('.').unwrap_or((stamp, ""));
+
+        matches!(digits.len(), 8 | 12 | 14)
+            && digits.chars().all(|c| c.is_ascii_digit())
+            && (fraction.is_empty() || fraction.len() == 3 && digits.len() == 14)
+            && fraction.chars().all(|c| c.is_ascii_digit())
+            && (zone.is_empty() || zone.ends_with(']'))
+    }
+
+    fn is_amount(value: &str) -> bool {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+
+        value.len() <= 32
+            && !whole.is_empty()
+            && whole.chars().all(|c| c.is_ascii_digit())
+            && fraction.chars().all(|c| c.is_ascii_digit())
+    }
+
+    fn validate(element: &Element) -> Result<(), String> {
+        let name = element.name.as_str();
+        let content = schema(name).ok_or(format!("{} is not in the transcription", name))?;
+
+        match content {
+            Content::Value(kind) => {
+                let value = element
+                    .text
+                    .as_deref()
+                    .ok_or(format!("{} is empty", name))?;
+                let value = value
+                    .replace("&amp;", "&")
+                    .replace("&lt;", "<")
+                    .replace("&gt;", ">");
+                let is_valid = match kind {
+                    Value::Text(length) => !value.is_empty() && value.chars().count() <= length,
+                    Value::Amount => is_amount(&value),
+                    Value::Date => is_date(&value),
+                    Value::Currency => {
+                        value.len() == 3 && value.chars().all(|c| c.is_ascii_uppercase())
+                    }
+                    Value::Of(values) => values.contains(&value.as_str()),
+                };
+                if !is_valid {
+                    return Err(format!("{} is not a valid {}", value, name));
+                }
+            }
+            Content::Sequence(items) => {
+                if element.text.is_some() {
+                    return Err(format!("{} takes elements", name));
+                }
+
+                let mut children = element.children.iter().peekable();
+                for (names, min, max) in items {
+                    let names: Vec<&str> = names.split('|').map(str::trim).collect();
+                    let mut count = 0;
+                    while let Some(child) = children.peek() {
+                        if count == *max || !names.contains(&child.name.as_str()) {
+                            break;
+                        }
+                        validate(child)?;
+                        children.next();
+                        count += 1;
+                    }
+                    if count < *min {
+                        return Err(format!("{} lacks {}", name, names.join("|")));
+                    }
+                }
+                if let Some(child) = children.next() {
+                    return Err(format!("{} is out of place in {}", child.name, name));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the header and the body of an OFX 2.2 file against the
+    /// transcription in `schema`. This is no validation against the XSD,
+    /// which a reader of the file may still be stricter than.
+    fn check(text: &str) -> Result<(), String> {
+        let mut lines = text.splitn(3, '\n');
+        let declaration = lines.next().unwrap_or_default();
+        let header = lines.next().unwrap_or_default();
+        let mut body = lines.next().unwrap_or_default();
+
+        if !declaration.starts_with("<?xml version=\"1.0\"") {
+            return Err("the XML declaration is missing".to_string());
+        }
+        if !header.starts_with("<?OFX OFXHEADER=\"200\" VERSION=\"220\"") {
+            return Err("the OFX header is missing".to_string());
+        }
+
+        let root = parse_element(&mut body)?;
+        if root.name != "OFX" || !body.trim().is_empty() {
+            return Err("the body is not one OFX element".to_string());
+        }
+
+        validate(&root)
+    }
+
+    #[test]
+    fn test_render() {
+        let Setup { conn, owner } = setup();
+        let at = |day| Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+        let statement = Statement::load(&conn, owner, Some(at(1)), at(31)).unwrap();
+
+        let text = render(&statement);
+
+        check(&text).unwrap();
+        assert!(text.contains("<DTSTART>20240101000000.000[0:GMT]</DTSTART>"));
+        assert_eq!(text.matches("<INVSTMTRS>").count(), 2);
+        assert_eq!(text.matches("<BUYOTHER>").count(), 2);
+        assert!(text.contains(
+            "<UNITS>-2.5</UNITS>\n\
+             \x20             <UNITPRICE>300</UNITPRICE>\n\
+             \x20             <FEES>1</FEES>\n\
+             \x20             <TOTAL>749</TOTAL>\n"
+        ));
+        assert!(text.contains("<UNIQUEID>BTC</UNIQUEID>"));
+    }
+
+    #[test]
+    fn test_check() {
+        let Setup { conn, owner } = setup();
+        let statement = Statement::load(&conn, owner, None, Utc::now()).unwrap();
+        let text = render(&statement);
+
+        check(&text).unwrap();
+
+        // Fees come before the total
+        let reordered = text.replace(
+            "<FEES>1</FEES>\n              <TOTAL>749</TOTAL>",
+            "<TOTAL>749</TOTAL>\n              <FEES>1</FEES>",
+        );
+        assert_ne!(reordered, text);
+        assert!(check(&reordered).is_err());
+        assert!(check(&text.replace("<CURDEF>USD", "<CURDEF>usd")).is_err());
+        assert!(check(&text.replace("<TOTAL>749", "<TOTAL>7,49")).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt::Write;
+
+use crate::model::finance::object::AssetType;
+
+use super::number;
+use super::statement::Statement;
+
+/// Keeps a field to one line, as every line of QIF starts a field.
+fn field(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+fn security_type(asset_type: AssetType) -> &'static str {
+    match asset_type {
+        AssetType::Equity => "Stock",
+        AssetType::Fund => "Mutual Fund",
+        AssetType::Bond => "Bond",
+        _ => "Other",
+    }
+}
+
+/// Renders a statement as a QIF file, with the securities first and then
+/// an investment account for each account. QIF has no currencies, so
+/// amounts are in whatever the entries are paid in.
+pub fn render(statement: &Statement) -> String {
+    let mut output = String::new();
+
+    for security in &statement.securities {
+        output.push_str("!Type:Security\n");
+        writeln!(output, "N{}", field(&security.name)).ok();
+        writeln!(output, "S{}", field(&security.symbol)).ok();
+        writeln!(output, "T{}", security_type(security.asset_type)).ok();
+        output.push_str("^\n");
+    }
+
+    output.push_str("!Option:AutoSwitch\n");
+
+    for account in &statement.accounts {
+        output.push_str("!Account\n");
+        writeln!(output, "N{}", field(&account.name)).ok();
+        output.push_str("TInvst\n^\n");
+        output.push_str("!Type:Invst\n");
+
+        for entry in &account.entries {
+            let Some(security) = statement.security(entry.object_id) else {
+                continue;
+            };
+
+            writeln!(output, "D{}", entry.occurrence_at.format("%m/%d/%Y")).ok();
+            writeln!(output, "N{}", if entry.is_sell { "Sell" } else { "Buy" }).ok();
+            writeln!(output, "Y{}", field(&security.name)).ok();
+            writeln!(output, "I{}", number(entry.unit_price)).ok();
+            writeln!(output, "Q{}", number(entry.units)).ok();
+            writeln!(output, "T{}", number(entry.total.abs())).ok();
+            if !entry.fee.is_zero() {
+                writeln!(output, "O{}", number(entry.fee)).ok();
+            }
+            if let Some(memo) = &entry.memo {
+                writeln!(output, "M{}", field(memo)).ok();
+            }
+            output.push_str("^\n");
+        }
+    }
+
+    output.push_str("!Clear:AutoSwitch\n");
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::export::statement::Statement;
+    use crate::export::tests::{setup, Setup};
+
+    use super::render;
+
+    #[test]
+    fn test_render() {
+        let Setup { conn, owner } = setup();
+        let at = |day| Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+        let statement = Statement::load(&conn, owner, Some(at(1)), at(31)).unwrap();
+
+        let text = render(&statement);
+
+        assert!(text.starts_with("!Type:Security\nNBTC\nSBTC\nTOther\n^\n!Option:AutoSwitch\n"));
+        assert!(text.contains(
+            "!Account\nNcold wallet\nTInvst\n^\n!Type:Invst\n\
+             D01/04/2024\nNSell\nYBTC\nI300\nQ2.5\nT749\nO1\n^\n"
+        ));
+        assert_eq!(text.matches("NBuy\n").count(), 2);
+        assert!(text.contains("D01/01/2024\nNBuy\nYBTC\nI100\nQ2\nT200\n^\n"));
+        assert!(text.ends_with("!Clear:AutoSwitch\n"));
+
+        // Every record ends before the next header
+        for block in text.split('!').skip(1) {
+            let body = block
+                .split_once('\n')
+                .map(|(_, body)| body)
+                .unwrap_or_default();
+            assert!(body.is_empty() || body.ends_with("^\n"), "{}", block);
+        }
+    }
+}
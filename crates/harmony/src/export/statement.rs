@@ -0,0 +1,334 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::model::finance::account::Account;
+use crate::model::finance::object::{AssetType, Object};
+use crate::model::finance::trade::transaction::Transaction;
+use crate::model::finance::trade::Trade;
+use crate::model::finance::Quantity;
+
+use super::{number, ofx, qif};
+
+/// The file formats of desktop accounting tools a statement renders to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Ofx,
+    Qif,
+}
+
+impl Format {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ofx => "ofx",
+            Self::Qif => "qif",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Ofx => "application/x-ofx",
+            Self::Qif => "application/qif",
+        }
+    }
+
+    pub fn render(&self, statement: &Statement) -> String {
+        match self {
+            Self::Ofx => ofx::render(statement),
+            Self::Qif => qif::render(statement),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ofx" => Ok(Self::Ofx),
+            "qif" => Ok(Self::Qif),
+            _ => Err(format!("unknown statement format {}", s)),
+        }
+    }
+}
+
+/// An object bought or sold in a statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Security {
+    pub object_id: i64,
+    pub symbol: String,
+    /// The alias of the object, or its symbol, distinct across securities.
+    pub name: String,
+    pub asset_type: AssetType,
+    /// An ISIN or CUSIP.
+    pub identifier: Option<String>,
+}
+
+/// A trade transaction as a purchase or sale of a security for cash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub transaction_id: i64,
+    pub occurrence_at: DateTime<Utc>,
+    pub settled_at: Option<DateTime<Utc>>,
+    pub is_sell: bool,
+    pub object_id: i64,
+    /// Always positive.
+    pub units: Decimal,
+    pub unit_price: Decimal,
+    /// The symbol of the quote object.
+    pub currency: String,
+    /// Whether the quote object is fiat money.
+    pub is_cash: bool,
+    /// Charged in `currency`. Fees charged in other objects are noted in
+    /// the memo instead.
+    pub fee: Decimal,
+    /// The cash the entry brings into the account, negative for a purchase.
+    pub total: Decimal,
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountStatement {
+    pub account_id: i64,
+    pub name: String,
+    /// In time order.
+    pub entries: Vec<Entry>,
+}
+
+/// The settled trade transactions of an owner within a date range, by
+/// account, as desktop accounting tools take them in. Transactions without
+/// a price are left out, as nothing tells what they were worth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Accounts with entries, by name.
+    pub accounts: Vec<AccountStatement>,
+    /// Securities of the entries, by symbol.
+    pub securities: Vec<Security>,
+}
+
+impl Statement {
+    /// Loads the transactions that occurred from `from`, or the first one
+    /// when omitted, up to `to`.
+    pub fn load(
+        conn: &Connection,
+        owner: i64,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let objects: HashMap<i64, Object> = Object::select_all_by_owner(conn, owner)?
+            .into_iter()
+            .map(|object| (object.id(), object))
+            .collect();
+        let accounts: HashMap<i64, Account> = Account::select_all_by_owner(conn, owner)?
+            .into_iter()
+            .map(|account| (account.id(), account))
+            .collect();
+        let trades: HashMap<i64, Trade> = Trade::select_all_by_owner(conn, owner)?
+            .into_iter()
+            .map(|trade| (trade.id(), trade))
+            .collect();
+
+        let object = |id: i64| -> Result<&Object, Box<dyn Error>> {
+            Ok(objects
+                .get(&id)
+                .ok_or(format!("object {} does not exist", id))?)
+        };
+
+        let transactions: Vec<Transaction> =
+            Transaction::select_by_owner_until(conn, owner, to, false)?
+                .into_iter()
+                .filter(|transaction| from.is_none_or(|from| transaction.occurrence_at >= from))
+                .collect();
+        let from = from
+            .or(transactions
+                .first()
+                .map(|transaction| transaction.occurrence_at))
+            .unwrap_or(to);
+
+        let mut entries: BTreeMap<i64, Vec<Entry>> = BTreeMap::new();
+
+        for transaction in transactions {
+            let Some(price) = &transaction.price else {
+                continue;
+            };
+            let trade = trades
+                .get(&transaction.trade_id)
+                .ok_or(format!("trade {} does not exist", transaction.trade_id))?;
+            let quote = object(trade.quote_object_id)?;
+
+            let mut memo = transaction.remark.clone().or(transaction.alias.clone());
+            let mut fee = Decimal::ZERO;
+            if let (Some(value), Some(fee_object_id)) =
+                (&transaction.fee, transaction.fee_object_id)
+            {
+                if fee_object_id == trade.quote_object_id {
+                    fee = value.value();
+                } else {
+                    let note = format!(
+                        "fee {} {}",
+                        number(value.value()),
+                        object(fee_object_id)?.symbol
+                    );
+                    memo = Some(match memo {
+                        Some(memo) => format!("{}, {}", memo, note),
+                        None => note,
+                    });
+                }
+            }
+
+            let overflow = || format!("transaction {} overflows its total", transaction.id());
+            let units = transaction.quantity.value();
+            let amount = transaction
+                .quantity
+                .checked_mul(price)
+                .ok_or_else(overflow)?;
+            let total = if transaction.is_base_to_quote {
+                amount.checked_sub(&fee.into())
+            } else {
+                amount
+                    .checked_add(&fee.into())
+                    .map(|total| Quantity::from(-total.value()))
+            }
+            .ok_or_else(overflow)?
+            .value();
+
+            entries
+                .entry(transaction.account_id)
+                .or_default()
+                .push(Entry {
+                    transaction_id: transaction.id(),
+                    occurrence_at: transaction.occurrence_at,
+                    settled_at: transaction.settled_at,
+                    is_sell: transaction.is_base_to_quote,
+                    object_id: trade.base_object_id,
+                    units,
+                    unit_price: price.value(),
+                    currency: quote.symbol.clone(),
+                    is_cash: quote.classification.asset_type == AssetType::Fiat,
+                    fee,
+                    total,
+                    memo,
+                });
+        }
+
+        let mut statements = entries
+            .into_iter()
+            .map(|(account_id, entries)| {
+                let account = accounts
+                    .get(&account_id)
+                    .ok_or(format!("account {} does not exist", account_id))?;
+
+                Ok(AccountStatement {
+                    account_id,
+                    name: account.name.clone(),
+                    entries,
+                })
+            })
+            .collect::<Result<Vec<AccountStatement>, Box<dyn Error>>>()?;
+        statements.sort_by(|a, b| a.name.cmp(&b.name).then(a.account_id.cmp(&b.account_id)));
+
+        let mut traded: Vec<&Object> = Vec::new();
+        for entry in statements.iter().flat_map(|statement| &statement.entries) {
+            if traded.iter().all(|object| object.id() != entry.object_id) {
+                traded.push(object(entry.object_id)?);
+            }
+        }
+        traded.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        // Tools tell securities apart by name
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for object in &traded {
+            let name = object.alias.as_deref().unwrap_or(&object.symbol);
+            *counts.entry(name).or_default() += 1;
+        }
+        let securities = traded
+            .iter()
+            .map(|object| {
+                let name = match object.alias.as_deref() {
+                    Some(alias) if counts[alias] > 1 => format!("{} ({})", alias, object.symbol),
+                    Some(alias) => alias.to_string(),
+                    None => object.symbol.clone(),
+                };
+
+                Security {
+                    object_id: object.id(),
+                    symbol: object.symbol.clone(),
+                    name,
+                    asset_type: object.classification.asset_type,
+                    identifier: object.classification.identifier.clone(),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            from,
+            to,
+            accounts: statements,
+            securities,
+        })
+    }
+
+    pub fn security(&self, object_id: i64) -> Option<&Security> {
+        self.securities
+            .iter()
+            .find(|security| security.object_id == object_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use crate::export::tests::{setup, Setup};
+
+    use super::Statement;
+
+    #[test]
+    fn test_load() {
+        let Setup { conn, owner } = setup();
+        let at = |day| Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+
+        let statement = Statement::load(&conn, owner, Some(at(2)), at(5)).unwrap();
+
+        let names: Vec<&str> = statement
+            .accounts
+            .iter()
+            .map(|account| account.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["My exchange", "cold wallet"]);
+        assert_eq!(statement.securities.len(), 1);
+        assert_eq!(statement.securities[0].name, "BTC");
+
+        // The first purchase is before the range
+        let entries = &statement.accounts[0].entries;
+        assert_eq!(entries.len(), 1);
+        assert!(!entries[0].is_sell);
+        assert_eq!(entries[0].total, Decimal::from(-200));
+
+        let sale = &statement.accounts[1].entries[0];
+        assert!(sale.is_sell);
+        assert!(sale.is_cash);
+        assert_eq!(sale.units, Decimal::new(25, 1));
+        assert_eq!(sale.fee, Decimal::ONE);
+        assert_eq!(sale.total, Decimal::from(749));
+
+        let statement = Statement::load(&conn, owner, None, at(5)).unwrap();
+        assert_eq!(statement.from, at(1) + chrono::Duration::hours(12));
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+
+use super::csv::parse_amount;
+use super::{Line, Record};
+
+/// A cost or price written on a posting, per unit or for all its units.
+#[derive(Debug, Clone, PartialEq)]
+struct Annotation {
+    number: Decimal,
+    commodity: String,
+    is_total: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Posting {
+    account: String,
+    units: Option<(Decimal, String)>,
+    cost: Option<Annotation>,
+    price: Option<Annotation>,
+}
+
+/// A dated entry with the indented lines below it.
+struct Directive {
+    line: u64,
+    header: String,
+    body: Vec<String>,
+}
+
+/// Cuts a line at the `;` starting a comment, leaving strings alone.
+fn strip_comment(line: &str) -> &str {
+    let mut is_quoted = false;
+    let mut is_escaped = false;
+
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if is_escaped => is_escaped = false,
+            '\\' if is_quoted => is_escaped = true,
+            '"' => is_quoted = !is_quoted,
+            ';' if !is_quoted => return &line[..index],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+/// The strings of a line, unescaped.
+fn strings(text: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut current: Option<String> = None;
+    let mut is_escaped = false;
+
+    for c in text.chars() {
+        match (&mut current, c) {
+            (Some(string), _) if is_escaped => {
+                string.push(c);
+                is_escaped = false;
+            }
+            (Some(_), '\\') => is_escaped = true,
+            (Some(_), '"') => strings.extend(current.take()),
+            (Some(string), _) => string.push(c),
+            (None, '"') => current = Some(String::new()),
+            (None, _) => {}
+        }
+    }
+
+    strings
+}
+
+/// The `key: value` metadata of a body, strings unquoted.
+fn metadata(body: &[String]) -> HashMap<String, String> {
+    body.iter()
+        .filter(|line| line.starts_with(|c: char| c.is_ascii_lowercase()))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = strings(value)
+                .into_iter()
+                .next()
+                .unwrap_or(value.to_string());
+            (key.trim().to_string(), value)
+        })
+        .collect()
+}
+
+fn parse_units(text: &str) -> Result<(Decimal, String), String> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let [number, commodity] = tokens[..] else {
+        return Err(format!("{} is not an amount", text.trim()));
+    };
+    let number =
+        parse_amount("amount", number, '.', ',')?.ok_or(format!("{} is not an amount", text))?;
+
+    Ok((number, commodity.to_string()))
+}
+
+/// Reads the amount in a cost such as `{100 USD, 2024-01-01, "lot"}`,
+/// `None` for a cost left for Beancount to work out.
+fn parse_cost(text: &str, is_total: bool) -> Result<Option<Annotation>, String> {
+    for component in text.split(',') {
+        let tokens: Vec<&str> = component.split_whitespace().collect();
+        if let [number, commodity] = tokens[..] {
+            if let Ok(Some(number)) = parse_amount("cost", number, '.', ',') {
+                return Ok(Some(Annotation {
+                    number,
+                    commodity: commodity.to_string(),
+                    is_total,
+                }));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn parse_posting(line: &str) -> Result<Posting, String> {
+    let line = line.trim_start_matches(['*', '!']).trim();
+    let (account, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+    let (rest, price) = match rest.find('@') {
+        Some(index) => {
+            let is_total = rest[index..].starts_with("@@");
+            let text = rest[index..].trim_start_matches('@');
+            let (number, commodity) = parse_units(text)?;
+            let price = Annotation {
+                number,
+                commodity,
+                is_total,
+            };
+            (&rest[..index], Some(price))
+        }
+        None => (rest, None),
+    };
+
+    let (rest, cost) = match rest.find('{') {
+        Some(start) => {
+            let end = rest
+                .rfind('}')
+                .ok_or(format!("{} has an unclosed cost", account))?;
+            let is_total = rest[start..].starts_with("{{");
+            let text = rest[start..end].trim_matches(['{', '}']);
+            (&rest[..start], parse_cost(text, is_total)?)
+        }
+        None => (rest, None),
+    };
+
+    let units = if rest.trim().is_empty() {
+        None
+    } else {
+        Some(parse_units(rest)?)
+    };
+
+    Ok(Posting {
+        account: account.to_string(),
+        units,
+        cost,
+        price,
+    })
+}
+
+fn split(data: &str) -> Vec<Directive> {
+    let mut directives: Vec<Directive> = Vec::new();
+    let mut is_open = false;
+
+    for (index, line) in data.lines().enumerate() {
+        let line = strip_comment(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            if let (true, Some(directive)) = (is_open, directives.last_mut()) {
+                directive.body.push(line.trim().to_string());
+            }
+        } else if line.starts_with(|c: char| c.is_ascii_digit()) {
+            directives.push(Directive {
+                line: index as u64 + 1,
+                header: line.trim().to_string(),
+                body: Vec::new(),
+            });
+            is_open = true;
+        } else {
+            // Options, includes, plugins and tags
+            is_open = false;
+        }
+    }
+
+    directives
+}
+
+/// What the file says about its commodities and accounts.
+#[derive(Default)]
+struct Names {
+    /// Symbols of objects by commodity.
+    symbols: HashMap<String, String>,
+    /// Names of accounts of the owner by account.
+    accounts: HashMap<String, String>,
+}
+
+impl Names {
+    fn symbol(&self, commodity: &str) -> String {
+        self.symbols
+            .get(commodity)
+            .cloned()
+            .unwrap_or_else(|| commodity.to_string())
+    }
+
+    fn account(&self, account: &str) -> String {
+        self.accounts.get(account).cloned().unwrap_or_else(|| {
+            account
+                .strip_prefix("Assets:")
+                .unwrap_or(account)
+                .to_string()
+        })
+    }
+}
+
+/// Reads a transaction as a trade, `None` when it is not one. A trade moves
+/// two commodities within an asset account, one of them priced or costed in
+/// the other, and may pay fees to an expense account named for fees or
+/// commissions.
+fn record(
+    names: &Names,
+    date: NaiveDate,
+    narration: Option<String>,
+    meta: &HashMap<String, String>,
+    postings: &[Posting],
+) -> Result<Option<Record>, String> {
+    let assets: Vec<&Posting> = postings
+        .iter()
+        .filter(|posting| posting.account.starts_with("Assets:") && posting.units.is_some())
+        .collect();
+
+    let annotated = assets
+        .iter()
+        .find(|posting| posting.price.is_some())
+        .or_else(|| assets.iter().find(|posting| posting.cost.is_some()));
+    let Some(annotated) = annotated else {
+        return Ok(None);
+    };
+    let (units, base) = annotated.units.clone().unwrap_or_default();
+    let Some(annotation) = annotated.price.as_ref().or(annotated.cost.as_ref()) else {
+        return Ok(None);
+    };
+    let quote = &annotation.commodity;
+
+    let legs = |commodity: &str| {
+        assets
+            .iter()
+            .filter(|posting| posting.account == annotated.account)
+            .filter_map(|posting| posting.units.as_ref())
+            .filter(|(_, units)| units == commodity)
+            .map(|(number, _)| *number)
+            .collect::<Vec<Decimal>>()
+    };
+    // Lots moved between accounts are not traded
+    let is_paid = postings.iter().any(|posting| {
+        posting.account == annotated.account
+            && posting
+                .units
+                .as_ref()
+                .is_none_or(|(_, units)| units == quote)
+    });
+    if !is_paid {
+        return Ok(None);
+    }
+
+    let quantity: Decimal = legs(&base).into_iter().sum();
+    if quantity.is_zero() {
+        return Err(format!("{} is not traded in {}", base, annotated.account));
+    }
+
+    let price = if annotation.is_total {
+        annotation
+            .number
+            .checked_div(units.abs())
+            .ok_or(format!("{} has no units to price", annotated.account))?
+    } else {
+        annotation.number
+    };
+
+    let mut fee: Option<(Decimal, String)> = None;
+    for posting in postings {
+        let account = posting.account.to_lowercase();
+        let is_fee = account.starts_with("expenses:")
+            && (account.contains("fee") || account.contains("commission"));
+        let Some((number, commodity)) = posting.units.clone().filter(|_| is_fee) else {
+            continue;
+        };
+
+        fee = match fee {
+            None => Some((number, commodity)),
+            Some((sum, symbol)) if symbol == commodity => Some((sum + number, symbol)),
+            Some(_) => return Err("fees are paid in more than one commodity".to_string()),
+        };
+    }
+
+    let occurrence_at = match meta.get("time") {
+        Some(time) => DateTime::parse_from_rfc3339(time)
+            .map_err(|_| format!("{} is not a time", time))?
+            .with_timezone(&Utc),
+        None => date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+    };
+
+    Ok(Some(Record {
+        base: names.symbol(&base),
+        quote: names.symbol(quote),
+        is_base_to_quote: quantity.is_sign_negative(),
+        quantity: quantity.abs(),
+        price: Some(price.abs()),
+        fee: fee.as_ref().map(|(number, _)| number.abs()),
+        fee_symbol: fee.map(|(_, commodity)| names.symbol(&commodity)),
+        remark: narration,
+        occurrence_at,
+        account: Some(names.account(&annotated.account)),
+    }))
+}
+
+/// Reads the trades of a Beancount file, each in the account it is booked
+/// to. Transactions that are not trades, such as deposits and transfers, are
+/// left out, as is everything but transactions. Symbols and account names
+/// are taken from the `symbol` metadata of commodities and the `name`
+/// metadata of accounts when there is some, and times of day from the
+/// `time` metadata of transactions.
+pub fn parse(data: &str) -> Result<Vec<Line>, Box<dyn Error>> {
+    let directives = split(data);
+    let mut names = Names::default();
+
+    for directive in &directives {
+        let tokens: Vec<&str> = directive.header.split_whitespace().collect();
+        let meta = metadata(&directive.body);
+
+        match tokens[..] {
+            [_, "open", account, ..] => {
+                if let Some(name) = meta.get("name") {
+                    names.accounts.insert(account.to_string(), name.clone());
+                }
+            }
+            [_, "commodity", commodity, ..] => {
+                if let Some(symbol) = meta.get("symbol") {
+                    names.symbols.insert(commodity.to_string(), symbol.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut lines = Vec::new();
+
+    for directive in &directives {
+        let mut tokens = directive.header.split_whitespace();
+        let (Some(date), Some(flag)) = (tokens.next(), tokens.next()) else {
+            continue;
+        };
+        if !matches!(flag, "*" | "!" | "txn") {
+            continue;
+        }
+
+        let result = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| format!("{} is not a date", date))
+            .and_then(|date| {
+                let postings = directive
+                    .body
+                    .iter()
+                    .filter(|line| !line.starts_with(|c: char| c.is_ascii_lowercase()))
+                    .map(|line| parse_posting(line))
+                    .collect::<Result<Vec<Posting>, String>>()?;
+                let narration = strings(&directive.header)
+                    .pop()
+                    .filter(|narration| !narration.is_empty());
+
+                record(
+                    &names,
+                    date,
+                    narration,
+                    &metadata(&directive.body),
+                    &postings,
+                )
+            });
+
+        if let Some(record) = result.transpose() {
+            lines.push(Line {
+                line: directive.line,
+                record,
+            });
+        }
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use crate::export::beancount::render;
+    use crate::export::tests::{setup, Setup};
+    use crate::export::Journal;
+
+    use super::parse;
+
+    #[test]
+    fn test_round_trip() {
+        let Setup { conn, owner } = setup();
+        let journal = Journal::load(&conn, owner, Utc::now()).unwrap();
+
+        let lines = parse(&render(&journal)).unwrap();
+
+        // The transfer is left out
+        assert_eq!(lines.len(), 3);
+
+        let record = lines[2].record.as_ref().unwrap();
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("BTC", "USD")
+        );
+        assert!(record.is_base_to_quote);
+        assert_eq!(record.quantity, Decimal::new(25, 1));
+        assert_eq!(record.price, Some(Decimal::from(300)));
+        assert_eq!(record.fee, Some(Decimal::ONE));
+        assert_eq!(record.fee_symbol.as_deref(), Some("USD"));
+        assert_eq!(record.account.as_deref(), Some("cold wallet"));
+        assert_eq!(
+            record.occurrence_at,
+            Utc.with_ymd_and_hms(2024, 1, 4, 12, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = r#"
+option "operating_currency" "EUR"
+
+2024-01-01 open Assets:Broker:Cash
+2024-01-01 commodity VWRL
+  symbol: "VWRL.AS"
+
+2024-02-01 * "Broker" "Monthly buy; ETF" #invest
+  Assets:Broker:Cash    10 VWRL @@ 1,050.00 EUR ; ten units
+  Assets:Broker:Cash
+  Expenses:Commissions  2 EUR
+
+2024-02-02 * "Salary"
+  Assets:Broker:Cash   1000 EUR
+  Income:Salary
+
+2024-02-03 * "Rebalance"
+  Assets:Broker:Cash   -1 VWRL @ (1 + 1) EUR
+  Assets:Broker:Cash
+"#;
+
+        let lines = parse(data).unwrap();
+        assert_eq!(lines.len(), 2);
+
+        assert_eq!(lines[0].line, 8);
+        let record = lines[0].record.as_ref().unwrap();
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("VWRL.AS", "EUR")
+        );
+        assert!(!record.is_base_to_quote);
+        assert_eq!(record.price, Some(Decimal::from(105)));
+        assert_eq!(record.fee, Some(Decimal::from(2)));
+        assert_eq!(record.remark.as_deref(), Some("Monthly buy; ETF"));
+        assert_eq!(record.account.as_deref(), Some("Broker:Cash"));
+
+        assert!(lines[1].record.is_err());
+    }
+}
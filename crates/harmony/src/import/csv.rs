@@ -0,0 +1,552 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::{Line, Record};
+
+/// Which columns of a CSV file hold what, by header name.
+///
+/// Symbols come either from a `pair` column such as `BTC/USDT` or from
+/// separate `base` and `quote` columns. Without a `price` column the price is
+/// worked out from a `total` column of the quote amount, when there is one.
+/// The side alone tells which way a row goes, so amounts are positive but in
+/// the `signed` columns.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Mapping {
+    pub occurrence_at: String,
+    /// A chrono format of the time in UTC. RFC 3339, `%Y-%m-%d %H:%M:%S`
+    /// and `%Y-%m-%d` are tried when omitted.
+    pub time_format: Option<String>,
+    pub pair: Option<String>,
+    /// `/` when omitted.
+    pub pair_separator: Option<String>,
+    /// Quote symbols a pair without a separator ends with, such as `USDT` in
+    /// `BTCUSDT`. The longest match wins. Pairs are split on the separator
+    /// when empty.
+    #[serde(default)]
+    pub quotes: Vec<String>,
+    /// Symbols of the file renamed to symbols of objects, applied after
+    /// splitting pairs.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    pub base: Option<String>,
+    pub quote: Option<String>,
+    pub side: String,
+    /// Values of `side` that buy the base object, `buy` when empty. Compared
+    /// without case.
+    #[serde(default)]
+    pub buy: Vec<String>,
+    /// Values of `side` that sell the base object, `sell` when empty.
+    #[serde(default)]
+    pub sell: Vec<String>,
+    /// Leaves out rows that are neither a buy nor a sell, such as deposits,
+    /// instead of failing them.
+    #[serde(default)]
+    pub skip_others: bool,
+    pub quantity: String,
+    pub price: Option<String>,
+    pub total: Option<String>,
+    pub fee: Option<String>,
+    pub fee_symbol: Option<String>,
+    pub remark: Option<String>,
+    /// Amount columns written negative for one side, such as quantities of
+    /// sells or commissions. Their sign is dropped, where a negative amount
+    /// in any other column fails its row.
+    #[serde(default)]
+    pub signed: Vec<String>,
+    /// `,` when omitted.
+    pub delimiter: Option<char>,
+    /// What separates the decimal places of amounts, `.` when omitted.
+    pub decimal_separator: Option<char>,
+    /// What groups the thousands of amounts, `,` when omitted, or `.` when
+    /// the decimal separator is `,`.
+    pub thousands_separator: Option<char>,
+    /// Takes the first line naming the `occurrence_at` column as the header,
+    /// ignoring any lines above it.
+    #[serde(default)]
+    pub seek_header: bool,
+}
+
+/// Column positions of a mapping in one file.
+struct Columns {
+    occurrence_at: usize,
+    pair: Option<usize>,
+    base: Option<usize>,
+    quote: Option<usize>,
+    side: usize,
+    quantity: usize,
+    price: Option<usize>,
+    total: Option<usize>,
+    fee: Option<usize>,
+    fee_symbol: Option<usize>,
+    remark: Option<usize>,
+    signed: Vec<usize>,
+}
+
+/// Parses a time with `format`, or with the usual formats without one.
+/// Times without an offset are taken as UTC.
+pub fn parse_time(value: &str, format: Option<&str>) -> Result<DateTime<Utc>, String> {
+    let invalid = || format!("{} is not a time", value);
+
+    let Some(format) = format else {
+        if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+            return Ok(time.with_timezone(&Utc));
+        }
+        if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+            return Ok(time.and_utc());
+        }
+        let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| invalid())?;
+        return Ok(date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?.and_utc());
+    };
+
+    if let Ok(time) = DateTime::parse_from_str(value, format) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+        return Ok(time.and_utc());
+    }
+    let date = NaiveDate::parse_from_str(value, format).map_err(|_| invalid())?;
+
+    Ok(date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?.and_utc())
+}
+
+/// Parses an amount written with `decimal` and `thousands` separators,
+/// ignoring a currency sign and surrounding space. Thousands must come in
+/// groups of three, so that `1,5` is not read as fifteen when it was meant
+/// as one and a half. An empty value is `None`.
+pub fn parse_amount(
+    name: &str,
+    value: &str,
+    decimal: char,
+    thousands: char,
+) -> Result<Option<Decimal>, String> {
+    let value = value.replace(['$', '€', '£'], "");
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    let (integer, fraction) = match value.split_once(decimal) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (value, None),
+    };
+
+    let groups: Vec<&str> = integer.split(thousands).collect();
+    let is_grouped = match groups.split_first() {
+        Some((first, rest)) if !rest.is_empty() => {
+            let first = first.trim_start_matches(['-', '+']);
+            (1..=3).contains(&first.len()) && rest.iter().all(|group| group.len() == 3)
+        }
+        _ => true,
+    };
+    if !is_grouped || fraction.is_some_and(|fraction| fraction.contains(thousands)) {
+        return Err(format!(
+            "{} {} is ambiguous with {} between thousands and {} before decimals",
+            name, value, thousands, decimal
+        ));
+    }
+
+    let number = match fraction {
+        Some(fraction) => format!("{}.{}", groups.concat(), fraction),
+        None => groups.concat(),
+    };
+
+    Decimal::from_str(&number)
+        .or_else(|_| Decimal::from_scientific(&number))
+        .map(Some)
+        .map_err(|_| format!("{} {} is not a number", name, value))
+}
+
+impl Mapping {
+    fn columns(&self, headers: &::csv::StringRecord) -> Result<Columns, String> {
+        let find = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header.trim() == name)
+                .ok_or_else(|| format!("column {} does not exist", name))
+        };
+        let find_optional = |name: &Option<String>| name.as_deref().map(find).transpose();
+
+        let columns = Columns {
+            occurrence_at: find(&self.occurrence_at)?,
+            pair: find_optional(&self.pair)?,
+            base: find_optional(&self.base)?,
+            quote: find_optional(&self.quote)?,
+            side: find(&self.side)?,
+            quantity: find(&self.quantity)?,
+            price: find_optional(&self.price)?,
+            total: find_optional(&self.total)?,
+            fee: find_optional(&self.fee)?,
+            fee_symbol: find_optional(&self.fee_symbol)?,
+            remark: find_optional(&self.remark)?,
+            signed: self
+                .signed
+                .iter()
+                .map(|name| find(name))
+                .collect::<Result<_, _>>()?,
+        };
+
+        if columns.pair.is_none() && (columns.base.is_none() || columns.quote.is_none()) {
+            return Err("either pair or both base and quote are required".into());
+        }
+
+        let (decimal, thousands) = self.separators();
+        if decimal == thousands {
+            return Err(format!(
+                "{} cannot separate both decimals and thousands",
+                decimal
+            ));
+        }
+
+        Ok(columns)
+    }
+
+    /// The decimal and thousands separators of amounts.
+    fn separators(&self) -> (char, char) {
+        let decimal = self.decimal_separator.unwrap_or('.');
+        let thousands = self
+            .thousands_separator
+            .unwrap_or(if decimal == ',' { '.' } else { ',' });
+
+        (decimal, thousands)
+    }
+
+    /// Whether `side` sells the base object, `None` for neither a buy nor a
+    /// sell.
+    fn is_base_to_quote(&self, side: &str) -> Option<bool> {
+        let matches = |values: &[String], default: &str| {
+            if values.is_empty() {
+                side.eq_ignore_ascii_case(default)
+            } else {
+                values
+                    .iter()
+                    .any(|value| side.eq_ignore_ascii_case(value.trim()))
+            }
+        };
+
+        if matches(&self.buy, "buy") {
+            Some(false)
+        } else if matches(&self.sell, "sell") {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    fn split<'a>(&self, pair: &'a str) -> Result<(&'a str, &'a str), String> {
+        if self.quotes.is_empty() {
+            let separator = self.pair_separator.as_deref().unwrap_or("/");
+            let (base, quote) = pair
+                .split_once(separator)
+                .ok_or_else(|| format!("pair {} has no {}", pair, separator))?;
+
+            return Ok((base.trim(), quote.trim()));
+        }
+
+        self.quotes
+            .iter()
+            .filter(|quote| pair.len() > quote.len() && pair.ends_with(quote.as_str()))
+            .max_by_key(|quote| quote.len())
+            .map(|quote| pair.split_at(pair.len() - quote.len()))
+            .ok_or_else(|| format!("pair {} ends with no known quote", pair))
+    }
+
+    fn alias(&self, symbol: &str) -> String {
+        self.aliases
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| symbol.to_string())
+    }
+
+    /// Reads a row, `None` for a row left out.
+    fn record(
+        &self,
+        columns: &Columns,
+        row: &::csv::StringRecord,
+    ) -> Result<Option<Record>, String> {
+        let get = |index: usize| row.get(index).map(str::trim).unwrap_or_default();
+        let get_optional = |index: Option<usize>| index.map(get).filter(|value| !value.is_empty());
+        let required = |index: usize, name: &str| {
+            Some(get(index))
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| format!("{} is empty", name))
+        };
+
+        let side = required(columns.side, "side")?;
+        let is_base_to_quote = match self.is_base_to_quote(side) {
+            Some(is_base_to_quote) => is_base_to_quote,
+            None if self.skip_others => return Ok(None),
+            None => return Err(format!("side {} is neither a buy nor a sell", side)),
+        };
+
+        let (base, quote) = match columns.pair {
+            Some(index) => self.split(required(index, "pair")?)?,
+            None => (
+                required(columns.base.unwrap_or_default(), "base")?,
+                required(columns.quote.unwrap_or_default(), "quote")?,
+            ),
+        };
+
+        let (decimal, thousands) = self.separators();
+        let amount = |index: Option<usize>, name: &str| match index {
+            Some(index) => match parse_amount(name, get(index), decimal, thousands)? {
+                Some(amount) if columns.signed.contains(&index) => Ok(Some(amount.abs())),
+                Some(amount) if amount.is_sign_negative() => {
+                    Err(format!("{} {} is negative", name, amount))
+                }
+                amount => Ok(amount),
+            },
+            None => Ok(None),
+        };
+        let quantity = amount(Some(columns.quantity), "quantity")?
+            .ok_or_else(|| "quantity is empty".to_string())?;
+        let price = match amount(columns.price, "price")? {
+            Some(price) => Some(price),
+            None => amount(columns.total, "total")?.and_then(|total| total.checked_div(quantity)),
+        };
+
+        Ok(Some(Record {
+            base: self.alias(base),
+            quote: self.alias(quote),
+            is_base_to_quote,
+            quantity,
+            price,
+            fee: amount(columns.fee, "fee")?,
+            fee_symbol: get_optional(columns.fee_symbol).map(|symbol| self.alias(symbol)),
+            remark: get_optional(columns.remark).map(str::to_string),
+            occurrence_at: parse_time(
+                required(columns.occurrence_at, "time")?,
+                self.time_format.as_deref(),
+            )?,
+            account: None,
+        }))
+    }
+
+    /// Reads every row of a file with a header. A row that cannot be read
+    /// becomes an error on its line, a file the mapping does not fit fails
+    /// as a whole. Line numbers count from the top of the file.
+    pub fn parse(&self, data: &[u8]) -> Result<Vec<Line>, Box<dyn Error>> {
+        let mut skipped = 0;
+        let mut data = data;
+        if self.seek_header {
+            while !data.is_empty() && !self.is_header(data) {
+                let end = data
+                    .iter()
+                    .position(|&byte| byte == b'\n')
+                    .map_or(data.len(), |i| i + 1);
+                data = &data[end..];
+                skipped += 1;
+            }
+        }
+
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(self.delimiter.unwrap_or(',') as u8)
+            .flexible(true)
+            .from_reader(data);
+
+        let columns = self.columns(reader.headers()?)?;
+        let mut lines = Vec::new();
+
+        for row in reader.records() {
+            let row = row?;
+            let line = row.position().map_or(0, |position| position.line()) + skipped;
+
+            if let Some(record) = self.record(&columns, &row).transpose() {
+                lines.push(Line { line, record });
+            }
+        }
+
+        Ok(lines)
+    }
+
+    /// Whether the first line of `data` names the time column.
+    fn is_header(&self, data: &[u8]) -> bool {
+        let end = data
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .unwrap_or(data.len());
+        let delimiter = self.delimiter.unwrap_or(',');
+
+        String::from_utf8_lossy(&data[..end])
+            .split(delimiter)
+            .map(|header| {
+                header
+                    .trim_start_matches('\u{feff}')
+                    .trim()
+                    .trim_matches('"')
+            })
+            .any(|header| header == self.occurrence_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use super::Mapping;
+
+    fn mapping() -> Mapping {
+        Mapping {
+            occurrence_at: "Date".to_string(),
+            pair: Some("Market".to_string()),
+            side: "Type".to_string(),
+            buy: vec!["Bought".to_string()],
+            sell: vec!["Sold".to_string()],
+            quantity: "Amount".to_string(),
+            total: Some("Total".to_string()),
+            fee: Some("Fee".to_string()),
+            fee_symbol: Some("Fee Coin".to_string()),
+            ..Mapping::default()
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = "\
+Date,Market,Type,Amount,Total,Fee,Fee Coin
+2024-01-02 03:04:05,BTC/USDT,Bought,0.5,\"20,000\",0.001,BNB
+2024-01-03,ETH/USDT,sold,2,4000,,
+";
+
+        let lines = mapping().parse(data.as_bytes()).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].line, 2);
+
+        let record = lines[0].record.as_ref().unwrap();
+        assert_eq!(record.base, "BTC");
+        assert_eq!(record.quote, "USDT");
+        assert!(!record.is_base_to_quote);
+        assert_eq!(record.price, Some(Decimal::from(40000)));
+        assert_eq!(record.fee_symbol.as_deref(), Some("BNB"));
+        assert_eq!(
+            record.occurrence_at,
+            Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+        );
+
+        let record = lines[1].record.as_ref().unwrap();
+        assert!(record.is_base_to_quote);
+        assert_eq!(record.fee, None);
+    }
+
+    #[test]
+    fn test_row_errors_stay_on_their_line() {
+        let data = "\
+Date,Market,Type,Amount,Total,Fee,Fee Coin
+2024-01-02,BTCUSDT,Bought,0.5,20000,,
+yesterday,BTC/USDT,Bought,0.5,20000,,
+2024-01-02,BTC/USDT,Lent,0.5,20000,,
+2024-01-02,BTC/USDT,Bought,half,20000,,
+";
+
+        let lines = mapping().parse(data.as_bytes()).unwrap();
+
+        let errors: Vec<String> = lines
+            .into_iter()
+            .map(|line| line.record.unwrap_err())
+            .collect();
+        assert_eq!(errors[0], "pair BTCUSDT has no /");
+        assert_eq!(errors[1], "yesterday is not a time");
+        assert_eq!(errors[2], "side Lent is neither a buy nor a sell");
+        assert_eq!(errors[3], "quantity half is not a number");
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        let parse = |value: &str, decimal: char, thousands: char| {
+            super::parse_amount("amount", value, decimal, thousands)
+        };
+
+        assert_eq!(
+            parse(" $1,234.5 ", '.', ','),
+            Ok(Some(Decimal::new(12345, 1)))
+        );
+        assert_eq!(
+            parse("-1,234,567", '.', ','),
+            Ok(Some(Decimal::from(-1234567)))
+        );
+        assert_eq!(
+            parse("1.234,56 €", ',', '.'),
+            Ok(Some(Decimal::new(123456, 2)))
+        );
+        assert_eq!(parse("1 234,5", ',', ' '), Ok(Some(Decimal::new(12345, 1))));
+        assert_eq!(parse("0,5", ',', '.'), Ok(Some(Decimal::new(5, 1))));
+        assert_eq!(parse("1e-3", '.', ','), Ok(Some(Decimal::new(1, 3))));
+        assert_eq!(parse("", '.', ','), Ok(None));
+
+        // A separator that could be either is refused rather than guessed
+        assert!(parse("1,5", '.', ',').is_err());
+        assert!(parse("1,2345", '.', ',').is_err());
+        assert!(parse("1234,567.8", '.', ',').is_err());
+        assert!(parse("1.5,000", '.', ',').is_err());
+        assert!(parse("1.000.5", ',', '.').is_err());
+        assert!(parse("one", '.', ',').is_err());
+    }
+
+    #[test]
+    fn test_decimal_comma() {
+        let data = "\
+Date;Market;Type;Amount;Total;Fee;Fee Coin
+2024-01-02;BTC/EUR;Bought;0,5;20.000,00;0,25;EUR
+";
+        let mapping = Mapping {
+            delimiter: Some(';'),
+            decimal_separator: Some(','),
+            ..mapping()
+        };
+
+        let lines = mapping.parse(data.as_bytes()).unwrap();
+        let record = lines[0].record.as_ref().unwrap();
+        assert_eq!(record.quantity, Decimal::new(5, 1));
+        assert_eq!(record.price, Some(Decimal::from(40000)));
+        assert_eq!(record.fee, Some(Decimal::new(25, 2)));
+
+        let mapping = Mapping {
+            thousands_separator: Some(','),
+            ..mapping
+        };
+        assert!(mapping.parse(data.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_signed_columns() {
+        let data = "\
+Date,Market,Type,Amount,Total,Fee,Fee Coin
+2024-01-02,BTC/USDT,Sold,-0.5,20000,-0.001,BNB
+";
+
+        let lines = mapping().parse(data.as_bytes()).unwrap();
+        let errors: Vec<String> = lines
+            .into_iter()
+            .map(|line| line.record.unwrap_err())
+            .collect();
+        assert_eq!(errors, ["quantity -0.5 is negative"]);
+
+        let mapping = Mapping {
+            signed: vec!["Amount".to_string(), "Fee".to_string()],
+            ..mapping()
+        };
+        let lines = mapping.parse(data.as_bytes()).unwrap();
+        let record = lines[0].record.as_ref().unwrap();
+        assert_eq!(record.quantity, Decimal::new(5, 1));
+        assert_eq!(record.price, Some(Decimal::from(40000)));
+        assert_eq!(record.fee, Some(Decimal::new(1, 3)));
+
+        let mapping = Mapping {
+            signed: vec!["Volume".to_string()],
+            ..mapping
+        };
+        assert!(mapping.parse(data.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_missing_column_fails_the_file() {
+        let data = "Date,Market,Type,Amount\n";
+
+        assert!(mapping().parse(data.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,480 @@
+pub mod beancount;
+pub mod csv;
+pub mod preset;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::common::hash::{digest_to_hex, sha256_digest};
+use crate::model::database::savepoint;
+use crate::model::finance::account::Account;
+use crate::model::finance::import::Import;
+use crate::model::finance::object::{Classification, Object};
+use crate::model::finance::trade::transaction::{Status as TransactionStatus, Transaction};
+use crate::model::finance::trade::Trade;
+use crate::model::finance::Quantity;
+use crate::portfolio::ledger;
+
+/// One transaction read from a file, naming objects by symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub base: String,
+    pub quote: String,
+    pub is_base_to_quote: bool,
+    pub quantity: Decimal,
+    pub price: Option<Decimal>,
+    pub fee: Option<Decimal>,
+    /// The quote object when omitted.
+    pub fee_symbol: Option<String>,
+    pub remark: Option<String>,
+    pub occurrence_at: DateTime<Utc>,
+    /// The account by name, created when missing. The account imported into
+    /// when omitted.
+    pub account: Option<String>,
+}
+
+impl Record {
+    /// Identifies the content of the record for an owner and account, so
+    /// the same row read twice, from any file, hashes the same.
+    pub fn hash(&self, owner: i64, account_id: i64) -> String {
+        let text = |value: Option<&Decimal>| value.map(|value| value.normalize().to_string());
+        let content = [
+            owner.to_string(),
+            account_id.to_string(),
+            self.base.clone(),
+            self.quote.clone(),
+            self.is_base_to_quote.to_string(),
+            self.quantity.normalize().to_string(),
+            text(self.price.as_ref()).unwrap_or_default(),
+            text(self.fee.as_ref()).unwrap_or_default(),
+            self.fee_symbol.clone().unwrap_or_default(),
+            self.occurrence_at.to_rfc3339(),
+        ]
+        .join("\u{1f}");
+
+        digest_to_hex(&sha256_digest(content.as_bytes())).unwrap_or_default()
+    }
+
+    /// Checks what can be checked without the database.
+    fn validate(&self) -> Result<(), String> {
+        if self.base == self.quote {
+            return Err(format!("{} cannot be traded for itself", self.base));
+        }
+
+        let amounts = [
+            ("quantity", Some(self.quantity)),
+            ("price", self.price),
+            ("fee", self.fee),
+        ];
+        for (name, amount) in amounts {
+            if amount.is_some_and(|amount| amount <= Decimal::ZERO) {
+                return Err(format!("{} must be positive", name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A record, or why it could not be read, with the line of the file it is on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub line: u64,
+    pub record: Result<Record, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Created,
+    Duplicate,
+    Invalid,
+}
+
+/// What became of one line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub line: u64,
+    pub status: Status,
+    pub hash: Option<String>,
+    pub trade_id: Option<i64>,
+    /// The transaction created, or imported before for a duplicate.
+    pub transaction_id: Option<i64>,
+    pub imported_at: Option<DateTime<Utc>>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// Whether anything was stored, false for a dry run or an invalid line.
+    pub committed: bool,
+    pub outcomes: Vec<Outcome>,
+    /// Symbols of the objects created.
+    pub objects: Vec<String>,
+    /// Names of the accounts created.
+    pub accounts: Vec<String>,
+    /// Trades created.
+    pub trades: Vec<i64>,
+}
+
+impl Outcome {
+    fn invalid(line: u64, message: String) -> Self {
+        Self {
+            line,
+            status: Status::Invalid,
+            hash: None,
+            trade_id: None,
+            transaction_id: None,
+            imported_at: None,
+            message: Some(message),
+        }
+    }
+}
+
+/// Objects of the owner by symbol, creating missing ones as they come up.
+struct Objects {
+    owner: i64,
+    known: HashMap<String, (i64, u32)>,
+    created: Vec<String>,
+}
+
+impl Objects {
+    fn load(conn: &Connection, owner: i64) -> Result<Self, Box<dyn Error>> {
+        let mut known = HashMap::new();
+
+        // The oldest object wins when symbols repeat
+        for object in Object::select_all_by_owner(conn, owner)? {
+            known
+                .entry(object.symbol.clone())
+                .or_insert((object.id(), object.classification.precision));
+        }
+
+        Ok(Self {
+            owner,
+            known,
+            created: Vec::new(),
+        })
+    }
+
+    fn precision(&self, symbol: &str) -> u32 {
+        self.known
+            .get(symbol)
+            .map_or(Classification::default().precision, |&(_, precision)| {
+                precision
+            })
+    }
+
+    fn id(&mut self, conn: &Connection, symbol: &str) -> Result<i64, Box<dyn Error>> {
+        if let Some(&(id, _)) = self.known.get(symbol) {
+            return Ok(id);
+        }
+
+        let classification = Classification::default();
+        let precision = classification.precision;
+        let id = Object::insert(
+            conn,
+            self.owner,
+            symbol.to_string(),
+            None,
+            None,
+            classification,
+        )?;
+
+        self.known.insert(symbol.to_string(), (id, precision));
+        self.created.push(symbol.to_string());
+
+        Ok(id)
+    }
+}
+
+/// Accounts of the owner by name, creating missing ones as they come up.
+struct Accounts {
+    owner: i64,
+    known: HashMap<String, i64>,
+    created: Vec<String>,
+}
+
+impl Accounts {
+    fn load(conn: &Connection, owner: i64) -> Result<Self, Box<dyn Error>> {
+        let known = Account::select_all_by_owner(conn, owner)?
+            .into_iter()
+            .map(|account| (account.name.clone(), account.id()))
+            .collect();
+
+        Ok(Self {
+            owner,
+            known,
+            created: Vec::new(),
+        })
+    }
+
+    fn id(&mut self, conn: &Connection, name: &str) -> Result<i64, Box<dyn Error>> {
+        if let Some(&id) = self.known.get(name) {
+            return Ok(id);
+        }
+
+        let id = Account::insert(conn, self.owner, name.to_string(), None, None)?;
+
+        self.known.insert(name.to_string(), id);
+        self.created.push(name.to_string());
+
+        Ok(id)
+    }
+}
+
+fn write(
+    conn: &Connection,
+    owner: i64,
+    account_id: Option<i64>,
+    lines: Vec<Line>,
+) -> Result<Summary, Box<dyn Error>> {
+    let mut objects = Objects::load(conn, owner)?;
+    let mut accounts = Accounts::load(conn, owner)?;
+    let mut trades: HashMap<(i64, i64), i64> = HashMap::new();
+    let mut created_trades = Vec::new();
+    let mut outcomes = Vec::new();
+
+    for Line { line, record } in lines {
+        let record = match record.and_then(|record| record.validate().map(|_| record)) {
+            Ok(record) => record,
+            Err(message) => {
+                outcomes.push(Outcome::invalid(line, message));
+                continue;
+            }
+        };
+
+        let account_id = match (&record.account, account_id) {
+            (Some(name), _) => accounts.id(conn, name)?,
+            (None, Some(account_id)) => account_id,
+            (None, None) => {
+                outcomes.push(Outcome::invalid(line, "account is required".to_string()));
+                continue;
+            }
+        };
+
+        let hash = record.hash(owner, account_id);
+        if let Some(import) = Import::select_by_owner_hash(conn, owner, &hash)? {
+            let id = import.id();
+            outcomes.push(Outcome {
+                line,
+                status: Status::Duplicate,
+                hash: Some(import.hash),
+                trade_id: None,
+                transaction_id: Some(import.transaction_id),
+                imported_at: Some(import.created_at),
+                message: Some(format!("already imported as import {}", id)),
+            });
+            continue;
+        }
+
+        let quantity = match Quantity::from(record.quantity)
+            .with_precision(objects.precision(&record.base), None)
+        {
+            Ok(quantity) => quantity,
+            Err(error) => {
+                outcomes.push(Outcome::invalid(line, error.to_string()));
+                continue;
+            }
+        };
+
+        let base = objects.id(conn, &record.base)?;
+        let quote = objects.id(conn, &record.quote)?;
+        let fee_object_id = match (&record.fee, &record.fee_symbol) {
+            (Some(_), Some(symbol)) => Some(objects.id(conn, symbol)?),
+            (Some(_), None) => Some(quote),
+            (None, _) => None,
+        };
+
+        let trade_id = match trades.get(&(base, quote)) {
+            Some(&trade_id) => trade_id,
+            None => {
+                let (trade_id, created) =
+                    Trade::insert_or_select_by_owner_pair(conn, owner, base, quote, None, None)?;
+                if created {
+                    created_trades.push(trade_id);
+                }
+                trades.insert((base, quote), trade_id);
+                trade_id
+            }
+        };
+        let trade = Trade::select_by_id_owner(conn, trade_id, owner)?
+            .ok_or(format!("trade {} does not exist", trade_id))?;
+
+        let id = Transaction::insert(
+            conn,
+            trade.id(),
+            account_id,
+            quantity,
+            record.price.map(Quantity::from),
+            record.fee.map(Quantity::from),
+            fee_object_id,
+            record.is_base_to_quote,
+            TransactionStatus::Settled,
+            None,
+            None,
+            record.remark,
+            Some(record.occurrence_at),
+        )?;
+        let transaction = Transaction::select_by_id_trade_id(conn, id, trade.id())?
+            .ok_or(format!("transaction {} does not exist", id))?;
+        ledger::record_transaction(conn, owner, &trade, &transaction)?;
+        Import::insert(conn, owner, &hash, id)?;
+
+        outcomes.push(Outcome {
+            line,
+            status: Status::Created,
+            hash: Some(hash),
+            trade_id: Some(trade.id()),
+            transaction_id: Some(id),
+            imported_at: None,
+            message: None,
+        });
+    }
+
+    Ok(Summary {
+        committed: false,
+        outcomes,
+        objects: objects.created,
+        accounts: accounts.created,
+        trades: created_trades,
+    })
+}
+
+/// Creates the objects, trades and transactions of `lines` in the account
+/// each line names, or else in `account_id`, skipping lines imported
+/// before. Everything is stored together or, for a dry run or when any line
+/// is invalid, nothing is and the summary shows what would have been.
+pub fn import(
+    conn: &Connection,
+    owner: i64,
+    account_id: Option<i64>,
+    lines: Vec<Line>,
+    dry_run: bool,
+) -> Result<Summary, Box<dyn Error>> {
+    let mut committed = false;
+    let mut summary = savepoint(
+        conn,
+        || write(conn, owner, account_id, lines),
+        |summary| {
+            let is_valid = summary
+                .outcomes
+                .iter()
+                .all(|outcome| outcome.status != Status::Invalid);
+
+            committed = !dry_run && is_valid;
+            committed
+        },
+    )?;
+    summary.committed = committed;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::account::Account;
+    use crate::model::finance::object::Object;
+    use crate::model::finance::trade::Trade;
+    use crate::model::person::Person;
+
+    use super::{import, Line, Record, Status};
+
+    fn setup() -> (PooledConnection<SqliteConnectionManager>, i64, i64) {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+        let account = Account::insert(&conn, owner, "Exchange".to_string(), None, None).unwrap();
+
+        (conn, owner, account)
+    }
+
+    fn line(line: u64, quantity: i64, day: u32) -> Line {
+        Line {
+            line,
+            record: Ok(Record {
+                base: "BTC".to_string(),
+                quote: "USDT".to_string(),
+                is_base_to_quote: false,
+                quantity: Decimal::new(quantity, 2),
+                price: Some(Decimal::from(40000)),
+                fee: Some(Decimal::ONE),
+                fee_symbol: None,
+                remark: None,
+                occurrence_at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+                account: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_dry_run_stores_nothing() {
+        let (conn, owner, account) = setup();
+
+        let summary = import(&conn, owner, Some(account), vec![line(2, 10, 1)], true).unwrap();
+
+        assert!(!summary.committed);
+        assert_eq!(summary.outcomes[0].status, Status::Created);
+        assert_eq!(summary.objects, vec!["BTC".to_string(), "USDT".to_string()]);
+        assert_eq!(summary.trades.len(), 1);
+        assert!(Object::select_all_by_owner(&conn, owner)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_duplicates_are_skipped() {
+        let (conn, owner, account) = setup();
+
+        let lines = vec![line(2, 10, 1), line(3, 10, 1)];
+        let summary = import(&conn, owner, Some(account), lines, false).unwrap();
+        assert!(summary.committed);
+        assert_eq!(summary.outcomes[1].status, Status::Duplicate);
+        assert_eq!(
+            summary.outcomes[1].transaction_id,
+            summary.outcomes[0].transaction_id
+        );
+
+        let lines = vec![line(2, 10, 1), line(3, 20, 2)];
+        let summary = import(&conn, owner, Some(account), lines, false).unwrap();
+        assert_eq!(summary.outcomes[0].status, Status::Duplicate);
+        assert_eq!(summary.outcomes[1].status, Status::Created);
+        assert!(summary.objects.is_empty());
+        assert_eq!(Trade::select_all_by_owner(&conn, owner).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_line_stores_nothing() {
+        let (conn, owner, account) = setup();
+        let invalid = Line {
+            line: 3,
+            record: Err("quantity is not a number".to_string()),
+        };
+
+        let lines = vec![line(2, 10, 1), invalid];
+        let summary = import(&conn, owner, Some(account), lines, false).unwrap();
+
+        assert!(!summary.committed);
+        assert_eq!(summary.outcomes[1].status, Status::Invalid);
+        assert!(Trade::select_all_by_owner(&conn, owner).unwrap().is_empty());
+    }
+}
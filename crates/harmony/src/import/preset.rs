@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::csv::Mapping;
+
+/// Statement exports of exchanges and brokers with a mapping built in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    /// Spot trade history of Binance, with pairs such as `BTCUSDT`.
+    Binance,
+    /// Transaction history of Coinbase. Rows other than buys and sells, such
+    /// as sends and rewards, are left out.
+    Coinbase,
+    /// Trades export of Kraken, with pairs such as `XXBTZUSD`.
+    Kraken,
+    /// Flex query of trades of Interactive Brokers with the columns `Symbol`,
+    /// `CurrencyPrimary`, `DateTime`, `Quantity`, `TradePrice`,
+    /// `IBCommission`, `IBCommissionCurrency` and `Buy/Sell`. Times are
+    /// taken as UTC.
+    InteractiveBrokers,
+}
+
+impl Preset {
+    pub const ALL: [Self; 4] = [
+        Self::Binance,
+        Self::Coinbase,
+        Self::Kraken,
+        Self::InteractiveBrokers,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Binance => "binance",
+            Self::Coinbase => "coinbase",
+            Self::Kraken => "kraken",
+            Self::InteractiveBrokers => "interactive_brokers",
+        }
+    }
+
+    pub fn mapping(&self) -> Mapping {
+        let column = |name: &str| name.to_string();
+        let columns = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+        match self {
+            Self::Binance => Mapping {
+                occurrence_at: column("Date(UTC)"),
+                pair: Some(column("Market")),
+                quotes: columns(&[
+                    "USDT", "FDUSD", "USDC", "BUSD", "TUSD", "DAI", "BTC", "ETH", "BNB", "EUR",
+                    "GBP", "TRY", "BRL", "AUD",
+                ]),
+                side: column("Type"),
+                quantity: column("Amount"),
+                price: Some(column("Price")),
+                total: Some(column("Total")),
+                fee: Some(column("Fee")),
+                fee_symbol: Some(column("Fee Coin")),
+                ..Mapping::default()
+            },
+            Self::Coinbase => Mapping {
+                occurrence_at: column("Timestamp"),
+                time_format: Some(column("%Y-%m-%d %H:%M:%S UTC")),
+                base: Some(column("Asset")),
+                quote: Some(column("Spot Price Currency")),
+                side: column("Transaction Type"),
+                buy: columns(&["Buy", "Advanced Trade Buy"]),
+                sell: columns(&["Sell", "Advanced Trade Sell"]),
+                skip_others: true,
+                quantity: column("Quantity Transacted"),
+                price: Some(column("Spot Price at Transaction")),
+                fee: Some(column("Fees and/or Spread")),
+                remark: Some(column("Notes")),
+                signed: columns(&["Quantity Transacted", "Fees and/or Spread"]),
+                seek_header: true,
+                ..Mapping::default()
+            },
+            Self::Kraken => Mapping {
+                occurrence_at: column("time"),
+                time_format: Some(column("%Y-%m-%d %H:%M:%S%.f")),
+                pair: Some(column("pair")),
+                quotes: columns(&[
+                    "ZUSD", "ZEUR", "ZGBP", "ZCAD", "ZJPY", "XXBT", "XETH", "USDT", "USDC", "USD",
+                    "EUR", "GBP", "CAD", "JPY", "CHF", "AUD", "XBT", "ETH", "DAI",
+                ]),
+                aliases: BTreeMap::from(
+                    [
+                        ("XXBT", "BTC"),
+                        ("XBT", "BTC"),
+                        ("XETH", "ETH"),
+                        ("XXRP", "XRP"),
+                        ("XLTC", "LTC"),
+                        ("XXLM", "XLM"),
+                        ("XXDG", "DOGE"),
+                        ("XDG", "DOGE"),
+                        ("ZUSD", "USD"),
+                        ("ZEUR", "EUR"),
+                        ("ZGBP", "GBP"),
+                        ("ZCAD", "CAD"),
+                        ("ZJPY", "JPY"),
+                    ]
+                    .map(|(from, to)| (from.to_string(), to.to_string())),
+                ),
+                side: column("type"),
+                quantity: column("vol"),
+                price: Some(column("price")),
+                total: Some(column("cost")),
+                fee: Some(column("fee")),
+                ..Mapping::default()
+            },
+            Self::InteractiveBrokers => Mapping {
+                occurrence_at: column("DateTime"),
+                time_format: Some(column("%Y%m%d;%H%M%S")),
+                base: Some(column("Symbol")),
+                quote: Some(column("CurrencyPrimary")),
+                side: column("Buy/Sell"),
+                skip_others: true,
+                quantity: column("Quantity"),
+                price: Some(column("TradePrice")),
+                fee: Some(column("IBCommission")),
+                fee_symbol: Some(column("IBCommissionCurrency")),
+                signed: columns(&["Quantity", "IBCommission"]),
+                ..Mapping::default()
+            },
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.as_str() == s)
+            .ok_or_else(|| format!("{} is not a preset", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use crate::import::{Line, Record};
+
+    use super::Preset;
+
+    fn records(preset: Preset, data: &str) -> Vec<(u64, Record)> {
+        preset
+            .mapping()
+            .parse(data.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|Line { line, record }| (line, record.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_binance() {
+        let records = records(Preset::Binance, include_str!("samples/binance.csv"));
+
+        assert_eq!(records.len(), 3);
+
+        let (line, record) = &records[0];
+        assert_eq!(*line, 2);
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("BTC", "USDT")
+        );
+        assert!(!record.is_base_to_quote);
+        assert_eq!(record.quantity, Decimal::new(1, 2));
+        assert_eq!(record.price, Some(Decimal::new(420005, 1)));
+        assert_eq!(record.fee, Some(Decimal::new(75, 7)));
+        assert_eq!(record.fee_symbol.as_deref(), Some("BNB"));
+        assert_eq!(
+            record.occurrence_at,
+            Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+        );
+
+        let (_, record) = &records[1];
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("ETH", "BTC")
+        );
+        assert!(record.is_base_to_quote);
+
+        // FDUSD wins over USD
+        let (_, record) = &records[2];
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("BNB", "FDUSD")
+        );
+    }
+
+    #[test]
+    fn test_coinbase() {
+        let records = records(Preset::Coinbase, include_str!("samples/coinbase.csv"));
+
+        // The receive is left out
+        assert_eq!(records.len(), 2);
+
+        let (line, record) = &records[0];
+        assert_eq!(*line, 6);
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("BTC", "USD")
+        );
+        assert!(!record.is_base_to_quote);
+        assert_eq!(record.price, Some(Decimal::from(42000)));
+        assert_eq!(record.fee, Some(Decimal::new(499, 2)));
+        assert_eq!(record.fee_symbol, None);
+        assert_eq!(
+            record.occurrence_at,
+            Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+        );
+
+        let (line, record) = &records[1];
+        assert_eq!(*line, 8);
+        assert!(record.is_base_to_quote);
+        assert_eq!(record.quantity, Decimal::new(5, 3));
+    }
+
+    #[test]
+    fn test_kraken() {
+        let records = records(Preset::Kraken, include_str!("samples/kraken.csv"));
+
+        assert_eq!(records.len(), 3);
+
+        let (_, record) = &records[0];
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("BTC", "USD")
+        );
+        assert!(!record.is_base_to_quote);
+        assert_eq!(record.quantity, Decimal::new(1, 2));
+        assert_eq!(record.price, Some(Decimal::from(42000)));
+        assert_eq!(record.fee, Some(Decimal::new(672, 3)));
+        assert_eq!(
+            record.occurrence_at,
+            Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap()
+                + chrono::Duration::microseconds(123400)
+        );
+
+        let (_, record) = &records[1];
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("ETH", "BTC")
+        );
+        assert!(record.is_base_to_quote);
+
+        let (_, record) = &records[2];
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("SOL", "EUR")
+        );
+    }
+
+    #[test]
+    fn test_interactive_brokers() {
+        let records = records(
+            Preset::InteractiveBrokers,
+            include_str!("samples/interactive_brokers.csv"),
+        );
+
+        // The cancellation is left out
+        assert_eq!(records.len(), 2);
+
+        let (_, record) = &records[0];
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("AAPL", "USD")
+        );
+        assert!(!record.is_base_to_quote);
+        assert_eq!(record.quantity, Decimal::from(10));
+        assert_eq!(record.fee, Some(Decimal::ONE));
+        assert_eq!(
+            record.occurrence_at,
+            Utc.with_ymd_and_hms(2024, 1, 2, 9, 30, 0).unwrap()
+        );
+
+        let (_, record) = &records[1];
+        assert_eq!(
+            (record.base.as_str(), record.quote.as_str()),
+            ("VOD", "GBP")
+        );
+        assert!(record.is_base_to_quote);
+        assert_eq!(record.quantity, Decimal::from(100));
+        assert_eq!(record.fee_symbol.as_deref(), Some("GBP"));
+    }
+
+    #[test]
+    fn test_from_str() {
+        for preset in Preset::ALL {
+            assert_eq!(preset.to_string().parse::<Preset>(), Ok(preset));
+        }
+        assert!("mt_gox".parse::<Preset>().is_err());
+    }
+}
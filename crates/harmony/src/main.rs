@@ -1,30 +1,47 @@
 mod api;
+mod attachment;
+mod cli;
 mod common;
 mod consts;
+mod export;
+mod import;
+mod market;
 mod model;
+mod portfolio;
 mod time;
 
 use std::env;
 use std::net::SocketAddr;
 
 use axum_server::tls_rustls::RustlsConfig;
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
 
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(error) = cli::run(&args) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     tracing_subscriber::fmt::init();
 
     let cert_path = env::var("CERT_PATH");
     let key_path = env::var("KEY_PATH");
 
+    market::refresher::spawn();
+    portfolio::plan::spawn();
+
     let router = api::router();
 
-    if cert_path.is_ok() && key_path.is_ok() {
+    if let (Ok(cert_path), Ok(key_path)) = (cert_path, key_path) {
         let address = env::var("ADDRESS").unwrap_or("[::]:443".into());
         let addr: SocketAddr = address.parse().unwrap();
-        let config = RustlsConfig::from_pem_file(cert_path.unwrap(), key_path.unwrap())
+        let config = RustlsConfig::from_pem_file(cert_path, key_path)
             .await
             .unwrap();
 
@@ -0,0 +1,146 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+
+use super::{PricePoint, PriceProvider};
+
+// ===== Directory of CSV files =====
+/// Reads prices from `<directory>/<BASE>-<QUOTE>.csv` files with an
+/// `occurrence_at,price` header. A missing file means the pair is unknown.
+pub struct CsvProvider {
+    directory: PathBuf,
+}
+
+impl CsvProvider {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    fn path(&self, base: &str, quote: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let is_plain = |symbol: &str| {
+            !symbol.is_empty()
+                && symbol
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        };
+
+        if !is_plain(base) || !is_plain(quote) {
+            return Err(format!("unsupported symbol pair {}-{}", base, quote).into());
+        }
+
+        Ok(self.directory.join(format!("{}-{}.csv", base, quote)))
+    }
+
+    fn read(&self, base: &str, quote: &str) -> Result<Vec<PricePoint>, Box<dyn Error>> {
+        let path = self.path(base, quote)?;
+
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut reader = ::csv::Reader::from_reader(File::open(path)?);
+        let mut points = reader
+            .deserialize::<PricePoint>()
+            .collect::<Result<Vec<PricePoint>, _>>()?;
+
+        points.sort_by_key(|point| point.occurrence_at);
+
+        Ok(points)
+    }
+}
+
+impl PriceProvider for CsvProvider {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn latest(&self, base: &str, quote: &str) -> Result<Option<PricePoint>, Box<dyn Error>> {
+        Ok(self.read(base, quote)?.pop())
+    }
+
+    fn history(
+        &self,
+        base: &str,
+        quote: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<PricePoint>, Box<dyn Error>> {
+        let points = self
+            .read(base, quote)?
+            .into_iter()
+            .filter(|point| point.occurrence_at >= from && point.occurrence_at <= to)
+            .collect();
+
+        Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use super::{CsvProvider, PriceProvider};
+
+    fn setup(name: &str) -> CsvProvider {
+        let directory = std::env::temp_dir().join(format!("harmony-csv-provider-{}", name));
+        fs::create_dir_all(&directory).unwrap();
+
+        fs::write(
+            directory.join("BTC-USDT.csv"),
+            "occurrence_at,price\n\
+             2024-01-02T00:00:00Z,45000.5\n\
+             2024-01-01T00:00:00Z,42000\n\
+             2024-01-03T00:00:00Z,44000\n",
+        )
+        .unwrap();
+
+        CsvProvider::new(directory)
+    }
+
+    #[test]
+    fn test_latest() {
+        let provider = setup("latest");
+
+        let point = provider.latest("BTC", "USDT").unwrap().unwrap();
+        assert_eq!(point.price, Decimal::new(44000, 0));
+        assert_eq!(
+            point.occurrence_at,
+            Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap()
+        );
+
+        // Unknown pairs are not an error
+        assert!(provider.latest("ETH", "USDT").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_history() {
+        let provider = setup("history");
+
+        let points = provider
+            .history(
+                "BTC",
+                "USDT",
+                Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].price, Decimal::new(42000, 0));
+        assert_eq!(points[1].price, Decimal::new(450005, 1));
+    }
+
+    #[test]
+    fn test_rejects_path_traversal() {
+        let provider = setup("traversal");
+
+        assert!(provider.latest("../BTC", "USDT").is_err());
+    }
+}
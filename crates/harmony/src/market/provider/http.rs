@@ -0,0 +1,178 @@
+use std::error::Error;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{PricePoint, PriceProvider};
+
+// ===== HTTP JSON =====
+/// Fetches prices from a JSON service exposing:
+///
+/// - `GET <base_url>/latest?base=BTC&quote=USDT` returning a single price point,
+///   or `404` when the pair is unknown.
+/// - `GET <base_url>/history?base=BTC&quote=USDT&from=<rfc3339>&to=<rfc3339>`
+///   returning an array of price points.
+///
+/// A price point is `{"price": "42000.5", "occurrence_at": "2024-01-01T00:00:00Z"}`.
+pub struct HttpProvider {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpProvider {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .build();
+
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            agent,
+        }
+    }
+}
+
+impl PriceProvider for HttpProvider {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    fn latest(&self, base: &str, quote: &str) -> Result<Option<PricePoint>, Box<dyn Error>> {
+        let response = self
+            .agent
+            .get(&format!("{}/latest", self.base_url))
+            .query("base", base)
+            .query("quote", quote)
+            .call();
+
+        match response {
+            Ok(response) => Ok(Some(response.into_json()?)),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(err) => Err(err.to_string().into()),
+        }
+    }
+
+    fn history(
+        &self,
+        base: &str,
+        quote: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<PricePoint>, Box<dyn Error>> {
+        let response = self
+            .agent
+            .get(&format!("{}/history", self.base_url))
+            .query("base", base)
+            .query("quote", quote)
+            .query("from", &from.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .query("to", &to.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .call();
+
+        match response {
+            Ok(response) => {
+                let mut points: Vec<PricePoint> = response.into_json()?;
+                points.sort_by_key(|point| point.occurrence_at);
+
+                Ok(points)
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(Vec::new()),
+            Err(err) => Err(err.to_string().into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use super::{HttpProvider, PriceProvider};
+
+    /// Serves one canned response per route from a local socket and returns its base URL.
+    fn mock_server(requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                }
+
+                let (status, body) = if request_line.contains("base=ETH") {
+                    ("404 Not Found", "{}".to_string())
+                } else if request_line.starts_with("GET /latest?") {
+                    (
+                        "200 OK",
+                        r#"{"price":"42000.5","occurrence_at":"2024-01-02T00:00:00Z"}"#.to_string(),
+                    )
+                } else if request_line.starts_with("GET /history?") {
+                    (
+                        "200 OK",
+                        r#"[{"price":"2","occurrence_at":"2024-01-02T00:00:00Z"},
+                            {"price":"1","occurrence_at":"2024-01-01T00:00:00Z"}]"#
+                            .to_string(),
+                    )
+                } else {
+                    ("500 Internal Server Error", "{}".to_string())
+                };
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn test_latest() {
+        let provider = HttpProvider::new(mock_server(2));
+
+        let point = provider.latest("BTC", "USDT").unwrap().unwrap();
+        assert_eq!(point.price, Decimal::new(420005, 1));
+        assert_eq!(
+            point.occurrence_at,
+            Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap()
+        );
+
+        assert!(provider.latest("ETH", "USDT").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_history() {
+        let provider = HttpProvider::new(mock_server(1));
+
+        let points = provider
+            .history(
+                "BTC",
+                "USDT",
+                Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].price, Decimal::new(1, 0));
+        assert_eq!(points[1].price, Decimal::new(2, 0));
+    }
+}
@@ -0,0 +1,44 @@
+mod csv;
+mod http;
+
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+pub use self::csv::CsvProvider;
+pub use self::http::HttpProvider;
+
+/// The price of one unit of a base symbol expressed in a quote symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub price: Decimal,
+    pub occurrence_at: DateTime<Utc>,
+}
+
+pub trait PriceProvider: Send + Sync {
+    /// A short name recorded as the `source` of stored prices.
+    fn name(&self) -> &str;
+
+    /// Returns the most recent price of `base` in `quote`, if the provider knows the pair.
+    fn latest(&self, base: &str, quote: &str) -> Result<Option<PricePoint>, Box<dyn Error>>;
+
+    /// Returns the prices of `base` in `quote` within `[from, to]`, oldest first.
+    fn history(
+        &self,
+        base: &str,
+        quote: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<PricePoint>, Box<dyn Error>>;
+}
+
+/// Builds a provider from a kind (`csv` or `http`) and its source (a directory or a base URL).
+pub fn from_config(kind: &str, source: &str) -> Result<Box<dyn PriceProvider>, Box<dyn Error>> {
+    match kind {
+        "csv" => Ok(Box::new(CsvProvider::new(source))),
+        "http" => Ok(Box::new(HttpProvider::new(source))),
+        _ => Err(format!("unknown market data provider {}", kind).into()),
+    }
+}
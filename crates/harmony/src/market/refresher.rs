@@ -0,0 +1,261 @@
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+use crate::model::finance::price::{Price, TrackedPair};
+
+use super::provider::PriceProvider;
+
+/// Stores the latest price of every tracked pair. Pairs the provider fails on
+/// are logged and skipped so one bad symbol does not stall the others.
+/// Returns the number of prices stored.
+pub fn refresh(conn: &Connection, provider: &dyn PriceProvider) -> Result<usize, Box<dyn Error>> {
+    let mut stored = 0;
+
+    for pair in Price::select_tracked_pairs(conn, None)? {
+        match provider.latest(&pair.base_symbol, &pair.quote_symbol) {
+            Ok(Some(point)) => {
+                store(conn, provider, &pair, point.price, point.occurrence_at)?;
+                stored += 1;
+            }
+            Ok(None) => {}
+            Err(err) => tracing::warn!(
+                "failed to fetch {}-{} from {}: {}",
+                pair.base_symbol,
+                pair.quote_symbol,
+                provider.name(),
+                err
+            ),
+        }
+    }
+
+    Ok(stored)
+}
+
+/// A pair the provider failed on during a backfill.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub message: String,
+}
+
+/// What a backfill stored, and the pairs it had to skip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backfill {
+    pub stored: usize,
+    pub failures: Vec<Failure>,
+}
+
+/// Stores the price history within `[from, to]` of every pair traded by `owner`.
+/// Pairs the provider fails on are skipped and reported rather than ending
+/// the backfill.
+pub fn backfill(
+    conn: &Connection,
+    provider: &dyn PriceProvider,
+    owner: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Backfill, Box<dyn Error>> {
+    let mut stored = 0;
+    let mut failures = Vec::new();
+
+    for pair in Price::select_tracked_pairs(conn, Some(owner))? {
+        let points = match provider.history(&pair.base_symbol, &pair.quote_symbol, from, to) {
+            Ok(points) => points,
+            Err(err) => {
+                failures.push(Failure {
+                    base_symbol: pair.base_symbol.clone(),
+                    quote_symbol: pair.quote_symbol.clone(),
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        for point in points {
+            store(conn, provider, &pair, point.price, point.occurrence_at)?;
+            stored += 1;
+        }
+    }
+
+    Ok(Backfill { stored, failures })
+}
+
+fn store(
+    conn: &Connection,
+    provider: &dyn PriceProvider,
+    pair: &TrackedPair,
+    price: rust_decimal::Decimal,
+    occurrence_at: DateTime<Utc>,
+) -> Result<(), Box<dyn Error>> {
+    Price::upsert(
+        conn,
+        pair.owner,
+        pair.base_object_id,
+        pair.quote_object_id,
+        price.into(),
+        Some(provider.name().to_string()),
+        occurrence_at,
+    )?;
+
+    Ok(())
+}
+
+/// Spawns the periodic refresh onto the runtime when a provider is configured.
+pub fn spawn() {
+    use crate::consts::market::{PROVIDER, REFRESH_INTERVAL};
+
+    if PROVIDER.is_none() {
+        return;
+    }
+
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(*REFRESH_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let result = tokio::task::spawn_blocking(|| -> Result<usize, String> {
+                let provider = PROVIDER.as_deref().ok_or("provider is not configured")?;
+                let conn = crate::model::database::connection().map_err(|e| e.to_string())?;
+
+                refresh(&conn, provider).map_err(|e| e.to_string())
+            })
+            .await;
+
+            match result {
+                Ok(Ok(stored)) => tracing::info!("refreshed {} prices", stored),
+                Ok(Err(err)) => tracing::warn!("price refresh failed: {}", err),
+                Err(err) => tracing::warn!("price refresh panicked: {}", err),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use chrono::{DateTime, TimeZone, Utc};
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::market::provider::{PricePoint, PriceProvider};
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::price::Price;
+    use crate::model::finance::trade::Trade;
+    use crate::model::person::Person;
+
+    struct FixedProvider;
+
+    impl PriceProvider for FixedProvider {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn latest(&self, base: &str, _quote: &str) -> Result<Option<PricePoint>, Box<dyn Error>> {
+            match base {
+                "BTC" => Ok(Some(PricePoint {
+                    price: Decimal::new(42000, 0),
+                    occurrence_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+                })),
+                "ETH" => Err("unavailable".into()),
+                _ => Ok(None),
+            }
+        }
+
+        fn history(
+            &self,
+            base: &str,
+            _quote: &str,
+            from: DateTime<Utc>,
+            _to: DateTime<Utc>,
+        ) -> Result<Vec<PricePoint>, Box<dyn Error>> {
+            if base == "ETH" {
+                return Err("unavailable".into());
+            }
+
+            Ok(vec![PricePoint {
+                price: Decimal::new(1, 0),
+                occurrence_at: from,
+            }])
+        }
+    }
+
+    fn setup() -> (PooledConnection<SqliteConnectionManager>, i64) {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+
+        let usdt = Object::insert(
+            &conn,
+            owner,
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        for symbol in ["BTC", "ETH", "DOGE"] {
+            let base = Object::insert(
+                &conn,
+                owner,
+                symbol.to_string(),
+                None,
+                None,
+                Classification::default(),
+            )
+            .unwrap();
+            Trade::insert(&conn, owner, base, usdt, None, None).unwrap();
+        }
+
+        (conn, owner)
+    }
+
+    #[test]
+    fn test_refresh_skips_failing_pairs() {
+        let (conn, owner) = setup();
+
+        let stored = super::refresh(&conn, &FixedProvider).unwrap();
+
+        assert_eq!(stored, 1);
+        assert_eq!(Price::count_by_owner(&conn, owner).unwrap(), 1);
+
+        let prices = Price::select_by_owner(&conn, owner, 10, 0).unwrap();
+        assert_eq!(prices[0].price, Decimal::new(42000, 0).into());
+        assert_eq!(prices[0].source, Some("fixed".to_string()));
+    }
+
+    #[test]
+    fn test_backfill() {
+        let (conn, owner) = setup();
+        let from = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+
+        let backfill = super::backfill(&conn, &FixedProvider, owner, from, Utc::now()).unwrap();
+
+        // The failing pair is reported, the others are still stored
+        assert_eq!(backfill.stored, 2);
+        assert_eq!(Price::count_by_owner(&conn, owner).unwrap(), 2);
+        assert_eq!(
+            backfill.failures,
+            vec![super::Failure {
+                base_symbol: "ETH".to_string(),
+                quote_symbol: "USDT".to_string(),
+                message: "unavailable".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use chrono::{DateTime, Utc};
+
+/// Somewhere holdings sit, such as a bank account, an exchange or a wallet.
+pub struct Account {
+    id: i64,
+    pub owner: i64,
+    pub name: String,
+    pub institution: Option<String>,
+    pub remark: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Account {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+mod database {
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+
+    impl crate::model::Model for super::Account {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_account (
+                    id          INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner       INTEGER  NOT NULL,
+                    name        TEXT     NOT NULL,
+                    institution TEXT,
+                    remark      TEXT,
+                    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    UNIQUE(owner, name)
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_account_updated_at
+                AFTER UPDATE ON finance_account
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_account SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE INDEX IF NOT EXISTS idx_finance_account_owner ON finance_account(owner);
+            "
+        }
+    }
+
+    impl super::Account {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                name: row.get(2)?,
+                institution: row.get(3)?,
+                remark: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        }
+
+        pub fn insert(
+            conn: &Connection,
+            owner: i64,
+            name: String,
+            institution: Option<String>,
+            remark: Option<String>,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_account (owner, name, institution, remark)
+                VALUES (?1, ?2, ?3, ?4)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(sql, params![owner, name, institution, remark], |row| {
+                row.get(0)
+            })?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_account
+                WHERE owner = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![owner], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, name, institution, remark, created_at, updated_at
+                FROM finance_account
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, owner], Self::from_row)
+                .optional()
+        }
+
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, name, institution, remark, created_at, updated_at
+                FROM finance_account
+                WHERE owner = ?1
+                LIMIT ?2 OFFSET ?3;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let accounts = stmt
+                .query_map(params![owner, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(accounts)
+        }
+
+        pub fn select_all_by_owner(conn: &Connection, owner: i64) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, name, institution, remark, created_at, updated_at
+                FROM finance_account
+                WHERE owner = ?1
+                ORDER BY id;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let accounts = stmt
+                .query_map(params![owner], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(accounts)
+        }
+
+        /// Whether any transaction, transfer or flow still refers to the account.
+        pub fn is_referenced(conn: &Connection, id: i64) -> Result<bool> {
+            let sql = r#"
+                SELECT EXISTS (SELECT 1 FROM finance_trade_transaction WHERE account_id = ?1)
+                    OR EXISTS (SELECT 1 FROM finance_transfer WHERE from_account_id = ?1 OR to_account_id = ?1)
+                    OR EXISTS (SELECT 1 FROM finance_flow WHERE account_id = ?1)
+                    OR EXISTS (SELECT 1 FROM finance_trade_plan WHERE account_id = ?1);
+            "#;
+
+            let referenced = conn.query_row(sql, params![id], |row| row.get(0))?;
+
+            Ok(referenced)
+        }
+
+        pub fn update_by_id_owner(
+            conn: &Connection,
+            id: i64,
+            owner: i64,
+            name: String,
+            institution: Option<String>,
+            remark: Option<String>,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_account
+                SET name = ?1, institution = ?2, remark = ?3
+                WHERE id = ?4 AND owner = ?5;
+            "#;
+
+            conn.execute(sql, params![name, institution, remark, id, owner])?;
+
+            Ok(())
+        }
+
+        pub fn delete_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_account
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.execute(sql, params![id, owner])?;
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::finance::transfer::Transfer;
+    use crate::model::person::Person;
+
+    use super::Account;
+
+    fn setup() -> (PooledConnection<SqliteConnectionManager>, i64) {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+
+        (conn, person.id())
+    }
+
+    #[test]
+    fn test_insert_update_delete() {
+        let (conn, owner) = setup();
+
+        let id = Account::insert(&conn, owner, "Main".to_string(), None, None).unwrap();
+        Account::update_by_id_owner(
+            &conn,
+            id,
+            owner,
+            "Checking".to_string(),
+            Some("Bank".to_string()),
+            None,
+        )
+        .unwrap();
+
+        let account = Account::select_by_id_owner(&conn, id, owner)
+            .unwrap()
+            .unwrap();
+        assert_eq!(account.name, "Checking");
+        assert_eq!(account.institution, Some("Bank".to_string()));
+        assert!(Account::select_by_id_owner(&conn, id, owner + 1)
+            .unwrap()
+            .is_none());
+
+        Account::delete_by_id_owner(&conn, id, owner).unwrap();
+        assert_eq!(Account::count_by_owner(&conn, owner).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_name_is_unique_per_owner() {
+        let (conn, owner) = setup();
+
+        Account::insert(&conn, owner, "Main".to_string(), None, None).unwrap();
+        assert!(Account::insert(&conn, owner, "Main".to_string(), None, None).is_err());
+    }
+
+    #[test]
+    fn test_referenced_account_is_kept() {
+        let (conn, owner) = setup();
+        let btc = Object::insert(
+            &conn,
+            owner,
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let usdt = Object::insert(
+            &conn,
+            owner,
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let trade = Trade::insert(&conn, owner, btc, usdt, None, None).unwrap();
+        let exchange = Account::insert(&conn, owner, "Exchange".to_string(), None, None).unwrap();
+        let wallet = Account::insert(&conn, owner, "Wallet".to_string(), None, None).unwrap();
+
+        assert!(!Account::is_referenced(&conn, wallet).unwrap());
+
+        Transaction::insert(
+            &conn,
+            trade,
+            exchange,
+            Decimal::ONE.into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        Transfer::insert(
+            &conn,
+            owner,
+            btc,
+            exchange,
+            wallet,
+            Decimal::ONE.into(),
+            None,
+            Utc::now(),
+        )
+        .unwrap();
+
+        assert!(Account::is_referenced(&conn, exchange).unwrap());
+        assert!(Account::is_referenced(&conn, wallet).unwrap());
+        assert!(Account::delete_by_id_owner(&conn, wallet, owner).is_err());
+    }
+}
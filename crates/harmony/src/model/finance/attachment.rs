@@ -0,0 +1,244 @@
+use chrono::{DateTime, Utc};
+
+use crate::attachment::mime::Mime;
+
+/// A file uploaded against a trade or one of its transactions. The content
+/// lives in the blob store under `sha256`, shared by every attachment of
+/// the same content.
+pub struct Attachment {
+    id: i64,
+    pub owner: i64,
+    /// Set for attachments of a trade, otherwise `transaction_id` is.
+    pub trade_id: Option<i64>,
+    pub transaction_id: Option<i64>,
+    /// The file name as uploaded.
+    pub name: String,
+    /// What the content was sniffed to be.
+    pub mime: Mime,
+    /// In bytes, before any encryption.
+    pub size: i64,
+    /// The digest of the content as hex.
+    pub sha256: String,
+    /// Whether the content is stored encrypted.
+    pub is_encrypted: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+mod database {
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::attachment::mime::Mime;
+    use crate::model::database::Result;
+
+    impl crate::model::Model for super::Attachment {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_attachment (
+                    id              INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner           INTEGER  NOT NULL,
+                    trade_id        INTEGER,
+                    transaction_id  INTEGER,
+                    name            TEXT     NOT NULL,
+                    mime            TEXT     NOT NULL,
+                    size            INTEGER  NOT NULL CHECK (size >= 0),
+                    sha256          TEXT     NOT NULL CHECK (length(sha256) = 64),
+                    is_encrypted    BOOLEAN  NOT NULL DEFAULT FALSE,
+                    created_at      DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    FOREIGN KEY(trade_id) REFERENCES finance_trade(id) ON DELETE CASCADE,
+                    FOREIGN KEY(transaction_id) REFERENCES finance_trade_transaction(id) ON DELETE CASCADE,
+                    CHECK ((trade_id IS NULL) <> (transaction_id IS NULL))
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_finance_attachment_trade_id ON finance_attachment(trade_id);
+                CREATE INDEX IF NOT EXISTS idx_finance_attachment_transaction_id ON finance_attachment(transaction_id);
+                CREATE INDEX IF NOT EXISTS idx_finance_attachment_sha256 ON finance_attachment(sha256, is_encrypted);
+            "
+        }
+    }
+
+    impl super::Attachment {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                trade_id: row.get(2)?,
+                transaction_id: row.get(3)?,
+                name: row.get(4)?,
+                mime: row.get(5)?,
+                size: row.get(6)?,
+                sha256: row.get(7)?,
+                is_encrypted: row.get(8)?,
+                created_at: row.get(9)?,
+            })
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn insert(
+            conn: &Connection,
+            owner: i64,
+            trade_id: Option<i64>,
+            transaction_id: Option<i64>,
+            name: String,
+            mime: Mime,
+            size: i64,
+            sha256: String,
+            is_encrypted: bool,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_attachment (owner, trade_id, transaction_id, name, mime, size, sha256, is_encrypted)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![
+                    owner,
+                    trade_id,
+                    transaction_id,
+                    name,
+                    mime,
+                    size,
+                    sha256,
+                    is_encrypted
+                ],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn select_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, trade_id, transaction_id, name, mime, size, sha256, is_encrypted, created_at
+                FROM finance_attachment
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, owner], Self::from_row)
+                .optional()
+        }
+
+        /// Counts the attachments of a trade, or of a transaction when
+        /// `trade_id` is none.
+        pub fn count_by_entity(
+            conn: &Connection,
+            trade_id: Option<i64>,
+            transaction_id: Option<i64>,
+        ) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_attachment
+                WHERE trade_id IS ?1 AND transaction_id IS ?2;
+            "#;
+
+            let count = conn.query_row(sql, params![trade_id, transaction_id], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        /// Returns the attachments of a trade, or of a transaction when
+        /// `trade_id` is none, oldest first.
+        pub fn select_by_entity(
+            conn: &Connection,
+            trade_id: Option<i64>,
+            transaction_id: Option<i64>,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, trade_id, transaction_id, name, mime, size, sha256, is_encrypted, created_at
+                FROM finance_attachment
+                WHERE trade_id IS ?1 AND transaction_id IS ?2
+                ORDER BY created_at, id
+                LIMIT ?3 OFFSET ?4;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let attachments = stmt
+                .query_map(
+                    params![trade_id, transaction_id, limit, offset],
+                    Self::from_row,
+                )?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(attachments)
+        }
+
+        /// Returns the attachments that go when a trade goes, its own and
+        /// those of its transactions.
+        pub fn select_by_trade_id(conn: &Connection, trade_id: i64) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, trade_id, transaction_id, name, mime, size, sha256, is_encrypted, created_at
+                FROM finance_attachment
+                WHERE trade_id = ?1
+                   OR transaction_id IN (SELECT id FROM finance_trade_transaction WHERE trade_id = ?1)
+                ORDER BY id;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let attachments = stmt
+                .query_map(params![trade_id], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(attachments)
+        }
+
+        pub fn select_by_transaction_id(
+            conn: &Connection,
+            transaction_id: i64,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, trade_id, transaction_id, name, mime, size, sha256, is_encrypted, created_at
+                FROM finance_attachment
+                WHERE transaction_id = ?1
+                ORDER BY id;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let attachments = stmt
+                .query_map(params![transaction_id], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(attachments)
+        }
+
+        /// Counts the attachments, of any owner, that share stored content.
+        pub fn count_by_sha256(
+            conn: &Connection,
+            sha256: &str,
+            is_encrypted: bool,
+        ) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_attachment
+                WHERE sha256 = ?1 AND is_encrypted = ?2;
+            "#;
+
+            let count = conn.query_row(sql, params![sha256, is_encrypted], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn delete_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_attachment
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.execute(sql, params![id, owner])?;
+
+            Ok(())
+        }
+    }
+}
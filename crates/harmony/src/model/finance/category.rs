@@ -0,0 +1,426 @@
+use chrono::{DateTime, Utc};
+
+/// A node in the owner's tree of categories for transactions, such as
+/// `Fees` under `Expenses`. A transaction sits in at most one category.
+pub struct Category {
+    id: i64,
+    pub owner: i64,
+    /// The category this one sits in, or `None` at the top.
+    pub parent_id: Option<i64>,
+    /// Unique among its siblings, and without `/`.
+    pub name: String,
+    /// The names from the top down to this category, as `Expenses/Fees`.
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Category {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Whether this category is `other` or sits anywhere below it.
+    pub fn is_within(&self, other: &Category) -> bool {
+        self.id == other.id
+            || self
+                .path
+                .strip_prefix(&other.path)
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+mod database {
+    use std::collections::HashMap;
+
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+
+    /// The categories of the owner bound to `?1` as `tree`, with their
+    /// paths.
+    const TREE: &str = r#"
+        WITH RECURSIVE tree(id, path) AS (
+            SELECT id, name
+            FROM finance_category
+            WHERE owner = ?1 AND parent_id IS NULL
+            UNION ALL
+            SELECT c.id, tree.path || '/' || c.name
+            FROM finance_category c
+            JOIN tree ON c.parent_id = tree.id
+        )
+    "#;
+
+    impl crate::model::Model for super::Category {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_category (
+                    id          INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner       INTEGER  NOT NULL,
+                    parent_id   INTEGER,
+                    name        TEXT     NOT NULL CHECK (length(name) > 0 AND instr(name, '/') = 0),
+                    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    FOREIGN KEY(parent_id) REFERENCES finance_category(id) ON DELETE CASCADE,
+                    CHECK (parent_id IS NULL OR parent_id <> id)
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_category_updated_at
+                AFTER UPDATE ON finance_category
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_category SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_finance_category_owner_parent_id_name ON finance_category(owner, coalesce(parent_id, 0), name);
+                CREATE INDEX IF NOT EXISTS idx_finance_category_parent_id ON finance_category(parent_id);
+
+                CREATE TABLE IF NOT EXISTS finance_trade_transaction_category (
+                    transaction_id  INTEGER  NOT NULL UNIQUE PRIMARY KEY,
+                    category_id     INTEGER  NOT NULL,
+                    FOREIGN KEY(transaction_id) REFERENCES finance_trade_transaction(id) ON DELETE CASCADE,
+                    FOREIGN KEY(category_id) REFERENCES finance_category(id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_finance_trade_transaction_category_category_id ON finance_trade_transaction_category(category_id);
+            "
+        }
+    }
+
+    impl super::Category {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                parent_id: row.get(2)?,
+                name: row.get(3)?,
+                path: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        }
+
+        pub fn insert(
+            conn: &Connection,
+            owner: i64,
+            parent_id: Option<i64>,
+            name: String,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_category (owner, parent_id, name)
+                VALUES (?1, ?2, ?3)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(sql, params![owner, parent_id, name], |row| row.get(0))?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_category
+                WHERE owner = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![owner], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<Option<Self>> {
+            let sql = format!(
+                r#"
+                    {}
+                    SELECT c.id, c.owner, c.parent_id, c.name, tree.path, c.created_at, c.updated_at
+                    FROM finance_category c
+                    JOIN tree ON tree.id = c.id
+                    WHERE c.id = ?2;
+                "#,
+                TREE
+            );
+
+            conn.query_row(&sql, params![owner, id], Self::from_row)
+                .optional()
+        }
+
+        /// Returns a category of the owner by parent and name.
+        pub fn select_by_owner_parent_id_name(
+            conn: &Connection,
+            owner: i64,
+            parent_id: Option<i64>,
+            name: &str,
+        ) -> Result<Option<Self>> {
+            let sql = format!(
+                r#"
+                    {}
+                    SELECT c.id, c.owner, c.parent_id, c.name, tree.path, c.created_at, c.updated_at
+                    FROM finance_category c
+                    JOIN tree ON tree.id = c.id
+                    WHERE c.parent_id IS ?2 AND c.name = ?3;
+                "#,
+                TREE
+            );
+
+            conn.query_row(&sql, params![owner, parent_id, name], Self::from_row)
+                .optional()
+        }
+
+        /// Returns categories of the owner by path.
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = format!(
+                r#"
+                    {}
+                    SELECT c.id, c.owner, c.parent_id, c.name, tree.path, c.created_at, c.updated_at
+                    FROM finance_category c
+                    JOIN tree ON tree.id = c.id
+                    ORDER BY tree.path, c.id
+                    LIMIT ?2 OFFSET ?3;
+                "#,
+                TREE
+            );
+
+            let mut stmt = conn.prepare(&sql)?;
+            let categories = stmt
+                .query_map(params![owner, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(categories)
+        }
+
+        /// Returns the category a transaction of the owner sits in.
+        pub fn select_by_transaction_id(
+            conn: &Connection,
+            transaction_id: i64,
+            owner: i64,
+        ) -> Result<Option<Self>> {
+            let sql = format!(
+                r#"
+                    {}
+                    SELECT c.id, c.owner, c.parent_id, c.name, tree.path, c.created_at, c.updated_at
+                    FROM finance_category c
+                    JOIN tree ON tree.id = c.id
+                    JOIN finance_trade_transaction_category tc ON tc.category_id = c.id
+                    WHERE tc.transaction_id = ?2;
+                "#,
+                TREE
+            );
+
+            conn.query_row(&sql, params![owner, transaction_id], Self::from_row)
+                .optional()
+        }
+
+        /// Returns the paths of the categories the owner's transactions sit
+        /// in, by transaction id.
+        pub fn select_paths_by_owner(
+            conn: &Connection,
+            owner: i64,
+        ) -> Result<HashMap<i64, String>> {
+            let sql = format!(
+                r#"
+                    {}
+                    SELECT tc.transaction_id, tree.path
+                    FROM finance_trade_transaction_category tc
+                    JOIN tree ON tree.id = tc.category_id;
+                "#,
+                TREE
+            );
+
+            let mut stmt = conn.prepare(&sql)?;
+            let paths = stmt
+                .query_map(params![owner], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<HashMap<i64, String>>>()?;
+
+            Ok(paths)
+        }
+
+        pub fn update_by_id_owner(
+            conn: &Connection,
+            id: i64,
+            owner: i64,
+            parent_id: Option<i64>,
+            name: String,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_category
+                SET parent_id = ?1, name = ?2
+                WHERE id = ?3 AND owner = ?4;
+            "#;
+
+            conn.execute(sql, params![parent_id, name, id, owner])?;
+
+            Ok(())
+        }
+
+        /// Deletes a category along with those below it, leaving their
+        /// transactions without a category.
+        pub fn delete_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_category
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.execute(sql, params![id, owner])?;
+
+            Ok(())
+        }
+
+        /// Puts a transaction in a category, taking it out of any other.
+        pub fn assign(conn: &Connection, id: i64, transaction_id: i64) -> Result<()> {
+            let sql = r#"
+                INSERT INTO finance_trade_transaction_category (transaction_id, category_id)
+                VALUES (?1, ?2)
+                ON CONFLICT(transaction_id) DO UPDATE SET category_id = excluded.category_id;
+            "#;
+
+            conn.execute(sql, params![transaction_id, id])?;
+
+            Ok(())
+        }
+
+        pub fn unassign(conn: &Connection, transaction_id: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_trade_transaction_category
+                WHERE transaction_id = ?1;
+            "#;
+
+            conn.execute(sql, params![transaction_id])?;
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::fixture;
+    use crate::model::person::Person;
+
+    use super::Category;
+
+    fn setup() -> (PooledConnection<SqliteConnectionManager>, i64) {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+
+        (conn, person.id())
+    }
+
+    #[test]
+    fn test_tree() {
+        let (conn, owner) = setup();
+
+        let expenses = Category::insert(&conn, owner, None, "Expenses".to_string()).unwrap();
+        let fees = Category::insert(&conn, owner, Some(expenses), "Fees".to_string()).unwrap();
+        let income = Category::insert(&conn, owner, None, "Income".to_string()).unwrap();
+        Category::insert(&conn, owner, Some(income), "Fees".to_string()).unwrap();
+        assert!(Category::insert(&conn, owner, None, "Income".to_string()).is_err());
+        assert!(Category::insert(&conn, owner, Some(income), "Fees".to_string()).is_err());
+        assert!(Category::insert(&conn, owner, None, "Income/Salary".to_string()).is_err());
+
+        let paths: Vec<String> = Category::select_by_owner(&conn, owner, 10, 0)
+            .unwrap()
+            .into_iter()
+            .map(|category| category.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["Expenses", "Expenses/Fees", "Income", "Income/Fees"]
+        );
+
+        let select = |id| {
+            Category::select_by_id_owner(&conn, id, owner)
+                .unwrap()
+                .unwrap()
+        };
+        assert!(select(fees).is_within(&select(expenses)));
+        assert!(!select(expenses).is_within(&select(fees)));
+        assert!(Category::select_by_id_owner(&conn, fees, owner + 1)
+            .unwrap()
+            .is_none());
+
+        Category::update_by_id_owner(&conn, fees, owner, Some(income), "Rebates".to_string())
+            .unwrap();
+        assert_eq!(select(fees).path, "Income/Rebates");
+
+        Category::delete_by_id_owner(&conn, income, owner).unwrap();
+        assert_eq!(Category::count_by_owner(&conn, owner).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_assign() {
+        let (conn, owner) = setup();
+        let (btc, usd) = (
+            fixture::object(&conn, owner, "BTC"),
+            fixture::object(&conn, owner, "USD"),
+        );
+        let account = fixture::account(&conn, owner, "exchange");
+        let trade = fixture::trade(&conn, owner, btc, usd);
+        let transaction = Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::ONE.into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let savings = Category::insert(&conn, owner, None, "Savings".to_string()).unwrap();
+        let fun = Category::insert(&conn, owner, None, "Fun".to_string()).unwrap();
+
+        Category::assign(&conn, savings, transaction).unwrap();
+        Category::assign(&conn, fun, transaction).unwrap();
+        let category = Category::select_by_transaction_id(&conn, transaction, owner)
+            .unwrap()
+            .unwrap();
+        assert_eq!(category.id(), fun);
+        let paths = Category::select_paths_by_owner(&conn, owner).unwrap();
+        assert_eq!(paths[&transaction], "Fun");
+
+        // Deleting the category leaves the transaction without one
+        Category::delete_by_id_owner(&conn, fun, owner).unwrap();
+        assert!(
+            Category::select_by_transaction_id(&conn, transaction, owner)
+                .unwrap()
+                .is_none()
+        );
+
+        Category::assign(&conn, savings, transaction).unwrap();
+        Category::unassign(&conn, transaction).unwrap();
+        assert!(Category::select_paths_by_owner(&conn, owner)
+            .unwrap()
+            .is_empty());
+    }
+}
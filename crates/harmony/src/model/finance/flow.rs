@@ -0,0 +1,367 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::finance::Quantity;
+
+/// Holdings of one object coming into or leaving an account without being
+/// exchanged for another object of the owner.
+pub struct Flow {
+    id: i64,
+    pub owner: i64,
+    pub kind: Kind,
+    pub object_id: i64,
+    pub account_id: i64,
+    /// What lands in or leaves the account, always positive.
+    pub quantity: Quantity,
+    /// The object the flow stems from or is paid for, such as the stock
+    /// paying a dividend.
+    pub counterparty_object_id: Option<i64>,
+    /// Tax withheld at source in `object_id`, on top of `quantity`.
+    pub tax_withheld: Option<Quantity>,
+    /// How the flow is treated for tax, such as `qualified` or `exempt`.
+    pub tax_category: Option<String>,
+    pub remark: Option<String>,
+    pub occurrence_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Flow {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Deposit,
+    Withdrawal,
+    Dividend,
+    Interest,
+    Staking,
+    Airdrop,
+    Expense,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Deposit => "deposit",
+            Self::Withdrawal => "withdrawal",
+            Self::Dividend => "dividend",
+            Self::Interest => "interest",
+            Self::Staking => "staking",
+            Self::Airdrop => "airdrop",
+            Self::Expense => "expense",
+        }
+    }
+
+    /// Whether holdings come into the account.
+    pub fn is_inflow(&self) -> bool {
+        !matches!(self, Self::Withdrawal | Self::Expense)
+    }
+
+    /// Whether holdings are earned rather than moved in from outside.
+    pub fn is_income(&self) -> bool {
+        matches!(
+            self,
+            Self::Dividend | Self::Interest | Self::Staking | Self::Airdrop
+        )
+    }
+
+    /// Whether the owner moves money in or out of the portfolio, as opposed
+    /// to the portfolio earning or spending it.
+    pub fn is_external(&self) -> bool {
+        matches!(self, Self::Deposit | Self::Withdrawal)
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "deposit" => Ok(Self::Deposit),
+            "withdrawal" => Ok(Self::Withdrawal),
+            "dividend" => Ok(Self::Dividend),
+            "interest" => Ok(Self::Interest),
+            "staking" => Ok(Self::Staking),
+            "airdrop" => Ok(Self::Airdrop),
+            "expense" => Ok(Self::Expense),
+            _ => Err(format!("unknown flow kind {}", s)),
+        }
+    }
+}
+
+mod database {
+    use chrono::{DateTime, Utc};
+    use rusqlite::params;
+    use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+    use crate::model::finance::Quantity;
+
+    use super::Kind;
+
+    impl crate::model::Model for super::Flow {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_flow (
+                    id                      INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner                   INTEGER  NOT NULL,
+                    kind                    TEXT     NOT NULL CHECK (kind IN ('deposit', 'withdrawal', 'dividend', 'interest', 'staking', 'airdrop', 'expense')),
+                    object_id               INTEGER  NOT NULL,
+                    account_id              INTEGER  NOT NULL,
+                    quantity                TEXT     NOT NULL,
+                    counterparty_object_id  INTEGER,
+                    tax_withheld            TEXT,
+                    tax_category            TEXT,
+                    remark                  TEXT,
+                    occurrence_at           DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    created_at              DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at              DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    FOREIGN KEY(object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    FOREIGN KEY(account_id) REFERENCES finance_account(id) ON DELETE RESTRICT,
+                    FOREIGN KEY(counterparty_object_id) REFERENCES finance_object(id) ON DELETE SET NULL
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_flow_updated_at
+                AFTER UPDATE ON finance_flow
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_flow SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE INDEX IF NOT EXISTS idx_finance_flow_owner_occurrence_at ON finance_flow(owner, occurrence_at);
+                CREATE INDEX IF NOT EXISTS idx_finance_flow_account_id ON finance_flow(account_id);
+            "
+        }
+    }
+
+    impl FromSql for Kind {
+        fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+            value
+                .as_str()?
+                .parse()
+                .map_err(|error: String| FromSqlError::Other(error.into()))
+        }
+    }
+
+    impl ToSql for Kind {
+        fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+            Ok(ToSqlOutput::from(self.as_str()))
+        }
+    }
+
+    impl super::Flow {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                kind: row.get(2)?,
+                object_id: row.get(3)?,
+                account_id: row.get(4)?,
+                quantity: row.get(5)?,
+                counterparty_object_id: row.get(6)?,
+                tax_withheld: row.get(7)?,
+                tax_category: row.get(8)?,
+                remark: row.get(9)?,
+                occurrence_at: row.get(10)?,
+                created_at: row.get(11)?,
+                updated_at: row.get(12)?,
+            })
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn insert(
+            conn: &Connection,
+            owner: i64,
+            kind: Kind,
+            object_id: i64,
+            account_id: i64,
+            quantity: Quantity,
+            counterparty_object_id: Option<i64>,
+            tax_withheld: Option<Quantity>,
+            tax_category: Option<String>,
+            remark: Option<String>,
+            occurrence_at: DateTime<Utc>,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_flow (owner, kind, object_id, account_id, quantity, counterparty_object_id, tax_withheld, tax_category, remark, occurrence_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![
+                    owner,
+                    kind,
+                    object_id,
+                    account_id,
+                    quantity,
+                    counterparty_object_id,
+                    tax_withheld,
+                    tax_category,
+                    remark,
+                    occurrence_at
+                ],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64, kind: Option<Kind>) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_flow
+                WHERE owner = ?1 AND (?2 IS NULL OR kind = ?2);
+            "#;
+
+            let count = conn.query_row(sql, params![owner, kind], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, kind, object_id, account_id, quantity, counterparty_object_id, tax_withheld, tax_category, remark, occurrence_at, created_at, updated_at
+                FROM finance_flow
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, owner], Self::from_row)
+                .optional()
+        }
+
+        /// Returns the flows of the owner, newest first.
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            kind: Option<Kind>,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, kind, object_id, account_id, quantity, counterparty_object_id, tax_withheld, tax_category, remark, occurrence_at, created_at, updated_at
+                FROM finance_flow
+                WHERE owner = ?1 AND (?2 IS NULL OR kind = ?2)
+                ORDER BY occurrence_at DESC, id DESC
+                LIMIT ?3 OFFSET ?4;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let flows = stmt
+                .query_map(params![owner, kind, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(flows)
+        }
+
+        /// Returns every flow of the owner that occurred at or before `until`,
+        /// in `occurrence_at` order.
+        pub fn select_by_owner_until(
+            conn: &Connection,
+            owner: i64,
+            until: DateTime<Utc>,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, kind, object_id, account_id, quantity, counterparty_object_id, tax_withheld, tax_category, remark, occurrence_at, created_at, updated_at
+                FROM finance_flow
+                WHERE owner = ?1 AND occurrence_at <= ?2
+                ORDER BY occurrence_at ASC, id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let flows = stmt
+                .query_map(params![owner, until], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(flows)
+        }
+
+        /// Returns every flow of the owner in `occurrence_at` order.
+        pub fn select_all_by_owner(conn: &Connection, owner: i64) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, kind, object_id, account_id, quantity, counterparty_object_id, tax_withheld, tax_category, remark, occurrence_at, created_at, updated_at
+                FROM finance_flow
+                WHERE owner = ?1
+                ORDER BY occurrence_at ASC, id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let flows = stmt
+                .query_map(params![owner], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(flows)
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn update_by_id_owner(
+            conn: &Connection,
+            id: i64,
+            owner: i64,
+            kind: Kind,
+            object_id: i64,
+            account_id: i64,
+            quantity: Quantity,
+            counterparty_object_id: Option<i64>,
+            tax_withheld: Option<Quantity>,
+            tax_category: Option<String>,
+            remark: Option<String>,
+            occurrence_at: DateTime<Utc>,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_flow
+                SET kind = ?1, object_id = ?2, account_id = ?3, quantity = ?4, counterparty_object_id = ?5, tax_withheld = ?6, tax_category = ?7, remark = ?8, occurrence_at = ?9
+                WHERE id = ?10 AND owner = ?11;
+            "#;
+
+            conn.execute(
+                sql,
+                params![
+                    kind,
+                    object_id,
+                    account_id,
+                    quantity,
+                    counterparty_object_id,
+                    tax_withheld,
+                    tax_category,
+                    remark,
+                    occurrence_at,
+                    id,
+                    owner
+                ],
+            )?;
+
+            Ok(())
+        }
+
+        pub fn delete_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_flow
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.execute(sql, params![id, owner])?;
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+
+/// The content hash of an imported row, kept so the same row is never
+/// imported twice. Deleting the transaction forgets the row.
+pub struct Import {
+    id: i64,
+    pub hash: String,
+    pub transaction_id: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Import {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+mod database {
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+
+    impl crate::model::Model for super::Import {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_import (
+                    id             INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner          INTEGER  NOT NULL,
+                    hash           TEXT     NOT NULL,
+                    transaction_id INTEGER  NOT NULL,
+                    created_at     DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    FOREIGN KEY(transaction_id) REFERENCES finance_trade_transaction(id) ON DELETE CASCADE,
+                    UNIQUE(owner, hash)
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_finance_import_transaction_id ON finance_import(transaction_id);
+            "
+        }
+    }
+
+    impl super::Import {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                hash: row.get(1)?,
+                transaction_id: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        }
+
+        pub fn insert(
+            conn: &Connection,
+            owner: i64,
+            hash: &str,
+            transaction_id: i64,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_import (owner, hash, transaction_id)
+                VALUES (?1, ?2, ?3)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(sql, params![owner, hash, transaction_id], |row| row.get(0))?;
+
+            Ok(id)
+        }
+
+        pub fn select_by_owner_hash(
+            conn: &Connection,
+            owner: i64,
+            hash: &str,
+        ) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, hash, transaction_id, created_at
+                FROM finance_import
+                WHERE owner = ?1 AND hash = ?2;
+            "#;
+
+            conn.query_row(sql, params![owner, hash], Self::from_row)
+                .optional()
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::finance::Quantity;
+
+/// The side of the books a posting lands on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// Holdings in one of the owner's accounts.
+    Asset,
+    /// The counterpart of an exchange between two commodities, so that each
+    /// commodity balances on its own.
+    Trading,
+    /// Fees paid for trades.
+    Fee,
+    /// Earnings such as dividends, interest or staking rewards.
+    Income,
+    /// Spending, including tax withheld from income.
+    Expense,
+    /// Money the owner put into or took out of the books.
+    Equity,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Asset => "asset",
+            Self::Trading => "trading",
+            Self::Fee => "fee",
+            Self::Income => "income",
+            Self::Expense => "expense",
+            Self::Equity => "equity",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asset" => Ok(Self::Asset),
+            "trading" => Ok(Self::Trading),
+            "fee" => Ok(Self::Fee),
+            "income" => Ok(Self::Income),
+            "expense" => Ok(Self::Expense),
+            "equity" => Ok(Self::Equity),
+            _ => Err(format!("unknown posting kind {}", s)),
+        }
+    }
+}
+
+/// One event in the books, made of postings that sum to zero per object.
+pub struct Entry {
+    id: i64,
+    pub owner: i64,
+    /// The trade transaction this entry was derived from, if any.
+    pub transaction_id: Option<i64>,
+    /// The flow this entry was derived from, if any.
+    pub flow_id: Option<i64>,
+    pub description: Option<String>,
+    pub occurrence_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Entry {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+/// A signed amount of one object booked to one side of the books.
+pub struct Posting {
+    id: i64,
+    pub entry_id: i64,
+    pub kind: Kind,
+    /// The account holding the amount, only set for asset postings.
+    pub account_id: Option<i64>,
+    pub object_id: i64,
+    pub amount: Quantity,
+}
+
+impl Posting {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+mod database {
+    use chrono::{DateTime, Utc};
+    use rusqlite::params;
+    use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+    use rusqlite::Connection;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+    use crate::model::finance::Quantity;
+
+    use super::Kind;
+
+    impl crate::model::Model for super::Entry {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_journal_entry (
+                    id              INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner           INTEGER  NOT NULL,
+                    transaction_id  INTEGER  UNIQUE,
+                    flow_id         INTEGER  UNIQUE,
+                    description     TEXT,
+                    occurrence_at   DATETIME NOT NULL,
+                    created_at      DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    FOREIGN KEY(transaction_id) REFERENCES finance_trade_transaction(id) ON DELETE CASCADE,
+                    FOREIGN KEY(flow_id) REFERENCES finance_flow(id) ON DELETE CASCADE,
+                    CHECK (transaction_id IS NULL OR flow_id IS NULL)
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_finance_journal_entry_owner_occurrence_at ON finance_journal_entry(owner, occurrence_at);
+            "
+        }
+    }
+
+    impl crate::model::Model for super::Posting {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_journal_posting (
+                    id          INTEGER NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    entry_id    INTEGER NOT NULL,
+                    kind        TEXT    NOT NULL CHECK (kind IN ('asset', 'trading', 'fee', 'income', 'expense', 'equity')),
+                    account_id  INTEGER,
+                    object_id   INTEGER NOT NULL,
+                    amount      TEXT    NOT NULL,
+                    FOREIGN KEY(entry_id) REFERENCES finance_journal_entry(id) ON DELETE CASCADE,
+                    FOREIGN KEY(account_id) REFERENCES finance_account(id) ON DELETE RESTRICT,
+                    FOREIGN KEY(object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    CHECK ((kind = 'asset') = (account_id IS NOT NULL))
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_finance_journal_posting_entry_id ON finance_journal_posting(entry_id);
+            "
+        }
+    }
+
+    impl FromSql for Kind {
+        fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+            value
+                .as_str()?
+                .parse()
+                .map_err(|error: String| FromSqlError::Other(error.into()))
+        }
+    }
+
+    impl ToSql for Kind {
+        fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+            Ok(ToSqlOutput::from(self.as_str()))
+        }
+    }
+
+    impl super::Entry {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                transaction_id: row.get(2)?,
+                flow_id: row.get(3)?,
+                description: row.get(4)?,
+                occurrence_at: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        }
+
+        pub fn insert(
+            conn: &Connection,
+            owner: i64,
+            transaction_id: Option<i64>,
+            flow_id: Option<i64>,
+            description: Option<String>,
+            occurrence_at: DateTime<Utc>,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_journal_entry (owner, transaction_id, flow_id, description, occurrence_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![owner, transaction_id, flow_id, description, occurrence_at],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_journal_entry
+                WHERE owner = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![owner], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        /// Returns the entries of the owner, newest first.
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, transaction_id, flow_id, description, occurrence_at, created_at
+                FROM finance_journal_entry
+                WHERE owner = ?1
+                ORDER BY occurrence_at DESC, id DESC
+                LIMIT ?2 OFFSET ?3;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let entries = stmt
+                .query_map(params![owner, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(entries)
+        }
+
+        /// Returns the executed transactions of the owner that have no entry
+        /// yet.
+        pub fn select_unrecorded_transaction_ids(
+            conn: &Connection,
+            owner: i64,
+        ) -> Result<Vec<i64>> {
+            let sql = r#"
+                SELECT tx.id
+                FROM finance_trade_transaction tx
+                JOIN finance_trade t ON t.id = tx.trade_id
+                LEFT JOIN finance_journal_entry e ON e.transaction_id = tx.id
+                WHERE t.owner = ?1 AND e.id IS NULL AND tx.status IN ('pending', 'settled')
+                ORDER BY tx.id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let ids = stmt
+                .query_map(params![owner], |row| row.get(0))?
+                .collect::<Result<Vec<i64>>>()?;
+
+            Ok(ids)
+        }
+
+        /// Returns the flows of the owner that have no entry yet.
+        pub fn select_unrecorded_flow_ids(conn: &Connection, owner: i64) -> Result<Vec<i64>> {
+            let sql = r#"
+                SELECT f.id
+                FROM finance_flow f
+                LEFT JOIN finance_journal_entry e ON e.flow_id = f.id
+                WHERE f.owner = ?1 AND e.id IS NULL
+                ORDER BY f.id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let ids = stmt
+                .query_map(params![owner], |row| row.get(0))?
+                .collect::<Result<Vec<i64>>>()?;
+
+            Ok(ids)
+        }
+
+        pub fn delete_by_flow_id(conn: &Connection, flow_id: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_journal_entry
+                WHERE flow_id = ?1;
+            "#;
+
+            conn.execute(sql, params![flow_id])?;
+
+            Ok(())
+        }
+
+        pub fn delete_by_transaction_id(conn: &Connection, transaction_id: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_journal_entry
+                WHERE transaction_id = ?1;
+            "#;
+
+            conn.execute(sql, params![transaction_id])?;
+
+            Ok(())
+        }
+    }
+
+    impl super::Posting {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                entry_id: row.get(1)?,
+                kind: row.get(2)?,
+                account_id: row.get(3)?,
+                object_id: row.get(4)?,
+                amount: row.get(5)?,
+            })
+        }
+
+        pub fn insert(
+            conn: &Connection,
+            entry_id: i64,
+            kind: Kind,
+            account_id: Option<i64>,
+            object_id: i64,
+            amount: Quantity,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_journal_posting (entry_id, kind, account_id, object_id, amount)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![entry_id, kind, account_id, object_id, amount],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn select_by_entry_id(conn: &Connection, entry_id: i64) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, entry_id, kind, account_id, object_id, amount
+                FROM finance_journal_posting
+                WHERE entry_id = ?1
+                ORDER BY id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let postings = stmt
+                .query_map(params![entry_id], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(postings)
+        }
+
+        /// Returns every posting of the owner, grouped by entry.
+        pub fn select_by_owner(conn: &Connection, owner: i64) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT p.id, p.entry_id, p.kind, p.account_id, p.object_id, p.amount
+                FROM finance_journal_posting p
+                JOIN finance_journal_entry e ON e.id = p.entry_id
+                WHERE e.owner = ?1
+                ORDER BY p.entry_id ASC, p.id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let postings = stmt
+                .query_map(params![owner], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(postings)
+        }
+    }
+}
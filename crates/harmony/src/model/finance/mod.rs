@@ -1,48 +1,410 @@
+pub mod account;
+pub mod attachment;
+pub mod category;
+pub mod flow;
+pub mod import;
+pub mod journal;
 pub mod object;
+pub mod price;
+pub mod search;
+pub mod snapshot;
+pub mod tag;
+pub mod target;
 pub mod trade;
+pub mod transfer;
 
-use rust_decimal::Decimal;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Quantity(Decimal);
 
+/// How a quantity with too many decimal places is brought to precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// Midpoints go to the even neighbour, also known as banker's rounding.
+    HalfEven,
+    HalfUp,
+    HalfDown,
+    Up,
+    Down,
+    Floor,
+    Ceiling,
+}
+
+impl Rounding {
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            Self::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Self::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Self::HalfDown => RoundingStrategy::MidpointTowardZero,
+            Self::Up => RoundingStrategy::AwayFromZero,
+            Self::Down => RoundingStrategy::ToZero,
+            Self::Floor => RoundingStrategy::ToNegativeInfinity,
+            Self::Ceiling => RoundingStrategy::ToPositiveInfinity,
+        }
+    }
+}
+
 impl Quantity {
-    pub fn to_string(&self) -> String {
-        self.0.to_string()
+    pub fn value(&self) -> Decimal {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > Decimal::ZERO
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+        self.0.checked_mul(other.0).map(Self)
+    }
+
+    /// `None` on overflow and when dividing by zero.
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        self.0.checked_div(other.0).map(Self)
+    }
+
+    pub fn round(&self, precision: u32, rounding: Rounding) -> Self {
+        Self(
+            self.0
+                .round_dp_with_strategy(precision, rounding.strategy())
+                .normalize(),
+        )
+    }
+
+    /// Brings the quantity to `precision` decimal places. Without a rounding
+    /// mode, a quantity that does not already fit is an error.
+    pub fn with_precision(
+        &self,
+        precision: u32,
+        rounding: Option<Rounding>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let normalized = self.0.normalize();
+
+        if normalized.scale() <= precision {
+            return Ok(Self(normalized));
+        }
+
+        match rounding {
+            Some(rounding) => Ok(self.round(precision, rounding)),
+            None => Err(format!("{} has more than {} decimal places", self, precision).into()),
+        }
     }
 }
 
-mod database {
-    use std::str::FromStr;
+impl From<Decimal> for Quantity {
+    fn from(value: Decimal) -> Self {
+        Self(value)
+    }
+}
 
-    use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
 
-    use rust_decimal::prelude::FromPrimitive;
+/// Deserializes amounts of requests, which must be greater than zero, so
+/// that a handler cannot forget to check them. Use it with
+/// `#[serde(deserialize_with = "positive::deserialize")]`, or with
+/// `positive::option::deserialize` and `default` for optional amounts.
+pub mod positive {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer};
 
     use super::Quantity;
 
+    fn check<E: Error>(quantity: Quantity) -> Result<Quantity, E> {
+        match quantity.is_positive() {
+            true => Ok(quantity),
+            false => Err(E::custom(format!("{} is not greater than zero", quantity))),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Quantity, D::Error> {
+        check(Quantity::deserialize(deserializer)?)
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer};
+
+        use super::super::Quantity;
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Quantity>, D::Error> {
+            Option::<Quantity>::deserialize(deserializer)?
+                .map(super::check)
+                .transpose()
+        }
+    }
+}
+
+/// The kinds of rows that search hits point to and tags attach to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Entity {
+    Object,
+    Trade,
+    Transaction,
+}
+
+impl Entity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Object => "object",
+            Self::Trade => "trade",
+            Self::Transaction => "transaction",
+        }
+    }
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Entity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "object" => Ok(Self::Object),
+            "trade" => Ok(Self::Trade),
+            "transaction" => Ok(Self::Transaction),
+            _ => Err(format!("unknown entity {}", s)),
+        }
+    }
+}
+
+mod database {
+    use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+    use rust_decimal::Decimal;
+
+    use super::{Entity, Quantity};
+
+    /// Quantities are read back exactly as written. Reals are refused rather
+    /// than converted, since going through `f64` would lose digits.
     impl FromSql for Quantity {
         fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-            if let Ok(text) = value.as_str() {
-                if let Ok(decimal) = rust_decimal::Decimal::from_str(text) {
-                    return Ok(Quantity(decimal));
+            match value {
+                ValueRef::Text(_) => {
+                    let text = value.as_str()?;
+                    Decimal::from_str_exact(text).map(Quantity).map_err(|_| {
+                        FromSqlError::Other(format!("invalid quantity {}", text).into())
+                    })
                 }
+                ValueRef::Integer(integer) => Ok(Quantity(Decimal::from(integer))),
+                _ => Err(FromSqlError::InvalidType),
             }
+        }
+    }
 
-            if let Ok(float) = value.as_f64() {
-                if let Some(decimal) = rust_decimal::Decimal::from_f64(float) {
-                    return Ok(Quantity(decimal));
-                }
-            }
+    /// Quantities are stored as normalized text, so equal values are stored
+    /// identically whatever scale they were written with.
+    impl ToSql for Quantity {
+        fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+            Ok(ToSqlOutput::from(self.0.normalize().to_string()))
+        }
+    }
 
-            Err(rusqlite::types::FromSqlError::InvalidType)
+    impl FromSql for Entity {
+        fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+            value
+                .as_str()?
+                .parse()
+                .map_err(|error: String| FromSqlError::Other(error.into()))
         }
     }
 
-    impl ToSql for Quantity {
+    impl ToSql for Entity {
         fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
-            Ok(ToSqlOutput::from(self.to_string()))
+            Ok(ToSqlOutput::from(self.as_str()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rusqlite::Connection;
+    use rust_decimal::Decimal;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::{Quantity, Rounding};
+
+    fn decimal() -> impl Strategy<Value = Decimal> {
+        (
+            any::<u32>(),
+            any::<u32>(),
+            any::<u32>(),
+            any::<bool>(),
+            0u32..=28,
+        )
+            .prop_map(|(lo, mid, hi, negative, scale)| {
+                Decimal::from_parts(lo, mid, hi, negative, scale)
+            })
+    }
+
+    fn round_trip(conn: &Connection, quantity: &Quantity) -> Quantity {
+        conn.query_row("SELECT ?1", [quantity], |row| row.get(0))
+            .unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn test_sql_round_trip(value in decimal()) {
+            let conn = Connection::open_in_memory().unwrap();
+            let quantity = Quantity::from(value);
+
+            let stored: String = conn
+                .query_row("SELECT ?1", [&quantity], |row| row.get(0))
+                .unwrap();
+            prop_assert_eq!(stored, value.normalize().to_string());
+            prop_assert_eq!(round_trip(&conn, &quantity).value(), value);
+        }
+
+        #[test]
+        fn test_json_round_trip(value in decimal()) {
+            let quantity = Quantity::from(value);
+
+            let json = serde_json::to_string(&quantity).unwrap();
+            let parsed: Quantity = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(parsed, quantity);
         }
+
+        #[test]
+        fn test_with_precision(value in decimal(), precision in 0u32..=28) {
+            let quantity = Quantity::from(value);
+            let rounded = quantity.with_precision(precision, Some(Rounding::HalfEven)).unwrap();
+
+            prop_assert!(rounded.value().scale() <= precision);
+            prop_assert_eq!(rounded.with_precision(precision, None).unwrap(), rounded);
+        }
+    }
+
+    #[test]
+    fn test_equal_values_are_stored_identically() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        let stored = |quantity: Quantity| -> String {
+            conn.query_row("SELECT ?1", [&quantity], |row| row.get(0))
+                .unwrap()
+        };
+
+        assert_eq!(stored(Decimal::new(150, 2).into()), "1.5");
+        assert_eq!(stored(Decimal::new(15, 1).into()), "1.5");
+        assert_eq!(stored(Decimal::new(-0, 3).into()), "0");
+    }
+
+    #[test]
+    fn test_real_is_refused() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        let real = conn.query_row("SELECT 0.1", [], |row| row.get::<_, Quantity>(0));
+        assert!(real.is_err());
+
+        let integer = conn
+            .query_row("SELECT 42", [], |row| row.get::<_, Quantity>(0))
+            .unwrap();
+        assert_eq!(integer.value(), Decimal::new(42, 0));
+    }
+
+    #[test]
+    fn test_rounding_modes() {
+        let quantity = Quantity::from(Decimal::new(-125, 2));
+
+        let round = |rounding| quantity.round(1, rounding).value();
+        assert_eq!(round(Rounding::HalfEven), Decimal::new(-12, 1));
+        assert_eq!(round(Rounding::HalfUp), Decimal::new(-13, 1));
+        assert_eq!(round(Rounding::HalfDown), Decimal::new(-12, 1));
+        assert_eq!(round(Rounding::Up), Decimal::new(-13, 1));
+        assert_eq!(round(Rounding::Down), Decimal::new(-12, 1));
+        assert_eq!(round(Rounding::Floor), Decimal::new(-13, 1));
+        assert_eq!(round(Rounding::Ceiling), Decimal::new(-12, 1));
+    }
+
+    #[test]
+    fn test_with_precision_without_rounding() {
+        let quantity = Quantity::from(Decimal::new(12340, 4));
+
+        // Trailing zeros do not count against the precision
+        assert_eq!(
+            quantity.with_precision(3, None).unwrap().value(),
+            Decimal::new(1234, 3)
+        );
+        assert!(quantity.with_precision(2, None).is_err());
+    }
+
+    #[test]
+    fn test_checked_arithmetic_and_sign() {
+        let max = Quantity::from(Decimal::MAX);
+        let one = Quantity::from(Decimal::ONE);
+        let zero = Quantity::from(Decimal::ZERO);
+
+        assert!(max.checked_mul(&max).is_none());
+        assert_eq!(max.checked_mul(&one), Some(max.clone()));
+        assert!(max.checked_add(&one).is_none());
+        assert_eq!(max.checked_add(&zero), Some(max.clone()));
+        assert!(zero.checked_sub(&max).unwrap().checked_sub(&one).is_none());
+        assert_eq!(one.checked_sub(&one), Some(zero.clone()));
+        assert!(one.checked_div(&zero).is_none());
+        assert_eq!(
+            one.checked_div(&Quantity::from(Decimal::TWO)),
+            Some(Quantity::from(Decimal::new(5, 1)))
+        );
+
+        assert!(one.is_positive());
+        assert!(!zero.is_positive());
+        assert!(!Quantity::from(Decimal::NEGATIVE_ONE).is_positive());
+    }
+
+    #[test]
+    fn test_positive_deserialization() {
+        #[derive(Debug, Deserialize)]
+        struct Body {
+            #[serde(deserialize_with = "super::positive::deserialize")]
+            amount: Quantity,
+            #[serde(default, deserialize_with = "super::positive::option::deserialize")]
+            fee: Option<Quantity>,
+        }
+
+        let parse = |json: serde_json::Value| serde_json::from_value::<Body>(json);
+
+        let body = parse(json!({ "amount": "1.5" })).unwrap();
+        assert_eq!(body.amount.value(), Decimal::new(15, 1));
+        assert!(body.fee.is_none());
+        assert!(parse(json!({ "amount": "1", "fee": null }))
+            .unwrap()
+            .fee
+            .is_none());
+        assert!(parse(json!({ "amount": "1", "fee": "0.1" }))
+            .unwrap()
+            .fee
+            .is_some());
+
+        assert!(parse(json!({ "amount": "0" })).is_err());
+        assert!(parse(json!({ "amount": "-1" })).is_err());
+        assert!(parse(json!({ "amount": "1", "fee": "0" })).is_err());
+        assert!(parse(json!({ "amount": "1", "fee": "-0.1" })).is_err());
     }
 }
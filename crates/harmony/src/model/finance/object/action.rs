@@ -0,0 +1,306 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::finance::Quantity;
+
+/// A change to an object decided by its issuer, such as a split, that
+/// changes what every holding of it amounts to from `effective_at` on.
+///
+/// Historical transactions keep the quantities they were made in; balances
+/// and cost basis apply the actions on top of them.
+pub struct CorporateAction {
+    id: i64,
+    pub object_id: i64,
+    pub kind: Kind,
+    /// Units received per unit held.
+    pub ratio: Quantity,
+    /// The object holdings turn into, for actions that replace the object.
+    pub successor_object_id: Option<i64>,
+    pub remark: Option<String>,
+    pub effective_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CorporateAction {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// The object holdings are in once the action took effect.
+    pub fn target_object_id(&self) -> i64 {
+        self.successor_object_id.unwrap_or(self.object_id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Split,
+    ReverseSplit,
+    SymbolChange,
+    Merger,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Split => "split",
+            Self::ReverseSplit => "reverse_split",
+            Self::SymbolChange => "symbol_change",
+            Self::Merger => "merger",
+        }
+    }
+
+    /// Whether holdings move to a successor object.
+    pub fn is_replacement(&self) -> bool {
+        matches!(self, Self::SymbolChange | Self::Merger)
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "split" => Ok(Self::Split),
+            "reverse_split" => Ok(Self::ReverseSplit),
+            "symbol_change" => Ok(Self::SymbolChange),
+            "merger" => Ok(Self::Merger),
+            _ => Err(format!("unknown corporate action kind {}", s)),
+        }
+    }
+}
+
+mod database {
+    use chrono::{DateTime, Utc};
+    use rusqlite::params;
+    use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+    use crate::model::finance::Quantity;
+
+    use super::Kind;
+
+    impl crate::model::Model for super::CorporateAction {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_object_action (
+                    id                   INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    object_id            INTEGER  NOT NULL,
+                    kind                 TEXT     NOT NULL CHECK (kind IN ('split', 'reverse_split', 'symbol_change', 'merger')),
+                    ratio                TEXT     NOT NULL,
+                    successor_object_id  INTEGER,
+                    remark               TEXT,
+                    effective_at         DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    created_at           DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at           DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    FOREIGN KEY(successor_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    CHECK ((kind IN ('symbol_change', 'merger')) = (successor_object_id IS NOT NULL)),
+                    CHECK (successor_object_id <> object_id)
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_object_action_updated_at
+                AFTER UPDATE ON finance_object_action
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_object_action SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE INDEX IF NOT EXISTS idx_finance_object_action_object_id ON finance_object_action(object_id);
+                CREATE INDEX IF NOT EXISTS idx_finance_object_action_successor_object_id ON finance_object_action(successor_object_id);
+            "
+        }
+    }
+
+    impl FromSql for Kind {
+        fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+            value
+                .as_str()?
+                .parse()
+                .map_err(|error: String| FromSqlError::Other(error.into()))
+        }
+    }
+
+    impl ToSql for Kind {
+        fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+            Ok(ToSqlOutput::from(self.as_str()))
+        }
+    }
+
+    impl super::CorporateAction {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                object_id: row.get(1)?,
+                kind: row.get(2)?,
+                ratio: row.get(3)?,
+                successor_object_id: row.get(4)?,
+                remark: row.get(5)?,
+                effective_at: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+            })
+        }
+
+        pub fn insert(
+            conn: &Connection,
+            object_id: i64,
+            kind: Kind,
+            ratio: Quantity,
+            successor_object_id: Option<i64>,
+            remark: Option<String>,
+            effective_at: DateTime<Utc>,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_object_action (object_id, kind, ratio, successor_object_id, remark, effective_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![
+                    object_id,
+                    kind,
+                    ratio,
+                    successor_object_id,
+                    remark,
+                    effective_at
+                ],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_object_id(conn: &Connection, object_id: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_object_action
+                WHERE object_id = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![object_id], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_id_object_id(
+            conn: &Connection,
+            id: i64,
+            object_id: i64,
+        ) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, object_id, kind, ratio, successor_object_id, remark, effective_at, created_at, updated_at
+                FROM finance_object_action
+                WHERE id = ?1 AND object_id = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, object_id], Self::from_row)
+                .optional()
+        }
+
+        pub fn select_by_object_id(
+            conn: &Connection,
+            object_id: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, object_id, kind, ratio, successor_object_id, remark, effective_at, created_at, updated_at
+                FROM finance_object_action
+                WHERE object_id = ?1
+                ORDER BY effective_at ASC, id ASC
+                LIMIT ?2 OFFSET ?3;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let actions = stmt
+                .query_map(params![object_id, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(actions)
+        }
+
+        /// Returns every action on the owner's objects that took effect at or
+        /// before `until`, in `effective_at` order.
+        pub fn select_by_owner_until(
+            conn: &Connection,
+            owner: i64,
+            until: DateTime<Utc>,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT a.id, a.object_id, a.kind, a.ratio, a.successor_object_id, a.remark, a.effective_at, a.created_at, a.updated_at
+                FROM finance_object_action a
+                JOIN finance_object o ON o.id = a.object_id
+                WHERE o.owner = ?1 AND a.effective_at <= ?2
+                ORDER BY a.effective_at ASC, a.id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let actions = stmt
+                .query_map(params![owner, until], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(actions)
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn update_by_id_object_id(
+            conn: &Connection,
+            id: i64,
+            object_id: i64,
+            kind: Kind,
+            ratio: Quantity,
+            successor_object_id: Option<i64>,
+            remark: Option<String>,
+            effective_at: DateTime<Utc>,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_object_action
+                SET kind = ?1, ratio = ?2, successor_object_id = ?3, remark = ?4, effective_at = ?5
+                WHERE id = ?6 AND object_id = ?7;
+            "#;
+
+            conn.execute(
+                sql,
+                params![
+                    kind,
+                    ratio,
+                    successor_object_id,
+                    remark,
+                    effective_at,
+                    id,
+                    object_id
+                ],
+            )?;
+
+            Ok(())
+        }
+
+        pub fn delete_by_id_object_id(conn: &Connection, id: i64, object_id: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_object_action
+                WHERE id = ?1 AND object_id = ?2;
+            "#;
+
+            conn.execute(sql, params![id, object_id])?;
+
+            Ok(())
+        }
+    }
+}
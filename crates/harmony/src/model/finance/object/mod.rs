@@ -0,0 +1,1088 @@
+pub mod action;
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The largest number of decimal places a `Decimal` can carry.
+pub const MAX_PRECISION: u32 = 28;
+
+pub struct Object {
+    id: i64,
+    pub owner: i64,
+    pub symbol: String,
+    pub alias: Option<String>,
+    pub remark: Option<String>,
+    pub classification: Classification,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Object {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetType {
+    Fiat,
+    Crypto,
+    Equity,
+    Fund,
+    Bond,
+    Commodity,
+    Other,
+}
+
+impl AssetType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fiat => "fiat",
+            Self::Crypto => "crypto",
+            Self::Equity => "equity",
+            Self::Fund => "fund",
+            Self::Bond => "bond",
+            Self::Commodity => "commodity",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for AssetType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AssetType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fiat" => Ok(Self::Fiat),
+            "crypto" => Ok(Self::Crypto),
+            "equity" => Ok(Self::Equity),
+            "fund" => Ok(Self::Fund),
+            "bond" => Ok(Self::Bond),
+            "commodity" => Ok(Self::Commodity),
+            "other" => Ok(Self::Other),
+            _ => Err(format!("unknown asset type {}", s)),
+        }
+    }
+}
+
+/// What kind of asset an object is and where it is traded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub asset_type: AssetType,
+    /// Decimal places quantities of this object are kept to.
+    pub precision: u32,
+    /// An ISIN or CUSIP for securities, a contract address for tokens.
+    pub identifier: Option<String>,
+    /// The exchange a security is listed on, or the network of a token.
+    pub exchange: Option<String>,
+}
+
+impl Default for Classification {
+    fn default() -> Self {
+        Self {
+            asset_type: AssetType::Other,
+            precision: 8,
+            identifier: None,
+            exchange: None,
+        }
+    }
+}
+
+impl Classification {
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.precision > MAX_PRECISION {
+            return Err(format!("precision must be at most {}", MAX_PRECISION).into());
+        }
+
+        if let Some(identifier) = &self.identifier {
+            match self.asset_type {
+                AssetType::Equity | AssetType::Fund | AssetType::Bond => {
+                    if !is_isin(identifier) && !is_cusip(identifier) {
+                        return Err(format!("{} is neither an ISIN nor a CUSIP", identifier).into());
+                    }
+                }
+                _ => {
+                    if !is_token(identifier, 128) {
+                        return Err(format!("{} is not a valid identifier", identifier).into());
+                    }
+                }
+            }
+        }
+
+        if let Some(exchange) = &self.exchange {
+            if exchange.is_empty() || exchange.len() > 64 || exchange.trim() != exchange {
+                return Err(format!("{:?} is not a valid exchange", exchange).into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_token(value: &str, max: usize) -> bool {
+    !value.is_empty()
+        && value.len() <= max
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '.' | '_' | '-'))
+}
+
+/// Checks the format and the Luhn check digit of an ISIN.
+fn is_isin(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    if bytes.len() != 12
+        || !bytes[..2].iter().all(u8::is_ascii_uppercase)
+        || !bytes[2..11]
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        || !bytes[11].is_ascii_digit()
+    {
+        return false;
+    }
+
+    // Letters expand to two digits (A = 10 ... Z = 35) before the Luhn check
+    let digits: Vec<u32> = bytes
+        .iter()
+        .flat_map(|&b| match b {
+            b'0'..=b'9' => vec![(b - b'0') as u32],
+            _ => {
+                let value = (b - b'A') as u32 + 10;
+                vec![value / 10, value % 10]
+            }
+        })
+        .collect();
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| {
+            if i % 2 == 1 {
+                let doubled = digit * 2;
+                doubled / 10 + doubled % 10
+            } else {
+                digit
+            }
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+/// Checks the format and the check digit of a CUSIP.
+fn is_cusip(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    if bytes.len() != 9 || !bytes[8].is_ascii_digit() {
+        return false;
+    }
+
+    let mut sum = 0;
+    for (i, &b) in bytes[..8].iter().enumerate() {
+        let mut value = match b {
+            b'0'..=b'9' => (b - b'0') as u32,
+            b'A'..=b'Z' => (b - b'A') as u32 + 10,
+            b'*' => 36,
+            b'@' => 37,
+            b'#' => 38,
+            _ => return false,
+        };
+
+        if i % 2 == 1 {
+            value *= 2;
+        }
+
+        sum += value / 10 + value % 10;
+    }
+
+    (10 - sum % 10) % 10 == (bytes[8] - b'0') as u32
+}
+
+/// Narrows the objects of an owner down in listings.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub asset_type: Option<AssetType>,
+    pub exchange: Option<String>,
+    /// Objects created at or after.
+    pub from: Option<DateTime<Utc>>,
+    /// Objects created at or before.
+    pub to: Option<DateTime<Utc>>,
+    /// Found in the symbol, the alias or the remark, ignoring ASCII case.
+    pub text: Option<String>,
+    /// Objects the tag is attached to.
+    pub tag_id: Option<i64>,
+}
+
+/// The columns objects are listed by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sort {
+    #[default]
+    Id,
+    Symbol,
+    Alias,
+    Remark,
+    AssetType,
+    Precision,
+    Identifier,
+    Exchange,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl Sort {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Symbol => "symbol",
+            Self::Alias => "alias",
+            Self::Remark => "remark",
+            Self::AssetType => "asset_type",
+            Self::Precision => "precision",
+            Self::Identifier => "identifier",
+            Self::Exchange => "exchange",
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
+        }
+    }
+}
+
+mod database {
+    use rusqlite::params;
+    use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::model::database::{Order, Result};
+
+    use super::{AssetType, Classification, Filter, Sort};
+
+    /// The conditions of `Filter`, bound from `?2` to `?7`.
+    const FILTER: &str = r#"
+        (?2 IS NULL OR asset_type = ?2)
+        AND (?3 IS NULL OR exchange = ?3)
+        AND (?4 IS NULL OR created_at >= ?4)
+        AND (?5 IS NULL OR created_at <= ?5)
+        AND (?6 IS NULL OR instr(lower(symbol), lower(?6)) > 0
+            OR instr(lower(coalesce(alias, '')), lower(?6)) > 0
+            OR instr(lower(coalesce(remark, '')), lower(?6)) > 0)
+        AND (?7 IS NULL OR EXISTS (SELECT 1 FROM finance_object_tag
+            WHERE object_id = finance_object.id AND tag_id = ?7))
+    "#;
+
+    impl crate::model::Model for super::Object {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_object (
+                    id         INTEGER  NOT NULL  UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner      INTEGER  NOT NULL,
+                    symbol     TEXT     NOT NULL,
+                    alias      TEXT,
+                    remark     TEXT,
+                    asset_type TEXT     NOT NULL DEFAULT 'other' CHECK (asset_type IN ('fiat', 'crypto', 'equity', 'fund', 'bond', 'commodity', 'other')),
+                    precision  INTEGER  NOT NULL DEFAULT 8 CHECK (precision BETWEEN 0 AND 28),
+                    identifier TEXT,
+                    exchange   TEXT,
+                    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_object_updated_at
+                AFTER UPDATE ON finance_object
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_object SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE INDEX IF NOT EXISTS idx_finance_object_owner ON finance_object(owner);
+                CREATE INDEX IF NOT EXISTS idx_finance_object_owner_asset_type ON finance_object(owner, asset_type);
+            "
+        }
+    }
+
+    impl FromSql for AssetType {
+        fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+            value
+                .as_str()?
+                .parse()
+                .map_err(|error: String| FromSqlError::Other(error.into()))
+        }
+    }
+
+    impl ToSql for AssetType {
+        fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+            Ok(ToSqlOutput::from(self.as_str()))
+        }
+    }
+
+    impl super::Object {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                symbol: row.get(2)?,
+                alias: row.get(3)?,
+                remark: row.get(4)?,
+                classification: Classification {
+                    asset_type: row.get(5)?,
+                    precision: row.get(6)?,
+                    identifier: row.get(7)?,
+                    exchange: row.get(8)?,
+                },
+                created_at: row.get(9)?,
+                updated_at: row.get(10)?,
+            })
+        }
+
+        pub fn insert(
+            conn: &Connection,
+            owner: i64,
+            symbol: String,
+            alias: Option<String>,
+            remark: Option<String>,
+            classification: Classification,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_object (owner, symbol, alias, remark, asset_type, precision, identifier, exchange)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![
+                    owner,
+                    symbol,
+                    alias,
+                    remark,
+                    classification.asset_type,
+                    classification.precision,
+                    classification.identifier,
+                    classification.exchange
+                ],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64, filter: &Filter) -> Result<usize> {
+            let sql = format!(
+                r#"
+                    SELECT COUNT(*)
+                    FROM finance_object
+                    WHERE owner = ?1 AND {};
+                "#,
+                FILTER
+            );
+
+            let count = conn.query_row(
+                &sql,
+                params![
+                    owner,
+                    filter.asset_type,
+                    filter.exchange,
+                    filter.from,
+                    filter.to,
+                    filter.text,
+                    filter.tag_id,
+                ],
+                |row| row.get(0),
+            )?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, symbol, alias, remark, asset_type, precision, identifier, exchange, created_at, updated_at
+                FROM finance_object
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, owner], Self::from_row)
+                .optional()
+        }
+
+        /// Returns the objects of the owner that pass `filter`, ordered by
+        /// `sort` and then by id.
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            filter: &Filter,
+            sort: Sort,
+            order: Order,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = format!(
+                r#"
+                    SELECT id, owner, symbol, alias, remark, asset_type, precision, identifier, exchange, created_at, updated_at
+                    FROM finance_object
+                    WHERE owner = ?1 AND {}
+                    ORDER BY {} {}, id {}
+                    LIMIT ?8 OFFSET ?9;
+                "#,
+                FILTER,
+                sort.as_sql(),
+                order.as_sql(),
+                order.as_sql()
+            );
+
+            let mut stmt = conn.prepare(&sql)?;
+            let objects = stmt
+                .query_map(
+                    params![
+                        owner,
+                        filter.asset_type,
+                        filter.exchange,
+                        filter.from,
+                        filter.to,
+                        filter.text,
+                        filter.tag_id,
+                        limit,
+                        offset,
+                    ],
+                    Self::from_row,
+                )?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(objects)
+        }
+
+        pub fn select_all_by_owner(conn: &Connection, owner: i64) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, symbol, alias, remark, asset_type, precision, identifier, exchange, created_at, updated_at
+                FROM finance_object
+                WHERE owner = ?1;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let objects = stmt
+                .query_map(params![owner], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(objects)
+        }
+
+        pub fn update_by_id_owner(
+            conn: &Connection,
+            id: i64,
+            owner: i64,
+            symbol: String,
+            alias: Option<String>,
+            remark: Option<String>,
+            classification: Classification,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_object
+                SET symbol = ?1, alias = ?2, remark = ?3, asset_type = ?4, precision = ?5, identifier = ?6, exchange = ?7
+                WHERE id = ?8 AND owner = ?9;
+            "#;
+
+            conn.execute(
+                sql,
+                params![
+                    symbol,
+                    alias,
+                    remark,
+                    classification.asset_type,
+                    classification.precision,
+                    classification.identifier,
+                    classification.exchange,
+                    id,
+                    owner
+                ],
+            )?;
+
+            Ok(())
+        }
+
+        pub fn delete_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_object
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.execute(sql, params![id, owner])?;
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+
+    use crate::model::database::Order;
+    use crate::model::person::Person;
+
+    use super::{AssetType, Classification, Filter, Object, Sort};
+
+    // Helper function to set up the database and create a test user
+    fn setup() -> (PooledConnection<SqliteConnectionManager>, i64) {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+
+        let conn = pool.get().unwrap();
+
+        // Initialize the whole schema, as listings filter by tags
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        // Insert a test user into the database
+        let nickname = "test_user".to_string();
+        let password = "test_password".to_string();
+        let person = Person::insert_one(&conn, &nickname, &password).unwrap();
+
+        (conn, person.id())
+    }
+
+    #[test]
+    fn test_insert() {
+        let (conn, owner_id) = setup();
+        let symbol = "AAPL".to_string();
+
+        // Insert a new object into the database
+        let id = Object::insert(
+            &conn,
+            owner_id,
+            symbol.clone(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        assert_eq!(id, 1);
+
+        // Retrieve the inserted object and verify its fields
+        let obj = Object::select_by_id_owner(&conn, id, owner_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(obj.id, id);
+        assert_eq!(obj.owner, owner_id);
+        assert_eq!(obj.symbol, symbol);
+        assert_eq!(obj.alias, None);
+        assert_eq!(obj.remark, None);
+    }
+
+    #[test]
+    fn test_count_by_owner() {
+        let (conn, owner_id) = setup();
+
+        // Insert two objects for the same owner
+        Object::insert(
+            &conn,
+            owner_id,
+            "AAPL".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        Object::insert(
+            &conn,
+            owner_id,
+            "GOOG".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+
+        // Verify that the count of objects for the owner is correct
+        assert_eq!(
+            Object::count_by_owner(&conn, owner_id, &Filter::default()).unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_select_by_id_owner() {
+        let (conn, owner_id) = setup();
+
+        // Insert an object and retrieve it by ID and owner
+        let id = Object::insert(
+            &conn,
+            owner_id,
+            "AAPL".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+
+        // Correct query: Retrieve the object with the correct ID and owner
+        let obj = Object::select_by_id_owner(&conn, id, owner_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(obj.symbol, "AAPL");
+
+        // Incorrect query: Attempt to retrieve the object with a different owner
+        assert!(Object::select_by_id_owner(&conn, id, owner_id + 1)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_select_by_owner() {
+        let (conn, owner_id) = setup();
+
+        // Insert two objects for the same owner
+        Object::insert(
+            &conn,
+            owner_id,
+            "AAPL".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        Object::insert(
+            &conn,
+            owner_id,
+            "GOOG".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+
+        // Retrieve all objects for the owner with a limit of 10 and offset of 0
+        let objs = Object::select_by_owner(
+            &conn,
+            owner_id,
+            &Filter::default(),
+            Sort::default(),
+            Order::default(),
+            10,
+            0,
+        )
+        .unwrap();
+        assert_eq!(objs.len(), 2);
+        assert_eq!(objs[0].symbol, "AAPL");
+        assert_eq!(objs[1].symbol, "GOOG");
+
+        // Retrieve objects with a limit of 1 and offset of 1 (pagination)
+        let objs = Object::select_by_owner(
+            &conn,
+            owner_id,
+            &Filter::default(),
+            Sort::default(),
+            Order::default(),
+            1,
+            1,
+        )
+        .unwrap();
+        assert_eq!(objs.len(), 1);
+        assert_eq!(objs[0].symbol, "GOOG");
+    }
+
+    #[test]
+    fn test_update_by_id_owner() {
+        let (conn, owner_id) = setup();
+        let id = Object::insert(
+            &conn,
+            owner_id,
+            "AAPL".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+
+        // Update the object's fields
+        Object::update_by_id_owner(
+            &conn,
+            id,
+            owner_id,
+            "GOOG".to_string(),
+            Some("Google".to_string()),
+            Some("Test".to_string()),
+            Classification::default(),
+        )
+        .unwrap();
+
+        // Retrieve the updated object and verify the changes
+        let obj = Object::select_by_id_owner(&conn, id, owner_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(obj.symbol, "GOOG");
+        assert_eq!(obj.alias, Some("Google".to_string()));
+        assert_eq!(obj.remark, Some("Test".to_string()));
+    }
+
+    #[test]
+    fn test_delete_by_id_owner() {
+        let (conn, owner_id) = setup();
+        let id = Object::insert(
+            &conn,
+            owner_id,
+            "AAPL".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+
+        // Delete the object by ID and owner
+        Object::delete_by_id_owner(&conn, id, owner_id).unwrap();
+
+        // Verify that the object no longer exists in the database
+        assert!(Object::select_by_id_owner(&conn, id, owner_id)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_insert_with_optional_fields() {
+        let (conn, owner_id) = setup();
+        let symbol = "AAPL".to_string();
+
+        // Insert with alias and remark as None
+        let id = Object::insert(
+            &conn,
+            owner_id,
+            symbol.clone(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let obj = Object::select_by_id_owner(&conn, id, owner_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(obj.alias, None);
+        assert_eq!(obj.remark, None);
+
+        // Insert with alias and remark as Some
+        let id = Object::insert(
+            &conn,
+            owner_id,
+            "GOOG".to_string(),
+            Some("Google".to_string()),
+            Some("Test".to_string()),
+            Classification::default(),
+        )
+        .unwrap();
+        let obj = Object::select_by_id_owner(&conn, id, owner_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(obj.alias, Some("Google".to_string()));
+        assert_eq!(obj.remark, Some("Test".to_string()));
+    }
+
+    #[test]
+    fn test_update_optional_fields() {
+        let (conn, owner_id) = setup();
+        let id = Object::insert(
+            &conn,
+            owner_id,
+            "AAPL".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+
+        // Update alias and remark from None to Some
+        Object::update_by_id_owner(
+            &conn,
+            id,
+            owner_id,
+            "AAPL".to_string(),
+            Some("Apple".to_string()),
+            Some("Updated".to_string()),
+            Classification::default(),
+        )
+        .unwrap();
+        let obj = Object::select_by_id_owner(&conn, id, owner_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(obj.alias, Some("Apple".to_string()));
+        assert_eq!(obj.remark, Some("Updated".to_string()));
+
+        // Update alias and remark from Some to None
+        Object::update_by_id_owner(
+            &conn,
+            id,
+            owner_id,
+            "AAPL".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let obj = Object::select_by_id_owner(&conn, id, owner_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(obj.alias, None);
+        assert_eq!(obj.remark, None);
+    }
+
+    #[test]
+    fn test_foreign_key_constraint() {
+        let (conn, owner_id) = setup();
+
+        // Attempt to insert an object with an invalid owner
+        let result = Object::insert(
+            &conn,
+            owner_id + 1,
+            "AAPL".to_string(),
+            None,
+            None,
+            Classification::default(),
+        );
+        assert!(result.is_err()); // Should fail due to foreign key constraint
+    }
+
+    #[test]
+    fn test_select_by_owner_pagination() {
+        let (conn, owner_id) = setup();
+
+        // Insert multiple objects
+        for i in 0..5 {
+            Object::insert(
+                &conn,
+                owner_id,
+                format!("SYM{}", i),
+                None,
+                None,
+                Classification::default(),
+            )
+            .unwrap();
+        }
+
+        // Test limit and offset
+        let objs = Object::select_by_owner(
+            &conn,
+            owner_id,
+            &Filter::default(),
+            Sort::default(),
+            Order::default(),
+            2,
+            0,
+        )
+        .unwrap();
+        assert_eq!(objs.len(), 2);
+        assert_eq!(objs[0].symbol, "SYM0");
+        assert_eq!(objs[1].symbol, "SYM1");
+
+        let objs = Object::select_by_owner(
+            &conn,
+            owner_id,
+            &Filter::default(),
+            Sort::default(),
+            Order::default(),
+            2,
+            2,
+        )
+        .unwrap();
+        assert_eq!(objs.len(), 2);
+        assert_eq!(objs[0].symbol, "SYM2");
+        assert_eq!(objs[1].symbol, "SYM3");
+
+        // Test offset beyond the total count
+        let objs = Object::select_by_owner(
+            &conn,
+            owner_id,
+            &Filter::default(),
+            Sort::default(),
+            Order::default(),
+            2,
+            10,
+        )
+        .unwrap();
+        assert_eq!(objs.len(), 0);
+    }
+
+    #[test]
+    fn test_delete_non_existent_object() {
+        let (conn, owner_id) = setup();
+
+        Object::delete_by_id_owner(&conn, 999, owner_id).unwrap();
+
+        assert_eq!(
+            Object::count_by_owner(&conn, owner_id, &Filter::default()).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_count_by_owner_edge_cases() {
+        let (conn, owner_id) = setup();
+
+        // Verify count is 0 when no objects exist
+        assert_eq!(
+            Object::count_by_owner(&conn, owner_id, &Filter::default()).unwrap(),
+            0
+        );
+
+        // Insert an object and verify count is 1
+        Object::insert(
+            &conn,
+            owner_id,
+            "AAPL".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            Object::count_by_owner(&conn, owner_id, &Filter::default()).unwrap(),
+            1
+        );
+
+        // Delete the object and verify count is 0
+        Object::delete_by_id_owner(&conn, 1, owner_id).unwrap();
+        assert_eq!(
+            Object::count_by_owner(&conn, owner_id, &Filter::default()).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_classification_round_trip_and_filter() {
+        let (conn, owner_id) = setup();
+
+        let classification = Classification {
+            asset_type: AssetType::Equity,
+            precision: 0,
+            identifier: Some("US0378331005".to_string()),
+            exchange: Some("NASDAQ".to_string()),
+        };
+        let id = Object::insert(
+            &conn,
+            owner_id,
+            "AAPL".to_string(),
+            None,
+            None,
+            classification.clone(),
+        )
+        .unwrap();
+        Object::insert(
+            &conn,
+            owner_id,
+            "USD".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+
+        let obj = Object::select_by_id_owner(&conn, id, owner_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(obj.classification, classification);
+
+        let filter = Filter {
+            asset_type: Some(AssetType::Equity),
+            ..Filter::default()
+        };
+        assert_eq!(Object::count_by_owner(&conn, owner_id, &filter).unwrap(), 1);
+        let objs = Object::select_by_owner(
+            &conn,
+            owner_id,
+            &filter,
+            Sort::default(),
+            Order::default(),
+            10,
+            0,
+        )
+        .unwrap();
+        assert_eq!(objs[0].symbol, "AAPL");
+
+        let filter = Filter {
+            exchange: Some("NYSE".to_string()),
+            ..Filter::default()
+        };
+        assert_eq!(Object::count_by_owner(&conn, owner_id, &filter).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_select_by_owner_text_and_sort() {
+        let (conn, owner_id) = setup();
+
+        let objects = [
+            ("AAPL", Some("Apple")),
+            ("MSFT", None),
+            ("PINE", Some("apple pie")),
+        ];
+        for (symbol, alias) in objects {
+            let alias = alias.map(str::to_string);
+            let classification = Classification::default();
+            Object::insert(
+                &conn,
+                owner_id,
+                symbol.to_string(),
+                alias,
+                None,
+                classification,
+            )
+            .unwrap();
+        }
+
+        let filter = Filter {
+            text: Some("APPLE".to_string()),
+            ..Filter::default()
+        };
+        assert_eq!(Object::count_by_owner(&conn, owner_id, &filter).unwrap(), 2);
+
+        let objs =
+            Object::select_by_owner(&conn, owner_id, &filter, Sort::Symbol, Order::Desc, 10, 0)
+                .unwrap();
+        let symbols: Vec<&str> = objs.iter().map(|obj| obj.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["PINE", "AAPL"]);
+    }
+
+    #[test]
+    fn test_classification_validate() {
+        let security = |identifier: &str| Classification {
+            asset_type: AssetType::Equity,
+            precision: 0,
+            identifier: Some(identifier.to_string()),
+            exchange: None,
+        };
+
+        // ISIN and CUSIP of Apple Inc.
+        assert!(security("US0378331005").validate().is_ok());
+        assert!(security("037833100").validate().is_ok());
+
+        // Wrong check digits
+        assert!(security("US0378331006").validate().is_err());
+        assert!(security("037833101").validate().is_err());
+
+        let token = Classification {
+            asset_type: AssetType::Crypto,
+            precision: 18,
+            identifier: Some("0xdAC17F958D2ee523a2206206994597C13D831ec7".to_string()),
+            exchange: Some("ethereum".to_string()),
+        };
+        assert!(token.validate().is_ok());
+
+        let precision = Classification {
+            precision: 29,
+            ..Classification::default()
+        };
+        assert!(precision.validate().is_err());
+
+        let exchange = Classification {
+            exchange: Some(" NYSE".to_string()),
+            ..Classification::default()
+        };
+        assert!(exchange.validate().is_err());
+    }
+}
@@ -0,0 +1,451 @@
+use chrono::{DateTime, Utc};
+
+use crate::model::finance::Quantity;
+
+pub struct Price {
+    id: i64,
+    pub owner: i64,
+    pub base_object_id: i64,
+    pub quote_object_id: i64,
+    pub price: Quantity,
+    pub source: Option<String>,
+    pub occurrence_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Price {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+/// A base/quote pair referenced by at least one trade, together with the
+/// symbols a market data provider knows it by.
+pub struct TrackedPair {
+    pub owner: i64,
+    pub base_object_id: i64,
+    pub base_symbol: String,
+    pub quote_object_id: i64,
+    pub quote_symbol: String,
+}
+
+mod database {
+    use chrono::{DateTime, Utc};
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+    use crate::model::finance::Quantity;
+
+    use super::TrackedPair;
+
+    impl crate::model::Model for super::Price {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_price (
+                    id               INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner            INTEGER  NOT NULL,
+                    base_object_id   INTEGER  NOT NULL,
+                    quote_object_id  INTEGER  NOT NULL,
+                    price            TEXT     NOT NULL,
+                    source           TEXT,
+                    occurrence_at    DATETIME NOT NULL,
+                    created_at       DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at       DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    FOREIGN KEY(base_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    FOREIGN KEY(quote_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    UNIQUE(base_object_id, quote_object_id, occurrence_at)
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_price_updated_at
+                AFTER UPDATE ON finance_price
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_price SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE INDEX IF NOT EXISTS idx_finance_price_owner ON finance_price(owner);
+                CREATE INDEX IF NOT EXISTS idx_finance_price_pair_occurrence_at ON finance_price(base_object_id, quote_object_id, occurrence_at);
+            "
+        }
+    }
+
+    impl super::Price {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                base_object_id: row.get(2)?,
+                quote_object_id: row.get(3)?,
+                price: row.get(4)?,
+                source: row.get(5)?,
+                occurrence_at: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+            })
+        }
+
+        /// Stores a price, replacing any existing price for the same pair and instant.
+        pub fn upsert(
+            conn: &Connection,
+            owner: i64,
+            base_object_id: i64,
+            quote_object_id: i64,
+            price: Quantity,
+            source: Option<String>,
+            occurrence_at: DateTime<Utc>,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_price (owner, base_object_id, quote_object_id, price, source, occurrence_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                ON CONFLICT(base_object_id, quote_object_id, occurrence_at)
+                DO UPDATE SET price = excluded.price, source = excluded.source
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![
+                    owner,
+                    base_object_id,
+                    quote_object_id,
+                    price,
+                    source,
+                    occurrence_at
+                ],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_price
+                WHERE owner = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![owner], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, base_object_id, quote_object_id, price, source, occurrence_at, created_at, updated_at
+                FROM finance_price
+                WHERE owner = ?1
+                ORDER BY occurrence_at DESC, id DESC
+                LIMIT ?2 OFFSET ?3;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let prices = stmt
+                .query_map(params![owner, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(prices)
+        }
+
+        /// Returns every price of the owner observed at or before `until`, oldest first.
+        pub fn select_by_owner_until(
+            conn: &Connection,
+            owner: i64,
+            until: DateTime<Utc>,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, base_object_id, quote_object_id, price, source, occurrence_at, created_at, updated_at
+                FROM finance_price
+                WHERE owner = ?1 AND occurrence_at <= ?2
+                ORDER BY occurrence_at ASC, id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let prices = stmt
+                .query_map(params![owner, until], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(prices)
+        }
+
+        /// Returns, for every pair of the owner, the most recent price observed
+        /// at or before `at`.
+        pub fn select_latest_by_owner(
+            conn: &Connection,
+            owner: i64,
+            at: DateTime<Utc>,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT p.id, p.owner, p.base_object_id, p.quote_object_id, p.price, p.source, p.occurrence_at, p.created_at, p.updated_at
+                FROM finance_price p
+                WHERE p.owner = ?1 AND p.occurrence_at = (
+                    SELECT MAX(l.occurrence_at)
+                    FROM finance_price l
+                    WHERE l.base_object_id = p.base_object_id
+                      AND l.quote_object_id = p.quote_object_id
+                      AND l.occurrence_at <= ?2
+                )
+                ORDER BY p.base_object_id, p.quote_object_id;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let prices = stmt
+                .query_map(params![owner, at], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(prices)
+        }
+
+        /// Returns how many prices were deleted, none when the owner has no
+        /// price `id`.
+        pub fn delete_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<usize> {
+            let sql = r#"
+                DELETE FROM finance_price
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.execute(sql, params![id, owner])
+        }
+
+        /// Lists the distinct pairs of every trade, optionally limited to one owner.
+        pub fn select_tracked_pairs(
+            conn: &Connection,
+            owner: Option<i64>,
+        ) -> Result<Vec<TrackedPair>> {
+            let sql = r#"
+                SELECT DISTINCT t.owner, b.id, b.symbol, q.id, q.symbol
+                FROM finance_trade t
+                JOIN finance_object b ON b.id = t.base_object_id
+                JOIN finance_object q ON q.id = t.quote_object_id
+                WHERE ?1 IS NULL OR t.owner = ?1
+                ORDER BY t.owner, b.id, q.id;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let pairs = stmt
+                .query_map(params![owner], |row| {
+                    Ok(TrackedPair {
+                        owner: row.get(0)?,
+                        base_object_id: row.get(1)?,
+                        base_symbol: row.get(2)?,
+                        quote_object_id: row.get(3)?,
+                        quote_symbol: row.get(4)?,
+                    })
+                })?
+                .collect::<Result<Vec<TrackedPair>>>()?;
+
+            Ok(pairs)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::trade::Trade;
+    use crate::model::person::Person;
+    use crate::model::Model;
+
+    use super::Price;
+
+    fn setup() -> (PooledConnection<SqliteConnectionManager>, i64, i64, i64) {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(Person::initialize()).unwrap();
+        conn.execute_batch(Object::initialize()).unwrap();
+        conn.execute_batch(Trade::initialize()).unwrap();
+        conn.execute_batch(Price::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let btc = Object::insert(
+            &conn,
+            person.id(),
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let usdt = Object::insert(
+            &conn,
+            person.id(),
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+
+        (conn, person.id(), btc, usdt)
+    }
+
+    #[test]
+    fn test_upsert_replaces_same_instant() {
+        let (conn, owner, btc, usdt) = setup();
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        let first = Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(42000, 0).into(),
+            None,
+            at,
+        )
+        .unwrap();
+        let second = Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(43000, 0).into(),
+            Some("csv".to_string()),
+            at,
+        )
+        .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(Price::count_by_owner(&conn, owner).unwrap(), 1);
+
+        let prices = Price::select_by_owner(&conn, owner, 10, 0).unwrap();
+        assert_eq!(prices[0].price, Decimal::new(43000, 0).into());
+        assert_eq!(prices[0].source, Some("csv".to_string()));
+    }
+
+    #[test]
+    fn test_select_by_owner_newest_first() {
+        let (conn, owner, btc, usdt) = setup();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(1, 0).into(),
+            None,
+            day1,
+        )
+        .unwrap();
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(2, 0).into(),
+            None,
+            day2,
+        )
+        .unwrap();
+
+        let prices = Price::select_by_owner(&conn, owner, 10, 0).unwrap();
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[0].occurrence_at, day2);
+        assert_eq!(prices[1].occurrence_at, day1);
+
+        // Prices of other owners are not visible
+        assert!(Price::select_by_owner(&conn, owner + 1, 10, 0)
+            .unwrap()
+            .is_empty());
+
+        // Nor can they be deleted
+        let id = prices[0].id();
+        assert_eq!(Price::delete_by_id_owner(&conn, id, owner + 1).unwrap(), 0);
+        assert_eq!(Price::delete_by_id_owner(&conn, id, owner).unwrap(), 1);
+        assert_eq!(Price::delete_by_id_owner(&conn, id, owner).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_select_latest_by_owner() {
+        let (conn, owner, btc, usdt) = setup();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(1, 0).into(),
+            None,
+            day1,
+        )
+        .unwrap();
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(2, 0).into(),
+            None,
+            day2,
+        )
+        .unwrap();
+        Price::upsert(
+            &conn,
+            owner,
+            usdt,
+            btc,
+            Decimal::new(3, 0).into(),
+            None,
+            day2,
+        )
+        .unwrap();
+
+        let before = Utc.with_ymd_and_hms(2023, 12, 31, 0, 0, 0).unwrap();
+        assert!(Price::select_latest_by_owner(&conn, owner, before)
+            .unwrap()
+            .is_empty());
+
+        let prices = Price::select_latest_by_owner(&conn, owner, day1).unwrap();
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[0].price, Decimal::new(1, 0).into());
+
+        let prices = Price::select_latest_by_owner(&conn, owner, day2).unwrap();
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[0].price, Decimal::new(2, 0).into());
+        assert_eq!(prices[1].price, Decimal::new(3, 0).into());
+    }
+
+    #[test]
+    fn test_select_tracked_pairs() {
+        let (conn, owner, btc, usdt) = setup();
+
+        assert!(Price::select_tracked_pairs(&conn, None).unwrap().is_empty());
+
+        Trade::insert(&conn, owner, btc, usdt, None, None).unwrap();
+
+        let pairs = Price::select_tracked_pairs(&conn, Some(owner)).unwrap();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].base_symbol, "BTC");
+        assert_eq!(pairs[0].quote_symbol, "USDT");
+
+        assert!(Price::select_tracked_pairs(&conn, Some(owner + 1))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,377 @@
+use crate::model::finance::Entity;
+
+/// An object, trade or transaction whose symbol, alias or remark matches a
+/// search. Trades and transactions go by the symbols of their pair, as
+/// `BASE/QUOTE`.
+pub struct Hit {
+    pub kind: Entity,
+    /// The id of the object, trade or transaction.
+    pub id: i64,
+    pub symbol: String,
+    pub alias: Option<String>,
+    pub remark: Option<String>,
+    /// The best matching part of the text, escaped for HTML, with matches
+    /// in `<mark>` tags.
+    pub snippet: String,
+    /// How well the hit matches, lower being better.
+    pub rank: f64,
+}
+
+/// Turns the words of a search into an FTS5 query matching documents that
+/// have every word, or a word starting with it. Quoting each word keeps
+/// the query syntax out of reach. Returns `None` without any words.
+pub fn query(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Escapes a snippet for HTML and turns the control characters SQLite put
+/// around matches into `<mark>` tags, so those tags are the only markup a
+/// snippet carries, whatever the text it was taken from.
+fn highlight(snippet: &str) -> String {
+    let mut result = String::with_capacity(snippet.len());
+
+    for c in snippet.chars() {
+        match c {
+            '\u{2}' => result.push_str("<mark>"),
+            '\u{3}' => result.push_str("</mark>"),
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+mod database {
+    use rusqlite::params;
+    use rusqlite::Connection;
+
+    use crate::model::database::Result;
+    use crate::model::finance::Entity;
+
+    use super::Hit;
+
+    impl crate::model::Model for Hit {
+        // Documents sit at `id * 3` for objects, `id * 3 + 1` for trades
+        // and `id * 3 + 2` for transactions, so triggers reach them by
+        // rowid. Documents of existing rows are added when the index is
+        // empty, as it is when first created.
+        fn initialize() -> &'static str {
+            "
+                CREATE VIRTUAL TABLE IF NOT EXISTS finance_search USING fts5(
+                    symbol,
+                    alias,
+                    remark,
+                    kind UNINDEXED,
+                    entity_id UNINDEXED,
+                    owner UNINDEXED
+                );
+
+                CREATE VIEW IF NOT EXISTS finance_search_pair AS
+                SELECT t.id AS trade_id, b.symbol || '/' || q.symbol AS symbol
+                FROM finance_trade t
+                JOIN finance_object b ON b.id = t.base_object_id
+                JOIN finance_object q ON q.id = t.quote_object_id;
+
+                INSERT INTO finance_search (rowid, symbol, alias, remark, kind, entity_id, owner)
+                SELECT * FROM (
+                    SELECT id * 3, symbol, alias, remark, 'object', id, owner
+                    FROM finance_object
+                    UNION ALL
+                    SELECT t.id * 3 + 1, p.symbol, t.alias, t.remark, 'trade', t.id, t.owner
+                    FROM finance_trade t
+                    JOIN finance_search_pair p ON p.trade_id = t.id
+                    UNION ALL
+                    SELECT tx.id * 3 + 2, p.symbol, tx.alias, tx.remark, 'transaction', tx.id, t.owner
+                    FROM finance_trade_transaction tx
+                    JOIN finance_trade t ON t.id = tx.trade_id
+                    JOIN finance_search_pair p ON p.trade_id = t.id
+                )
+                WHERE NOT EXISTS (SELECT 1 FROM finance_search);
+
+                CREATE TRIGGER IF NOT EXISTS insert_finance_object_search
+                AFTER INSERT ON finance_object
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO finance_search (rowid, symbol, alias, remark, kind, entity_id, owner)
+                    VALUES (NEW.id * 3, NEW.symbol, NEW.alias, NEW.remark, 'object', NEW.id, NEW.owner);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_object_search
+                AFTER UPDATE OF symbol, alias, remark ON finance_object
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_search SET symbol = NEW.symbol, alias = NEW.alias, remark = NEW.remark
+                    WHERE rowid = NEW.id * 3;
+                    UPDATE finance_search SET symbol = (SELECT symbol FROM finance_search_pair WHERE trade_id = finance_search.entity_id)
+                    WHERE rowid IN (SELECT id * 3 + 1 FROM finance_trade WHERE NEW.id IN (base_object_id, quote_object_id));
+                    UPDATE finance_search SET symbol = (SELECT p.symbol FROM finance_trade_transaction tx JOIN finance_search_pair p ON p.trade_id = tx.trade_id WHERE tx.id = finance_search.entity_id)
+                    WHERE rowid IN (SELECT tx.id * 3 + 2 FROM finance_trade_transaction tx JOIN finance_trade t ON t.id = tx.trade_id WHERE NEW.id IN (t.base_object_id, t.quote_object_id));
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS delete_finance_object_search
+                AFTER DELETE ON finance_object
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_search WHERE rowid = OLD.id * 3;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS insert_finance_trade_search
+                AFTER INSERT ON finance_trade
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO finance_search (rowid, symbol, alias, remark, kind, entity_id, owner)
+                    SELECT NEW.id * 3 + 1, symbol, NEW.alias, NEW.remark, 'trade', NEW.id, NEW.owner
+                    FROM finance_search_pair WHERE trade_id = NEW.id;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_trade_search
+                AFTER UPDATE OF base_object_id, quote_object_id, alias, remark ON finance_trade
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_search SET symbol = (SELECT symbol FROM finance_search_pair WHERE trade_id = NEW.id), alias = NEW.alias, remark = NEW.remark
+                    WHERE rowid = NEW.id * 3 + 1;
+                    UPDATE finance_search SET symbol = (SELECT symbol FROM finance_search_pair WHERE trade_id = NEW.id)
+                    WHERE rowid IN (SELECT id * 3 + 2 FROM finance_trade_transaction WHERE trade_id = NEW.id);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS delete_finance_trade_search
+                AFTER DELETE ON finance_trade
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_search WHERE rowid = OLD.id * 3 + 1;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS insert_finance_trade_transaction_search
+                AFTER INSERT ON finance_trade_transaction
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO finance_search (rowid, symbol, alias, remark, kind, entity_id, owner)
+                    SELECT NEW.id * 3 + 2, p.symbol, NEW.alias, NEW.remark, 'transaction', NEW.id, t.owner
+                    FROM finance_trade t JOIN finance_search_pair p ON p.trade_id = t.id WHERE t.id = NEW.trade_id;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_trade_transaction_search
+                AFTER UPDATE OF trade_id, alias, remark ON finance_trade_transaction
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_search SET symbol = (SELECT symbol FROM finance_search_pair WHERE trade_id = NEW.trade_id), alias = NEW.alias, remark = NEW.remark
+                    WHERE rowid = NEW.id * 3 + 2;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS delete_finance_trade_transaction_search
+                AFTER DELETE ON finance_trade_transaction
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_search WHERE rowid = OLD.id * 3 + 2;
+                END;
+            "
+        }
+    }
+
+    impl Hit {
+        /// Counts the documents of the owner matching an FTS5 query, as
+        /// made by `query`.
+        pub fn count(
+            conn: &Connection,
+            owner: i64,
+            query: &str,
+            kind: Option<Entity>,
+        ) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_search
+                WHERE finance_search MATCH ?1 AND owner = ?2 AND (?3 IS NULL OR kind = ?3);
+            "#;
+
+            conn.query_row(sql, params![query, owner, kind], |row| row.get(0))
+        }
+
+        /// Returns the documents of the owner matching an FTS5 query, best
+        /// first. Matches in the symbol weigh more than in the alias, and
+        /// those more than in the remark.
+        pub fn search(
+            conn: &Connection,
+            owner: i64,
+            query: &str,
+            kind: Option<Entity>,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT kind, entity_id, symbol, alias, remark, snippet(finance_search, -1, char(2), char(3), '…', 16), bm25(finance_search, 4.0, 2.0, 1.0) AS score
+                FROM finance_search
+                WHERE finance_search MATCH ?1 AND owner = ?2 AND (?3 IS NULL OR kind = ?3)
+                ORDER BY score, rowid
+                LIMIT ?4 OFFSET ?5;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let hits = stmt
+                .query_map(params![query, owner, kind, limit, offset], |row| {
+                    Ok(Self {
+                        kind: row.get(0)?,
+                        id: row.get(1)?,
+                        symbol: row.get(2)?,
+                        alias: row.get(3)?,
+                        remark: row.get(4)?,
+                        snippet: super::highlight(&row.get::<_, String>(5)?),
+                        rank: row.get(6)?,
+                    })
+                })?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(hits)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::Entity;
+    use crate::model::fixture;
+    use crate::model::Model;
+
+    use super::{highlight, query, Hit};
+
+    #[test]
+    fn test_query() {
+        assert_eq!(query("  btc  dca "), Some("\"btc\"* \"dca\"*".to_string()));
+        assert_eq!(
+            query("say \"hi\" OR"),
+            Some("\"say\"* \"\"\"hi\"\"\"* \"OR\"*".to_string())
+        );
+        assert_eq!(query(" "), None);
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("a \u{2}<script>\u{3} & \"b\""),
+            "a <mark>&lt;script&gt;</mark> &amp; &quot;b&quot;"
+        );
+        assert_eq!(highlight("it's"), "it&#39;s");
+    }
+
+    #[test]
+    fn test_search() {
+        let conn = fixture::connection();
+        let owner = fixture::person(&conn, "test_user");
+        let other = fixture::person(&conn, "other_user");
+
+        let object = |owner, symbol: &str, remark: Option<&str>| {
+            let remark = remark.map(|remark| remark.to_string());
+            let classification = Classification::default();
+            Object::insert(
+                &conn,
+                owner,
+                symbol.to_string(),
+                None,
+                remark,
+                classification,
+            )
+            .unwrap()
+        };
+        let btc = object(owner, "BTC", Some("Bitcoin held for the long run"));
+        let usd = object(owner, "USD", None);
+        object(other, "BTC", Some("Bitcoin of someone else"));
+
+        let account = fixture::account(&conn, owner, "exchange");
+        let trade = fixture::trade(&conn, owner, btc, usd);
+        let remarks = ["Monthly DCA", "bought the dip", "DCA after payday"];
+        let transactions: Vec<i64> = remarks
+            .iter()
+            .map(|remark| {
+                Transaction::insert(
+                    &conn,
+                    trade,
+                    account,
+                    rust_decimal::Decimal::ONE.into(),
+                    None,
+                    None,
+                    None,
+                    false,
+                    Status::Settled,
+                    None,
+                    None,
+                    Some(remark.to_string()),
+                    None,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let search = |text: &str, kind| {
+            let query = query(text).unwrap();
+            Hit::search(&conn, owner, &query, kind, 10, 0).unwrap()
+        };
+
+        // Hits of others stay out
+        let hits = search("bitcoin", None);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].kind, hits[0].id), (Entity::Object, btc));
+        assert_eq!(
+            hits[0].snippet,
+            "<mark>Bitcoin</mark> held for the long run"
+        );
+
+        // Remarks lengthen transactions, which rank below the trade
+        let hits = search("btc", None);
+        assert_eq!(hits.len(), 5);
+        assert_eq!((hits[0].kind, hits[0].id), (Entity::Trade, trade));
+        assert_eq!(hits[0].symbol, "BTC/USD");
+        assert!(hits[0].rank < hits[2].rank);
+        assert!(hits[2..].iter().all(|hit| hit.kind != Entity::Trade));
+
+        let hits = search("dc", Some(Entity::Transaction));
+        let ids: Vec<i64> = hits.iter().map(|hit| hit.id).collect();
+        assert_eq!(ids, vec![transactions[0], transactions[2]]);
+        assert_eq!(
+            Hit::count(&conn, owner, &query("dca usd").unwrap(), None).unwrap(),
+            2
+        );
+
+        // Triggers follow updates and deletes
+        conn.execute(
+            "UPDATE finance_object SET symbol = 'XBT' WHERE id = ?1",
+            [btc],
+        )
+        .unwrap();
+        conn.execute(
+            "DELETE FROM finance_trade_transaction WHERE id = ?1",
+            [transactions[0]],
+        )
+        .unwrap();
+        assert_eq!(search("btc", None).len(), 0);
+        let hits = search("xbt dca", None);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].symbol, "XBT/USD");
+        assert_eq!(hits[0].remark.as_deref(), Some("DCA after payday"));
+
+        // Rows from before the index are added when it is created
+        conn.execute_batch("DROP TABLE finance_search;").unwrap();
+        conn.execute_batch(Hit::initialize()).unwrap();
+        assert_eq!(search("xbt", None).len(), 4);
+        conn.execute_batch(Hit::initialize()).unwrap();
+        assert_eq!(search("xbt", None).len(), 4);
+
+        // Markup in the text comes back escaped around the matches
+        object(owner, "EVIL", Some("<script>alert(1)</script> & more"));
+        let hits = search("alert", None);
+        assert_eq!(
+            hits[0].snippet,
+            "&lt;script&gt;<mark>alert</mark>(1)&lt;/script&gt; &amp; more"
+        );
+    }
+}
@@ -0,0 +1,411 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+/// The cached net worth of an owner, valued in one object, at one instant.
+///
+/// Snapshots are dropped by triggers whenever a transaction, trade, flow,
+/// corporate action or price that could change them is written, so a stored
+/// snapshot is always current.
+pub struct Snapshot {
+    pub at: DateTime<Utc>,
+    pub total: Decimal,
+    pub unpriced: usize,
+}
+
+mod database {
+    use chrono::{DateTime, Utc};
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::Row;
+    use rust_decimal::Decimal;
+
+    use crate::model::database::Result;
+    use crate::model::finance::Quantity;
+
+    impl crate::model::Model for super::Snapshot {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_snapshot (
+                    id          INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner       INTEGER  NOT NULL,
+                    object_id   INTEGER  NOT NULL,
+                    at          DATETIME NOT NULL,
+                    total       TEXT     NOT NULL,
+                    unpriced    INTEGER  NOT NULL,
+                    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    FOREIGN KEY(object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    UNIQUE(owner, object_id, at)
+                );
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_transaction_insert
+                AFTER INSERT ON finance_trade_transaction
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = (SELECT owner FROM finance_trade WHERE id = NEW.trade_id)
+                      AND at >= NEW.occurrence_at;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_transaction_update
+                AFTER UPDATE ON finance_trade_transaction
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = (SELECT owner FROM finance_trade WHERE id = NEW.trade_id)
+                      AND at >= MIN(OLD.occurrence_at, NEW.occurrence_at);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_transaction_delete
+                AFTER DELETE ON finance_trade_transaction
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = (SELECT owner FROM finance_trade WHERE id = OLD.trade_id)
+                      AND at >= OLD.occurrence_at;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_trade_update
+                AFTER UPDATE OF base_object_id, quote_object_id ON finance_trade
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot WHERE owner = NEW.owner;
+                END;
+
+                -- Transactions and actions deleted along with their trade or
+                -- object no longer find an owner to invalidate, so the parent
+                -- invalidates in their place
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_trade_delete
+                AFTER DELETE ON finance_trade
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot WHERE owner = OLD.owner;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_object_delete
+                AFTER DELETE ON finance_object
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot WHERE owner = OLD.owner;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_flow_insert
+                AFTER INSERT ON finance_flow
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = NEW.owner AND at >= NEW.occurrence_at;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_flow_update
+                AFTER UPDATE ON finance_flow
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = NEW.owner AND at >= MIN(OLD.occurrence_at, NEW.occurrence_at);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_flow_delete
+                AFTER DELETE ON finance_flow
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = OLD.owner AND at >= OLD.occurrence_at;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_action_insert
+                AFTER INSERT ON finance_object_action
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = (SELECT owner FROM finance_object WHERE id = NEW.object_id)
+                      AND at >= NEW.effective_at;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_action_update
+                AFTER UPDATE ON finance_object_action
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = (SELECT owner FROM finance_object WHERE id = NEW.object_id)
+                      AND at >= MIN(OLD.effective_at, NEW.effective_at);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_action_delete
+                AFTER DELETE ON finance_object_action
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = (SELECT owner FROM finance_object WHERE id = OLD.object_id)
+                      AND at >= OLD.effective_at;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_price_insert
+                AFTER INSERT ON finance_price
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = NEW.owner AND at >= NEW.occurrence_at;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_price_update
+                AFTER UPDATE OF price, occurrence_at ON finance_price
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = NEW.owner AND at >= MIN(OLD.occurrence_at, NEW.occurrence_at);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invalidate_finance_snapshot_on_price_delete
+                AFTER DELETE ON finance_price
+                FOR EACH ROW
+                BEGIN
+                    DELETE FROM finance_snapshot
+                    WHERE owner = OLD.owner AND at >= OLD.occurrence_at;
+                END;
+            "
+        }
+    }
+
+    impl super::Snapshot {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            let total: Quantity = row.get(1)?;
+
+            Ok(Self {
+                at: row.get(0)?,
+                total: total.value(),
+                unpriced: row.get(2)?,
+            })
+        }
+
+        pub fn upsert(
+            conn: &Connection,
+            owner: i64,
+            object_id: i64,
+            at: DateTime<Utc>,
+            total: Decimal,
+            unpriced: usize,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_snapshot (owner, object_id, at, total, unpriced)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT(owner, object_id, at)
+                DO UPDATE SET total = excluded.total, unpriced = excluded.unpriced
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![owner, object_id, at, Quantity::from(total), unpriced],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn select_by_owner_object_id_between(
+            conn: &Connection,
+            owner: i64,
+            object_id: i64,
+            from: DateTime<Utc>,
+            to: DateTime<Utc>,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT at, total, unpriced
+                FROM finance_snapshot
+                WHERE owner = ?1 AND object_id = ?2 AND at >= ?3 AND at <= ?4
+                ORDER BY at ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let snapshots = stmt
+                .query_map(params![owner, object_id, from, to], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(snapshots)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::account::Account;
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::price::Price;
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::person::Person;
+
+    use super::Snapshot;
+
+    type Setup = (
+        PooledConnection<SqliteConnectionManager>,
+        i64,
+        i64,
+        i64,
+        i64,
+        i64,
+    );
+
+    fn setup() -> Setup {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+        let btc = Object::insert(
+            &conn,
+            owner,
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let usdt = Object::insert(
+            &conn,
+            owner,
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let trade = Trade::insert(&conn, owner, btc, usdt, None, None).unwrap();
+        let account = Account::insert(&conn, owner, "Exchange".to_string(), None, None).unwrap();
+
+        (conn, owner, btc, usdt, trade, account)
+    }
+
+    fn count(conn: &rusqlite::Connection, owner: i64, object_id: i64) -> usize {
+        let from = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2100, 1, 1, 0, 0, 0).unwrap();
+
+        Snapshot::select_by_owner_object_id_between(conn, owner, object_id, from, to)
+            .unwrap()
+            .len()
+    }
+
+    #[test]
+    fn test_upsert_and_select() {
+        let (conn, owner, _btc, usdt, _trade, _account) = setup();
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        Snapshot::upsert(&conn, owner, usdt, at, Decimal::new(-15, 1), 2).unwrap();
+        Snapshot::upsert(&conn, owner, usdt, at, Decimal::new(25, 1), 1).unwrap();
+
+        let snapshots =
+            Snapshot::select_by_owner_object_id_between(&conn, owner, usdt, at, at).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].total, Decimal::new(25, 1));
+        assert_eq!(snapshots[0].unpriced, 1);
+    }
+
+    #[test]
+    fn test_invalidated_from_transaction_onwards() {
+        let (conn, owner, _btc, usdt, trade, account) = setup();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let day3 = Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap();
+
+        for at in [day1, day2, day3] {
+            Snapshot::upsert(&conn, owner, usdt, at, Decimal::ZERO, 0).unwrap();
+        }
+
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::new(1, 0).into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(day2),
+        )
+        .unwrap();
+
+        // Only the snapshot before the transaction survives
+        assert_eq!(count(&conn, owner, usdt), 1);
+    }
+
+    #[test]
+    fn test_invalidated_by_trade_delete() {
+        let (conn, owner, _btc, usdt, trade, account) = setup();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::new(1, 0).into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(day1),
+        )
+        .unwrap();
+        Snapshot::upsert(&conn, owner, usdt, day2, Decimal::ONE, 0).unwrap();
+
+        // The transaction goes with the trade, and the snapshot with it
+        Trade::delete_by_id_owner(&conn, trade, owner).unwrap();
+        assert_eq!(count(&conn, owner, usdt), 0);
+    }
+
+    #[test]
+    fn test_invalidated_by_price() {
+        let (conn, owner, btc, usdt, _trade, _account) = setup();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+
+        Snapshot::upsert(&conn, owner, usdt, day1, Decimal::ZERO, 0).unwrap();
+        Snapshot::upsert(&conn, owner, usdt, day2, Decimal::ZERO, 0).unwrap();
+
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(1, 0).into(),
+            None,
+            day2,
+        )
+        .unwrap();
+        assert_eq!(count(&conn, owner, usdt), 1);
+
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(2, 0).into(),
+            None,
+            day1,
+        )
+        .unwrap();
+        assert_eq!(count(&conn, owner, usdt), 0);
+    }
+}
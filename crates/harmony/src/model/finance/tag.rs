@@ -0,0 +1,370 @@
+use chrono::{DateTime, Utc};
+
+/// A label of the owner's choosing, attached to any number of objects,
+/// trades and transactions.
+pub struct Tag {
+    id: i64,
+    pub owner: i64,
+    pub name: String,
+    /// As `#rrggbb`.
+    pub color: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Tag {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+/// Whether a color is written as `#rrggbb`.
+pub fn is_color(text: &str) -> bool {
+    text.len() == 7 && text.starts_with('#') && text[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+mod database {
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+    use crate::model::finance::Entity;
+
+    /// The table linking tags to an entity, and its column for the entity.
+    fn links(entity: Entity) -> (&'static str, &'static str) {
+        match entity {
+            Entity::Object => ("finance_object_tag", "object_id"),
+            Entity::Trade => ("finance_trade_tag", "trade_id"),
+            Entity::Transaction => ("finance_trade_transaction_tag", "transaction_id"),
+        }
+    }
+
+    impl crate::model::Model for super::Tag {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_tag (
+                    id          INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner       INTEGER  NOT NULL,
+                    name        TEXT     NOT NULL,
+                    color       TEXT     NOT NULL CHECK (length(color) = 7 AND color GLOB '#[0-9a-fA-F][0-9a-fA-F][0-9a-fA-F][0-9a-fA-F][0-9a-fA-F][0-9a-fA-F]'),
+                    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    UNIQUE(owner, name)
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_tag_updated_at
+                AFTER UPDATE ON finance_tag
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_tag SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE INDEX IF NOT EXISTS idx_finance_tag_owner ON finance_tag(owner);
+
+                CREATE TABLE IF NOT EXISTS finance_object_tag (
+                    object_id   INTEGER  NOT NULL,
+                    tag_id      INTEGER  NOT NULL,
+                    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    PRIMARY KEY(object_id, tag_id),
+                    FOREIGN KEY(object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    FOREIGN KEY(tag_id) REFERENCES finance_tag(id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_finance_object_tag_tag_id ON finance_object_tag(tag_id);
+
+                CREATE TABLE IF NOT EXISTS finance_trade_tag (
+                    trade_id    INTEGER  NOT NULL,
+                    tag_id      INTEGER  NOT NULL,
+                    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    PRIMARY KEY(trade_id, tag_id),
+                    FOREIGN KEY(trade_id) REFERENCES finance_trade(id) ON DELETE CASCADE,
+                    FOREIGN KEY(tag_id) REFERENCES finance_tag(id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_finance_trade_tag_tag_id ON finance_trade_tag(tag_id);
+
+                CREATE TABLE IF NOT EXISTS finance_trade_transaction_tag (
+                    transaction_id  INTEGER  NOT NULL,
+                    tag_id          INTEGER  NOT NULL,
+                    created_at      DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    PRIMARY KEY(transaction_id, tag_id),
+                    FOREIGN KEY(transaction_id) REFERENCES finance_trade_transaction(id) ON DELETE CASCADE,
+                    FOREIGN KEY(tag_id) REFERENCES finance_tag(id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_finance_trade_transaction_tag_tag_id ON finance_trade_transaction_tag(tag_id);
+            "
+        }
+    }
+
+    impl super::Tag {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                name: row.get(2)?,
+                color: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        }
+
+        pub fn insert(conn: &Connection, owner: i64, name: String, color: String) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_tag (owner, name, color)
+                VALUES (?1, ?2, ?3)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(sql, params![owner, name, color], |row| row.get(0))?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_tag
+                WHERE owner = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![owner], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, name, color, created_at, updated_at
+                FROM finance_tag
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, owner], Self::from_row)
+                .optional()
+        }
+
+        pub fn select_by_owner_name(
+            conn: &Connection,
+            owner: i64,
+            name: &str,
+        ) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, name, color, created_at, updated_at
+                FROM finance_tag
+                WHERE owner = ?1 AND name = ?2;
+            "#;
+
+            conn.query_row(sql, params![owner, name], Self::from_row)
+                .optional()
+        }
+
+        /// Returns tags of the owner by name.
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, name, color, created_at, updated_at
+                FROM finance_tag
+                WHERE owner = ?1
+                ORDER BY name, id
+                LIMIT ?2 OFFSET ?3;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let tags = stmt
+                .query_map(params![owner, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(tags)
+        }
+
+        /// Returns the tags attached to an object, trade or transaction by
+        /// name.
+        pub fn select_by_entity(
+            conn: &Connection,
+            entity: Entity,
+            entity_id: i64,
+        ) -> Result<Vec<Self>> {
+            let (table, column) = links(entity);
+            let sql = format!(
+                r#"
+                    SELECT t.id, t.owner, t.name, t.color, t.created_at, t.updated_at
+                    FROM finance_tag t
+                    JOIN {} l ON l.tag_id = t.id
+                    WHERE l.{} = ?1
+                    ORDER BY t.name, t.id;
+                "#,
+                table, column
+            );
+
+            let mut stmt = conn.prepare(&sql)?;
+            let tags = stmt
+                .query_map(params![entity_id], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(tags)
+        }
+
+        pub fn update_by_id_owner(
+            conn: &Connection,
+            id: i64,
+            owner: i64,
+            name: String,
+            color: String,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_tag
+                SET name = ?1, color = ?2
+                WHERE id = ?3 AND owner = ?4;
+            "#;
+
+            conn.execute(sql, params![name, color, id, owner])?;
+
+            Ok(())
+        }
+
+        /// Deletes a tag, detaching it from everything it was attached to.
+        pub fn delete_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_tag
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.execute(sql, params![id, owner])?;
+
+            Ok(())
+        }
+
+        /// Attaches a tag to an object, trade or transaction, doing nothing
+        /// when it is attached already.
+        pub fn attach(conn: &Connection, id: i64, entity: Entity, entity_id: i64) -> Result<()> {
+            let (table, column) = links(entity);
+            let sql = format!(
+                r#"
+                    INSERT OR IGNORE INTO {} ({}, tag_id)
+                    VALUES (?1, ?2);
+                "#,
+                table, column
+            );
+
+            conn.execute(&sql, params![entity_id, id])?;
+
+            Ok(())
+        }
+
+        pub fn detach(conn: &Connection, id: i64, entity: Entity, entity_id: i64) -> Result<()> {
+            let (table, column) = links(entity);
+            let sql = format!(
+                r#"
+                    DELETE FROM {}
+                    WHERE {} = ?1 AND tag_id = ?2;
+                "#,
+                table, column
+            );
+
+            conn.execute(&sql, params![entity_id, id])?;
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+
+    use crate::model::finance::object::{Filter, Object};
+    use crate::model::finance::Entity;
+    use crate::model::fixture;
+    use crate::model::person::Person;
+
+    use super::{is_color, Tag};
+
+    fn setup() -> (PooledConnection<SqliteConnectionManager>, i64) {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+
+        (conn, person.id())
+    }
+
+    #[test]
+    fn test_is_color() {
+        assert!(is_color("#1a2B3c"));
+        assert!(!is_color("1a2B3c"));
+        assert!(!is_color("#1a2B3"));
+        assert!(!is_color("#1a2B3g"));
+        assert!(!is_color("#1a2B3ü"));
+    }
+
+    #[test]
+    fn test_insert_attach_delete() {
+        let (conn, owner) = setup();
+        let (btc, eth) = (
+            fixture::object(&conn, owner, "BTC"),
+            fixture::object(&conn, owner, "ETH"),
+        );
+
+        let long =
+            Tag::insert(&conn, owner, "long term".to_string(), "#00ff00".to_string()).unwrap();
+        let core = Tag::insert(&conn, owner, "core".to_string(), "#0000ff".to_string()).unwrap();
+        assert!(Tag::insert(&conn, owner, "core".to_string(), "#000000".to_string()).is_err());
+        assert!(Tag::insert(&conn, owner, "red".to_string(), "red".to_string()).is_err());
+
+        Tag::attach(&conn, long, Entity::Object, btc).unwrap();
+        Tag::attach(&conn, long, Entity::Object, btc).unwrap();
+        Tag::attach(&conn, core, Entity::Object, btc).unwrap();
+        Tag::attach(&conn, core, Entity::Object, eth).unwrap();
+
+        let names = |entity_id| -> Vec<String> {
+            Tag::select_by_entity(&conn, Entity::Object, entity_id)
+                .unwrap()
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect()
+        };
+        assert_eq!(names(btc), vec!["core", "long term"]);
+
+        let tagged = |tag_id| {
+            let filter = Filter {
+                tag_id: Some(tag_id),
+                ..Filter::default()
+            };
+            Object::count_by_owner(&conn, owner, &filter).unwrap()
+        };
+        assert_eq!((tagged(long), tagged(core)), (1, 2));
+
+        Tag::detach(&conn, core, Entity::Object, btc).unwrap();
+        assert_eq!(names(btc), vec!["long term"]);
+
+        Tag::delete_by_id_owner(&conn, core, owner).unwrap();
+        assert!(names(eth).is_empty());
+        assert_eq!(Tag::count_by_owner(&conn, owner).unwrap(), 1);
+
+        // Deleting what a tag is attached to detaches it
+        Object::delete_by_id_owner(&conn, btc, owner).unwrap();
+        let count: usize = conn
+            .query_row("SELECT COUNT(*) FROM finance_object_tag", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,277 @@
+use chrono::{DateTime, Utc};
+
+use crate::model::finance::object::AssetType;
+use crate::model::finance::Quantity;
+
+/// The share of net worth a person aims to hold in one object, or in every
+/// object of an asset type that has no target of its own.
+pub struct Target {
+    id: i64,
+    pub owner: i64,
+    pub object_id: Option<i64>,
+    pub asset_type: Option<AssetType>,
+    /// Share of the total, between 0 and 1.
+    pub weight: Quantity,
+    /// How far the actual share may stray either way before rebalancing.
+    pub tolerance: Quantity,
+    pub remark: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Target {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+mod database {
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+    use crate::model::finance::object::AssetType;
+    use crate::model::finance::Quantity;
+
+    impl crate::model::Model for super::Target {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_target (
+                    id          INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner       INTEGER  NOT NULL,
+                    object_id   INTEGER,
+                    asset_type  TEXT     CHECK (asset_type IN ('fiat', 'crypto', 'equity', 'fund', 'bond', 'commodity', 'other')),
+                    weight      TEXT     NOT NULL,
+                    tolerance   TEXT     NOT NULL,
+                    remark      TEXT,
+                    created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    FOREIGN KEY(object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    CHECK ((object_id IS NULL) <> (asset_type IS NULL)),
+                    UNIQUE(owner, object_id),
+                    UNIQUE(owner, asset_type)
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_target_updated_at
+                AFTER UPDATE ON finance_target
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_target SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE INDEX IF NOT EXISTS idx_finance_target_owner ON finance_target(owner);
+            "
+        }
+    }
+
+    impl super::Target {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                object_id: row.get(2)?,
+                asset_type: row.get(3)?,
+                weight: row.get(4)?,
+                tolerance: row.get(5)?,
+                remark: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+            })
+        }
+
+        pub fn insert(
+            conn: &Connection,
+            owner: i64,
+            object_id: Option<i64>,
+            asset_type: Option<AssetType>,
+            weight: Quantity,
+            tolerance: Quantity,
+            remark: Option<String>,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_target (owner, object_id, asset_type, weight, tolerance, remark)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![owner, object_id, asset_type, weight, tolerance, remark],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_target
+                WHERE owner = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![owner], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, object_id, asset_type, weight, tolerance, remark, created_at, updated_at
+                FROM finance_target
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, owner], Self::from_row)
+                .optional()
+        }
+
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, object_id, asset_type, weight, tolerance, remark, created_at, updated_at
+                FROM finance_target
+                WHERE owner = ?1
+                ORDER BY id
+                LIMIT ?2 OFFSET ?3;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let targets = stmt
+                .query_map(params![owner, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(targets)
+        }
+
+        pub fn select_all_by_owner(conn: &Connection, owner: i64) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, object_id, asset_type, weight, tolerance, remark, created_at, updated_at
+                FROM finance_target
+                WHERE owner = ?1
+                ORDER BY id;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let targets = stmt
+                .query_map(params![owner], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(targets)
+        }
+
+        pub fn update_by_id_owner(
+            conn: &Connection,
+            id: i64,
+            owner: i64,
+            weight: Quantity,
+            tolerance: Quantity,
+            remark: Option<String>,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_target
+                SET weight = ?1, tolerance = ?2, remark = ?3
+                WHERE id = ?4 AND owner = ?5;
+            "#;
+
+            conn.execute(sql, params![weight, tolerance, remark, id, owner])?;
+
+            Ok(())
+        }
+
+        pub fn delete_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_target
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.execute(sql, params![id, owner])?;
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::object::{AssetType, Classification, Object};
+    use crate::model::person::Person;
+
+    use super::Target;
+
+    fn setup() -> (PooledConnection<SqliteConnectionManager>, i64, i64) {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+        let btc = Object::insert(
+            &conn,
+            owner,
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+
+        (conn, owner, btc)
+    }
+
+    #[test]
+    fn test_one_target_per_object_or_asset_type() {
+        let (conn, owner, btc) = setup();
+        let weight = || Decimal::new(5, 1).into();
+        let tolerance = || Decimal::new(5, 2).into();
+
+        Target::insert(&conn, owner, Some(btc), None, weight(), tolerance(), None).unwrap();
+        Target::insert(
+            &conn,
+            owner,
+            None,
+            Some(AssetType::Fiat),
+            weight(),
+            tolerance(),
+            None,
+        )
+        .unwrap();
+
+        assert!(
+            Target::insert(&conn, owner, Some(btc), None, weight(), tolerance(), None).is_err()
+        );
+        assert!(Target::insert(&conn, owner, None, None, weight(), tolerance(), None).is_err());
+        assert!(Target::insert(
+            &conn,
+            owner,
+            Some(btc),
+            Some(AssetType::Crypto),
+            weight(),
+            tolerance(),
+            None
+        )
+        .is_err());
+
+        let targets = Target::select_all_by_owner(&conn, owner).unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[1].asset_type, Some(AssetType::Fiat));
+    }
+}
@@ -1,6 +1,8 @@
+pub mod plan;
 pub mod transaction;
 
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 pub struct Trade {
     id: i64,
@@ -19,12 +21,69 @@ impl Trade {
     }
 }
 
+/// Narrows the trades of an owner down in listings.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub base_object_id: Option<i64>,
+    pub quote_object_id: Option<i64>,
+    /// Trades created at or after.
+    pub from: Option<DateTime<Utc>>,
+    /// Trades created at or before.
+    pub to: Option<DateTime<Utc>>,
+    /// Found in the alias or the remark, ignoring ASCII case.
+    pub text: Option<String>,
+    /// Trades the tag is attached to.
+    pub tag_id: Option<i64>,
+}
+
+/// The columns trades are listed by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sort {
+    #[default]
+    Id,
+    BaseObjectId,
+    QuoteObjectId,
+    Alias,
+    Remark,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl Sort {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::BaseObjectId => "base_object_id",
+            Self::QuoteObjectId => "quote_object_id",
+            Self::Alias => "alias",
+            Self::Remark => "remark",
+            Self::CreatedAt => "created_at",
+            Self::UpdatedAt => "updated_at",
+        }
+    }
+}
+
 mod database {
     use rusqlite::params;
     use rusqlite::Connection;
     use rusqlite::OptionalExtension;
 
-    use crate::model::database::Result;
+    use crate::model::database::{Order, Result};
+
+    use super::{Filter, Sort};
+
+    /// The conditions of `Filter`, bound from `?2` to `?7`.
+    const FILTER: &str = r#"
+        (?2 IS NULL OR base_object_id = ?2)
+        AND (?3 IS NULL OR quote_object_id = ?3)
+        AND (?4 IS NULL OR created_at >= ?4)
+        AND (?5 IS NULL OR created_at <= ?5)
+        AND (?6 IS NULL OR instr(lower(coalesce(alias, '')), lower(?6)) > 0
+            OR instr(lower(coalesce(remark, '')), lower(?6)) > 0)
+        AND (?7 IS NULL OR EXISTS (SELECT 1 FROM finance_trade_tag
+            WHERE trade_id = finance_trade.id AND tag_id = ?7))
+    "#;
 
     impl crate::model::Model for super::Trade {
         fn initialize() -> &'static str {
@@ -40,7 +99,8 @@ mod database {
                     updated_at       DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
                     FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
                     FOREIGN KEY(base_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
-                    FOREIGN KEY(quote_object_id) REFERENCES finance_object(id) ON DELETE CASCADE
+                    FOREIGN KEY(quote_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    CHECK (base_object_id <> quote_object_id)
                 );
 
                 CREATE TRIGGER IF NOT EXISTS update_finance_trade_updated_at
@@ -51,6 +111,7 @@ mod database {
                 END;
 
                 CREATE INDEX IF NOT EXISTS idx_finance_trade_owner ON finance_trade(owner);
+                CREATE INDEX IF NOT EXISTS idx_finance_trade_owner_pair ON finance_trade(owner, base_object_id, quote_object_id);
             "
         }
     }
@@ -79,14 +140,80 @@ mod database {
             Ok(id)
         }
 
-        pub fn count_by_owner(conn: &Connection, owner: i64) -> Result<usize> {
+        /// Creates the trade of the owner for a pair unless there is one
+        /// already, in one statement so that two callers cannot both create
+        /// it. Returns the id, and whether the trade was created.
+        pub fn insert_or_select_by_owner_pair(
+            conn: &Connection,
+            owner: i64,
+            base_object_id: i64,
+            quote_object_id: i64,
+            alias: Option<String>,
+            remark: Option<String>,
+        ) -> Result<(i64, bool)> {
             let sql = r#"
-                SELECT COUNT(*)
-                FROM finance_trade
-                WHERE owner = ?1;
+                INSERT INTO finance_trade (owner, base_object_id, quote_object_id, alias, remark)
+                SELECT ?1, ?2, ?3, ?4, ?5
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM finance_trade
+                    WHERE owner = ?1 AND base_object_id = ?2 AND quote_object_id = ?3
+                )
+                RETURNING id;
             "#;
 
-            let count = conn.query_row(sql, params![owner], |row| row.get(0))?;
+            let id = conn
+                .query_row(
+                    sql,
+                    params![owner, base_object_id, quote_object_id, alias, remark],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            match id {
+                Some(id) => Ok((id, true)),
+                None => {
+                    let trade =
+                        Self::select_by_owner_pair(conn, owner, base_object_id, quote_object_id)?
+                            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+                    Ok((trade.id(), false))
+                }
+            }
+        }
+
+        /// Has the database refuse a second trade of an owner for a pair, or
+        /// allow it again. Fails to refuse while an owner has such trades.
+        pub fn set_unique_pair(conn: &Connection, is_unique: bool) -> Result<()> {
+            let sql = match is_unique {
+                true => "CREATE UNIQUE INDEX IF NOT EXISTS idx_finance_trade_owner_pair_unique ON finance_trade(owner, base_object_id, quote_object_id);",
+                false => "DROP INDEX IF EXISTS idx_finance_trade_owner_pair_unique;",
+            };
+
+            conn.execute_batch(sql)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64, filter: &Filter) -> Result<usize> {
+            let sql = format!(
+                r#"
+                    SELECT COUNT(*)
+                    FROM finance_trade
+                    WHERE owner = ?1 AND {};
+                "#,
+                FILTER
+            );
+
+            let count = conn.query_row(
+                &sql,
+                params![
+                    owner,
+                    filter.base_object_id,
+                    filter.quote_object_id,
+                    filter.from,
+                    filter.to,
+                    filter.text,
+                    filter.tag_id,
+                ],
+                |row| row.get(0),
+            )?;
 
             Ok(count)
         }
@@ -98,8 +225,84 @@ mod database {
                 WHERE id = ?1 AND owner = ?2;
             "#;
 
-            let result = conn
-                .query_row(sql, params![id, owner], |row| {
+            conn.query_row(sql, params![id, owner], |row| {
+                Ok(Self {
+                    id: row.get(0)?,
+                    owner: row.get(1)?,
+                    base_object_id: row.get(2)?,
+                    quote_object_id: row.get(3)?,
+                    alias: row.get(4)?,
+                    remark: row.get(5)?,
+                    created_at: row.get(6)?,
+                    updated_at: row.get(7)?,
+                })
+            })
+            .optional()
+        }
+
+        /// Looks a trade up regardless of its owner, for work done on behalf
+        /// of every owner such as running plans.
+        pub fn select_by_id(conn: &Connection, id: i64) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, base_object_id, quote_object_id, alias, remark, created_at, updated_at
+                FROM finance_trade
+                WHERE id = ?1;
+            "#;
+
+            conn.query_row(sql, params![id], |row| {
+                Ok(Self {
+                    id: row.get(0)?,
+                    owner: row.get(1)?,
+                    base_object_id: row.get(2)?,
+                    quote_object_id: row.get(3)?,
+                    alias: row.get(4)?,
+                    remark: row.get(5)?,
+                    created_at: row.get(6)?,
+                    updated_at: row.get(7)?,
+                })
+            })
+            .optional()
+        }
+
+        /// Returns the trades of the owner that pass `filter`, ordered by
+        /// `sort` and then by id.
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            filter: &Filter,
+            sort: Sort,
+            order: Order,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = format!(
+                r#"
+                    SELECT id, owner, base_object_id, quote_object_id, alias, remark, created_at, updated_at
+                    FROM finance_trade
+                    WHERE owner = ?1 AND {}
+                    ORDER BY {} {}, id {}
+                    LIMIT ?8 OFFSET ?9;
+                "#,
+                FILTER,
+                sort.as_sql(),
+                order.as_sql(),
+                order.as_sql()
+            );
+
+            let params = params![
+                owner,
+                filter.base_object_id,
+                filter.quote_object_id,
+                filter.from,
+                filter.to,
+                filter.text,
+                filter.tag_id,
+                limit,
+                offset,
+            ];
+            let mut stmt = conn.prepare(&sql)?;
+            let trades = stmt
+                .query_map(params, |row| {
                     Ok(Self {
                         id: row.get(0)?,
                         owner: row.get(1)?,
@@ -110,28 +313,22 @@ mod database {
                         created_at: row.get(6)?,
                         updated_at: row.get(7)?,
                     })
-                })
-                .optional();
+                })?
+                .collect::<Result<Vec<Self>>>()?;
 
-            result
+            Ok(trades)
         }
 
-        pub fn select_by_owner(
-            conn: &Connection,
-            owner: i64,
-            limit: usize,
-            offset: usize,
-        ) -> Result<Vec<Self>> {
+        pub fn select_all_by_owner(conn: &Connection, owner: i64) -> Result<Vec<Self>> {
             let sql = r#"
                 SELECT id, owner, base_object_id, quote_object_id, alias, remark, created_at, updated_at
                 FROM finance_trade
-                WHERE owner = ?1
-                LIMIT ?2 OFFSET ?3;
+                WHERE owner = ?1;
             "#;
 
             let mut stmt = conn.prepare(sql)?;
             let trades = stmt
-                .query_map(params![owner, limit, offset], |row| {
+                .query_map(params![owner], |row| {
                     Ok(Self {
                         id: row.get(0)?,
                         owner: row.get(1)?,
@@ -148,6 +345,40 @@ mod database {
             Ok(trades)
         }
 
+        /// The oldest trade of the owner for exactly this base and quote.
+        pub fn select_by_owner_pair(
+            conn: &Connection,
+            owner: i64,
+            base_object_id: i64,
+            quote_object_id: i64,
+        ) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, base_object_id, quote_object_id, alias, remark, created_at, updated_at
+                FROM finance_trade
+                WHERE owner = ?1 AND base_object_id = ?2 AND quote_object_id = ?3
+                ORDER BY id ASC
+                LIMIT 1;
+            "#;
+
+            conn.query_row(
+                sql,
+                params![owner, base_object_id, quote_object_id],
+                |row| {
+                    Ok(Self {
+                        id: row.get(0)?,
+                        owner: row.get(1)?,
+                        base_object_id: row.get(2)?,
+                        quote_object_id: row.get(3)?,
+                        alias: row.get(4)?,
+                        remark: row.get(5)?,
+                        created_at: row.get(6)?,
+                        updated_at: row.get(7)?,
+                    })
+                },
+            )
+            .optional()
+        }
+
         pub fn update_by_id_owner(
             conn: &Connection,
             id: i64,
@@ -0,0 +1,499 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::common::cron::Cron;
+use crate::model::finance::Quantity;
+
+/// A recurring exchange on a trade, such as buying 100 USDT of BTC every
+/// Monday, that the scheduler turns into transactions as each run falls due.
+pub struct Plan {
+    id: i64,
+    pub trade_id: i64,
+    pub account_id: i64,
+    pub schedule: Cron,
+    /// What is given up per run, in the quote object when buying and in the
+    /// base object when selling, unless `is_base_amount` is set.
+    pub amount: Quantity,
+    /// Whether `amount` is always in the base object.
+    pub is_base_amount: bool,
+    pub is_base_to_quote: bool,
+    /// Whether runs become planned transactions awaiting confirmation
+    /// rather than settled ones.
+    pub needs_confirmation: bool,
+    pub is_paused: bool,
+    pub start_at: DateTime<Utc>,
+    pub end_at: Option<DateTime<Utc>>,
+    /// The next run to materialize, `None` once the plan has ended.
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub remark: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Plan {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+/// One run of a plan that was dealt with.
+pub struct Run {
+    id: i64,
+    pub plan_id: i64,
+    pub due_at: DateTime<Utc>,
+    pub outcome: Outcome,
+    pub transaction_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Run {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// A transaction was created.
+    Created,
+    /// Skipped on request.
+    Skipped,
+    /// No quantity could be worked out, for lack of a price or as the
+    /// amount buys less than the base object's precision.
+    Unpriced,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Skipped => "skipped",
+            Self::Unpriced => "unpriced",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created" => Ok(Self::Created),
+            "skipped" => Ok(Self::Skipped),
+            "unpriced" => Ok(Self::Unpriced),
+            _ => Err(format!("unknown run outcome {}", s)),
+        }
+    }
+}
+
+mod database {
+    use chrono::{DateTime, Utc};
+    use rusqlite::params;
+    use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::common::cron::Cron;
+    use crate::model::database::Result;
+    use crate::model::finance::Quantity;
+
+    use super::Outcome;
+
+    impl crate::model::Model for super::Plan {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_trade_plan (
+                    id                  INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    trade_id            INTEGER  NOT NULL,
+                    account_id          INTEGER  NOT NULL,
+                    schedule            TEXT     NOT NULL,
+                    amount              TEXT     NOT NULL,
+                    is_base_amount      BOOL     NOT NULL,
+                    is_base_to_quote    BOOL     NOT NULL,
+                    needs_confirmation  BOOL     NOT NULL,
+                    is_paused           BOOL     NOT NULL DEFAULT FALSE,
+                    start_at            DATETIME NOT NULL,
+                    end_at              DATETIME,
+                    next_run_at         DATETIME,
+                    remark              TEXT,
+                    created_at          DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at          DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(trade_id) REFERENCES finance_trade(id) ON DELETE CASCADE,
+                    FOREIGN KEY(account_id) REFERENCES finance_account(id) ON DELETE RESTRICT
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_trade_plan_updated_at
+                AFTER UPDATE ON finance_trade_plan
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_trade_plan SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE INDEX IF NOT EXISTS idx_finance_trade_plan_trade_id ON finance_trade_plan(trade_id);
+                CREATE INDEX IF NOT EXISTS idx_finance_trade_plan_next_run_at ON finance_trade_plan(next_run_at);
+            "
+        }
+    }
+
+    impl crate::model::Model for super::Run {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_trade_plan_run (
+                    id              INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    plan_id         INTEGER  NOT NULL,
+                    due_at          DATETIME NOT NULL,
+                    outcome         TEXT     NOT NULL CHECK (outcome IN ('created', 'skipped', 'unpriced')),
+                    transaction_id  INTEGER,
+                    created_at      DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(plan_id) REFERENCES finance_trade_plan(id) ON DELETE CASCADE,
+                    FOREIGN KEY(transaction_id) REFERENCES finance_trade_transaction(id) ON DELETE SET NULL,
+                    UNIQUE(plan_id, due_at)
+                );
+            "
+        }
+    }
+
+    impl FromSql for Cron {
+        fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+            value
+                .as_str()?
+                .parse()
+                .map_err(|error: String| FromSqlError::Other(error.into()))
+        }
+    }
+
+    impl ToSql for Cron {
+        fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+            Ok(ToSqlOutput::from(self.to_string()))
+        }
+    }
+
+    impl FromSql for Outcome {
+        fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+            value
+                .as_str()?
+                .parse()
+                .map_err(|error: String| FromSqlError::Other(error.into()))
+        }
+    }
+
+    impl ToSql for Outcome {
+        fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+            Ok(ToSqlOutput::from(self.as_str()))
+        }
+    }
+
+    impl super::Plan {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                trade_id: row.get(1)?,
+                account_id: row.get(2)?,
+                schedule: row.get(3)?,
+                amount: row.get(4)?,
+                is_base_amount: row.get(5)?,
+                is_base_to_quote: row.get(6)?,
+                needs_confirmation: row.get(7)?,
+                is_paused: row.get(8)?,
+                start_at: row.get(9)?,
+                end_at: row.get(10)?,
+                next_run_at: row.get(11)?,
+                remark: row.get(12)?,
+                created_at: row.get(13)?,
+                updated_at: row.get(14)?,
+            })
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn insert(
+            conn: &Connection,
+            trade_id: i64,
+            account_id: i64,
+            schedule: &Cron,
+            amount: Quantity,
+            is_base_amount: bool,
+            is_base_to_quote: bool,
+            needs_confirmation: bool,
+            start_at: DateTime<Utc>,
+            end_at: Option<DateTime<Utc>>,
+            next_run_at: Option<DateTime<Utc>>,
+            remark: Option<String>,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_trade_plan (trade_id, account_id, schedule, amount, is_base_amount, is_base_to_quote, needs_confirmation, start_at, end_at, next_run_at, remark)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![
+                    trade_id,
+                    account_id,
+                    schedule,
+                    amount,
+                    is_base_amount,
+                    is_base_to_quote,
+                    needs_confirmation,
+                    start_at,
+                    end_at,
+                    next_run_at,
+                    remark
+                ],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_trade_id(conn: &Connection, trade_id: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_trade_plan
+                WHERE trade_id = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![trade_id], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_id_trade_id(
+            conn: &Connection,
+            id: i64,
+            trade_id: i64,
+        ) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, trade_id, account_id, schedule, amount, is_base_amount, is_base_to_quote, needs_confirmation, is_paused, start_at, end_at, next_run_at, remark, created_at, updated_at
+                FROM finance_trade_plan
+                WHERE id = ?1 AND trade_id = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, trade_id], Self::from_row)
+                .optional()
+        }
+
+        pub fn select_by_trade_id(
+            conn: &Connection,
+            trade_id: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, trade_id, account_id, schedule, amount, is_base_amount, is_base_to_quote, needs_confirmation, is_paused, start_at, end_at, next_run_at, remark, created_at, updated_at
+                FROM finance_trade_plan
+                WHERE trade_id = ?1
+                LIMIT ?2 OFFSET ?3;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let plans = stmt
+                .query_map(params![trade_id, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(plans)
+        }
+
+        /// Returns the plans of every owner with a run due at or before `at`
+        /// that are not paused.
+        pub fn select_due(conn: &Connection, at: DateTime<Utc>) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, trade_id, account_id, schedule, amount, is_base_amount, is_base_to_quote, needs_confirmation, is_paused, start_at, end_at, next_run_at, remark, created_at, updated_at
+                FROM finance_trade_plan
+                WHERE NOT is_paused AND next_run_at <= ?1
+                ORDER BY next_run_at ASC, id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let plans = stmt
+                .query_map(params![at], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(plans)
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn update_by_id_trade_id(
+            conn: &Connection,
+            id: i64,
+            trade_id: i64,
+            account_id: i64,
+            schedule: &Cron,
+            amount: Quantity,
+            is_base_amount: bool,
+            is_base_to_quote: bool,
+            needs_confirmation: bool,
+            start_at: DateTime<Utc>,
+            end_at: Option<DateTime<Utc>>,
+            next_run_at: Option<DateTime<Utc>>,
+            remark: Option<String>,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_trade_plan
+                SET account_id = ?1, schedule = ?2, amount = ?3, is_base_amount = ?4, is_base_to_quote = ?5, needs_confirmation = ?6, start_at = ?7, end_at = ?8, next_run_at = ?9, remark = ?10
+                WHERE id = ?11 AND trade_id = ?12;
+            "#;
+
+            conn.execute(
+                sql,
+                params![
+                    account_id,
+                    schedule,
+                    amount,
+                    is_base_amount,
+                    is_base_to_quote,
+                    needs_confirmation,
+                    start_at,
+                    end_at,
+                    next_run_at,
+                    remark,
+                    id,
+                    trade_id
+                ],
+            )?;
+
+            Ok(())
+        }
+
+        pub fn update_paused_by_id_trade_id(
+            conn: &Connection,
+            id: i64,
+            trade_id: i64,
+            is_paused: bool,
+            next_run_at: Option<DateTime<Utc>>,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_trade_plan
+                SET is_paused = ?1, next_run_at = ?2
+                WHERE id = ?3 AND trade_id = ?4;
+            "#;
+
+            conn.execute(sql, params![is_paused, next_run_at, id, trade_id])?;
+
+            Ok(())
+        }
+
+        pub fn update_next_run_at_by_id(
+            conn: &Connection,
+            id: i64,
+            next_run_at: Option<DateTime<Utc>>,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_trade_plan
+                SET next_run_at = ?1
+                WHERE id = ?2;
+            "#;
+
+            conn.execute(sql, params![next_run_at, id])?;
+
+            Ok(())
+        }
+
+        pub fn delete_by_id_trade_id(conn: &Connection, id: i64, trade_id: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_trade_plan
+                WHERE id = ?1 AND trade_id = ?2;
+            "#;
+
+            conn.execute(sql, params![id, trade_id])?;
+
+            Ok(())
+        }
+    }
+
+    impl super::Run {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                plan_id: row.get(1)?,
+                due_at: row.get(2)?,
+                outcome: row.get(3)?,
+                transaction_id: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        }
+
+        pub fn insert(
+            conn: &Connection,
+            plan_id: i64,
+            due_at: DateTime<Utc>,
+            outcome: Outcome,
+            transaction_id: Option<i64>,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_trade_plan_run (plan_id, due_at, outcome, transaction_id)
+                VALUES (?1, ?2, ?3, ?4)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![plan_id, due_at, outcome, transaction_id],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_plan_id(conn: &Connection, plan_id: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_trade_plan_run
+                WHERE plan_id = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![plan_id], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_plan_id_due_at(
+            conn: &Connection,
+            plan_id: i64,
+            due_at: DateTime<Utc>,
+        ) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, plan_id, due_at, outcome, transaction_id, created_at
+                FROM finance_trade_plan_run
+                WHERE plan_id = ?1 AND due_at = ?2;
+            "#;
+
+            conn.query_row(sql, params![plan_id, due_at], Self::from_row)
+                .optional()
+        }
+
+        /// Returns the runs of a plan, the latest first.
+        pub fn select_by_plan_id(
+            conn: &Connection,
+            plan_id: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, plan_id, due_at, outcome, transaction_id, created_at
+                FROM finance_trade_plan_run
+                WHERE plan_id = ?1
+                ORDER BY due_at DESC
+                LIMIT ?2 OFFSET ?3;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let runs = stmt
+                .query_map(params![plan_id, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(runs)
+        }
+    }
+}
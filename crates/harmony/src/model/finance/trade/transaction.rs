@@ -1,12 +1,53 @@
+use std::fmt;
+use std::str::FromStr;
+
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 
 use crate::model::finance::Quantity;
 
+/// An amount column as text that orders like its value: the integer part
+/// padded to 29 digits and the fraction to 28, as many as a `Decimal` holds.
+/// Amounts are never negative, so the sign is left out.
+macro_rules! sortable {
+    ($column:literal) => {
+        concat!(
+            "(substr('00000000000000000000000000000' || CASE instr(",
+            $column,
+            ", '.') WHEN 0 THEN ",
+            $column,
+            " ELSE substr(",
+            $column,
+            ", 1, instr(",
+            $column,
+            ", '.') - 1) END, -29) || substr(CASE instr(",
+            $column,
+            ", '.') WHEN 0 THEN '' ELSE substr(",
+            $column,
+            ", instr(",
+            $column,
+            ", '.') + 1) END || '0000000000000000000000000000', 1, 28))"
+        )
+    };
+}
+
 pub struct Transaction {
     id: i64,
     pub trade_id: i64,
+    /// The account the traded holdings sit in.
+    pub account_id: i64,
     pub quantity: Quantity,
+    /// Units of the quote object exchanged per unit of the base object.
+    pub price: Option<Quantity>,
+    /// Charged on top of the exchange, in `fee_object_id`.
+    pub fee: Option<Quantity>,
+    pub fee_object_id: Option<i64>,
     pub is_base_to_quote: bool,
+    pub status: Status,
+    /// When the exchange settles or settled, apart from when it was agreed
+    /// on at `occurrence_at`.
+    pub settled_at: Option<DateTime<Utc>>,
     pub alias: Option<String>,
     pub remark: Option<String>,
     pub occurrence_at: DateTime<Utc>,
@@ -20,30 +61,210 @@ impl Transaction {
     }
 }
 
+/// Where a transaction stands between being entered ahead of time and
+/// being reconciled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// Intended but not executed yet, such as an open limit order.
+    Planned,
+    /// Executed but not settled yet.
+    Pending,
+    Settled,
+    Cancelled,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Planned => "planned",
+            Self::Pending => "pending",
+            Self::Settled => "settled",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    /// Whether the exchange took place and moves holdings.
+    pub fn is_executed(&self) -> bool {
+        matches!(self, Self::Pending | Self::Settled)
+    }
+
+    /// Whether a transaction may move from this status to `next`. Settled
+    /// and cancelled transactions are final.
+    pub fn can_become(&self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (
+                Self::Planned,
+                Self::Pending | Self::Settled | Self::Cancelled
+            ) | (Self::Pending, Self::Settled | Self::Cancelled)
+        )
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "planned" => Ok(Self::Planned),
+            "pending" => Ok(Self::Pending),
+            "settled" => Ok(Self::Settled),
+            "cancelled" => Ok(Self::Cancelled),
+            _ => Err(format!("unknown transaction status {}", s)),
+        }
+    }
+}
+
+/// A transaction of an owner along with the pair it trades.
+pub struct Listing {
+    pub transaction: Transaction,
+    pub base_object_id: i64,
+    pub base_symbol: String,
+    pub quote_object_id: i64,
+    pub quote_symbol: String,
+    pub fee_symbol: Option<String>,
+}
+
+/// Narrows transactions down in listings.
+#[derive(Debug, Default)]
+pub struct Filter {
+    /// Transactions that occurred at or after.
+    pub from: Option<DateTime<Utc>>,
+    /// Transactions that occurred at or before.
+    pub to: Option<DateTime<Utc>>,
+    pub is_base_to_quote: Option<bool>,
+    pub min_quantity: Option<Decimal>,
+    pub max_quantity: Option<Decimal>,
+    /// Found in the alias or the remark, ignoring ASCII case.
+    pub text: Option<String>,
+    pub status: Option<Status>,
+    /// Transactions the tag is attached to.
+    pub tag_id: Option<i64>,
+}
+
+/// The columns transactions are listed by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sort {
+    Id,
+    AccountId,
+    Quantity,
+    Price,
+    Fee,
+    FeeObjectId,
+    IsBaseToQuote,
+    Status,
+    SettledAt,
+    Alias,
+    Remark,
+    #[default]
+    OccurrenceAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl Sort {
+    /// What to order transactions as `tx` by, amounts by their exact value
+    /// rather than as text.
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Id => "tx.id",
+            Self::AccountId => "tx.account_id",
+            Self::Quantity => sortable!("tx.quantity"),
+            Self::Price => sortable!("tx.price"),
+            Self::Fee => sortable!("tx.fee"),
+            Self::FeeObjectId => "tx.fee_object_id",
+            Self::IsBaseToQuote => "tx.is_base_to_quote",
+            Self::Status => "tx.status",
+            Self::SettledAt => "tx.settled_at",
+            Self::Alias => "tx.alias",
+            Self::Remark => "tx.remark",
+            Self::OccurrenceAt => "tx.occurrence_at",
+            Self::CreatedAt => "tx.created_at",
+            Self::UpdatedAt => "tx.updated_at",
+        }
+    }
+}
+
 mod database {
     use chrono::DateTime;
     use chrono::Utc;
     use rusqlite::params;
+    use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
     use rusqlite::Connection;
     use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+    use rust_decimal::Decimal;
 
-    use crate::model::database::Result;
+    use crate::model::database::{Order, Result};
     use crate::model::finance::Quantity;
 
+    use super::{Filter, Listing, Sort, Status};
+
+    /// The conditions of `Filter` on transactions as `tx`, bound from `?2`
+    /// to `?9`, with the quantity bounds as `sortable` keys.
+    const FILTER: &str = concat!(
+        r#"
+        (?2 IS NULL OR tx.occurrence_at >= ?2)
+        AND (?3 IS NULL OR tx.occurrence_at <= ?3)
+        AND (?4 IS NULL OR tx.is_base_to_quote = ?4)
+        AND (?5 IS NULL OR "#,
+        sortable!("tx.quantity"),
+        r#" >= ?5)
+        AND (?6 IS NULL OR "#,
+        sortable!("tx.quantity"),
+        r#" <= ?6)
+        AND (?7 IS NULL OR instr(lower(coalesce(tx.alias, '')), lower(?7)) > 0
+            OR instr(lower(coalesce(tx.remark, '')), lower(?7)) > 0)
+        AND (?8 IS NULL OR tx.status = ?8)
+        AND (?9 IS NULL OR EXISTS (SELECT 1 FROM finance_trade_transaction_tag
+            WHERE transaction_id = tx.id AND tag_id = ?9))
+    "#
+    );
+
+    /// The key `sortable!` gives an amount column holding `value`. Negative
+    /// values come before every amount.
+    fn sortable(value: Decimal) -> String {
+        if value.is_sign_negative() && !value.is_zero() {
+            return "-".to_string();
+        }
+
+        let text = value.normalize().to_string();
+        let (integer, fraction) = text.split_once('.').unwrap_or((&text, ""));
+
+        format!("{:0>29}{:0<28}", integer, fraction)
+    }
+
     impl crate::model::Model for super::Transaction {
         fn initialize() -> &'static str {
             "
                 CREATE TABLE IF NOT EXISTS finance_trade_transaction (
                     id                INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
                     trade_id          INTEGER  NOT NULL,
+                    account_id        INTEGER  NOT NULL,
                     quantity          TEXT     NOT NULL,
+                    price             TEXT,
+                    fee               TEXT,
+                    fee_object_id     INTEGER,
                     is_base_to_quote  BOOL     NOT NULL,
+                    status            TEXT     NOT NULL DEFAULT 'settled' CHECK (status IN ('planned', 'pending', 'settled', 'cancelled')),
+                    settled_at        DATETIME,
                     alias             TEXT,
                     remark            TEXT,
                     occurrence_at     DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
                     created_at        DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
                     updated_at        DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
-                    FOREIGN KEY(trade_id) REFERENCES finance_trade(id) ON DELETE CASCADE
+                    FOREIGN KEY(trade_id) REFERENCES finance_trade(id) ON DELETE CASCADE,
+                    FOREIGN KEY(account_id) REFERENCES finance_account(id) ON DELETE RESTRICT,
+                    FOREIGN KEY(fee_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    CHECK ((fee IS NULL) = (fee_object_id IS NULL))
                 );
 
                 CREATE TRIGGER IF NOT EXISTS update_finance_trade_transaction_updated_at
@@ -54,23 +275,67 @@ mod database {
                 END;
 
                 CREATE INDEX IF NOT EXISTS idx_finance_trade_transaction_trade_id ON finance_trade_transaction(trade_id);
+                CREATE INDEX IF NOT EXISTS idx_finance_trade_transaction_account_id ON finance_trade_transaction(account_id);
+                CREATE INDEX IF NOT EXISTS idx_finance_trade_transaction_trade_id_occurrence_at ON finance_trade_transaction(trade_id, occurrence_at);
             "
         }
     }
 
+    impl FromSql for Status {
+        fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+            value
+                .as_str()?
+                .parse()
+                .map_err(|error: String| FromSqlError::Other(error.into()))
+        }
+    }
+
+    impl ToSql for Status {
+        fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+            Ok(ToSqlOutput::from(self.as_str()))
+        }
+    }
+
     impl super::Transaction {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                trade_id: row.get(1)?,
+                account_id: row.get(2)?,
+                quantity: row.get(3)?,
+                price: row.get(4)?,
+                fee: row.get(5)?,
+                fee_object_id: row.get(6)?,
+                is_base_to_quote: row.get(7)?,
+                status: row.get(8)?,
+                settled_at: row.get(9)?,
+                alias: row.get(10)?,
+                remark: row.get(11)?,
+                occurrence_at: row.get(12)?,
+                created_at: row.get(13)?,
+                updated_at: row.get(14)?,
+            })
+        }
+
+        #[allow(clippy::too_many_arguments)]
         pub fn insert(
             conn: &Connection,
             trade_id: i64,
+            account_id: i64,
             quantity: Quantity,
+            price: Option<Quantity>,
+            fee: Option<Quantity>,
+            fee_object_id: Option<i64>,
             is_base_to_quote: bool,
+            status: Status,
+            settled_at: Option<DateTime<Utc>>,
             alias: Option<String>,
             remark: Option<String>,
             occurrence_at: Option<DateTime<Utc>>,
         ) -> Result<i64> {
             let sql = r#"
-                INSERT INTO finance_trade_transaction (trade_id, quantity, is_base_to_quote, alias, remark, occurrence_at)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                INSERT INTO finance_trade_transaction (trade_id, account_id, quantity, price, fee, fee_object_id, is_base_to_quote, status, settled_at, alias, remark, occurrence_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
                 RETURNING id;
             "#;
 
@@ -80,8 +345,14 @@ mod database {
                 sql,
                 params![
                     trade_id,
-                    quantity.to_string(),
+                    account_id,
+                    quantity,
+                    price,
+                    fee,
+                    fee_object_id,
                     is_base_to_quote,
+                    status,
+                    settled_at,
                     alias,
                     remark,
                     occurrence_at
@@ -92,14 +363,35 @@ mod database {
             Ok(id)
         }
 
-        pub fn count_by_trade_id(conn: &Connection, trade_id: i64) -> Result<usize> {
-            let sql = r#"
-                SELECT COUNT(*)
-                FROM finance_trade_transaction
-                WHERE trade_id = ?1;
-            "#;
+        pub fn count_by_trade_id(
+            conn: &Connection,
+            trade_id: i64,
+            filter: &Filter,
+        ) -> Result<usize> {
+            let sql = format!(
+                r#"
+                    SELECT COUNT(*)
+                    FROM finance_trade_transaction tx
+                    WHERE tx.trade_id = ?1 AND {};
+                "#,
+                FILTER
+            );
 
-            let count = conn.query_row(sql, params![trade_id], |row| row.get(0))?;
+            let count = conn.query_row(
+                &sql,
+                params![
+                    trade_id,
+                    filter.from,
+                    filter.to,
+                    filter.is_base_to_quote,
+                    filter.min_quantity.map(sortable),
+                    filter.max_quantity.map(sortable),
+                    filter.text,
+                    filter.status,
+                    filter.tag_id,
+                ],
+                |row| row.get(0),
+            )?;
 
             Ok(count)
         }
@@ -110,68 +402,180 @@ mod database {
             trade_id: i64,
         ) -> Result<Option<Self>> {
             let sql = r#"
-                SELECT id, trade_id, quantity, is_base_to_quote, alias, remark, occurrence_at, created_at, updated_at
+                SELECT id, trade_id, account_id, quantity, price, fee, fee_object_id, is_base_to_quote, status, settled_at, alias, remark, occurrence_at, created_at, updated_at
                 FROM finance_trade_transaction
                 WHERE id = ?1 AND trade_id = ?2;
             "#;
 
-            let result = conn
-                .query_row(sql, params![id, trade_id], |row| {
-                    Ok(Self {
-                        id: row.get(0)?,
-                        trade_id: row.get(1)?,
-                        quantity: row.get(2)?,
-                        is_base_to_quote: row.get(3)?,
-                        alias: row.get(4)?,
-                        remark: row.get(5)?,
-                        occurrence_at: row.get(6)?,
-                        created_at: row.get(7)?,
-                        updated_at: row.get(8)?,
-                    })
-                })
-                .optional();
-
-            result
+            conn.query_row(sql, params![id, trade_id], Self::from_row)
+                .optional()
         }
 
+        /// Returns a transaction of any trade of the owner.
+        pub fn select_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT tx.id, tx.trade_id, tx.account_id, tx.quantity, tx.price, tx.fee, tx.fee_object_id, tx.is_base_to_quote, tx.status, tx.settled_at, tx.alias, tx.remark, tx.occurrence_at, tx.created_at, tx.updated_at
+                FROM finance_trade_transaction tx
+                JOIN finance_trade t ON t.id = tx.trade_id
+                WHERE tx.id = ?1 AND t.owner = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, owner], Self::from_row)
+                .optional()
+        }
+
+        /// Returns the transactions of a trade that pass `filter`, ordered by
+        /// `sort` and then by id.
+        #[allow(clippy::too_many_arguments)]
         pub fn select_by_trade_id(
             conn: &Connection,
             trade_id: i64,
+            filter: &Filter,
+            sort: Sort,
+            order: Order,
             limit: usize,
             offset: usize,
         ) -> Result<Vec<Self>> {
-            let sql = r#"
-                SELECT id, trade_id, quantity, is_base_to_quote, alias, remark, occurrence_at, created_at, updated_at
-                FROM finance_trade_transaction
-                WHERE trade_id = ?1
-                LIMIT ?2 OFFSET ?3;
-            "#;
+            let sql = format!(
+                r#"
+                    SELECT tx.id, tx.trade_id, tx.account_id, tx.quantity, tx.price, tx.fee, tx.fee_object_id, tx.is_base_to_quote, tx.status, tx.settled_at, tx.alias, tx.remark, tx.occurrence_at, tx.created_at, tx.updated_at
+                    FROM finance_trade_transaction tx
+                    WHERE tx.trade_id = ?1 AND {}
+                    ORDER BY {} {}, tx.id {}
+                    LIMIT ?10 OFFSET ?11;
+                "#,
+                FILTER,
+                sort.as_sql(),
+                order.as_sql(),
+                order.as_sql()
+            );
 
-            let mut stmt = conn.prepare(sql)?;
+            let mut stmt = conn.prepare(&sql)?;
             let transactions = stmt
-                .query_map(params![trade_id, limit, offset], |row| {
-                    Ok(Self {
-                        id: row.get(0)?,
-                        trade_id: row.get(1)?,
-                        quantity: row.get(2)?,
-                        is_base_to_quote: row.get(3)?,
-                        alias: row.get(4)?,
-                        remark: row.get(5)?,
-                        occurrence_at: row.get(6)?,
-                        created_at: row.get(7)?,
-                        updated_at: row.get(8)?,
-                    })
-                })?
+                .query_map(
+                    params![
+                        trade_id,
+                        filter.from,
+                        filter.to,
+                        filter.is_base_to_quote,
+                        filter.min_quantity.map(sortable),
+                        filter.max_quantity.map(sortable),
+                        filter.text,
+                        filter.status,
+                        filter.tag_id,
+                        limit,
+                        offset,
+                    ],
+                    Self::from_row,
+                )?
                 .collect::<Result<Vec<Self>>>()?;
 
             Ok(transactions)
         }
 
+        pub fn count_by_owner(conn: &Connection, owner: i64, filter: &Filter) -> Result<usize> {
+            let sql = format!(
+                r#"
+                    SELECT COUNT(*)
+                    FROM finance_trade_transaction tx
+                    JOIN finance_trade t ON t.id = tx.trade_id
+                    WHERE t.owner = ?1 AND {};
+                "#,
+                FILTER
+            );
+
+            let count = conn.query_row(
+                &sql,
+                params![
+                    owner,
+                    filter.from,
+                    filter.to,
+                    filter.is_base_to_quote,
+                    filter.min_quantity.map(sortable),
+                    filter.max_quantity.map(sortable),
+                    filter.text,
+                    filter.status,
+                    filter.tag_id,
+                ],
+                |row| row.get(0),
+            )?;
+
+            Ok(count)
+        }
+
+        /// Returns the transactions of every trade of the owner that pass
+        /// `filter` along with the symbols they involve, ordered by `sort`
+        /// and then by id.
+        #[allow(clippy::too_many_arguments)]
+        pub fn select_listings_by_owner(
+            conn: &Connection,
+            owner: i64,
+            filter: &Filter,
+            sort: Sort,
+            order: Order,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Listing>> {
+            let sql = format!(
+                r#"
+                    SELECT tx.id, tx.trade_id, tx.account_id, tx.quantity, tx.price, tx.fee, tx.fee_object_id, tx.is_base_to_quote, tx.status, tx.settled_at, tx.alias, tx.remark, tx.occurrence_at, tx.created_at, tx.updated_at, t.base_object_id, b.symbol, t.quote_object_id, q.symbol, f.symbol
+                    FROM finance_trade_transaction tx
+                    JOIN finance_trade t ON t.id = tx.trade_id
+                    JOIN finance_object b ON b.id = t.base_object_id
+                    JOIN finance_object q ON q.id = t.quote_object_id
+                    LEFT JOIN finance_object f ON f.id = tx.fee_object_id
+                    WHERE t.owner = ?1 AND {}
+                    ORDER BY {} {}, tx.id {}
+                    LIMIT ?10 OFFSET ?11;
+                "#,
+                FILTER,
+                sort.as_sql(),
+                order.as_sql(),
+                order.as_sql()
+            );
+
+            let mut stmt = conn.prepare(&sql)?;
+            let listings = stmt
+                .query_map(
+                    params![
+                        owner,
+                        filter.from,
+                        filter.to,
+                        filter.is_base_to_quote,
+                        filter.min_quantity.map(sortable),
+                        filter.max_quantity.map(sortable),
+                        filter.text,
+                        filter.status,
+                        filter.tag_id,
+                        limit,
+                        offset,
+                    ],
+                    |row| {
+                        Ok(Listing {
+                            transaction: Self::from_row(row)?,
+                            base_object_id: row.get(15)?,
+                            base_symbol: row.get(16)?,
+                            quote_object_id: row.get(17)?,
+                            quote_symbol: row.get(18)?,
+                            fee_symbol: row.get(19)?,
+                        })
+                    },
+                )?
+                .collect::<Result<Vec<Listing>>>()?;
+
+            Ok(listings)
+        }
+
+        #[allow(clippy::too_many_arguments)]
         pub fn update_by_id_trade_id(
             conn: &Connection,
             id: i64,
             trade_id: i64,
+            account_id: i64,
             quantity: Quantity,
+            price: Option<Quantity>,
+            fee: Option<Quantity>,
+            fee_object_id: Option<i64>,
             is_base_to_quote: bool,
             occurrence_at: DateTime<Utc>,
             alias: Option<String>,
@@ -179,14 +583,18 @@ mod database {
         ) -> Result<()> {
             let sql = r#"
                 UPDATE finance_trade_transaction
-                SET quantity = ?1, is_base_to_quote = ?2, alias = ?3, remark = ?4, occurrence_at = ?5
-                WHERE id = ?6 AND trade_id = ?7;
+                SET account_id = ?1, quantity = ?2, price = ?3, fee = ?4, fee_object_id = ?5, is_base_to_quote = ?6, alias = ?7, remark = ?8, occurrence_at = ?9
+                WHERE id = ?10 AND trade_id = ?11;
             "#;
 
             conn.execute(
                 sql,
                 params![
-                    quantity.to_string(),
+                    account_id,
+                    quantity,
+                    price,
+                    fee,
+                    fee_object_id,
                     is_base_to_quote,
                     alias,
                     remark,
@@ -199,6 +607,67 @@ mod database {
             Ok(())
         }
 
+        pub fn update_status_by_id_trade_id(
+            conn: &Connection,
+            id: i64,
+            trade_id: i64,
+            status: Status,
+            settled_at: Option<DateTime<Utc>>,
+        ) -> Result<()> {
+            let sql = r#"
+                UPDATE finance_trade_transaction
+                SET status = ?1, settled_at = ?2
+                WHERE id = ?3 AND trade_id = ?4;
+            "#;
+
+            conn.execute(sql, params![status, settled_at, id, trade_id])?;
+
+            Ok(())
+        }
+
+        /// Returns every settled transaction of the owner's trades that
+        /// occurred at or before `until`, in `occurrence_at` order. Pending
+        /// transactions are included when `pending` is set.
+        pub fn select_by_owner_until(
+            conn: &Connection,
+            owner: i64,
+            until: DateTime<Utc>,
+            pending: bool,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT tx.id, tx.trade_id, tx.account_id, tx.quantity, tx.price, tx.fee, tx.fee_object_id, tx.is_base_to_quote, tx.status, tx.settled_at, tx.alias, tx.remark, tx.occurrence_at, tx.created_at, tx.updated_at
+                FROM finance_trade_transaction tx
+                JOIN finance_trade t ON t.id = tx.trade_id
+                WHERE t.owner = ?1 AND tx.occurrence_at <= ?2
+                  AND (tx.status = 'settled' OR (?3 AND tx.status = 'pending'))
+                ORDER BY tx.occurrence_at ASC, tx.id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let transactions = stmt
+                .query_map(params![owner, until, pending], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(transactions)
+        }
+
+        /// Returns every transaction of a trade in `occurrence_at` order.
+        pub fn select_all_by_trade_id(conn: &Connection, trade_id: i64) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, trade_id, account_id, quantity, price, fee, fee_object_id, is_base_to_quote, status, settled_at, alias, remark, occurrence_at, created_at, updated_at
+                FROM finance_trade_transaction
+                WHERE trade_id = ?1
+                ORDER BY occurrence_at ASC, id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let transactions = stmt
+                .query_map(params![trade_id], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(transactions)
+        }
+
         pub fn delete_by_id_trade_id(conn: &Connection, id: i64, trade_id: i64) -> Result<()> {
             let sql = r#"
                 DELETE FROM finance_trade_transaction
@@ -211,3 +680,232 @@ mod database {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use crate::model::database::Order;
+    use crate::model::fixture;
+
+    use super::{Filter, Sort, Status, Transaction};
+
+    #[test]
+    fn test_select_by_trade_id_filter_and_sort() {
+        let conn = fixture::connection();
+        let owner = fixture::person(&conn, "test_user");
+        let (btc, usd) = (
+            fixture::object(&conn, owner, "BTC"),
+            fixture::object(&conn, owner, "USD"),
+        );
+        let account = fixture::account(&conn, owner, "exchange");
+        let trade = fixture::trade(&conn, owner, btc, usd);
+
+        // Quantities that sort differently as text than as numbers
+        let rows = [
+            (9, false, "Monthly DCA"),
+            (10, true, "rebalance"),
+            (100, false, "dca"),
+        ];
+        for (day, (quantity, is_base_to_quote, remark)) in rows.into_iter().enumerate() {
+            Transaction::insert(
+                &conn,
+                trade,
+                account,
+                Decimal::from(quantity).into(),
+                None,
+                None,
+                None,
+                is_base_to_quote,
+                Status::Settled,
+                None,
+                None,
+                Some(remark.to_string()),
+                Some(
+                    Utc.with_ymd_and_hms(2024, 1, day as u32 + 1, 0, 0, 0)
+                        .unwrap(),
+                ),
+            )
+            .unwrap();
+        }
+
+        let quantities = |filter: &Filter, sort, order| {
+            Transaction::select_by_trade_id(&conn, trade, filter, sort, order, 10, 0)
+                .unwrap()
+                .into_iter()
+                .map(|transaction| transaction.quantity.value())
+                .collect::<Vec<Decimal>>()
+        };
+        let numbers = |values: &[i64]| -> Vec<Decimal> {
+            values.iter().map(|value| Decimal::from(*value)).collect()
+        };
+
+        let all = Filter::default();
+        assert_eq!(
+            quantities(&all, Sort::Quantity, Order::Asc),
+            numbers(&[9, 10, 100])
+        );
+        assert_eq!(
+            quantities(&all, Sort::OccurrenceAt, Order::Desc),
+            numbers(&[100, 10, 9])
+        );
+
+        let filter = Filter {
+            text: Some("dca".to_string()),
+            ..Filter::default()
+        };
+        assert_eq!(
+            Transaction::count_by_trade_id(&conn, trade, &filter).unwrap(),
+            2
+        );
+
+        let filter = Filter {
+            min_quantity: Some(Decimal::from(10)),
+            is_base_to_quote: Some(false),
+            ..Filter::default()
+        };
+        assert_eq!(quantities(&filter, Sort::Id, Order::Asc), numbers(&[100]));
+        assert_eq!(
+            Transaction::count_by_trade_id(&conn, trade, &filter).unwrap(),
+            1
+        );
+
+        let filter = Filter {
+            to: Some(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap()),
+            status: Some(Status::Settled),
+            ..Filter::default()
+        };
+        assert_eq!(quantities(&filter, Sort::Id, Order::Asc), numbers(&[9, 10]));
+    }
+
+    #[test]
+    fn test_amounts_sort_and_filter_exactly() {
+        let conn = fixture::connection();
+        let owner = fixture::person(&conn, "test_user");
+        let (btc, usd) = (
+            fixture::object(&conn, owner, "BTC"),
+            fixture::object(&conn, owner, "USD"),
+        );
+        let account = fixture::account(&conn, owner, "exchange");
+        let trade = fixture::trade(&conn, owner, btc, usd);
+
+        // Equal once converted to a real
+        let amounts = ["1000000000000000000.02", "1000000000000000000.01", "0.5"];
+        for amount in amounts {
+            Transaction::insert(
+                &conn,
+                trade,
+                account,
+                amount.parse::<Decimal>().unwrap().into(),
+                None,
+                None,
+                None,
+                false,
+                Status::Settled,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        let ids = |filter: &Filter, order| {
+            Transaction::select_by_trade_id(&conn, trade, filter, Sort::Quantity, order, 10, 0)
+                .unwrap()
+                .into_iter()
+                .map(|transaction| transaction.id())
+                .collect::<Vec<i64>>()
+        };
+
+        assert_eq!(ids(&Filter::default(), Order::Asc), vec![3, 2, 1]);
+        assert_eq!(ids(&Filter::default(), Order::Desc), vec![1, 2, 3]);
+
+        let filter = Filter {
+            min_quantity: Some("1000000000000000000.02".parse().unwrap()),
+            ..Filter::default()
+        };
+        assert_eq!(ids(&filter, Order::Asc), vec![1]);
+
+        let filter = Filter {
+            min_quantity: Some(Decimal::NEGATIVE_ONE),
+            max_quantity: Some("1000000000000000000.01".parse().unwrap()),
+            ..Filter::default()
+        };
+        assert_eq!(ids(&filter, Order::Asc), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_select_listings_by_owner() {
+        let conn = fixture::connection();
+        let owner = fixture::person(&conn, "test_user");
+        let other = fixture::person(&conn, "other_user");
+
+        let mut trades = Vec::new();
+        let pairs = [
+            (owner, "BTC", "USD"),
+            (owner, "ETH", "EUR"),
+            (other, "SOL", "USD"),
+        ];
+        for (owner, base, quote) in pairs {
+            let base = fixture::object(&conn, owner, base);
+            let quote = fixture::object(&conn, owner, quote);
+            let account = fixture::account(&conn, owner, &base.to_string());
+            let trade = fixture::trade(&conn, owner, base, quote);
+            trades.push((trade, account, quote));
+        }
+
+        for (day, (trade, account, quote)) in trades.iter().enumerate() {
+            Transaction::insert(
+                &conn,
+                *trade,
+                *account,
+                Decimal::ONE.into(),
+                None,
+                Some(Decimal::ONE.into()),
+                Some(*quote),
+                false,
+                Status::Settled,
+                None,
+                None,
+                None,
+                Some(
+                    Utc.with_ymd_and_hms(2024, 1, day as u32 + 1, 0, 0, 0)
+                        .unwrap(),
+                ),
+            )
+            .unwrap();
+        }
+
+        let all = Filter::default();
+        assert_eq!(Transaction::count_by_owner(&conn, owner, &all).unwrap(), 2);
+
+        let listings = Transaction::select_listings_by_owner(
+            &conn,
+            owner,
+            &all,
+            Sort::OccurrenceAt,
+            Order::Desc,
+            10,
+            0,
+        )
+        .unwrap();
+        let pairs: Vec<(&str, &str)> = listings
+            .iter()
+            .map(|listing| (listing.base_symbol.as_str(), listing.quote_symbol.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![("ETH", "EUR"), ("BTC", "USD")]);
+        assert_eq!(listings[0].transaction.trade_id, trades[1].0);
+        assert_eq!(listings[0].fee_symbol.as_deref(), Some("EUR"));
+
+        let filter = Filter {
+            to: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            ..Filter::default()
+        };
+        assert_eq!(
+            Transaction::count_by_owner(&conn, owner, &filter).unwrap(),
+            1
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use chrono::{DateTime, Utc};
+
+use crate::model::finance::Quantity;
+
+/// Holdings of one object moved between two accounts of the same owner.
+/// A transfer changes where holdings sit but never how much is held.
+pub struct Transfer {
+    id: i64,
+    pub owner: i64,
+    pub object_id: i64,
+    pub from_account_id: i64,
+    pub to_account_id: i64,
+    pub quantity: Quantity,
+    pub remark: Option<String>,
+    pub occurrence_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Transfer {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+}
+
+mod database {
+    use chrono::{DateTime, Utc};
+    use rusqlite::params;
+    use rusqlite::Connection;
+    use rusqlite::OptionalExtension;
+    use rusqlite::Row;
+
+    use crate::model::database::Result;
+    use crate::model::finance::Quantity;
+
+    impl crate::model::Model for super::Transfer {
+        fn initialize() -> &'static str {
+            "
+                CREATE TABLE IF NOT EXISTS finance_transfer (
+                    id               INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+                    owner            INTEGER  NOT NULL,
+                    object_id        INTEGER  NOT NULL,
+                    from_account_id  INTEGER  NOT NULL,
+                    to_account_id    INTEGER  NOT NULL,
+                    quantity         TEXT     NOT NULL,
+                    remark           TEXT,
+                    occurrence_at    DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    created_at       DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    updated_at       DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+                    FOREIGN KEY(object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+                    FOREIGN KEY(from_account_id) REFERENCES finance_account(id) ON DELETE RESTRICT,
+                    FOREIGN KEY(to_account_id) REFERENCES finance_account(id) ON DELETE RESTRICT,
+                    CHECK (from_account_id <> to_account_id)
+                );
+
+                CREATE TRIGGER IF NOT EXISTS update_finance_transfer_updated_at
+                AFTER UPDATE ON finance_transfer
+                FOR EACH ROW
+                BEGIN
+                    UPDATE finance_transfer SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+                END;
+
+                CREATE INDEX IF NOT EXISTS idx_finance_transfer_owner_occurrence_at ON finance_transfer(owner, occurrence_at);
+            "
+        }
+    }
+
+    impl super::Transfer {
+        fn from_row(row: &Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(0)?,
+                owner: row.get(1)?,
+                object_id: row.get(2)?,
+                from_account_id: row.get(3)?,
+                to_account_id: row.get(4)?,
+                quantity: row.get(5)?,
+                remark: row.get(6)?,
+                occurrence_at: row.get(7)?,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
+            })
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn insert(
+            conn: &Connection,
+            owner: i64,
+            object_id: i64,
+            from_account_id: i64,
+            to_account_id: i64,
+            quantity: Quantity,
+            remark: Option<String>,
+            occurrence_at: DateTime<Utc>,
+        ) -> Result<i64> {
+            let sql = r#"
+                INSERT INTO finance_transfer (owner, object_id, from_account_id, to_account_id, quantity, remark, occurrence_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                RETURNING id;
+            "#;
+
+            let id = conn.query_row(
+                sql,
+                params![
+                    owner,
+                    object_id,
+                    from_account_id,
+                    to_account_id,
+                    quantity,
+                    remark,
+                    occurrence_at
+                ],
+                |row| row.get(0),
+            )?;
+
+            Ok(id)
+        }
+
+        pub fn count_by_owner(conn: &Connection, owner: i64) -> Result<usize> {
+            let sql = r#"
+                SELECT COUNT(*)
+                FROM finance_transfer
+                WHERE owner = ?1;
+            "#;
+
+            let count = conn.query_row(sql, params![owner], |row| row.get(0))?;
+
+            Ok(count)
+        }
+
+        pub fn select_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<Option<Self>> {
+            let sql = r#"
+                SELECT id, owner, object_id, from_account_id, to_account_id, quantity, remark, occurrence_at, created_at, updated_at
+                FROM finance_transfer
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.query_row(sql, params![id, owner], Self::from_row)
+                .optional()
+        }
+
+        /// Returns the transfers of the owner, newest first.
+        pub fn select_by_owner(
+            conn: &Connection,
+            owner: i64,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, object_id, from_account_id, to_account_id, quantity, remark, occurrence_at, created_at, updated_at
+                FROM finance_transfer
+                WHERE owner = ?1
+                ORDER BY occurrence_at DESC, id DESC
+                LIMIT ?2 OFFSET ?3;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let transfers = stmt
+                .query_map(params![owner, limit, offset], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(transfers)
+        }
+
+        /// Returns every transfer of the owner that occurred at or before
+        /// `until`, in `occurrence_at` order.
+        pub fn select_by_owner_until(
+            conn: &Connection,
+            owner: i64,
+            until: DateTime<Utc>,
+        ) -> Result<Vec<Self>> {
+            let sql = r#"
+                SELECT id, owner, object_id, from_account_id, to_account_id, quantity, remark, occurrence_at, created_at, updated_at
+                FROM finance_transfer
+                WHERE owner = ?1 AND occurrence_at <= ?2
+                ORDER BY occurrence_at ASC, id ASC;
+            "#;
+
+            let mut stmt = conn.prepare(sql)?;
+            let transfers = stmt
+                .query_map(params![owner, until], Self::from_row)?
+                .collect::<Result<Vec<Self>>>()?;
+
+            Ok(transfers)
+        }
+
+        pub fn delete_by_id_owner(conn: &Connection, id: i64, owner: i64) -> Result<()> {
+            let sql = r#"
+                DELETE FROM finance_transfer
+                WHERE id = ?1 AND owner = ?2;
+            "#;
+
+            conn.execute(sql, params![id, owner])?;
+
+            Ok(())
+        }
+    }
+}
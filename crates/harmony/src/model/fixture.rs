@@ -0,0 +1,37 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::Connection;
+
+use crate::model::finance::account::Account;
+use crate::model::finance::object::{Classification, Object};
+use crate::model::finance::trade::Trade;
+use crate::model::person::Person;
+
+/// An empty in-memory database with the whole schema.
+pub fn connection() -> PooledConnection<SqliteConnectionManager> {
+    let pool = r2d2::Pool::new(SqliteConnectionManager::memory()).unwrap();
+    let conn = pool.get().unwrap();
+    conn.execute_batch(&crate::model::initialize()).unwrap();
+
+    conn
+}
+
+pub fn person(conn: &Connection, nickname: &str) -> i64 {
+    Person::insert_one(conn, &nickname.to_string(), &"test".to_string())
+        .unwrap()
+        .id()
+}
+
+/// An object of the default classification.
+pub fn object(conn: &Connection, owner: i64, symbol: &str) -> i64 {
+    let classification = Classification::default();
+    Object::insert(conn, owner, symbol.to_string(), None, None, classification).unwrap()
+}
+
+pub fn account(conn: &Connection, owner: i64, name: &str) -> i64 {
+    Account::insert(conn, owner, name.to_string(), None, None).unwrap()
+}
+
+pub fn trade(conn: &Connection, owner: i64, base: i64, quote: i64) -> i64 {
+    Trade::insert(conn, owner, base, quote, None, None).unwrap()
+}
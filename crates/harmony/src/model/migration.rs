@@ -0,0 +1,391 @@
+use rusqlite::Connection;
+
+use crate::model::database::Result;
+
+/// Steps that bring a database from one version to the next, kept as they
+/// were written. The position of a step is the version it migrates from,
+/// and the version of a database is kept in `PRAGMA user_version`.
+///
+/// `initialize` only creates what is missing, so a step has to change what
+/// it cannot, the columns and checks added to tables that already exist.
+const MIGRATIONS: [&str; 2] = [
+    // Classification of objects, and accounts, prices, fees and statuses of
+    // transactions. Transactions are moved to a default account of their
+    // owner. A column added in place could neither be `NOT NULL` nor
+    // reference accounts, so the table is rebuilt instead.
+    "
+        ALTER TABLE finance_object ADD COLUMN asset_type TEXT NOT NULL DEFAULT 'other' CHECK (asset_type IN ('fiat', 'crypto', 'equity', 'fund', 'bond', 'commodity', 'other'));
+        ALTER TABLE finance_object ADD COLUMN precision INTEGER NOT NULL DEFAULT 8 CHECK (precision BETWEEN 0 AND 28);
+        ALTER TABLE finance_object ADD COLUMN identifier TEXT;
+        ALTER TABLE finance_object ADD COLUMN exchange TEXT;
+
+        CREATE TABLE IF NOT EXISTS finance_account (
+            id          INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+            owner       INTEGER  NOT NULL,
+            name        TEXT     NOT NULL,
+            institution TEXT,
+            remark      TEXT,
+            created_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at  DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+            UNIQUE(owner, name)
+        );
+
+        INSERT INTO finance_account (owner, name)
+        SELECT DISTINCT t.owner, 'Default'
+        FROM finance_trade_transaction tx
+        JOIN finance_trade t ON t.id = tx.trade_id;
+
+        CREATE TABLE finance_trade_transaction_migrated (
+            id                INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+            trade_id          INTEGER  NOT NULL,
+            account_id        INTEGER  NOT NULL,
+            quantity          TEXT     NOT NULL,
+            price             TEXT,
+            fee               TEXT,
+            fee_object_id     INTEGER,
+            is_base_to_quote  BOOL     NOT NULL,
+            status            TEXT     NOT NULL DEFAULT 'settled' CHECK (status IN ('planned', 'pending', 'settled', 'cancelled')),
+            settled_at        DATETIME,
+            alias             TEXT,
+            remark            TEXT,
+            occurrence_at     DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            created_at        DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at        DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(trade_id) REFERENCES finance_trade(id) ON DELETE CASCADE,
+            FOREIGN KEY(account_id) REFERENCES finance_account(id) ON DELETE RESTRICT,
+            FOREIGN KEY(fee_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+            CHECK ((fee IS NULL) = (fee_object_id IS NULL))
+        );
+
+        INSERT INTO finance_trade_transaction_migrated (id, trade_id, account_id, quantity, is_base_to_quote, alias, remark, occurrence_at, created_at, updated_at)
+        SELECT tx.id, tx.trade_id, a.id, tx.quantity, tx.is_base_to_quote, tx.alias, tx.remark, tx.occurrence_at, tx.created_at, tx.updated_at
+        FROM finance_trade_transaction tx
+        JOIN finance_trade t ON t.id = tx.trade_id
+        JOIN finance_account a ON a.owner = t.owner AND a.name = 'Default';
+
+        DROP TABLE finance_trade_transaction;
+        ALTER TABLE finance_trade_transaction_migrated RENAME TO finance_trade_transaction;
+    ",
+    // Trades of an object against itself are refused. A check cannot be
+    // added in place, so the table is rebuilt, and a database holding such
+    // a trade fails here until it is removed. Renaming the old way keeps
+    // SQLite from checking the views and triggers that use the table while
+    // it is briefly missing.
+    "
+        CREATE TABLE finance_trade_migrated (
+            id               INTEGER NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+            owner            INTEGER NOT NULL,
+            base_object_id   INTEGER NOT NULL,
+            quote_object_id  INTEGER NOT NULL,
+            alias            TEXT,
+            remark           TEXT,
+            created_at       DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at       DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+            FOREIGN KEY(base_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+            FOREIGN KEY(quote_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+            CHECK (base_object_id <> quote_object_id)
+        );
+
+        INSERT INTO finance_trade_migrated (id, owner, base_object_id, quote_object_id, alias, remark, created_at, updated_at)
+        SELECT id, owner, base_object_id, quote_object_id, alias, remark, created_at, updated_at
+        FROM finance_trade;
+
+        PRAGMA legacy_alter_table = ON;
+        DROP TABLE finance_trade;
+        ALTER TABLE finance_trade_migrated RENAME TO finance_trade;
+        PRAGMA legacy_alter_table = OFF;
+    ",
+];
+
+/// Brings the database up to the latest version, before `initialize` is
+/// run on it. A database without tables is new, and is created at the
+/// latest version rather than migrated.
+///
+/// Each step runs in a transaction of its own with foreign keys off, as
+/// rebuilding a table needs, and the keys are checked before it commits.
+pub(crate) fn migrate(conn: &Connection) -> Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    let is_new = !conn.query_row::<bool, _, _>(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'person');",
+        [],
+        |row| row.get(0),
+    )?;
+    if is_new {
+        return conn.pragma_update(None, "user_version", MIGRATIONS.len());
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.pragma_update(None, "foreign_keys", false)?;
+
+        let result = (|| {
+            let transaction = conn.unchecked_transaction()?;
+            transaction.execute_batch(migration)?;
+
+            let is_broken: bool = transaction.query_row(
+                "SELECT EXISTS (SELECT 1 FROM pragma_foreign_key_check);",
+                [],
+                |row| row.get(0),
+            )?;
+            if is_broken {
+                return Err(rusqlite::Error::SqliteFailure(
+                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT_FOREIGNKEY),
+                    Some(format!(
+                        "migration from version {} breaks foreign keys",
+                        from
+                    )),
+                ));
+            }
+
+            transaction.pragma_update(None, "user_version", from + 1)?;
+            transaction.commit()
+        })();
+
+        conn.pragma_update(None, "foreign_keys", true)?;
+        result?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::account::Account;
+    use crate::model::finance::object::{AssetType, Object};
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+
+    use super::{migrate, MIGRATIONS};
+
+    /// The schema as it was before any migration.
+    const BASELINE: &str = "
+        CREATE TABLE IF NOT EXISTS person (
+            id           INTEGER   NOT NULL  PRIMARY KEY AUTOINCREMENT,
+            nickname     TEXT      NOT NULL  UNIQUE,
+            password     TEXT      NOT NULL,
+            created_at   DATETIME  NOT NULL  DEFAULT CURRENT_TIMESTAMP,
+            updated_at   DATETIME  NOT NULL  DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TRIGGER IF NOT EXISTS update_person_updated_at
+        AFTER UPDATE ON person
+        FOR EACH ROW
+        BEGIN
+            UPDATE person
+            SET updated_at = CURRENT_TIMESTAMP
+            WHERE id = OLD.id;
+        END;
+
+        CREATE TABLE IF NOT EXISTS finance_object (
+            id         INTEGER  NOT NULL  UNIQUE PRIMARY KEY AUTOINCREMENT,
+            owner      INTEGER  NOT NULL,
+            symbol     TEXT     NOT NULL,
+            alias      TEXT,
+            remark     TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE
+        );
+
+        CREATE TRIGGER IF NOT EXISTS update_finance_object_updated_at
+        AFTER UPDATE ON finance_object
+        FOR EACH ROW
+        BEGIN
+            UPDATE finance_object SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+        END;
+
+        CREATE INDEX IF NOT EXISTS idx_finance_object_owner ON finance_object(owner);
+
+        CREATE TABLE IF NOT EXISTS finance_trade (
+            id               INTEGER NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+            owner            INTEGER NOT NULL,
+            base_object_id   INTEGER NOT NULL,
+            quote_object_id  INTEGER NOT NULL,
+            alias            TEXT,
+            remark           TEXT,
+            created_at       DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at       DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(owner) REFERENCES person(id) ON DELETE CASCADE,
+            FOREIGN KEY(base_object_id) REFERENCES finance_object(id) ON DELETE CASCADE,
+            FOREIGN KEY(quote_object_id) REFERENCES finance_object(id) ON DELETE CASCADE
+        );
+
+        CREATE TRIGGER IF NOT EXISTS update_finance_trade_updated_at
+        AFTER UPDATE ON finance_trade
+        FOR EACH ROW
+        BEGIN
+            UPDATE finance_trade SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+        END;
+
+        CREATE INDEX IF NOT EXISTS idx_finance_trade_owner ON finance_trade(owner);
+
+        CREATE TABLE IF NOT EXISTS finance_trade_transaction (
+            id                INTEGER  NOT NULL UNIQUE PRIMARY KEY AUTOINCREMENT,
+            trade_id          INTEGER  NOT NULL,
+            quantity          TEXT     NOT NULL,
+            is_base_to_quote  BOOL     NOT NULL,
+            alias             TEXT,
+            remark            TEXT,
+            occurrence_at     DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            created_at        DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at        DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(trade_id) REFERENCES finance_trade(id) ON DELETE CASCADE
+        );
+
+        CREATE TRIGGER IF NOT EXISTS update_finance_trade_transaction_updated_at
+        AFTER UPDATE ON finance_trade_transaction
+        FOR EACH ROW
+        BEGIN
+            UPDATE finance_trade_transaction SET updated_at = CURRENT_TIMESTAMP WHERE id = OLD.id;
+        END;
+
+        CREATE INDEX IF NOT EXISTS idx_finance_trade_transaction_trade_id ON finance_trade_transaction(trade_id);
+
+        INSERT INTO person (nickname, password) VALUES ('alice', 'test'), ('bob', 'test');
+        INSERT INTO finance_object (owner, symbol) VALUES (1, 'BTC'), (1, 'USD'), (2, 'ETH'), (2, 'EUR');
+        INSERT INTO finance_trade (owner, base_object_id, quote_object_id) VALUES (1, 1, 2), (2, 3, 4);
+        INSERT INTO finance_trade_transaction (trade_id, quantity, is_base_to_quote, alias)
+        VALUES (1, '0.5', FALSE, 'buy'), (1, '0.2', TRUE, 'sell'), (2, '3', FALSE, NULL);
+    ";
+
+    fn version(conn: &rusqlite::Connection) -> usize {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_migrate_baseline() {
+        let pool = r2d2::Pool::new(SqliteConnectionManager::memory()).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(BASELINE).unwrap();
+
+        migrate(&conn).unwrap();
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len());
+
+        let btc = Object::select_by_id_owner(&conn, 1, 1).unwrap().unwrap();
+        assert_eq!(btc.classification.asset_type, AssetType::Other);
+        assert_eq!(btc.classification.precision, 8);
+
+        // Every owner with transactions has them in a default account
+        for (owner, trade, count) in [(1, 1, 2), (2, 2, 1)] {
+            let accounts = Account::select_all_by_owner(&conn, owner).unwrap();
+            assert_eq!(accounts.len(), 1);
+            assert_eq!(accounts[0].name, "Default");
+
+            let transactions = Transaction::select_all_by_trade_id(&conn, trade).unwrap();
+            assert_eq!(transactions.len(), count);
+            for transaction in transactions {
+                assert_eq!(transaction.account_id, accounts[0].id());
+                assert_eq!(transaction.status, Status::Settled);
+                assert!(transaction.price.is_none());
+            }
+        }
+
+        let buy = Transaction::select_by_id_owner(&conn, 1, 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(buy.quantity.value(), Decimal::new(5, 1));
+        assert_eq!(buy.alias.as_deref(), Some("buy"));
+
+        // New rows keep counting from the migrated ones
+        let account = Account::select_all_by_owner(&conn, 1).unwrap()[0].id();
+        let id = Transaction::insert(
+            &conn,
+            1,
+            account,
+            Decimal::ONE.into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(id, 4);
+
+        // Constraints of the rebuilt tables hold
+        assert!(conn
+            .execute(
+                "UPDATE finance_trade_transaction SET account_id = 99 WHERE id = 1;",
+                []
+            )
+            .is_err());
+        assert!(conn
+            .execute(
+                "UPDATE finance_trade SET quote_object_id = base_object_id WHERE id = 1;",
+                []
+            )
+            .is_err());
+
+        // Migrating again does nothing
+        migrate(&conn).unwrap();
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len());
+        assert_eq!(
+            Transaction::select_all_by_trade_id(&conn, 1).unwrap().len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_rebuild_trades_under_views_and_triggers() {
+        let pool = r2d2::Pool::new(SqliteConnectionManager::memory()).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(BASELINE).unwrap();
+        migrate(&conn).unwrap();
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        // A database at the first version already has everything that uses
+        // trades around them
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        migrate(&conn).unwrap();
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len());
+
+        let symbols: Vec<String> = conn
+            .prepare("SELECT symbol FROM finance_search_pair ORDER BY trade_id;")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(symbols, vec!["BTC/USD", "ETH/EUR"]);
+        assert_eq!(
+            Transaction::select_all_by_trade_id(&conn, 1).unwrap().len(),
+            2
+        );
+
+        // A trade of an object against itself stops the migration
+        let pool = r2d2::Pool::new(SqliteConnectionManager::memory()).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(BASELINE).unwrap();
+        conn.execute_batch(
+            "INSERT INTO finance_trade (owner, base_object_id, quote_object_id) VALUES (1, 1, 1);",
+        )
+        .unwrap();
+        assert!(migrate(&conn).is_err());
+        assert_eq!(version(&conn), 1);
+    }
+
+    #[test]
+    fn test_migrate_new() {
+        let pool = r2d2::Pool::new(SqliteConnectionManager::memory()).unwrap();
+        let conn = pool.get().unwrap();
+
+        migrate(&conn).unwrap();
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len());
+
+        migrate(&conn).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len());
+    }
+}
@@ -1,8 +1,11 @@
 pub mod finance;
+#[cfg(test)]
+pub(crate) mod fixture;
+pub(crate) mod migration;
 pub mod person;
 
 pub mod database {
-    use rusqlite::Error;
+    use rusqlite::{Connection, Error};
 
     use r2d2::PooledConnection;
     use r2d2_sqlite::SqliteConnectionManager;
@@ -13,10 +16,65 @@ pub mod database {
 
     pub type Result<T> = std::result::Result<T, Error>;
 
+    /// Which way listings are sorted.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Order {
+        #[default]
+        Asc,
+        Desc,
+    }
+
+    impl Order {
+        pub fn as_sql(&self) -> &'static str {
+            match self {
+                Self::Asc => "ASC",
+                Self::Desc => "DESC",
+            }
+        }
+    }
+
+    /// Whether a statement failed for breaking a unique constraint.
+    pub fn is_unique_violation(error: &Error) -> bool {
+        matches!(
+            error,
+            Error::SqliteFailure(failure, _)
+                if failure.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+        )
+    }
+
+    /// Runs `f` inside a savepoint, keeping what it wrote only when it
+    /// succeeds and `keep` accepts its outcome. Savepoints nest, so this
+    /// works whether or not the caller already holds a transaction.
+    pub fn savepoint<T, E: From<Error>>(
+        conn: &Connection,
+        f: impl FnOnce() -> std::result::Result<T, E>,
+        keep: impl FnOnce(&T) -> bool,
+    ) -> std::result::Result<T, E> {
+        conn.execute_batch("SAVEPOINT atomic;")?;
+
+        let result = f();
+        match &result {
+            Ok(value) if keep(value) => conn.execute_batch("RELEASE atomic;")?,
+            _ => conn.execute_batch("ROLLBACK TO atomic; RELEASE atomic;")?,
+        }
+
+        result
+    }
+
+    /// Runs `f` inside a savepoint, so that it either applies in full or not
+    /// at all.
+    pub fn atomically<T>(
+        conn: &Connection,
+        f: impl FnOnce() -> std::result::Result<T, Box<dyn std::error::Error>>,
+    ) -> std::result::Result<T, Box<dyn std::error::Error>> {
+        savepoint(conn, f, |_| true)
+    }
+
     pub fn connection() -> Result<PooledConnection<SqliteConnectionManager>> {
         use crate::consts::database::DATABASE;
 
-        Ok(DATABASE.get().or(Err(Error::ExecuteReturnedResults))?)
+        DATABASE.get().or(Err(Error::ExecuteReturnedResults))
     }
 }
 
@@ -28,8 +86,58 @@ pub(crate) fn initialize() -> String {
     [
         person::Person::initialize(),
         finance::object::Object::initialize(),
+        finance::object::action::CorporateAction::initialize(),
+        finance::account::Account::initialize(),
         finance::trade::Trade::initialize(),
         finance::trade::transaction::Transaction::initialize(),
+        finance::import::Import::initialize(),
+        finance::trade::plan::Plan::initialize(),
+        finance::trade::plan::Run::initialize(),
+        finance::transfer::Transfer::initialize(),
+        finance::flow::Flow::initialize(),
+        finance::journal::Entry::initialize(),
+        finance::journal::Posting::initialize(),
+        finance::price::Price::initialize(),
+        finance::snapshot::Snapshot::initialize(),
+        finance::target::Target::initialize(),
+        finance::tag::Tag::initialize(),
+        finance::category::Category::initialize(),
+        finance::search::Hit::initialize(),
+        finance::attachment::Attachment::initialize(),
     ]
     .concat()
 }
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::database::{atomically, savepoint};
+
+    #[test]
+    fn test_savepoint() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (x INTEGER);").unwrap();
+        let insert = |x: i64| conn.execute("INSERT INTO t VALUES (?1)", [x]);
+        let count = || -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        savepoint(&conn, || insert(1), |_| true).unwrap();
+        savepoint(&conn, || insert(2), |_| false).unwrap();
+        assert!(atomically(&conn, || {
+            insert(3)?;
+            Err::<(), _>("failed".into())
+        })
+        .is_err());
+        assert_eq!(count(), 1);
+
+        // Nested in a transaction, only the inner part is undone
+        conn.execute_batch("BEGIN;").unwrap();
+        insert(4).unwrap();
+        savepoint(&conn, || insert(5), |_| false).unwrap();
+        conn.execute_batch("COMMIT;").unwrap();
+        assert_eq!(count(), 2);
+    }
+}
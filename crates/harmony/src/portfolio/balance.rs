@@ -0,0 +1,733 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+
+use crate::model::finance::flow::{self, Flow};
+use crate::model::finance::object::action::{self, CorporateAction};
+use crate::model::finance::trade::transaction::Transaction;
+use crate::model::finance::trade::Trade;
+use crate::model::finance::transfer::Transfer;
+
+/// The part of a trade transaction a movement comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Leg {
+    Base,
+    Quote,
+    Fee,
+}
+
+/// What caused a movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// One leg of a trade transaction.
+    Transaction {
+        trade_id: i64,
+        transaction_id: i64,
+        leg: Leg,
+    },
+    /// One side of a transfer between two accounts of the owner.
+    Transfer { transfer_id: i64 },
+    /// Holdings coming in or going out without an exchange.
+    Flow { flow_id: i64, kind: flow::Kind },
+    /// A corporate action converting what an account held of an object.
+    Action { action_id: i64, kind: action::Kind },
+}
+
+/// A signed change of the quantity held of one object in one account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movement {
+    pub object_id: i64,
+    pub account_id: i64,
+    pub source: Source,
+    pub quantity: Decimal,
+    pub occurrence_at: DateTime<Utc>,
+}
+
+/// Splits transactions into the movements of their base and quote objects.
+///
+/// A base-to-quote transaction sells `quantity` of the base object for
+/// `quantity * price` of the quote object, the opposite direction buys it.
+/// Transactions without a price only move the base object. A fee is taken
+/// from the same account in its own object. A quote amount that overflows
+/// fails rather than leaving the base leg unbalanced.
+pub fn movements(
+    trades: &[Trade],
+    transactions: &[Transaction],
+) -> Result<Vec<Movement>, Box<dyn Error>> {
+    let pairs: HashMap<i64, (i64, i64)> = trades
+        .iter()
+        .map(|trade| (trade.id(), (trade.base_object_id, trade.quote_object_id)))
+        .collect();
+
+    let mut result = Vec::with_capacity(transactions.len() * 3);
+
+    for transaction in transactions {
+        let Some(&(base_object_id, quote_object_id)) = pairs.get(&transaction.trade_id) else {
+            continue;
+        };
+
+        let sign = if transaction.is_base_to_quote {
+            Decimal::NEGATIVE_ONE
+        } else {
+            Decimal::ONE
+        };
+        let quantity = transaction.quantity.value();
+        let source = |leg| Source::Transaction {
+            trade_id: transaction.trade_id,
+            transaction_id: transaction.id(),
+            leg,
+        };
+
+        result.push(Movement {
+            object_id: base_object_id,
+            account_id: transaction.account_id,
+            source: source(Leg::Base),
+            quantity: sign * quantity,
+            occurrence_at: transaction.occurrence_at,
+        });
+
+        if let (Some(fee), Some(fee_object_id)) = (&transaction.fee, transaction.fee_object_id) {
+            result.push(Movement {
+                object_id: fee_object_id,
+                account_id: transaction.account_id,
+                source: source(Leg::Fee),
+                quantity: -fee.value(),
+                occurrence_at: transaction.occurrence_at,
+            });
+        }
+
+        if let Some(price) = &transaction.price {
+            let amount = transaction.quantity.checked_mul(price).ok_or(format!(
+                "transaction {} overflows its quote amount",
+                transaction.id()
+            ))?;
+
+            result.push(Movement {
+                object_id: quote_object_id,
+                account_id: transaction.account_id,
+                source: source(Leg::Quote),
+                quantity: -sign * amount.value(),
+                occurrence_at: transaction.occurrence_at,
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Splits transfers into a withdrawal from one account and a deposit into
+/// the other, which cancel out across accounts.
+pub fn transfers(transfers: &[Transfer]) -> Vec<Movement> {
+    let mut result = Vec::with_capacity(transfers.len() * 2);
+
+    for transfer in transfers {
+        let source = Source::Transfer {
+            transfer_id: transfer.id(),
+        };
+        let quantity = transfer.quantity.value();
+
+        for (account_id, quantity) in [
+            (transfer.from_account_id, -quantity),
+            (transfer.to_account_id, quantity),
+        ] {
+            result.push(Movement {
+                object_id: transfer.object_id,
+                account_id,
+                source,
+                quantity,
+                occurrence_at: transfer.occurrence_at,
+            });
+        }
+    }
+
+    result
+}
+
+/// Turns flows into movements into or out of their account.
+pub fn flows(flows: &[Flow]) -> Vec<Movement> {
+    flows
+        .iter()
+        .map(|flow| {
+            let quantity = flow.quantity.value();
+
+            Movement {
+                object_id: flow.object_id,
+                account_id: flow.account_id,
+                source: Source::Flow {
+                    flow_id: flow.id(),
+                    kind: flow.kind,
+                },
+                quantity: if flow.kind.is_inflow() {
+                    quantity
+                } else {
+                    -quantity
+                },
+                occurrence_at: flow.occurrence_at,
+            }
+        })
+        .collect()
+}
+
+/// Weaves corporate actions into time-ordered movements. Each action converts
+/// what every account holds of its object right before `effective_at`, so
+/// movements at that very instant are already in the new units. Both
+/// `movements` and `actions` must be in time order. A conversion that
+/// overflows fails rather than dropping what an account held.
+pub fn actions(
+    movements: Vec<Movement>,
+    actions: &[CorporateAction],
+) -> Result<Vec<Movement>, Box<dyn Error>> {
+    let mut held: BTreeMap<(i64, i64), Decimal> = BTreeMap::new();
+    let mut result = Vec::with_capacity(movements.len());
+    let mut movements = movements.into_iter().peekable();
+
+    for action in actions {
+        while let Some(movement) =
+            movements.next_if(|movement| movement.occurrence_at < action.effective_at)
+        {
+            *held
+                .entry((movement.account_id, movement.object_id))
+                .or_default() += movement.quantity;
+            result.push(movement);
+        }
+
+        let holdings: Vec<(i64, Decimal)> = held
+            .iter()
+            .filter(|((_, object_id), quantity)| {
+                *object_id == action.object_id && !quantity.is_zero()
+            })
+            .map(|(&(account_id, _), &quantity)| (account_id, quantity))
+            .collect();
+        let source = Source::Action {
+            action_id: action.id(),
+            kind: action.kind,
+        };
+
+        for (account_id, quantity) in holdings {
+            let overflow = || {
+                format!(
+                    "corporate action {} overflows account {}",
+                    action.id(),
+                    account_id
+                )
+            };
+            let converted = quantity
+                .checked_mul(action.ratio.value())
+                .ok_or_else(overflow)?;
+
+            let changes = match action.successor_object_id {
+                Some(successor_object_id) => vec![
+                    (action.object_id, -quantity),
+                    (successor_object_id, converted),
+                ],
+                None => vec![(
+                    action.object_id,
+                    converted.checked_sub(quantity).ok_or_else(overflow)?,
+                )],
+            };
+
+            for (object_id, quantity) in changes {
+                *held.entry((account_id, object_id)).or_default() += quantity;
+                result.push(Movement {
+                    object_id,
+                    account_id,
+                    source,
+                    quantity,
+                    occurrence_at: action.effective_at,
+                });
+            }
+        }
+    }
+
+    result.extend(movements);
+
+    Ok(result)
+}
+
+/// Loads the movements of every transaction, transfer, flow and corporate
+/// action of the owner up to `until`, in time order. Only settled
+/// transactions count unless `pending` is set, planned and cancelled ones
+/// never do.
+pub fn load(
+    conn: &Connection,
+    owner: i64,
+    until: DateTime<Utc>,
+    pending: bool,
+) -> Result<Vec<Movement>, Box<dyn Error>> {
+    let trades = Trade::select_all_by_owner(conn, owner)?;
+    let transactions = Transaction::select_by_owner_until(conn, owner, until, pending)?;
+
+    let mut result = movements(&trades, &transactions)?;
+    result.extend(transfers(&Transfer::select_by_owner_until(
+        conn, owner, until,
+    )?));
+    result.extend(flows(&Flow::select_by_owner_until(conn, owner, until)?));
+
+    // Stable, so movements at the same instant keep their own order
+    result.sort_by_key(|movement| movement.occurrence_at);
+
+    actions(
+        result,
+        &CorporateAction::select_by_owner_until(conn, owner, until)?,
+    )
+}
+
+/// Sums movements into the balance of each object. Objects whose movements
+/// cancel out are omitted.
+pub fn balances<'a>(movements: impl IntoIterator<Item = &'a Movement>) -> BTreeMap<i64, Decimal> {
+    let mut result: BTreeMap<i64, Decimal> = BTreeMap::new();
+
+    for movement in movements {
+        *result.entry(movement.object_id).or_default() += movement.quantity;
+    }
+
+    result.retain(|_, quantity| !quantity.is_zero());
+
+    result
+}
+
+/// Sums movements into the balance of each object per account, keyed by
+/// account and object. Empty positions are omitted.
+pub fn account_balances<'a>(
+    movements: impl IntoIterator<Item = &'a Movement>,
+) -> BTreeMap<(i64, i64), Decimal> {
+    let mut result: BTreeMap<(i64, i64), Decimal> = BTreeMap::new();
+
+    for movement in movements {
+        *result
+            .entry((movement.account_id, movement.object_id))
+            .or_default() += movement.quantity;
+    }
+
+    result.retain(|_, quantity| !quantity.is_zero());
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::account::Account;
+    use crate::model::finance::flow::{Flow, Kind};
+    use crate::model::finance::object::action::{self, CorporateAction};
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::finance::transfer::Transfer;
+    use crate::model::person::Person;
+
+    type Setup = (
+        PooledConnection<SqliteConnectionManager>,
+        i64,
+        i64,
+        i64,
+        i64,
+        i64,
+    );
+
+    fn setup() -> Setup {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+        let btc = Object::insert(
+            &conn,
+            owner,
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let usdt = Object::insert(
+            &conn,
+            owner,
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let trade = Trade::insert(&conn, owner, btc, usdt, None, None).unwrap();
+        let account = Account::insert(&conn, owner, "Exchange".to_string(), None, None).unwrap();
+
+        (conn, owner, btc, usdt, trade, account)
+    }
+
+    #[test]
+    fn test_balances() {
+        let (conn, owner, btc, usdt, trade, account) = setup();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+
+        // Buy 2 BTC at 100 USDT, then sell 0.5 BTC at 120 USDT
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::new(2, 0).into(),
+            Some(Decimal::new(100, 0).into()),
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(day1),
+        )
+        .unwrap();
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::new(5, 1).into(),
+            Some(Decimal::new(120, 0).into()),
+            None,
+            None,
+            true,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(day2),
+        )
+        .unwrap();
+
+        let movements = super::load(&conn, owner, day1, true).unwrap();
+        let balances = super::balances(&movements);
+        assert_eq!(balances[&btc], Decimal::new(2, 0));
+        assert_eq!(balances[&usdt], Decimal::new(-200, 0));
+
+        let movements = super::load(&conn, owner, day2, true).unwrap();
+        let balances = super::balances(&movements);
+        assert_eq!(balances[&btc], Decimal::new(15, 1));
+        assert_eq!(balances[&usdt], Decimal::new(-140, 0));
+    }
+
+    #[test]
+    fn test_balances_without_price() {
+        let (conn, owner, btc, usdt, trade, account) = setup();
+
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::new(1, 0).into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let movements = super::load(&conn, owner, Utc::now(), true).unwrap();
+        let balances = super::balances(&movements);
+        assert_eq!(balances[&btc], Decimal::new(1, 0));
+        assert!(!balances.contains_key(&usdt));
+    }
+
+    #[test]
+    fn test_overflowing_quote_amount_fails() {
+        let (conn, owner, _btc, _usdt, trade, account) = setup();
+
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::MAX.into(),
+            Some(Decimal::new(2, 0).into()),
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert!(super::load(&conn, owner, Utc::now(), true).is_err());
+    }
+
+    #[test]
+    fn test_fee_is_taken_from_account() {
+        let (conn, owner, btc, usdt, trade, account) = setup();
+
+        // Buy 1 BTC at 100 USDT, paying 1 USDT on top
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::new(1, 0).into(),
+            Some(Decimal::new(100, 0).into()),
+            Some(Decimal::new(1, 0).into()),
+            Some(usdt),
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let movements = super::load(&conn, owner, Utc::now(), true).unwrap();
+        let balances = super::balances(&movements);
+        assert_eq!(balances[&btc], Decimal::new(1, 0));
+        assert_eq!(balances[&usdt], Decimal::new(-101, 0));
+    }
+
+    #[test]
+    fn test_flows_move_in_and_out() {
+        let (conn, owner, _btc, usdt, _trade, account) = setup();
+
+        for (kind, quantity) in [
+            (Kind::Deposit, 100),
+            (Kind::Interest, 2),
+            (Kind::Expense, 5),
+            (Kind::Withdrawal, 30),
+        ] {
+            Flow::insert(
+                &conn,
+                owner,
+                kind,
+                usdt,
+                account,
+                Decimal::new(quantity, 0).into(),
+                None,
+                None,
+                None,
+                None,
+                Utc::now(),
+            )
+            .unwrap();
+        }
+
+        let movements = super::load(&conn, owner, Utc::now(), true).unwrap();
+        let balances = super::balances(&movements);
+        assert_eq!(balances[&usdt], Decimal::new(67, 0));
+    }
+
+    #[test]
+    fn test_transfer_moves_between_accounts() {
+        let (conn, owner, btc, _usdt, trade, exchange) = setup();
+        let wallet = Account::insert(&conn, owner, "Wallet".to_string(), None, None).unwrap();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+
+        Transaction::insert(
+            &conn,
+            trade,
+            exchange,
+            Decimal::new(2, 0).into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(day1),
+        )
+        .unwrap();
+        Transfer::insert(
+            &conn,
+            owner,
+            btc,
+            exchange,
+            wallet,
+            Decimal::new(5, 1).into(),
+            None,
+            day2,
+        )
+        .unwrap();
+
+        let movements = super::load(&conn, owner, day2, true).unwrap();
+
+        // The aggregated balance is unchanged by the transfer
+        let balances = super::balances(&movements);
+        assert_eq!(balances[&btc], Decimal::new(2, 0));
+
+        let balances = super::account_balances(&movements);
+        assert_eq!(balances[&(exchange, btc)], Decimal::new(15, 1));
+        assert_eq!(balances[&(wallet, btc)], Decimal::new(5, 1));
+    }
+
+    #[test]
+    fn test_split_applies_from_effective_date() {
+        let (conn, owner, btc, usdt, trade, account) = setup();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let day3 = Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap();
+
+        // Buy 2 BTC at 100 USDT, a 4-for-1 split, then sell 1 of the new units
+        for (quantity, price, is_base_to_quote, at) in [(2, 100, false, day1), (1, 30, true, day3)]
+        {
+            Transaction::insert(
+                &conn,
+                trade,
+                account,
+                Decimal::new(quantity, 0).into(),
+                Some(Decimal::new(price, 0).into()),
+                None,
+                None,
+                is_base_to_quote,
+                Status::Settled,
+                None,
+                None,
+                None,
+                Some(at),
+            )
+            .unwrap();
+        }
+        CorporateAction::insert(
+            &conn,
+            btc,
+            action::Kind::Split,
+            Decimal::new(4, 0).into(),
+            None,
+            None,
+            day2,
+        )
+        .unwrap();
+
+        let balances = super::balances(&super::load(&conn, owner, day1, true).unwrap());
+        assert_eq!(balances[&btc], Decimal::new(2, 0));
+
+        let balances = super::balances(&super::load(&conn, owner, day2, true).unwrap());
+        assert_eq!(balances[&btc], Decimal::new(8, 0));
+
+        let balances = super::balances(&super::load(&conn, owner, day3, true).unwrap());
+        assert_eq!(balances[&btc], Decimal::new(7, 0));
+        assert_eq!(balances[&usdt], Decimal::new(-170, 0));
+
+        // The transaction rows keep the quantity they were entered in
+        let transactions = Transaction::select_all_by_trade_id(&conn, trade).unwrap();
+        assert_eq!(transactions[0].quantity.value(), Decimal::new(2, 0));
+    }
+
+    #[test]
+    fn test_merger_moves_holdings_to_successor() {
+        let (conn, owner, btc, _usdt, trade, exchange) = setup();
+        let wallet = Account::insert(&conn, owner, "Wallet".to_string(), None, None).unwrap();
+        let successor = Object::insert(
+            &conn,
+            owner,
+            "NEW".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+
+        Transaction::insert(
+            &conn,
+            trade,
+            exchange,
+            Decimal::new(3, 0).into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(day1),
+        )
+        .unwrap();
+        Transfer::insert(
+            &conn,
+            owner,
+            btc,
+            exchange,
+            wallet,
+            Decimal::new(1, 0).into(),
+            None,
+            day1,
+        )
+        .unwrap();
+        CorporateAction::insert(
+            &conn,
+            btc,
+            action::Kind::Merger,
+            Decimal::new(15, 1).into(),
+            Some(successor),
+            None,
+            day2,
+        )
+        .unwrap();
+
+        let movements = super::load(&conn, owner, day2, true).unwrap();
+
+        let balances = super::balances(&movements);
+        assert!(!balances.contains_key(&btc));
+        assert_eq!(balances[&successor], Decimal::new(45, 1));
+
+        // Every account is converted on its own
+        let balances = super::account_balances(&movements);
+        assert_eq!(balances[&(exchange, successor)], Decimal::new(3, 0));
+        assert_eq!(balances[&(wallet, successor)], Decimal::new(15, 1));
+    }
+
+    #[test]
+    fn test_pending_can_be_excluded() {
+        let (conn, owner, btc, _usdt, trade, account) = setup();
+
+        for (quantity, status) in [
+            (1, Status::Settled),
+            (2, Status::Pending),
+            (4, Status::Planned),
+            (8, Status::Cancelled),
+        ] {
+            Transaction::insert(
+                &conn,
+                trade,
+                account,
+                Decimal::new(quantity, 0).into(),
+                None,
+                None,
+                None,
+                false,
+                status,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        let balances = super::balances(&super::load(&conn, owner, Utc::now(), true).unwrap());
+        assert_eq!(balances[&btc], Decimal::new(3, 0));
+
+        let balances = super::balances(&super::load(&conn, owner, Utc::now(), false).unwrap());
+        assert_eq!(balances[&btc], Decimal::ONE);
+    }
+}
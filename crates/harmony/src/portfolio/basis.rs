@@ -0,0 +1,624 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+
+use crate::model::finance::flow;
+use crate::model::finance::object::action::CorporateAction;
+use crate::model::finance::price::Price;
+
+use super::balance::{self, Leg, Movement, Source};
+use super::valuation::Timeline;
+
+/// A quantity of one object acquired at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lot {
+    pub object_id: i64,
+    pub acquired_at: DateTime<Utc>,
+    pub quantity: Decimal,
+    /// What the lot cost in the reporting object, `None` when it could not be
+    /// priced.
+    pub cost: Option<Decimal>,
+}
+
+/// Part of a lot given up in exchange for something else.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disposal {
+    pub object_id: i64,
+    /// When the lot was acquired, `None` when more was given up than held.
+    pub acquired_at: Option<DateTime<Utc>>,
+    pub disposed_at: DateTime<Utc>,
+    pub quantity: Decimal,
+    pub cost: Option<Decimal>,
+    pub proceeds: Option<Decimal>,
+}
+
+impl Disposal {
+    pub fn gain(&self) -> Option<Decimal> {
+        Some(self.proceeds? - self.cost?)
+    }
+}
+
+/// What happens to the holding of one object as seen by the cost basis.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Change(Change),
+    /// Every lot of `object_id` turns into `ratio` times as many units of
+    /// `into`, keeping its cost and when it was acquired.
+    Conversion {
+        object_id: i64,
+        into: i64,
+        ratio: Decimal,
+        at: DateTime<Utc>,
+    },
+}
+
+/// Holdings of one object acquired or given up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub object_id: i64,
+    pub at: DateTime<Utc>,
+    /// Positive when acquired, negative when given up.
+    pub quantity: Decimal,
+    /// What was paid for or received for the whole quantity, in the
+    /// reporting object.
+    pub value: Option<Decimal>,
+    /// Whether giving up realizes a gain. Otherwise lots leave at cost, as
+    /// when holdings are withdrawn.
+    pub realizes: bool,
+}
+
+/// The open lots and the disposals that closed the others.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Basis {
+    /// Grouped by object, oldest first.
+    pub lots: Vec<Lot>,
+    pub disposals: Vec<Disposal>,
+}
+
+/// The lots of one object taken together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub object_id: i64,
+    pub quantity: Decimal,
+    /// `None` when any lot could not be priced.
+    pub cost: Option<Decimal>,
+}
+
+impl Basis {
+    pub fn positions(&self) -> Vec<Position> {
+        self.lots
+            .chunk_by(|a, b| a.object_id == b.object_id)
+            .map(|lots| Position {
+                object_id: lots[0].object_id,
+                quantity: lots.iter().map(|lot| lot.quantity).sum(),
+                cost: lots.iter().map(|lot| lot.cost).sum(),
+            })
+            .collect()
+    }
+
+    /// Sums the gains of every disposal that could be priced.
+    pub fn realized(&self) -> Decimal {
+        self.disposals.iter().filter_map(Disposal::gain).sum()
+    }
+}
+
+/// Values movements in the reporting object `object_id` at the time they
+/// happened. Both `movements` and `prices` must be in time order.
+///
+/// A trade is valued at what changed hands, so both its legs carry the
+/// value of the quote amount and the fee adds to what was paid or takes from
+/// what was received. Everything else is valued at the market, so income
+/// establishes a basis at its value when received. Transfers don't change
+/// the basis and the reporting object itself has none. Corporate actions
+/// convert the lots rather than acquiring or giving up anything.
+pub fn events(
+    movements: &[Movement],
+    prices: &[Price],
+    actions: &[CorporateAction],
+    object_id: i64,
+) -> Vec<Event> {
+    // The quote and fee legs of each transaction, to value its base leg
+    let mut legs: HashMap<i64, [Option<(i64, Decimal)>; 2]> = HashMap::new();
+    for movement in movements {
+        if let Source::Transaction {
+            transaction_id,
+            leg,
+            ..
+        } = movement.source
+        {
+            let index = match leg {
+                Leg::Base => continue,
+                Leg::Quote => 0,
+                Leg::Fee => 1,
+            };
+
+            legs.entry(transaction_id).or_default()[index] =
+                Some((movement.object_id, movement.quantity.abs()));
+        }
+    }
+
+    let actions: HashMap<i64, &CorporateAction> =
+        actions.iter().map(|action| (action.id(), action)).collect();
+    let mut converted = HashSet::new();
+    let mut timeline = Timeline::new(prices);
+    let mut result = Vec::new();
+
+    for movement in movements {
+        if movement.object_id == object_id {
+            continue;
+        }
+
+        let graph = timeline.graph_at(movement.occurrence_at);
+        let value_of = |of: i64, quantity: Decimal| {
+            graph
+                .conversion(of, object_id)
+                .and_then(|conversion| quantity.abs().checked_mul(conversion.rate))
+        };
+        let market = value_of(movement.object_id, movement.quantity);
+
+        let (value, realizes) = match movement.source {
+            Source::Transfer { .. } => continue,
+            Source::Action { action_id, .. } => {
+                // One movement per account and object, but one conversion
+                if let Some(action) = actions.get(&action_id) {
+                    if movement.object_id == action.object_id && converted.insert(action_id) {
+                        result.push(Event::Conversion {
+                            object_id: action.object_id,
+                            into: action.target_object_id(),
+                            ratio: action.ratio.value(),
+                            at: action.effective_at,
+                        });
+                    }
+                }
+                continue;
+            }
+            Source::Flow { kind, .. } => (market, kind != flow::Kind::Withdrawal),
+            Source::Transaction {
+                transaction_id,
+                leg: Leg::Base,
+                ..
+            } => {
+                let [quote, fee] = legs.get(&transaction_id).copied().unwrap_or_default();
+                let exchange = quote.and_then(|(of, q)| value_of(of, q)).or(market);
+
+                let value = match fee {
+                    None => exchange,
+                    Some((of, q)) => exchange.zip(value_of(of, q)).map(|(exchange, fee)| {
+                        if movement.quantity.is_sign_positive() {
+                            exchange + fee
+                        } else {
+                            exchange - fee
+                        }
+                    }),
+                };
+
+                (value, true)
+            }
+            Source::Transaction { .. } => (market, true),
+        };
+
+        result.push(Event::Change(Change {
+            object_id: movement.object_id,
+            at: movement.occurrence_at,
+            quantity: movement.quantity,
+            value,
+            realizes,
+        }));
+    }
+
+    result
+}
+
+/// `part / whole` of `amount`.
+fn share(amount: Option<Decimal>, part: Decimal, whole: Decimal) -> Option<Decimal> {
+    amount?.checked_mul(part)?.checked_div(whole)
+}
+
+/// Matches disposals against the oldest lots first. Giving up more than is
+/// held leaves a disposal without a basis rather than a negative lot. A
+/// conversion that overflows a lot fails rather than losing the lot.
+pub fn fold(events: &[Event]) -> Result<Basis, Box<dyn Error>> {
+    let mut lots: BTreeMap<i64, VecDeque<Lot>> = BTreeMap::new();
+    let mut disposals = Vec::new();
+
+    for event in events {
+        let event = match event {
+            Event::Change(change) => change,
+            &Event::Conversion {
+                object_id,
+                into,
+                ratio,
+                ..
+            } => {
+                let converted = lots
+                    .remove(&object_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|lot| {
+                        Some(Lot {
+                            object_id: into,
+                            quantity: lot.quantity.checked_mul(ratio)?,
+                            ..lot
+                        })
+                    })
+                    .collect::<Option<Vec<Lot>>>()
+                    .ok_or(format!(
+                        "converting lots of object {} by {} overflows",
+                        object_id, ratio
+                    ))?;
+
+                let queue = lots.entry(into).or_default();
+                queue.extend(converted);
+                queue.make_contiguous().sort_by_key(|lot| lot.acquired_at);
+                continue;
+            }
+        };
+        let queue = lots.entry(event.object_id).or_default();
+
+        if event.quantity.is_sign_positive() {
+            queue.push_back(Lot {
+                object_id: event.object_id,
+                acquired_at: event.at,
+                quantity: event.quantity,
+                cost: event.value,
+            });
+            continue;
+        }
+
+        let total = -event.quantity;
+        let mut remaining = total;
+
+        while remaining > Decimal::ZERO {
+            let Some(lot) = queue.front_mut() else {
+                break;
+            };
+
+            let taken = remaining.min(lot.quantity);
+            let cost = share(lot.cost, taken, lot.quantity);
+            let acquired_at = lot.acquired_at;
+
+            lot.cost = lot.cost.zip(cost).map(|(lot, taken)| lot - taken);
+            lot.quantity -= taken;
+            if lot.quantity.is_zero() {
+                queue.pop_front();
+            }
+            remaining -= taken;
+
+            if event.realizes {
+                disposals.push(Disposal {
+                    object_id: event.object_id,
+                    acquired_at: Some(acquired_at),
+                    disposed_at: event.at,
+                    quantity: taken,
+                    cost,
+                    proceeds: share(event.value, taken, total),
+                });
+            }
+        }
+
+        if remaining > Decimal::ZERO && event.realizes {
+            disposals.push(Disposal {
+                object_id: event.object_id,
+                acquired_at: None,
+                disposed_at: event.at,
+                quantity: remaining,
+                cost: None,
+                proceeds: share(event.value, remaining, total),
+            });
+        }
+    }
+
+    Ok(Basis {
+        lots: lots.into_values().flatten().collect(),
+        disposals,
+    })
+}
+
+/// Loads the owner's data and computes the cost basis in `object_id` as of
+/// `until`.
+pub fn load(
+    conn: &Connection,
+    owner: i64,
+    object_id: i64,
+    until: DateTime<Utc>,
+) -> Result<Basis, Box<dyn Error>> {
+    let movements = balance::load(conn, owner, until, true)?;
+    let prices = Price::select_by_owner_until(conn, owner, until)?;
+    let actions = CorporateAction::select_by_owner_until(conn, owner, until)?;
+
+    fold(&events(&movements, &prices, &actions, object_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::account::Account;
+    use crate::model::finance::flow::{Flow, Kind};
+    use crate::model::finance::object::action::{self, CorporateAction};
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::price::Price;
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::person::Person;
+
+    use super::{fold, Change, Event};
+
+    type Setup = (
+        PooledConnection<SqliteConnectionManager>,
+        i64,
+        i64,
+        i64,
+        i64,
+        i64,
+    );
+
+    fn setup() -> Setup {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+        let btc = Object::insert(
+            &conn,
+            owner,
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let usdt = Object::insert(
+            &conn,
+            owner,
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let trade = Trade::insert(&conn, owner, btc, usdt, None, None).unwrap();
+        let account = Account::insert(&conn, owner, "Exchange".to_string(), None, None).unwrap();
+
+        (conn, owner, btc, usdt, trade, account)
+    }
+
+    fn event(quantity: i64, value: Option<i64>, day: u32) -> Event {
+        Event::Change(Change {
+            object_id: 1,
+            at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+            quantity: Decimal::from(quantity),
+            value: value.map(Decimal::from),
+            realizes: true,
+        })
+    }
+
+    #[test]
+    fn test_fold_matches_oldest_lots_first() {
+        let basis = fold(&[
+            event(2, Some(100), 1),
+            event(2, Some(300), 2),
+            event(-3, Some(600), 3),
+        ])
+        .unwrap();
+
+        assert_eq!(basis.disposals.len(), 2);
+        assert_eq!(basis.disposals[0].quantity, Decimal::from(2));
+        assert_eq!(basis.disposals[0].gain(), Some(Decimal::from(300)));
+        assert_eq!(basis.disposals[1].quantity, Decimal::from(1));
+        assert_eq!(basis.disposals[1].gain(), Some(Decimal::from(50)));
+
+        let positions = basis.positions();
+        assert_eq!(positions[0].quantity, Decimal::ONE);
+        assert_eq!(positions[0].cost, Some(Decimal::from(150)));
+        assert_eq!(basis.realized(), Decimal::from(350));
+    }
+
+    #[test]
+    fn test_fold_without_basis() {
+        let basis = fold(&[event(1, None, 1), event(-2, Some(10), 2)]).unwrap();
+
+        assert_eq!(basis.disposals[0].cost, None);
+        assert_eq!(basis.disposals[0].proceeds, Some(Decimal::from(5)));
+        assert_eq!(basis.disposals[1].acquired_at, None);
+        assert!(basis.lots.is_empty());
+        assert_eq!(basis.realized(), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_fold_converts_lots() {
+        let basis = fold(&[
+            event(2, Some(100), 1),
+            Event::Conversion {
+                object_id: 1,
+                into: 2,
+                ratio: Decimal::from(3),
+                at: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+            },
+        ])
+        .unwrap();
+
+        assert_eq!(basis.lots.len(), 1);
+        assert_eq!(basis.lots[0].object_id, 2);
+        assert_eq!(basis.lots[0].quantity, Decimal::from(6));
+        assert_eq!(basis.lots[0].cost, Some(Decimal::from(100)));
+        assert_eq!(
+            basis.lots[0].acquired_at,
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fold_fails_on_overflowing_conversion() {
+        let mut huge = event(1, Some(100), 1);
+        if let Event::Change(change) = &mut huge {
+            change.quantity = Decimal::MAX;
+        }
+
+        let result = fold(&[
+            huge,
+            Event::Conversion {
+                object_id: 1,
+                into: 1,
+                ratio: Decimal::from(2),
+                at: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+            },
+        ]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_load_after_split() {
+        let (conn, owner, btc, usdt, trade, account) = setup();
+        let day = |d| Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap();
+
+        // Buy 1 BTC at 100 USDT, split 10-for-1, then sell 5 new units at 12 USDT
+        for (quantity, price, is_base_to_quote, at) in [(1, 100, false, 1), (5, 12, true, 3)] {
+            Transaction::insert(
+                &conn,
+                trade,
+                account,
+                Decimal::from(quantity).into(),
+                Some(Decimal::from(price).into()),
+                None,
+                None,
+                is_base_to_quote,
+                Status::Settled,
+                None,
+                None,
+                None,
+                Some(day(at)),
+            )
+            .unwrap();
+        }
+        CorporateAction::insert(
+            &conn,
+            btc,
+            action::Kind::Split,
+            Decimal::from(10).into(),
+            None,
+            None,
+            day(2),
+        )
+        .unwrap();
+
+        let basis = super::load(&conn, owner, usdt, day(3)).unwrap();
+
+        assert_eq!(basis.disposals.len(), 1);
+        assert_eq!(basis.disposals[0].acquired_at, Some(day(1)));
+        assert_eq!(basis.disposals[0].cost, Some(Decimal::from(50)));
+        assert_eq!(basis.disposals[0].gain(), Some(Decimal::from(10)));
+
+        let positions = basis.positions();
+        assert_eq!(positions[0].quantity, Decimal::from(5));
+        assert_eq!(positions[0].cost, Some(Decimal::from(50)));
+    }
+
+    #[test]
+    fn test_load() {
+        let (conn, owner, btc, usdt, trade, account) = setup();
+        let day = |d| Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap();
+
+        // Buy 2 BTC at 100 USDT paying 1 USDT, then sell 1 BTC at 150 USDT
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::from(2).into(),
+            Some(Decimal::from(100).into()),
+            Some(Decimal::ONE.into()),
+            Some(usdt),
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(day(1)),
+        )
+        .unwrap();
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::ONE.into(),
+            Some(Decimal::from(150).into()),
+            None,
+            None,
+            true,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(day(2)),
+        )
+        .unwrap();
+
+        // A staking reward is acquired at its market value
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::from(200).into(),
+            None,
+            day(3),
+        )
+        .unwrap();
+        Flow::insert(
+            &conn,
+            owner,
+            Kind::Staking,
+            btc,
+            account,
+            Decimal::new(1, 1).into(),
+            None,
+            None,
+            None,
+            None,
+            day(4),
+        )
+        .unwrap();
+
+        // Withdrawing gives up lots without realizing a gain
+        Flow::insert(
+            &conn,
+            owner,
+            Kind::Withdrawal,
+            btc,
+            account,
+            Decimal::new(5, 1).into(),
+            None,
+            None,
+            None,
+            None,
+            day(5),
+        )
+        .unwrap();
+
+        let basis = super::load(&conn, owner, usdt, day(5)).unwrap();
+
+        assert_eq!(basis.disposals.len(), 1);
+        assert_eq!(basis.disposals[0].cost, Some(Decimal::new(1005, 1)));
+        assert_eq!(basis.disposals[0].gain(), Some(Decimal::new(495, 1)));
+
+        let positions = basis.positions();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].quantity, Decimal::new(6, 1));
+        assert_eq!(positions[0].cost, Some(Decimal::new(7025, 2)));
+    }
+}
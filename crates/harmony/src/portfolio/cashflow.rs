@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
+
+use crate::model::finance::flow::{Flow, Kind};
+use crate::model::finance::price::Price;
+
+use super::valuation::Timeline;
+
+/// The flows of one kind taken together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub kind: Kind,
+    pub count: usize,
+    /// What landed in or left the accounts, in the reporting object.
+    pub value: Decimal,
+    /// Tax withheld at source, in the reporting object.
+    pub tax_withheld: Decimal,
+    /// Flows that could not be valued and are missing from the sums.
+    pub unpriced: usize,
+}
+
+/// Values flows in the reporting object `object_id` at the time they
+/// happened and sums them up per kind. Both `flows` and `prices` must be in
+/// time order.
+pub fn summarize(flows: &[Flow], prices: &[Price], object_id: i64) -> Vec<Summary> {
+    let mut timeline = Timeline::new(prices);
+    let mut result: BTreeMap<Kind, Summary> = BTreeMap::new();
+
+    for flow in flows {
+        let graph = timeline.graph_at(flow.occurrence_at);
+        let rate = graph
+            .conversion(flow.object_id, object_id)
+            .map(|conversion| conversion.rate);
+
+        let summary = result.entry(flow.kind).or_insert(Summary {
+            kind: flow.kind,
+            count: 0,
+            value: Decimal::ZERO,
+            tax_withheld: Decimal::ZERO,
+            unpriced: 0,
+        });
+        summary.count += 1;
+
+        let value = rate.and_then(|rate| flow.quantity.value().checked_mul(rate));
+        let tax_withheld = match &flow.tax_withheld {
+            Some(tax) => rate.and_then(|rate| tax.value().checked_mul(rate)),
+            None => Some(Decimal::ZERO),
+        };
+
+        match value.zip(tax_withheld) {
+            Some((value, tax_withheld)) => {
+                summary.value += value;
+                summary.tax_withheld += tax_withheld;
+            }
+            None => summary.unpriced += 1,
+        }
+    }
+
+    result.into_values().collect()
+}
@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+
+use crate::model::finance::category::Category;
+use crate::model::finance::trade::transaction::Transaction;
+use crate::model::finance::trade::Trade;
+
+/// The settled trade transactions of one category that are paid in one
+/// quote object, taken together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// The path of the category, cut down to the depth asked for, or `None`
+    /// for transactions without a category.
+    pub path: Option<String>,
+    pub quote_object_id: i64,
+    pub count: usize,
+    /// Paid for the base object, in the quote object.
+    pub spent: Decimal,
+    /// Received for the base object, in the quote object.
+    pub received: Decimal,
+    /// Fees charged in the quote object.
+    pub fee: Decimal,
+    /// Transactions without a price, missing from `spent` and `received`.
+    pub unpriced: usize,
+}
+
+/// Keeps the first `depth` names of a path, so that `Expenses/Fees` counts
+/// towards `Expenses` at a depth of one.
+fn cut(path: &str, depth: Option<usize>) -> String {
+    match depth {
+        Some(depth) => path.split('/').take(depth).collect::<Vec<&str>>().join("/"),
+        None => path.to_string(),
+    }
+}
+
+/// Sums up the settled transactions of the owner that occurred from `from`,
+/// or the first one when omitted, up to `to` per category and quote object.
+/// With a `depth`, categories further down count towards their ancestor at
+/// that depth.
+pub fn summarize(
+    conn: &Connection,
+    owner: i64,
+    from: Option<DateTime<Utc>>,
+    to: DateTime<Utc>,
+    depth: Option<usize>,
+) -> Result<Vec<Summary>, Box<dyn Error>> {
+    let trades: HashMap<i64, Trade> = Trade::select_all_by_owner(conn, owner)?
+        .into_iter()
+        .map(|trade| (trade.id(), trade))
+        .collect();
+    let paths = Category::select_paths_by_owner(conn, owner)?;
+
+    let transactions = Transaction::select_by_owner_until(conn, owner, to, false)?;
+
+    let mut result: BTreeMap<(Option<String>, i64), Summary> = BTreeMap::new();
+
+    for transaction in transactions {
+        if from.is_some_and(|from| transaction.occurrence_at < from) {
+            continue;
+        }
+
+        let trade = trades
+            .get(&transaction.trade_id)
+            .ok_or(format!("trade {} does not exist", transaction.trade_id))?;
+        let path = paths.get(&transaction.id()).map(|path| cut(path, depth));
+
+        let summary = result
+            .entry((path.clone(), trade.quote_object_id))
+            .or_insert(Summary {
+                path,
+                quote_object_id: trade.quote_object_id,
+                count: 0,
+                spent: Decimal::ZERO,
+                received: Decimal::ZERO,
+                fee: Decimal::ZERO,
+                unpriced: 0,
+            });
+        summary.count += 1;
+
+        if let (Some(fee), Some(fee_object_id)) = (&transaction.fee, transaction.fee_object_id) {
+            if fee_object_id == trade.quote_object_id {
+                summary.fee += fee.value();
+            }
+        }
+
+        let Some(price) = &transaction.price else {
+            summary.unpriced += 1;
+            continue;
+        };
+        let amount = transaction
+            .quantity
+            .value()
+            .checked_mul(price.value())
+            .ok_or(format!(
+                "transaction {} overflows its total",
+                transaction.id()
+            ))?;
+
+        if transaction.is_base_to_quote {
+            summary.received += amount;
+        } else {
+            summary.spent += amount;
+        }
+    }
+
+    Ok(result.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::category::Category;
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::fixture;
+
+    use super::{cut, summarize};
+
+    #[test]
+    fn test_cut() {
+        assert_eq!(cut("Expenses/Fees/Trading", Some(2)), "Expenses/Fees");
+        assert_eq!(cut("Expenses", Some(2)), "Expenses");
+        assert_eq!(cut("Expenses/Fees", None), "Expenses/Fees");
+    }
+
+    #[test]
+    fn test_summarize() {
+        let conn = fixture::connection();
+        let owner = fixture::person(&conn, "test_user");
+        let (btc, usd) = (
+            fixture::object(&conn, owner, "BTC"),
+            fixture::object(&conn, owner, "USD"),
+        );
+        let account = fixture::account(&conn, owner, "exchange");
+        let trade = fixture::trade(&conn, owner, btc, usd);
+
+        let savings = Category::insert(&conn, owner, None, "Savings".to_string()).unwrap();
+        let retirement =
+            Category::insert(&conn, owner, Some(savings), "Retirement".to_string()).unwrap();
+        let spending = Category::insert(&conn, owner, None, "Spending".to_string()).unwrap();
+
+        // Quantity, price, sale, status, day and category
+        let rows = [
+            (2, Some(100), false, Status::Settled, 1, Some(savings)),
+            (1, Some(120), false, Status::Settled, 2, Some(retirement)),
+            (1, Some(150), true, Status::Settled, 3, Some(spending)),
+            (1, None, false, Status::Settled, 4, Some(spending)),
+            (5, Some(150), false, Status::Pending, 5, Some(spending)),
+            (1, Some(90), false, Status::Settled, 6, None),
+        ];
+        for (quantity, price, is_base_to_quote, status, day, category) in rows {
+            let id = Transaction::insert(
+                &conn,
+                trade,
+                account,
+                Decimal::from(quantity).into(),
+                price.map(|price| Decimal::from(price).into()),
+                Some(Decimal::ONE.into()),
+                Some(usd),
+                is_base_to_quote,
+                status,
+                None,
+                None,
+                None,
+                Some(Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap()),
+            )
+            .unwrap();
+            if let Some(category) = category {
+                Category::assign(&conn, category, id).unwrap();
+            }
+        }
+
+        let to = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        let summaries = summarize(&conn, owner, None, to, None).unwrap();
+        let paths: Vec<Option<&str>> = summaries
+            .iter()
+            .map(|summary| summary.path.as_deref())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                None,
+                Some("Savings"),
+                Some("Savings/Retirement"),
+                Some("Spending")
+            ]
+        );
+        assert_eq!(summaries[0].spent, Decimal::from(90));
+
+        let spending = &summaries[3];
+        assert_eq!(spending.count, 2);
+        assert_eq!(spending.received, Decimal::from(150));
+        assert_eq!(spending.spent, Decimal::ZERO);
+        assert_eq!(spending.fee, Decimal::from(2));
+        assert_eq!(spending.unpriced, 1);
+
+        // Retirement counts towards savings at the top level
+        let summaries = summarize(&conn, owner, None, to, Some(1)).unwrap();
+        assert_eq!(summaries[1].path.as_deref(), Some("Savings"));
+        assert_eq!(summaries[1].count, 2);
+        assert_eq!(summaries[1].spent, Decimal::from(320));
+
+        let from = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+        let summaries = summarize(&conn, owner, Some(from), to, Some(1)).unwrap();
+        assert_eq!(summaries[1].spent, Decimal::from(120));
+    }
+}
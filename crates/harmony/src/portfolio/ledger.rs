@@ -0,0 +1,585 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::model::database::atomically;
+use crate::model::finance::flow::{self, Flow};
+use crate::model::finance::journal::{Entry, Kind, Posting};
+use crate::model::finance::trade::transaction::Transaction;
+use crate::model::finance::trade::Trade;
+
+/// A posting about to be recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub kind: Kind,
+    pub account_id: Option<i64>,
+    pub object_id: i64,
+    pub amount: Decimal,
+}
+
+/// The amount by which one object of one entry fails to sum to zero.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Imbalance {
+    pub entry_id: i64,
+    pub object_id: i64,
+    pub sum: Decimal,
+}
+
+/// The outcome of checking the journal of an owner.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Report {
+    pub imbalances: Vec<Imbalance>,
+    /// Transactions that have no journal entry.
+    pub unrecorded: Vec<i64>,
+    /// Flows that have no journal entry.
+    pub unrecorded_flows: Vec<i64>,
+}
+
+/// Books a trade transaction across the base, quote and fee sides.
+///
+/// The account gains or loses the base and quote objects, and a trading
+/// posting takes the opposite amount of each so that both objects balance
+/// on their own without a conversion rate. A fee leaves the account into the
+/// fee side.
+pub fn transaction_lines(
+    trade: &Trade,
+    transaction: &Transaction,
+) -> Result<Vec<Line>, Box<dyn Error>> {
+    let sign = if transaction.is_base_to_quote {
+        Decimal::NEGATIVE_ONE
+    } else {
+        Decimal::ONE
+    };
+    let account_id = Some(transaction.account_id);
+    let quantity = transaction.quantity.value();
+
+    let mut lines = vec![
+        Line {
+            kind: Kind::Asset,
+            account_id,
+            object_id: trade.base_object_id,
+            amount: sign * quantity,
+        },
+        Line {
+            kind: Kind::Trading,
+            account_id: None,
+            object_id: trade.base_object_id,
+            amount: -sign * quantity,
+        },
+    ];
+
+    if let Some(price) = &transaction.price {
+        let amount = transaction
+            .quantity
+            .checked_mul(price)
+            .ok_or(format!(
+                "transaction {} overflows its quote amount",
+                transaction.id()
+            ))?
+            .value();
+
+        lines.push(Line {
+            kind: Kind::Asset,
+            account_id,
+            object_id: trade.quote_object_id,
+            amount: -sign * amount,
+        });
+        lines.push(Line {
+            kind: Kind::Trading,
+            account_id: None,
+            object_id: trade.quote_object_id,
+            amount: sign * amount,
+        });
+    }
+
+    if let (Some(fee), Some(fee_object_id)) = (&transaction.fee, transaction.fee_object_id) {
+        lines.push(Line {
+            kind: Kind::Asset,
+            account_id,
+            object_id: fee_object_id,
+            amount: -fee.value(),
+        });
+        lines.push(Line {
+            kind: Kind::Fee,
+            account_id: None,
+            object_id: fee_object_id,
+            amount: fee.value(),
+        });
+    }
+
+    Ok(lines)
+}
+
+/// Books a flow between an account and the income, expense or equity side.
+///
+/// Income is booked gross, so tax withheld at source shows up as an expense
+/// next to it.
+pub fn flow_lines(flow: &Flow) -> Vec<Line> {
+    let quantity = flow.quantity.value();
+    let asset = |amount| Line {
+        kind: Kind::Asset,
+        account_id: Some(flow.account_id),
+        object_id: flow.object_id,
+        amount,
+    };
+    let other = |kind, amount| Line {
+        kind,
+        account_id: None,
+        object_id: flow.object_id,
+        amount,
+    };
+
+    match flow.kind {
+        flow::Kind::Deposit => vec![asset(quantity), other(Kind::Equity, -quantity)],
+        flow::Kind::Withdrawal => vec![asset(-quantity), other(Kind::Equity, quantity)],
+        flow::Kind::Expense => vec![asset(-quantity), other(Kind::Expense, quantity)],
+        flow::Kind::Dividend | flow::Kind::Interest | flow::Kind::Staking | flow::Kind::Airdrop => {
+            let tax = flow
+                .tax_withheld
+                .as_ref()
+                .map(|tax| tax.value())
+                .unwrap_or_default();
+
+            let mut lines = vec![asset(quantity), other(Kind::Income, -quantity - tax)];
+            if !tax.is_zero() {
+                lines.push(other(Kind::Expense, tax));
+            }
+
+            lines
+        }
+    }
+}
+
+/// Sums amounts per object, keeping only the objects that don't cancel out.
+fn residuals(amounts: impl IntoIterator<Item = (i64, Decimal)>) -> BTreeMap<i64, Decimal> {
+    let mut result: BTreeMap<i64, Decimal> = BTreeMap::new();
+
+    for (object_id, amount) in amounts {
+        *result.entry(object_id).or_default() += amount;
+    }
+
+    result.retain(|_, sum| !sum.is_zero());
+
+    result
+}
+
+/// Finds the objects of each entry whose postings don't sum to zero.
+/// `postings` must be grouped by entry.
+pub fn imbalances(postings: &[Posting]) -> Vec<Imbalance> {
+    postings
+        .chunk_by(|a, b| a.entry_id == b.entry_id)
+        .flat_map(|entry| {
+            let entry_id = entry[0].entry_id;
+
+            residuals(entry.iter().map(|p| (p.object_id, p.amount.value())))
+                .into_iter()
+                .map(move |(object_id, sum)| Imbalance {
+                    entry_id,
+                    object_id,
+                    sum,
+                })
+        })
+        .collect()
+}
+
+/// Records a new entry, refusing lines that don't balance.
+pub fn record(
+    conn: &Connection,
+    owner: i64,
+    transaction_id: Option<i64>,
+    flow_id: Option<i64>,
+    description: Option<String>,
+    occurrence_at: DateTime<Utc>,
+    lines: &[Line],
+) -> Result<i64, Box<dyn Error>> {
+    if let Some((object_id, sum)) = residuals(lines.iter().map(|l| (l.object_id, l.amount)))
+        .into_iter()
+        .next()
+    {
+        return Err(format!("entry is off by {} in object {}", sum, object_id).into());
+    }
+
+    atomically(conn, || {
+        let entry_id = Entry::insert(
+            conn,
+            owner,
+            transaction_id,
+            flow_id,
+            description,
+            occurrence_at,
+        )?;
+
+        for line in lines {
+            Posting::insert(
+                conn,
+                entry_id,
+                line.kind,
+                line.account_id,
+                line.object_id,
+                line.amount.into(),
+            )?;
+        }
+
+        Ok(entry_id)
+    })
+}
+
+/// Replaces the entry of a trade transaction with one matching its current
+/// state. Transactions that were not executed have no entry.
+pub fn record_transaction(
+    conn: &Connection,
+    owner: i64,
+    trade: &Trade,
+    transaction: &Transaction,
+) -> Result<Option<i64>, Box<dyn Error>> {
+    let lines = transaction_lines(trade, transaction)?;
+
+    atomically(conn, || {
+        Entry::delete_by_transaction_id(conn, transaction.id())?;
+
+        if !transaction.status.is_executed() {
+            return Ok(None);
+        }
+
+        record(
+            conn,
+            owner,
+            Some(transaction.id()),
+            None,
+            transaction.alias.clone(),
+            transaction.occurrence_at,
+            &lines,
+        )
+        .map(Some)
+    })
+}
+
+/// Replaces the entry of a flow with one matching its current state.
+pub fn record_flow(conn: &Connection, flow: &Flow) -> Result<i64, Box<dyn Error>> {
+    let lines = flow_lines(flow);
+
+    atomically(conn, || {
+        Entry::delete_by_flow_id(conn, flow.id())?;
+
+        record(
+            conn,
+            flow.owner,
+            None,
+            Some(flow.id()),
+            flow.remark.clone(),
+            flow.occurrence_at,
+            &lines,
+        )
+    })
+}
+
+/// Records every transaction of a trade anew, such as after its objects
+/// changed, returning how many entries were written.
+pub fn record_trade(conn: &Connection, owner: i64, trade: &Trade) -> Result<usize, Box<dyn Error>> {
+    let transactions = Transaction::select_all_by_trade_id(conn, trade.id())?;
+
+    atomically(conn, || {
+        let mut count = 0;
+
+        for transaction in &transactions {
+            if record_transaction(conn, owner, trade, transaction)?.is_some() {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    })
+}
+
+/// Records every trade transaction and flow of the owner anew, returning
+/// how many entries were written.
+pub fn rebuild(conn: &Connection, owner: i64) -> Result<usize, Box<dyn Error>> {
+    let trades = Trade::select_all_by_owner(conn, owner)?;
+    let flows = Flow::select_all_by_owner(conn, owner)?;
+
+    atomically(conn, || {
+        let mut count = 0;
+
+        for trade in &trades {
+            count += record_trade(conn, owner, trade)?;
+        }
+
+        for flow in &flows {
+            record_flow(conn, flow)?;
+            count += 1;
+        }
+
+        Ok(count)
+    })
+}
+
+/// Reports the entries of the owner that don't balance and the transactions
+/// that were never recorded.
+pub fn check(conn: &Connection, owner: i64) -> Result<Report, Box<dyn Error>> {
+    let postings = Posting::select_by_owner(conn, owner)?;
+
+    Ok(Report {
+        imbalances: imbalances(&postings),
+        unrecorded: Entry::select_unrecorded_transaction_ids(conn, owner)?,
+        unrecorded_flows: Entry::select_unrecorded_flow_ids(conn, owner)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::account::Account;
+    use crate::model::finance::flow::{self, Flow};
+    use crate::model::finance::journal::{Entry, Kind, Posting};
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::person::Person;
+
+    type Setup = (
+        PooledConnection<SqliteConnectionManager>,
+        i64,
+        i64,
+        i64,
+        i64,
+        i64,
+    );
+
+    fn setup() -> Setup {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+        let btc = Object::insert(
+            &conn,
+            owner,
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let usdt = Object::insert(
+            &conn,
+            owner,
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let trade = Trade::insert(&conn, owner, btc, usdt, None, None).unwrap();
+        let account = Account::insert(&conn, owner, "Exchange".to_string(), None, None).unwrap();
+
+        (conn, owner, btc, usdt, trade, account)
+    }
+
+    #[test]
+    fn test_transaction_balances_per_object() {
+        let (conn, owner, btc, usdt, trade, account) = setup();
+
+        // Sell 0.5 BTC at 100 USDT, paying 1 USDT
+        let id = Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::new(5, 1).into(),
+            Some(Decimal::new(100, 0).into()),
+            Some(Decimal::ONE.into()),
+            Some(usdt),
+            true,
+            Status::Settled,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let trade = Trade::select_by_id_owner(&conn, trade, owner)
+            .unwrap()
+            .unwrap();
+        let transaction = Transaction::select_by_id_trade_id(&conn, id, trade.id())
+            .unwrap()
+            .unwrap();
+        let lines = super::transaction_lines(&trade, &transaction).unwrap();
+
+        let asset = |object_id| -> Decimal {
+            lines
+                .iter()
+                .filter(|l| l.kind == Kind::Asset && l.object_id == object_id)
+                .map(|l| l.amount)
+                .sum()
+        };
+        assert_eq!(asset(btc), Decimal::new(-5, 1));
+        assert_eq!(asset(usdt), Decimal::new(49, 0));
+        assert!(lines
+            .iter()
+            .all(|l| l.account_id.is_some() == (l.kind == Kind::Asset)));
+
+        super::record_transaction(&conn, owner, &trade, &transaction).unwrap();
+        // Recording again replaces the entry instead of adding one
+        super::record_transaction(&conn, owner, &trade, &transaction).unwrap();
+
+        assert_eq!(Entry::count_by_owner(&conn, owner).unwrap(), 1);
+        assert_eq!(Posting::select_by_owner(&conn, owner).unwrap().len(), 6);
+
+        let report = super::check(&conn, owner).unwrap();
+        assert!(report.imbalances.is_empty());
+        assert!(report.unrecorded.is_empty());
+    }
+
+    #[test]
+    fn test_income_is_booked_gross() {
+        let (conn, owner, _btc, usdt, _trade, account) = setup();
+
+        // A dividend of 9 USDT after 1 USDT withheld
+        let id = Flow::insert(
+            &conn,
+            owner,
+            flow::Kind::Dividend,
+            usdt,
+            account,
+            Decimal::new(9, 0).into(),
+            None,
+            Some(Decimal::ONE.into()),
+            None,
+            None,
+            Utc::now(),
+        )
+        .unwrap();
+        let flow = Flow::select_by_id_owner(&conn, id, owner).unwrap().unwrap();
+
+        super::record_flow(&conn, &flow).unwrap();
+
+        let postings = Posting::select_by_owner(&conn, owner).unwrap();
+        let sum = |kind| -> Decimal {
+            postings
+                .iter()
+                .filter(|p| p.kind == kind)
+                .map(|p| p.amount.value())
+                .sum()
+        };
+        assert_eq!(sum(Kind::Asset), Decimal::new(9, 0));
+        assert_eq!(sum(Kind::Income), Decimal::new(-10, 0));
+        assert_eq!(sum(Kind::Expense), Decimal::ONE);
+
+        let report = super::check(&conn, owner).unwrap();
+        assert!(report.imbalances.is_empty());
+        assert!(report.unrecorded_flows.is_empty());
+    }
+
+    #[test]
+    fn test_unbalanced_entry_is_refused() {
+        let (conn, owner, btc, _usdt, _trade, account) = setup();
+
+        let lines = [super::Line {
+            kind: Kind::Asset,
+            account_id: Some(account),
+            object_id: btc,
+            amount: Decimal::ONE,
+        }];
+
+        assert!(super::record(&conn, owner, None, None, None, Utc::now(), &lines).is_err());
+        assert_eq!(Entry::count_by_owner(&conn, owner).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_check_reports_problems() {
+        let (conn, owner, btc, _usdt, trade, account) = setup();
+
+        let id = Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::ONE.into(),
+            None,
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let report = super::check(&conn, owner).unwrap();
+        assert_eq!(report.unrecorded, vec![id]);
+
+        assert_eq!(super::rebuild(&conn, owner).unwrap(), 1);
+        assert!(super::check(&conn, owner).unwrap().unrecorded.is_empty());
+
+        // Tamper with a posting behind the ledger's back
+        let entry_id = Entry::select_by_owner(&conn, owner, 1, 0).unwrap()[0].id();
+        Posting::insert(&conn, entry_id, Kind::Fee, None, btc, Decimal::ONE.into()).unwrap();
+
+        let report = super::check(&conn, owner).unwrap();
+        assert_eq!(
+            report.imbalances,
+            vec![super::Imbalance {
+                entry_id,
+                object_id: btc,
+                sum: Decimal::ONE,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unexecuted_transaction_has_no_entry() {
+        let (conn, owner, _btc, _usdt, trade, account) = setup();
+
+        let id = Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::ONE.into(),
+            Some(Decimal::new(100, 0).into()),
+            None,
+            None,
+            false,
+            Status::Planned,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let trade = Trade::select_by_id_owner(&conn, trade, owner)
+            .unwrap()
+            .unwrap();
+        let transaction = Transaction::select_by_id_trade_id(&conn, id, trade.id())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            super::record_transaction(&conn, owner, &trade, &transaction).unwrap(),
+            None
+        );
+        assert!(super::check(&conn, owner).unwrap().unrecorded.is_empty());
+
+        // Once executed it is booked
+        Transaction::update_status_by_id_trade_id(&conn, id, trade.id(), Status::Pending, None)
+            .unwrap();
+        assert_eq!(super::check(&conn, owner).unwrap().unrecorded, vec![id]);
+        assert_eq!(super::rebuild(&conn, owner).unwrap(), 1);
+    }
+}
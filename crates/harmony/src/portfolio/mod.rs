@@ -0,0 +1,11 @@
+pub mod balance;
+pub mod basis;
+pub mod cashflow;
+pub mod category;
+pub mod ledger;
+pub mod performance;
+pub mod plan;
+pub mod rebalance;
+pub mod series;
+pub mod tax;
+pub mod valuation;
@@ -0,0 +1,683 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use rust_decimal::{Decimal, MathematicalOps};
+
+use crate::model::finance::price::Price;
+
+use super::balance::{self, Movement};
+use super::series::{replay, Interval, Point, Scope};
+use super::valuation::PriceGraph;
+
+/// Digits kept in reported metrics, so results do not depend on how far the
+/// last digits of a long division happened to carry.
+const SCALE: u32 = 12;
+
+/// Money put into (positive) or taken out of (negative) a portfolio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CashFlow {
+    pub at: DateTime<Utc>,
+    pub amount: Decimal,
+}
+
+/// Returns of each period between consecutive points.
+///
+/// Flows are assumed to arrive at the end of the period they fall in, so the
+/// return of a period is `(end - flows) / start - 1`. A period starting from
+/// nothing is measured against the money that came in instead. Periods with
+/// neither a starting value nor flows have no return and are skipped.
+pub fn period_returns(points: &[Point], flows: &[CashFlow]) -> Vec<Decimal> {
+    let mut returns = Vec::new();
+
+    for window in points.windows(2) {
+        let (start, end) = (&window[0], &window[1]);
+        let inflow: Decimal = flows
+            .iter()
+            .filter(|flow| flow.at > start.at && flow.at <= end.at)
+            .map(|flow| flow.amount)
+            .sum();
+
+        let growth = if !start.total.is_zero() {
+            (end.total - inflow).checked_div(start.total)
+        } else if !inflow.is_zero() {
+            end.total.checked_div(inflow)
+        } else {
+            None
+        };
+
+        if let Some(growth) = growth {
+            returns.push(growth - Decimal::ONE);
+        }
+    }
+
+    returns
+}
+
+/// Chains period returns into the time-weighted return over the whole window.
+pub fn time_weighted_return(returns: &[Decimal]) -> Option<Decimal> {
+    let mut growth = Decimal::ONE;
+
+    for r in returns {
+        growth = growth.checked_mul(Decimal::ONE.checked_add(*r)?)?;
+    }
+
+    growth.checked_sub(Decimal::ONE)
+}
+
+/// The largest peak-to-trough fall of the time-weighted index, as a positive
+/// fraction of the peak. Measuring the index rather than raw values keeps
+/// deposits and withdrawals from looking like gains and losses. `None` when
+/// the index overflows.
+pub fn max_drawdown(returns: &[Decimal]) -> Option<Decimal> {
+    let mut index = Decimal::ONE;
+    let mut peak = Decimal::ONE;
+    let mut drawdown = Decimal::ZERO;
+
+    for r in returns {
+        index = index.checked_mul(Decimal::ONE.checked_add(*r)?)?;
+
+        if index > peak {
+            peak = index;
+        } else if let Some(fall) = peak.checked_sub(index)?.checked_div(peak) {
+            drawdown = drawdown.max(fall);
+        }
+    }
+
+    Some(drawdown)
+}
+
+/// The sample standard deviation of period returns. `None` when there are
+/// fewer than two or their squares overflow.
+pub fn volatility(returns: &[Decimal]) -> Option<Decimal> {
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let count = Decimal::from(returns.len());
+    let sum = returns
+        .iter()
+        .try_fold(Decimal::ZERO, |sum, r| sum.checked_add(*r))?;
+    let mean = sum / count;
+    let squares = returns.iter().try_fold(Decimal::ZERO, |sum, r| {
+        let deviation = r.checked_sub(mean)?;
+        sum.checked_add(deviation.checked_mul(deviation)?)
+    })?;
+
+    (squares / (count - Decimal::ONE)).sqrt()
+}
+
+/// The annualised money-weighted return of cash flows seen from the
+/// investor: money paid in is negative and money received is positive.
+/// Years are counted as 365 days.
+///
+/// The rate is found by bisection, which always converges to the same digits
+/// for the same input. Returns `None` when the flows do not change sign.
+pub fn xirr(flows: &[CashFlow]) -> Option<Decimal> {
+    let first = flows.iter().map(|flow| flow.at).min()?;
+
+    let has_inflow = flows.iter().any(|flow| flow.amount > Decimal::ZERO);
+    let has_outflow = flows.iter().any(|flow| flow.amount < Decimal::ZERO);
+    if !has_inflow || !has_outflow {
+        return None;
+    }
+
+    let days_per_year = Decimal::from(365);
+    let years: Vec<(Decimal, Decimal)> = flows
+        .iter()
+        .map(|flow| {
+            let seconds = Decimal::from((flow.at - first).num_seconds());
+            (flow.amount, seconds / Decimal::from(86400) / days_per_year)
+        })
+        .collect();
+
+    // Discounting is done in log space. A discount too large to represent
+    // leaves nothing of its flow, while one too small is an overflow.
+    let npv = |rate: Decimal| -> Option<Decimal> {
+        let log = (Decimal::ONE + rate).checked_ln()?;
+        let mut total = Decimal::ZERO;
+
+        for (amount, year) in &years {
+            let exponent = year.checked_mul(log)?;
+            let Some(discount) = exp(exponent) else {
+                if exponent.is_sign_positive() {
+                    continue;
+                }
+                return None;
+            };
+            total = total.checked_add(amount.checked_div(discount)?)?;
+        }
+
+        Some(total)
+    };
+
+    // Start from a loss of 99% and give up some of it while discounting
+    // overflows, as it does over windows of several years
+    let mut low = Decimal::new(-99, 2);
+    let low_npv = loop {
+        match npv(low) {
+            Some(npv) => break npv,
+            None if low < Decimal::new(-1, 2) => low /= Decimal::TWO,
+            None => return None,
+        }
+    };
+    let mut high = Decimal::ONE;
+
+    // Widen the bracket until the net present value changes sign
+    let mut high_npv = npv(high)?;
+    while low_npv.is_sign_positive() == high_npv.is_sign_positive() {
+        if high > Decimal::from(1_000_000) {
+            return None;
+        }
+        high *= Decimal::TWO;
+        high_npv = npv(high)?;
+    }
+
+    for _ in 0..200 {
+        let mid = (low + high) / Decimal::TWO;
+        let mid_npv = npv(mid)?;
+
+        if mid_npv.is_zero() {
+            return Some(mid.round_dp(SCALE));
+        }
+
+        if mid_npv.is_sign_positive() == low_npv.is_sign_positive() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+
+        if (high - low) < Decimal::new(1, 20) {
+            break;
+        }
+    }
+
+    Some(((low + high) / Decimal::TWO).round_dp(SCALE))
+}
+
+/// `e^x` to the full precision of `Decimal`. The integer part is raised
+/// exactly and only the fraction goes through the Taylor series, which
+/// converges quickly below one. `Decimal::exp` stops around the eighth
+/// digit, too early for bisection on rates.
+fn exp(x: Decimal) -> Option<Decimal> {
+    let whole = x.trunc();
+    let fraction = x - whole;
+
+    let mut term = Decimal::ONE;
+    let mut sum = Decimal::ONE;
+    for n in 1..64u32 {
+        term = term.checked_mul(fraction)? / Decimal::from(n);
+        if term.is_zero() {
+            break;
+        }
+        sum += term;
+    }
+
+    Decimal::E
+        .checked_powi(i64::try_from(whole).ok()?)?
+        .checked_mul(sum)
+}
+
+/// Values each movement that counts as a flow for `scope` at the time it
+/// happened. Movements that cannot be priced are skipped and counted.
+pub fn flows(
+    movements: &[Movement],
+    prices: &[Price],
+    object_id: i64,
+    scope: Scope,
+) -> (Vec<CashFlow>, usize) {
+    let mut latest: BTreeMap<(i64, i64), Decimal> = BTreeMap::new();
+    let mut prices = prices.iter().peekable();
+    let mut flows = Vec::new();
+    let mut unpriced = 0;
+
+    for movement in movements.iter().filter(|movement| scope.is_flow(movement)) {
+        while let Some(price) =
+            prices.next_if(|price| price.occurrence_at <= movement.occurrence_at)
+        {
+            latest.insert(
+                (price.base_object_id, price.quote_object_id),
+                price.price.value(),
+            );
+        }
+
+        let mut graph = PriceGraph::new();
+        for (&(base_object_id, quote_object_id), &price) in &latest {
+            graph.insert(base_object_id, quote_object_id, price);
+        }
+
+        let amount = graph
+            .conversion(movement.object_id, object_id)
+            .and_then(|conversion| movement.quantity.checked_mul(conversion.rate));
+
+        match amount {
+            Some(amount) => flows.push(CashFlow {
+                at: movement.occurrence_at,
+                amount,
+            }),
+            None => unpriced += 1,
+        }
+    }
+
+    (flows, unpriced)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub start_value: Decimal,
+    pub end_value: Decimal,
+    /// Money put in minus money taken out within the window.
+    pub net_flow: Decimal,
+    pub time_weighted_return: Option<Decimal>,
+    pub money_weighted_return: Option<Decimal>,
+    /// `None` when the index overflows.
+    pub max_drawdown: Option<Decimal>,
+    /// Standard deviation of the per-interval returns.
+    pub volatility: Option<Decimal>,
+    /// `volatility` scaled to a year of intervals.
+    pub annualized_volatility: Option<Decimal>,
+    /// Points and flows that could not be valued and were left out.
+    pub unpriced: usize,
+}
+
+/// Computes every metric from a value series and the flows within it.
+pub fn report(points: &[Point], flows: &[CashFlow], interval: Interval) -> Option<Report> {
+    let first = points.first()?;
+    let last = points.last()?;
+
+    let window: Vec<CashFlow> = flows
+        .iter()
+        .filter(|flow| flow.at > first.at && flow.at <= last.at)
+        .cloned()
+        .collect();
+    let returns = period_returns(points, &window);
+
+    // The investor pays the starting value, every inflow, and receives the end value
+    let mut investor = Vec::with_capacity(window.len() + 2);
+    investor.push(CashFlow {
+        at: first.at,
+        amount: -first.total,
+    });
+    investor.extend(window.iter().map(|flow| CashFlow {
+        at: flow.at,
+        amount: -flow.amount,
+    }));
+    investor.push(CashFlow {
+        at: last.at,
+        amount: last.total,
+    });
+
+    let volatility = volatility(&returns);
+    let periods_per_year = match interval {
+        Interval::Hour => Decimal::from(365 * 24),
+        Interval::Day => Decimal::from(365),
+    };
+    let annualized_volatility = volatility
+        .zip(periods_per_year.sqrt())
+        .and_then(|(volatility, scale)| volatility.checked_mul(scale))
+        .map(|volatility| volatility.round_dp(SCALE));
+
+    Some(Report {
+        start_value: first.total,
+        end_value: last.total,
+        net_flow: window.iter().map(|flow| flow.amount).sum(),
+        time_weighted_return: time_weighted_return(&returns).map(|r| r.round_dp(SCALE)),
+        money_weighted_return: xirr(&investor),
+        max_drawdown: max_drawdown(&returns).map(|d| d.round_dp(SCALE)),
+        volatility: volatility.map(|v| v.round_dp(SCALE)),
+        annualized_volatility,
+        unpriced: points.iter().map(|point| point.unpriced).sum(),
+    })
+}
+
+/// Loads the owner's data and reports the performance of `scope`, valued in
+/// `object_id`, over the ascending `instants`.
+pub fn analyze(
+    conn: &Connection,
+    owner: i64,
+    object_id: i64,
+    scope: Scope,
+    instants: &[DateTime<Utc>],
+    interval: Interval,
+) -> Result<Option<Report>, Box<dyn Error>> {
+    let Some(&until) = instants.last() else {
+        return Ok(None);
+    };
+
+    let movements = balance::load(conn, owner, until, true)?;
+    let prices = Price::select_by_owner_until(conn, owner, until)?;
+
+    let scoped: Vec<Movement> = movements
+        .iter()
+        .filter(|movement| scope.contains(movement))
+        .cloned()
+        .collect();
+    let points = replay(&scoped, &prices, object_id, instants);
+    let (flows, unpriced) = flows(&movements, &prices, object_id, scope);
+
+    Ok(report(&points, &flows, interval).map(|mut report| {
+        report.unpriced += unpriced;
+        report
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use crate::portfolio::series::{Interval, Point};
+
+    use super::*;
+
+    fn day(month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, month, day, 0, 0, 0).unwrap()
+    }
+
+    fn points(totals: &[i64]) -> Vec<Point> {
+        totals
+            .iter()
+            .enumerate()
+            .map(|(i, total)| Point {
+                at: day(1, 1 + i as u32),
+                total: Decimal::from(*total),
+                unpriced: 0,
+            })
+            .collect()
+    }
+
+    // Reference portfolio: 100 grows 10%, 100 is deposited and it grows to
+    // 220 (+9.09% on the 110 held before the deposit), then falls 10% to 198.
+    // Chained: 1.1 * 1.2 / 1.1 * 0.9 = 1.08.
+    fn reference() -> (Vec<Point>, Vec<CashFlow>) {
+        let points = points(&[100, 110, 220, 198]);
+        let flows = vec![CashFlow {
+            at: day(1, 3),
+            amount: Decimal::from(100),
+        }];
+
+        (points, flows)
+    }
+
+    #[test]
+    fn test_period_returns() {
+        let (points, flows) = reference();
+
+        let returns = period_returns(&points, &flows);
+
+        assert_eq!(returns.len(), 3);
+        assert_eq!(returns[0], Decimal::new(1, 1));
+        assert_eq!(returns[1].round_dp(6), Decimal::new(90909, 6));
+        assert_eq!(returns[2], Decimal::new(-1, 1));
+    }
+
+    #[test]
+    fn test_time_weighted_return() {
+        let (points, flows) = reference();
+
+        let twr = time_weighted_return(&period_returns(&points, &flows)).unwrap();
+
+        assert_eq!(twr.round_dp(10), Decimal::new(8, 2));
+    }
+
+    #[test]
+    fn test_time_weighted_return_from_nothing() {
+        // 1000 deposited into an empty portfolio ends the day worth 1050
+        let points = points(&[0, 1050]);
+        let flows = vec![CashFlow {
+            at: day(1, 2),
+            amount: Decimal::from(1000),
+        }];
+
+        let twr = time_weighted_return(&period_returns(&points, &flows)).unwrap();
+
+        assert_eq!(twr, Decimal::new(5, 2));
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        let (points, flows) = reference();
+
+        // The index peaks at 1.2 and falls to 1.08
+        let drawdown = max_drawdown(&period_returns(&points, &flows)).unwrap();
+
+        assert_eq!(drawdown.round_dp(10), Decimal::new(1, 1));
+        assert_eq!(max_drawdown(&[]), Some(Decimal::ZERO));
+    }
+
+    #[test]
+    fn test_overflowing_returns() {
+        let returns = [Decimal::MAX / Decimal::TWO, Decimal::MAX / Decimal::TWO];
+
+        assert!(max_drawdown(&returns).is_none());
+        assert!(volatility(&returns).is_none());
+        assert!(time_weighted_return(&returns).is_none());
+    }
+
+    #[test]
+    fn test_volatility() {
+        let (points, flows) = reference();
+
+        let volatility = volatility(&period_returns(&points, &flows)).unwrap();
+
+        assert_eq!(volatility.round_dp(9), Decimal::new(112937243, 9));
+        assert!(super::volatility(&[Decimal::ONE]).is_none());
+    }
+
+    #[test]
+    fn test_xirr_one_year() {
+        let flows = vec![
+            CashFlow {
+                at: day(1, 1),
+                amount: Decimal::from(-1000),
+            },
+            CashFlow {
+                at: day(1, 1) + chrono::TimeDelta::days(365),
+                amount: Decimal::from(1100),
+            },
+        ];
+
+        assert_eq!(xirr(&flows).unwrap().round_dp(8), Decimal::new(1, 1));
+    }
+
+    #[test]
+    fn test_xirr_reference() {
+        // The well known spreadsheet example, whose XIRR is 37.336253%
+        let at = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap();
+        let flows = vec![
+            CashFlow {
+                at: at(2008, 1, 1),
+                amount: Decimal::from(-10000),
+            },
+            CashFlow {
+                at: at(2008, 3, 1),
+                amount: Decimal::from(2750),
+            },
+            CashFlow {
+                at: at(2008, 10, 30),
+                amount: Decimal::from(4250),
+            },
+            CashFlow {
+                at: at(2009, 2, 15),
+                amount: Decimal::from(3250),
+            },
+            CashFlow {
+                at: at(2009, 4, 1),
+                amount: Decimal::from(2750),
+            },
+        ];
+
+        let rate = xirr(&flows).unwrap();
+
+        assert_eq!(rate.round_dp(9), Decimal::new(373362534, 9));
+        assert_eq!(xirr(&flows).unwrap(), rate);
+    }
+
+    #[test]
+    fn test_exp() {
+        use std::str::FromStr;
+
+        let expected = Decimal::from_str("1.648721270700128146848650788").unwrap();
+        assert_eq!(
+            exp(Decimal::new(5, 1)).unwrap().round_dp(25),
+            expected.round_dp(25)
+        );
+
+        let expected = Decimal::from_str("0.0497870683678639429793424156").unwrap();
+        assert_eq!(
+            exp(Decimal::from(-3)).unwrap().round_dp(20),
+            expected.round_dp(20)
+        );
+    }
+
+    #[test]
+    fn test_xirr_without_sign_change() {
+        let flows = vec![CashFlow {
+            at: day(1, 1),
+            amount: Decimal::from(-1000),
+        }];
+
+        assert!(xirr(&flows).is_none());
+    }
+
+    #[test]
+    fn test_xirr_over_years() {
+        // 1000 growing 5% a year, which discounting from -99% would overflow
+        for years in [7u32, 10, 20, 40] {
+            let flows = vec![
+                CashFlow {
+                    at: day(1, 1),
+                    amount: Decimal::from(-1000),
+                },
+                CashFlow {
+                    at: day(1, 1) + chrono::TimeDelta::days(365 * i64::from(years)),
+                    amount: Decimal::from(1000) * Decimal::new(105, 2).powu(u64::from(years)),
+                },
+            ];
+
+            assert_eq!(xirr(&flows).unwrap().round_dp(8), Decimal::new(5, 2));
+        }
+
+        // A loss of 80% over ten years is still found
+        let flows = vec![
+            CashFlow {
+                at: day(1, 1),
+                amount: Decimal::from(-1000),
+            },
+            CashFlow {
+                at: day(1, 1) + chrono::TimeDelta::days(3650),
+                amount: Decimal::from(200),
+            },
+        ];
+        let rate = xirr(&flows).unwrap();
+        assert_eq!(rate.round_dp(6), Decimal::new(-148660, 6));
+    }
+
+    #[test]
+    fn test_report() {
+        let (points, flows) = reference();
+
+        let report = report(&points, &flows, Interval::Day).unwrap();
+
+        assert_eq!(report.start_value, Decimal::from(100));
+        assert_eq!(report.end_value, Decimal::from(198));
+        assert_eq!(report.net_flow, Decimal::from(100));
+        assert_eq!(report.time_weighted_return, Some(Decimal::new(8, 2)));
+        assert_eq!(report.max_drawdown, Some(Decimal::new(1, 1)));
+        assert!(report.money_weighted_return.unwrap() < Decimal::ZERO);
+        assert_eq!(report.unpriced, 0);
+    }
+
+    #[test]
+    fn test_analyze_trade_scope() {
+        use crate::model::finance::account::Account;
+        use crate::model::finance::object::{Classification, Object};
+        use crate::model::finance::price::Price;
+        use crate::model::finance::trade::transaction::{Status, Transaction};
+        use crate::model::finance::trade::Trade;
+        use crate::model::person::Person;
+
+        let database = r2d2_sqlite::SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+        let btc = Object::insert(
+            &conn,
+            owner,
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let usdt = Object::insert(
+            &conn,
+            owner,
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let trade = Trade::insert(&conn, owner, btc, usdt, None, None).unwrap();
+        let account = Account::insert(&conn, owner, "Exchange".to_string(), None, None).unwrap();
+
+        // 1 BTC bought at 100 halfway through the first day, then 150 and 120
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::ONE.into(),
+            Some(Decimal::from(100).into()),
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(day(1, 1) + chrono::TimeDelta::hours(12)),
+        )
+        .unwrap();
+        for (at, price) in [(day(1, 1), 100), (day(1, 2), 150), (day(1, 3), 120)] {
+            Price::upsert(
+                &conn,
+                owner,
+                btc,
+                usdt,
+                Decimal::from(price).into(),
+                None,
+                at,
+            )
+            .unwrap();
+        }
+
+        let instants = Interval::Day.instants(day(1, 1), day(1, 3));
+        let report = analyze(
+            &conn,
+            owner,
+            usdt,
+            Scope::Trade(trade),
+            &instants,
+            Interval::Day,
+        )
+        .unwrap()
+        .unwrap();
+
+        // +50% on the 100 put in, then -20%: 1.5 * 0.8 = 1.2
+        assert_eq!(report.net_flow, Decimal::from(100));
+        assert_eq!(report.end_value, Decimal::from(120));
+        assert_eq!(report.time_weighted_return, Some(Decimal::new(2, 1)));
+        assert_eq!(report.max_drawdown, Some(Decimal::new(2, 1)));
+        assert_eq!(report.unpriced, 0);
+    }
+}
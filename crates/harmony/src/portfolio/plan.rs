@@ -0,0 +1,368 @@
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+use crate::common::cron::Cron;
+use crate::model::database::atomically;
+use crate::model::finance::object::Object;
+use crate::model::finance::price::Price;
+use crate::model::finance::trade::plan::{Outcome, Plan, Run};
+use crate::model::finance::trade::transaction::{Status, Transaction};
+use crate::model::finance::trade::Trade;
+use crate::model::finance::{Quantity, Rounding};
+
+use super::ledger;
+use super::valuation::PriceGraph;
+
+/// The most runs of one plan worked through in one call of `run_due`.
+const MAX_RUNS_PER_CALL: usize = 64;
+
+/// The first run at or after `from` that is not past `end_at`.
+pub fn first_run(
+    schedule: &Cron,
+    from: DateTime<Utc>,
+    end_at: Option<DateTime<Utc>>,
+) -> Option<DateTime<Utc>> {
+    schedule
+        .first_from(from)
+        .filter(|run| end_at.is_none_or(|end_at| *run <= end_at))
+}
+
+/// Creates the transaction of one run at the latest price of the pair at
+/// `due_at`. Buying spends the amount in the quote object, so the base
+/// quantity is rounded down to what the amount can pay for.
+fn materialize(
+    conn: &Connection,
+    plan: &Plan,
+    trade: &Trade,
+    precision: u32,
+    due_at: DateTime<Utc>,
+) -> Result<Option<i64>, Box<dyn Error>> {
+    let graph = PriceGraph::from_prices(&Price::select_latest_by_owner(conn, trade.owner, due_at)?);
+    let Some(conversion) = graph.conversion(trade.base_object_id, trade.quote_object_id) else {
+        return Ok(None);
+    };
+
+    let rate = Quantity::from(conversion.rate);
+    let quantity = if plan.is_base_amount || plan.is_base_to_quote {
+        Some(plan.amount.clone())
+    } else {
+        plan.amount.checked_div(&rate)
+    };
+    let Some(quantity) = quantity.map(|quantity| quantity.round(precision, Rounding::Down)) else {
+        return Ok(None);
+    };
+    if quantity.is_zero() {
+        return Ok(None);
+    }
+
+    let status = if plan.needs_confirmation {
+        Status::Planned
+    } else {
+        Status::Settled
+    };
+
+    let id = Transaction::insert(
+        conn,
+        trade.id(),
+        plan.account_id,
+        quantity,
+        Some(rate),
+        None,
+        None,
+        plan.is_base_to_quote,
+        status,
+        None,
+        None,
+        plan.remark.clone(),
+        Some(due_at),
+    )?;
+
+    let transaction = Transaction::select_by_id_trade_id(conn, id, trade.id())?
+        .ok_or(format!("transaction {} does not exist", id))?;
+    ledger::record_transaction(conn, trade.owner, trade, &transaction)?;
+
+    Ok(Some(id))
+}
+
+/// Works through the runs of one plan that fell due at or before `now`.
+/// Each run is stored together with the plan moving on to the next, so a
+/// run is never materialized twice. Runs skipped ahead of time are passed.
+///
+/// At most `MAX_RUNS_PER_CALL` runs are worked through, so a plan started
+/// long ago or a server that was down catches up over several calls rather
+/// than holding up the others in one.
+fn run_plan(conn: &Connection, plan: &Plan, now: DateTime<Utc>) -> Result<usize, Box<dyn Error>> {
+    let trade = Trade::select_by_id(conn, plan.trade_id)?
+        .ok_or(format!("trade {} does not exist", plan.trade_id))?;
+    let base = Object::select_by_id_owner(conn, trade.base_object_id, trade.owner)?
+        .ok_or(format!("object {} does not exist", trade.base_object_id))?;
+
+    let mut created = 0;
+    let mut next = plan.next_run_at;
+
+    for _ in 0..MAX_RUNS_PER_CALL {
+        let Some(due_at) = next.filter(|due_at| *due_at <= now) else {
+            break;
+        };
+        next = plan
+            .schedule
+            .after(due_at)
+            .filter(|run| plan.end_at.is_none_or(|end_at| *run <= end_at));
+
+        created += atomically(conn, || {
+            let mut created = 0;
+
+            if Run::select_by_plan_id_due_at(conn, plan.id(), due_at)?.is_none() {
+                let transaction_id =
+                    materialize(conn, plan, &trade, base.classification.precision, due_at)?;
+                let outcome = match transaction_id {
+                    Some(_) => Outcome::Created,
+                    None => Outcome::Unpriced,
+                };
+                if transaction_id.is_some() {
+                    created += 1;
+                }
+
+                Run::insert(conn, plan.id(), due_at, outcome, transaction_id)?;
+            }
+
+            Plan::update_next_run_at_by_id(conn, plan.id(), next)?;
+
+            Ok(created)
+        })?;
+    }
+
+    Ok(created)
+}
+
+/// Materializes every run of every plan that fell due at or before `now`.
+/// Plans that fail are logged and retried on the next call so one broken
+/// plan does not stall the others. Returns the number of transactions
+/// created.
+pub fn run_due(conn: &Connection, now: DateTime<Utc>) -> Result<usize, Box<dyn Error>> {
+    let mut created = 0;
+
+    for plan in Plan::select_due(conn, now)? {
+        match run_plan(conn, &plan, now) {
+            Ok(count) => created += count,
+            Err(err) => tracing::warn!("failed to run plan {}: {}", plan.id(), err),
+        }
+    }
+
+    Ok(created)
+}
+
+/// Spawns the periodic run of due plans onto the runtime.
+pub fn spawn() {
+    use crate::consts::finance::PLAN_INTERVAL;
+
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(*PLAN_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let result = tokio::task::spawn_blocking(|| -> Result<usize, String> {
+                let conn = crate::model::database::connection().map_err(|e| e.to_string())?;
+
+                run_due(&conn, Utc::now()).map_err(|e| e.to_string())
+            })
+            .await;
+
+            match result {
+                Ok(Ok(0)) => {}
+                Ok(Ok(created)) => tracing::info!("created {} transactions from plans", created),
+                Ok(Err(err)) => tracing::warn!("running plans failed: {}", err),
+                Err(err) => tracing::warn!("running plans panicked: {}", err),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::common::cron::Cron;
+    use crate::model::finance::account::Account;
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::price::Price;
+    use crate::model::finance::trade::plan::{Outcome, Plan, Run};
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::person::Person;
+
+    type Setup = (
+        PooledConnection<SqliteConnectionManager>,
+        i64,
+        i64,
+        i64,
+        i64,
+        i64,
+    );
+
+    fn setup() -> Setup {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+        let btc = Object::insert(
+            &conn,
+            owner,
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let usdt = Object::insert(
+            &conn,
+            owner,
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let trade = Trade::insert(&conn, owner, btc, usdt, None, None).unwrap();
+        let account = Account::insert(&conn, owner, "Exchange".to_string(), None, None).unwrap();
+
+        (conn, owner, btc, usdt, trade, account)
+    }
+
+    /// Buys 100 USDT of BTC every Monday at 09:00 from 1 January 2024.
+    fn plan(
+        conn: &rusqlite::Connection,
+        trade: i64,
+        account: i64,
+        needs_confirmation: bool,
+    ) -> i64 {
+        let schedule: Cron = "0 9 * * 1".parse().unwrap();
+        let start_at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        Plan::insert(
+            conn,
+            trade,
+            account,
+            &schedule,
+            Decimal::from(100).into(),
+            false,
+            false,
+            needs_confirmation,
+            start_at,
+            None,
+            super::first_run(&schedule, start_at, None),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_runs_materialize_once() {
+        let (conn, owner, btc, usdt, trade, account) = setup();
+        let id = plan(&conn, trade, account, false);
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::from(40000).into(),
+            None,
+            Utc.with_ymd_and_hms(2023, 12, 31, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+
+        // Two Mondays have passed by Wednesday the 10th
+        let now = Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap();
+        assert_eq!(super::run_due(&conn, now).unwrap(), 2);
+        assert_eq!(super::run_due(&conn, now).unwrap(), 0);
+
+        let transactions = Transaction::select_all_by_trade_id(&conn, trade).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].quantity.value(), Decimal::new(25, 4));
+        assert_eq!(transactions[0].status, Status::Settled);
+        assert_eq!(
+            transactions[1].occurrence_at,
+            Utc.with_ymd_and_hms(2024, 1, 8, 9, 0, 0).unwrap()
+        );
+
+        let plan = Plan::select_by_id_trade_id(&conn, id, trade)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            plan.next_run_at,
+            Some(Utc.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_catch_up_is_capped() {
+        let (conn, owner, btc, usdt, trade, account) = setup();
+        plan(&conn, trade, account, false);
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::from(40000).into(),
+            None,
+            Utc.with_ymd_and_hms(2023, 12, 31, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+
+        // 105 Mondays are due after two years, worked off over two calls
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            super::run_due(&conn, now).unwrap(),
+            super::MAX_RUNS_PER_CALL
+        );
+        assert_eq!(
+            super::run_due(&conn, now).unwrap(),
+            105 - super::MAX_RUNS_PER_CALL
+        );
+        assert_eq!(super::run_due(&conn, now).unwrap(), 0);
+
+        let transactions = Transaction::select_all_by_trade_id(&conn, trade).unwrap();
+        assert_eq!(transactions.len(), 105);
+    }
+
+    #[test]
+    fn test_skipped_and_unpriced_runs() {
+        let (conn, _owner, _btc, _usdt, trade, account) = setup();
+        let id = plan(&conn, trade, account, true);
+
+        Run::insert(
+            &conn,
+            id,
+            Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap(),
+            Outcome::Skipped,
+            None,
+        )
+        .unwrap();
+
+        // Without a price the second run cannot be worked out
+        let now = Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap();
+        assert_eq!(super::run_due(&conn, now).unwrap(), 0);
+
+        let runs = Run::select_by_plan_id(&conn, id, 10, 0).unwrap();
+        let outcomes: Vec<Outcome> = runs.iter().map(|run| run.outcome).collect();
+        assert_eq!(outcomes, vec![Outcome::Unpriced, Outcome::Skipped]);
+        assert!(Transaction::select_all_by_trade_id(&conn, trade)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,701 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use rust_decimal::Decimal;
+
+use crate::model::finance::object::{AssetType, Classification};
+use crate::model::finance::{Quantity, Rounding};
+
+use super::valuation::{PriceGraph, Valuation};
+
+/// The part of a portfolio one target is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Sleeve {
+    Object(i64),
+    /// Every object of the type that has no target of its own.
+    AssetType(AssetType),
+    /// Holdings no target covers, aimed at nothing.
+    Untargeted,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Band {
+    pub sleeve: Sleeve,
+    pub weight: Decimal,
+    pub tolerance: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    pub sleeve: Sleeve,
+    pub value: Decimal,
+    /// Share of the total, `None` when the total is zero.
+    pub weight: Option<Decimal>,
+    pub target_weight: Decimal,
+    /// `None` when it overflows.
+    pub target_value: Option<Decimal>,
+    /// How far the share is above the target, negative when below.
+    pub drift: Option<Decimal>,
+    pub is_outside: bool,
+    pub object_ids: Vec<i64>,
+}
+
+/// The objects of a trade, which can be traded in either direction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pair {
+    pub trade_id: i64,
+    pub base_object_id: i64,
+    pub quote_object_id: i64,
+}
+
+/// A suggested transaction on a trade.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub trade_id: i64,
+    pub is_base_to_quote: bool,
+    /// In the base object, rounded down to its precision.
+    pub quantity: Decimal,
+    /// Units of the quote object per unit of the base object.
+    pub price: Decimal,
+    /// In the reporting object.
+    pub value: Decimal,
+}
+
+/// Value that no trade can move, positive when still to be bought and
+/// negative when still to be sold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Residual {
+    pub sleeve: Sleeve,
+    /// `None` for an asset type without any object that can be bought, and
+    /// for a sleeve whose value could not be spread over its objects.
+    pub object_id: Option<i64>,
+    pub value: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Buys and sells back to every target once one is outside its band.
+    Full,
+    /// Only spends new money of `value`, in the reporting object, arriving
+    /// in `object_id`, on whatever is furthest below its target.
+    Cash { object_id: i64, value: Decimal },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rebalance {
+    pub drifts: Vec<Drift>,
+    pub orders: Vec<Order>,
+    pub residuals: Vec<Residual>,
+}
+
+/// The sleeve an object falls in, its own target winning over the one of
+/// its asset type.
+pub fn sleeve_of(object_id: i64, bands: &[Band], asset_types: &HashMap<i64, AssetType>) -> Sleeve {
+    let has = |sleeve: Sleeve| bands.iter().any(|band| band.sleeve == sleeve);
+
+    if has(Sleeve::Object(object_id)) {
+        return Sleeve::Object(object_id);
+    }
+
+    match asset_types.get(&object_id) {
+        Some(&asset_type) if has(Sleeve::AssetType(asset_type)) => Sleeve::AssetType(asset_type),
+        _ => Sleeve::Untargeted,
+    }
+}
+
+/// How far every target is off. Untargeted holdings come last, when there
+/// are any. Unpriced balances carry no value and are left out.
+pub fn drifts(
+    valuation: &Valuation,
+    bands: &[Band],
+    asset_types: &HashMap<i64, AssetType>,
+) -> Vec<Drift> {
+    let mut members: BTreeMap<Sleeve, (Decimal, Vec<i64>)> = BTreeMap::new();
+    for holding in &valuation.holdings {
+        let (value, object_ids) = members
+            .entry(sleeve_of(holding.object_id, bands, asset_types))
+            .or_default();
+        *value += holding.value;
+        object_ids.push(holding.object_id);
+    }
+
+    let untargeted = members.contains_key(&Sleeve::Untargeted).then_some(Band {
+        sleeve: Sleeve::Untargeted,
+        weight: Decimal::ZERO,
+        tolerance: Decimal::ZERO,
+    });
+
+    bands
+        .iter()
+        .chain(untargeted.iter())
+        .map(|band| {
+            let (value, object_ids) = members.remove(&band.sleeve).unwrap_or_default();
+            let weight = if valuation.total.is_zero() {
+                None
+            } else {
+                value.checked_div(valuation.total)
+            };
+            let drift = weight.map(|weight| weight - band.weight);
+
+            Drift {
+                sleeve: band.sleeve,
+                value,
+                weight,
+                target_weight: band.weight,
+                target_value: valuation.total.checked_mul(band.weight),
+                drift,
+                is_outside: drift.is_some_and(|drift| drift.abs() > band.tolerance),
+                object_ids,
+            }
+        })
+        .collect()
+}
+
+/// Spreads the value a sleeve should change by over its objects in
+/// proportion to their value. A sleeve of an asset type holding nothing buys
+/// the first object of the type that some pair trades. A sleeve too large to
+/// be worked out is left as it is and reported as residual.
+fn spread(
+    drift: &Drift,
+    delta: Decimal,
+    values: &HashMap<i64, Decimal>,
+    asset_types: &HashMap<i64, AssetType>,
+    pairs: &[Pair],
+    deltas: &mut BTreeMap<i64, Decimal>,
+    residuals: &mut Vec<Residual>,
+) {
+    if delta.is_zero() {
+        return;
+    }
+
+    let held: Decimal = drift
+        .object_ids
+        .iter()
+        .filter_map(|object_id| values.get(object_id))
+        .sum();
+
+    let residual = Residual {
+        sleeve: drift.sleeve,
+        object_id: None,
+        value: delta,
+    };
+
+    if !held.is_zero() {
+        let shares: Option<Vec<(i64, Decimal)>> = drift
+            .object_ids
+            .iter()
+            .map(|&object_id| {
+                let value = values.get(&object_id).copied().unwrap_or_default();
+                let share = delta.checked_mul(value)?.checked_div(held)?;
+                let sum = deltas.get(&object_id).copied().unwrap_or_default();
+                Some((object_id, sum.checked_add(share)?))
+            })
+            .collect();
+
+        match shares {
+            Some(shares) => deltas.extend(shares),
+            None => residuals.push(residual),
+        }
+        return;
+    }
+
+    let object_id = match drift.sleeve {
+        Sleeve::Object(object_id) => Some(object_id),
+        Sleeve::AssetType(asset_type) => pairs
+            .iter()
+            .flat_map(|pair| [pair.base_object_id, pair.quote_object_id])
+            .filter(|object_id| asset_types.get(object_id) == Some(&asset_type))
+            .min(),
+        Sleeve::Untargeted => None,
+    };
+
+    let sum = object_id.and_then(|object_id| {
+        let sum = deltas.get(&object_id).copied().unwrap_or_default();
+        Some((object_id, sum.checked_add(delta)?))
+    });
+    match sum {
+        Some((object_id, sum)) => {
+            deltas.insert(object_id, sum);
+        }
+        None => residuals.push(residual),
+    }
+}
+
+/// The shortest chain of pairs from one object to another, through objects
+/// that can be valued only. Returns the pair index and the object given up
+/// for every hop.
+fn route(
+    pairs: &[Pair],
+    from: i64,
+    to: i64,
+    is_priced: impl Fn(i64) -> bool,
+) -> Option<Vec<(usize, i64)>> {
+    let mut previous: HashMap<i64, (usize, i64)> = HashMap::new();
+    let mut visited = HashSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(object_id) = queue.pop_front() {
+        if object_id == to {
+            let mut hops = Vec::new();
+            let mut current = to;
+            while let Some(&(index, given)) = previous.get(&current) {
+                hops.push((index, given));
+                current = given;
+            }
+            hops.reverse();
+            return Some(hops);
+        }
+
+        for (index, pair) in pairs.iter().enumerate() {
+            let next = if pair.base_object_id == object_id {
+                pair.quote_object_id
+            } else if pair.quote_object_id == object_id {
+                pair.base_object_id
+            } else {
+                continue;
+            };
+
+            if is_priced(next) && visited.insert(next) {
+                previous.insert(next, (index, object_id));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// Works out the drift of every target and the transactions that bring the
+/// portfolio back to them, using existing trades only.
+///
+/// Sellers are matched to buyers over a direct pair first and over a chain
+/// of pairs after, largest amounts first, and the flows on every pair are
+/// netted into one order. Whatever cannot be routed is reported as residual
+/// rather than dropped. A sleeve whose target value overflows is left alone.
+pub fn rebalance(
+    valuation: &Valuation,
+    bands: &[Band],
+    classifications: &HashMap<i64, Classification>,
+    pairs: &[Pair],
+    graph: &PriceGraph,
+    mode: Mode,
+) -> Rebalance {
+    let asset_types: HashMap<i64, AssetType> = classifications
+        .iter()
+        .map(|(&object_id, classification)| (object_id, classification.asset_type))
+        .collect();
+    let drifts = drifts(valuation, bands, &asset_types);
+
+    let mut values: HashMap<i64, Decimal> = valuation
+        .holdings
+        .iter()
+        .map(|holding| (holding.object_id, holding.value))
+        .collect();
+    let mut deltas: BTreeMap<i64, Decimal> = BTreeMap::new();
+    let mut residuals = Vec::new();
+
+    match mode {
+        Mode::Full => {
+            if drifts.iter().any(|drift| drift.is_outside) {
+                for drift in &drifts {
+                    let Some(delta) = drift
+                        .target_value
+                        .and_then(|target_value| target_value.checked_sub(drift.value))
+                    else {
+                        continue;
+                    };
+                    spread(
+                        drift,
+                        delta,
+                        &values,
+                        &asset_types,
+                        pairs,
+                        &mut deltas,
+                        &mut residuals,
+                    );
+                }
+            }
+        }
+        Mode::Cash { object_id, value } => {
+            let held = values.get(&object_id).copied().unwrap_or_default();
+            values.insert(object_id, held.checked_add(value).unwrap_or(held));
+            let total = valuation.total.checked_add(value);
+            let cash_sleeve = sleeve_of(object_id, bands, &asset_types);
+
+            let shortfalls: Vec<(&Drift, Decimal)> = drifts
+                .iter()
+                .filter(|drift| drift.sleeve != cash_sleeve)
+                .filter_map(|drift| {
+                    let target_value = total?.checked_mul(drift.target_weight)?;
+                    Some((drift, target_value.checked_sub(drift.value)?))
+                })
+                .filter(|(_, shortfall)| shortfall.is_sign_positive() && !shortfall.is_zero())
+                .collect();
+
+            // Shortfalls too large to add up are left as residual
+            let sum = shortfalls
+                .iter()
+                .try_fold(Decimal::ZERO, |sum, (_, shortfall)| {
+                    sum.checked_add(*shortfall)
+                });
+            let scale = match sum {
+                Some(sum) if sum > value => value.checked_div(sum),
+                Some(_) => Some(Decimal::ONE),
+                None => None,
+            };
+
+            let mut spent = Decimal::ZERO;
+            for (drift, shortfall) in shortfalls {
+                let Some(delta) = scale.and_then(|scale| shortfall.checked_mul(scale)) else {
+                    residuals.push(Residual {
+                        sleeve: drift.sleeve,
+                        object_id: None,
+                        value: shortfall,
+                    });
+                    continue;
+                };
+
+                let before: Decimal = deltas.values().sum();
+                spread(
+                    drift,
+                    delta,
+                    &values,
+                    &asset_types,
+                    pairs,
+                    &mut deltas,
+                    &mut residuals,
+                );
+                // Nothing is spent on a sleeve that has no object to buy
+                spent += deltas.values().sum::<Decimal>() - before;
+            }
+            *deltas.entry(object_id).or_default() -= spent;
+        }
+    }
+
+    let mut sellers: Vec<(i64, Decimal)> = deltas
+        .iter()
+        .filter(|(_, delta)| delta.is_sign_negative() && !delta.is_zero())
+        .map(|(&object_id, delta)| (object_id, -*delta))
+        .collect();
+    let mut buyers: Vec<(i64, Decimal)> = deltas
+        .iter()
+        .filter(|(_, delta)| delta.is_sign_positive() && !delta.is_zero())
+        .map(|(&object_id, &delta)| (object_id, delta))
+        .collect();
+    sellers.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    buyers.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let reporting = valuation.object_id;
+    let is_priced = |object_id: i64| graph.conversion(object_id, reporting).is_some();
+
+    // Value given up in the base object of every pair, negative for the quote
+    let mut flows: BTreeMap<usize, Decimal> = BTreeMap::new();
+    for direct in [true, false] {
+        for buyer in buyers.iter_mut() {
+            for seller in sellers.iter_mut() {
+                if buyer.1.is_zero() || seller.1.is_zero() {
+                    continue;
+                }
+
+                let Some(hops) = route(pairs, seller.0, buyer.0, is_priced) else {
+                    continue;
+                };
+                if direct && hops.len() != 1 {
+                    continue;
+                }
+
+                let amount = buyer.1.min(seller.1);
+                for (index, given) in hops {
+                    let flow = flows.entry(index).or_default();
+                    if pairs[index].base_object_id == given {
+                        *flow += amount;
+                    } else {
+                        *flow -= amount;
+                    }
+                }
+                buyer.1 -= amount;
+                seller.1 -= amount;
+            }
+        }
+    }
+
+    let orders = flows
+        .into_iter()
+        .filter_map(|(index, flow)| {
+            let pair = &pairs[index];
+            let base_rate = graph.conversion(pair.base_object_id, reporting)?.rate;
+            let price = graph
+                .conversion(pair.base_object_id, pair.quote_object_id)?
+                .rate;
+            let precision = classifications
+                .get(&pair.base_object_id)
+                .map_or(Classification::default().precision, |classification| {
+                    classification.precision
+                });
+
+            let quantity = Quantity::from(flow.abs().checked_div(base_rate)?)
+                .round(precision, Rounding::Down)
+                .value();
+            if quantity.is_zero() {
+                return None;
+            }
+
+            Some(Order {
+                trade_id: pair.trade_id,
+                is_base_to_quote: flow.is_sign_positive(),
+                quantity,
+                price,
+                value: quantity * base_rate,
+            })
+        })
+        .collect();
+
+    for (object_id, value) in buyers.into_iter().chain(
+        sellers
+            .into_iter()
+            .map(|(object_id, value)| (object_id, -value)),
+    ) {
+        if !value.is_zero() {
+            residuals.push(Residual {
+                sleeve: sleeve_of(object_id, bands, &asset_types),
+                object_id: Some(object_id),
+                value,
+            });
+        }
+    }
+
+    Rebalance {
+        drifts,
+        orders,
+        residuals,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::object::{AssetType, Classification};
+    use crate::portfolio::valuation::{valuate, Holding, PriceGraph, Valuation};
+
+    use super::{rebalance, Band, Mode, Pair, Sleeve};
+
+    const BTC: i64 = 1;
+    const ETH: i64 = 2;
+    const USDT: i64 = 3;
+
+    fn classifications() -> HashMap<i64, Classification> {
+        let classification = |asset_type, precision| Classification {
+            asset_type,
+            precision,
+            ..Classification::default()
+        };
+
+        HashMap::from([
+            (BTC, classification(AssetType::Crypto, 8)),
+            (ETH, classification(AssetType::Crypto, 8)),
+            (USDT, classification(AssetType::Fiat, 2)),
+        ])
+    }
+
+    fn graph() -> PriceGraph {
+        let mut graph = PriceGraph::new();
+        graph.insert(BTC, USDT, Decimal::from(40000));
+        graph.insert(ETH, USDT, Decimal::from(2000));
+        graph
+    }
+
+    fn pairs() -> Vec<Pair> {
+        vec![
+            Pair {
+                trade_id: 10,
+                base_object_id: BTC,
+                quote_object_id: USDT,
+            },
+            Pair {
+                trade_id: 20,
+                base_object_id: ETH,
+                quote_object_id: USDT,
+            },
+        ]
+    }
+
+    fn band(sleeve: Sleeve, weight: i64) -> Band {
+        Band {
+            sleeve,
+            weight: Decimal::new(weight, 2),
+            tolerance: Decimal::new(5, 2),
+        }
+    }
+
+    #[test]
+    fn test_within_bands_needs_no_orders() {
+        // 6000 BTC, 4000 USDT against 60/40
+        let balances = BTreeMap::from([(BTC, Decimal::new(15, 2)), (USDT, Decimal::from(4000))]);
+        let valuation = valuate(&balances, &graph(), USDT);
+        let bands = [
+            band(Sleeve::Object(BTC), 60),
+            band(Sleeve::Object(USDT), 40),
+        ];
+
+        let result = rebalance(
+            &valuation,
+            &bands,
+            &classifications(),
+            &pairs(),
+            &graph(),
+            Mode::Full,
+        );
+
+        assert_eq!(result.drifts.len(), 2);
+        assert_eq!(result.drifts[0].drift, Some(Decimal::ZERO));
+        assert!(result.orders.is_empty());
+        assert!(result.residuals.is_empty());
+    }
+
+    #[test]
+    fn test_full_rebalance_routes_through_pairs() {
+        // 8000 BTC, 2000 ETH against 50% BTC and 50% in crypto besides
+        let balances = BTreeMap::from([(BTC, Decimal::new(2, 1)), (ETH, Decimal::from(1))]);
+        let valuation = valuate(&balances, &graph(), USDT);
+        let bands = [
+            band(Sleeve::Object(BTC), 50),
+            band(Sleeve::AssetType(AssetType::Crypto), 50),
+        ];
+
+        let result = rebalance(
+            &valuation,
+            &bands,
+            &classifications(),
+            &pairs(),
+            &graph(),
+            Mode::Full,
+        );
+
+        assert!(result.drifts[0].is_outside);
+        assert_eq!(result.drifts[1].object_ids, vec![ETH]);
+
+        // BTC to ETH has no pair of its own and goes through USDT
+        assert_eq!(result.orders.len(), 2);
+        assert_eq!(result.orders[0].trade_id, 10);
+        assert!(result.orders[0].is_base_to_quote);
+        assert_eq!(result.orders[0].quantity, Decimal::new(75, 3));
+        assert_eq!(result.orders[1].trade_id, 20);
+        assert!(!result.orders[1].is_base_to_quote);
+        assert_eq!(result.orders[1].quantity, Decimal::new(15, 1));
+        assert_eq!(result.orders[1].value, Decimal::from(3000));
+        assert!(result.residuals.is_empty());
+    }
+
+    #[test]
+    fn test_cash_only_buys_what_is_furthest_below() {
+        // 6000 BTC, 1000 ETH and 3000 USDT of new money against 50/30/20
+        let balances = BTreeMap::from([(BTC, Decimal::new(15, 2)), (ETH, Decimal::new(5, 1))]);
+        let valuation = valuate(&balances, &graph(), USDT);
+        let bands = [
+            band(Sleeve::Object(BTC), 50),
+            band(Sleeve::Object(ETH), 30),
+            band(Sleeve::Object(USDT), 20),
+        ];
+        let mode = Mode::Cash {
+            object_id: USDT,
+            value: Decimal::from(3000),
+        };
+
+        let result = rebalance(
+            &valuation,
+            &bands,
+            &classifications(),
+            &pairs(),
+            &graph(),
+            mode,
+        );
+
+        // BTC is already above target and is neither bought nor sold
+        assert_eq!(result.orders.len(), 1);
+        assert_eq!(result.orders[0].trade_id, 20);
+        assert!(!result.orders[0].is_base_to_quote);
+        assert_eq!(result.orders[0].value, Decimal::from(2000));
+        assert!(result.residuals.is_empty());
+    }
+
+    #[test]
+    fn test_unroutable_value_is_residual() {
+        let balances = BTreeMap::from([(BTC, Decimal::new(2, 1)), (ETH, Decimal::from(1))]);
+        let valuation = valuate(&balances, &graph(), USDT);
+        let bands = [band(Sleeve::Object(BTC), 50), band(Sleeve::Object(ETH), 50)];
+
+        let result = rebalance(
+            &valuation,
+            &bands,
+            &classifications(),
+            &[],
+            &graph(),
+            Mode::Full,
+        );
+
+        assert!(result.orders.is_empty());
+        assert_eq!(result.residuals.len(), 2);
+        assert_eq!(result.residuals[0].object_id, Some(ETH));
+        assert_eq!(result.residuals[0].value, Decimal::from(3000));
+        assert_eq!(result.residuals[1].value, Decimal::from(-3000));
+    }
+
+    #[test]
+    fn test_overflowing_sleeve_is_residual() {
+        let holding = |object_id, value| Holding {
+            object_id,
+            quantity: value,
+            price: Decimal::ONE,
+            value,
+            weight: None,
+            path: Vec::new(),
+        };
+        let quarter = Decimal::MAX / Decimal::from(4);
+        let valuation = Valuation {
+            object_id: USDT,
+            holdings: vec![
+                holding(BTC, quarter),
+                holding(ETH, quarter),
+                holding(USDT, Decimal::ONE),
+            ],
+            unpriced: Vec::new(),
+            total: quarter * Decimal::TWO + Decimal::ONE,
+        };
+        let bands = [
+            band(Sleeve::AssetType(AssetType::Crypto), 20),
+            band(Sleeve::Object(USDT), 80),
+        ];
+
+        let result = rebalance(
+            &valuation,
+            &bands,
+            &classifications(),
+            &pairs(),
+            &graph(),
+            Mode::Full,
+        );
+
+        // Crypto cannot be spread over BTC and ETH without overflowing
+        assert!(result.orders.is_empty());
+        let residual = &result.residuals[0];
+        assert_eq!(residual.sleeve, Sleeve::AssetType(AssetType::Crypto));
+        assert_eq!(residual.object_id, None);
+        assert!(residual.value.is_sign_negative());
+
+        let mode = Mode::Cash {
+            object_id: USDT,
+            value: Decimal::MAX,
+        };
+        let result = rebalance(
+            &valuation,
+            &bands,
+            &classifications(),
+            &pairs(),
+            &graph(),
+            mode,
+        );
+        assert!(result.orders.is_empty());
+    }
+}
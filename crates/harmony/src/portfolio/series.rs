@@ -0,0 +1,405 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::model::finance::price::Price;
+use crate::model::finance::snapshot::Snapshot;
+
+use super::balance::{self, Leg, Movement, Source};
+use super::valuation::{valuate, PriceGraph};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interval {
+    Hour,
+    Day,
+}
+
+impl Interval {
+    pub fn step(&self) -> TimeDelta {
+        match self {
+            Self::Hour => TimeDelta::hours(1),
+            Self::Day => TimeDelta::days(1),
+        }
+    }
+
+    /// Lists the interval boundaries within `[from, to]`.
+    pub fn instants(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let step = self.step();
+        let Ok(mut at) = from.duration_trunc(step) else {
+            return Vec::new();
+        };
+
+        if at < from {
+            at += step;
+        }
+
+        let mut instants = Vec::new();
+        while at <= to {
+            instants.push(at);
+            at += step;
+        }
+
+        instants
+    }
+}
+
+/// The part of a portfolio a series or report covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Every holding of the owner.
+    Person,
+    /// Only the holding of one object.
+    Object(i64),
+    /// Only the base position built up by one trade.
+    Trade(i64),
+}
+
+impl Scope {
+    pub fn contains(&self, movement: &Movement) -> bool {
+        match *self {
+            Self::Person => true,
+            Self::Object(object_id) => movement.object_id == object_id,
+            Self::Trade(trade_id) => matches!(
+                movement.source,
+                Source::Transaction { trade_id: id, leg: Leg::Base, .. } if id == trade_id
+            ),
+        }
+    }
+
+    /// Whether a movement crosses the boundary of the scope, i.e. is money
+    /// put in or taken out rather than a change within it. Transfers only
+    /// move holdings between accounts and are never flows, fees, income and
+    /// expenses are gains or costs of the scope. Deposits and withdrawals
+    /// cross every boundary, including the owner's. Splits keep holdings in
+    /// the same object, while a successor object takes over from its
+    /// predecessor like a trade would.
+    pub fn is_flow(&self, movement: &Movement) -> bool {
+        match movement.source {
+            Source::Transfer { .. } | Source::Transaction { leg: Leg::Fee, .. } => return false,
+            Source::Flow { kind, .. } => return kind.is_external() && self.contains(movement),
+            Source::Action { kind, .. } if !kind.is_replacement() => return false,
+            Source::Transaction { .. } | Source::Action { .. } => {}
+        }
+
+        match self {
+            Self::Person => false,
+            Self::Object(_) | Self::Trade(_) => self.contains(movement),
+        }
+    }
+}
+
+/// The net worth at one instant. `unpriced` counts the holdings that could
+/// not be converted into the reporting object and are missing from `total`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    pub at: DateTime<Utc>,
+    pub total: Decimal,
+    pub unpriced: usize,
+}
+
+/// Replays movements and prices in time order and values the portfolio at
+/// each of the ascending `instants`, counting everything up to and including
+/// the instant. Both `movements` and `prices` must be in time order.
+pub fn replay(
+    movements: &[Movement],
+    prices: &[Price],
+    object_id: i64,
+    instants: &[DateTime<Utc>],
+) -> Vec<Point> {
+    let mut balances: BTreeMap<i64, Decimal> = BTreeMap::new();
+    let mut latest: BTreeMap<(i64, i64), Decimal> = BTreeMap::new();
+    let mut movements = movements.iter().peekable();
+    let mut prices = prices.iter().peekable();
+
+    let mut points = Vec::with_capacity(instants.len());
+
+    for &at in instants {
+        while let Some(movement) = movements.next_if(|movement| movement.occurrence_at <= at) {
+            *balances.entry(movement.object_id).or_default() += movement.quantity;
+        }
+
+        while let Some(price) = prices.next_if(|price| price.occurrence_at <= at) {
+            latest.insert(
+                (price.base_object_id, price.quote_object_id),
+                price.price.value(),
+            );
+        }
+
+        let mut graph = PriceGraph::new();
+        for (&(base_object_id, quote_object_id), &price) in &latest {
+            graph.insert(base_object_id, quote_object_id, price);
+        }
+
+        let held = balances
+            .iter()
+            .filter(|(_, quantity)| !quantity.is_zero())
+            .map(|(&object_id, &quantity)| (object_id, quantity))
+            .collect();
+        let valuation = valuate(&held, &graph, object_id);
+
+        points.push(Point {
+            at,
+            total: valuation.total,
+            unpriced: valuation.unpriced.len(),
+        });
+    }
+
+    points
+}
+
+/// Values the `scope` of the owner's portfolio in `object_id` at each of the
+/// ascending `instants`. With `cache` set, stored snapshots of the whole
+/// portfolio are reused and the points not in the future are stored for the
+/// next query.
+pub fn net_worth(
+    conn: &Connection,
+    owner: i64,
+    object_id: i64,
+    scope: Scope,
+    instants: &[DateTime<Utc>],
+    cache: bool,
+) -> Result<Vec<Point>, Box<dyn Error>> {
+    let cache = cache && scope == Scope::Person;
+
+    let (Some(&first), Some(&last)) = (instants.first(), instants.last()) else {
+        return Ok(Vec::new());
+    };
+
+    let mut cached: HashMap<DateTime<Utc>, Point> = HashMap::new();
+    if cache {
+        for snapshot in
+            Snapshot::select_by_owner_object_id_between(conn, owner, object_id, first, last)?
+        {
+            cached.insert(
+                snapshot.at,
+                Point {
+                    at: snapshot.at,
+                    total: snapshot.total,
+                    unpriced: snapshot.unpriced,
+                },
+            );
+        }
+    }
+
+    let missing: Vec<DateTime<Utc>> = instants
+        .iter()
+        .filter(|at| !cached.contains_key(at))
+        .copied()
+        .collect();
+
+    if let Some(&until) = missing.last() {
+        let mut movements = balance::load(conn, owner, until, true)?;
+        movements.retain(|movement| scope.contains(movement));
+        let prices = Price::select_by_owner_until(conn, owner, until)?;
+        let now = Utc::now();
+
+        for point in replay(&movements, &prices, object_id, &missing) {
+            if cache && point.at <= now {
+                Snapshot::upsert(
+                    conn,
+                    owner,
+                    object_id,
+                    point.at,
+                    point.total,
+                    point.unpriced,
+                )?;
+            }
+
+            cached.insert(point.at, point);
+        }
+    }
+
+    Ok(instants.iter().filter_map(|at| cached.remove(at)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use r2d2::PooledConnection;
+    use r2d2_sqlite::SqliteConnectionManager;
+    use rust_decimal::Decimal;
+
+    use crate::model::finance::account::Account;
+    use crate::model::finance::object::{Classification, Object};
+    use crate::model::finance::price::Price;
+    use crate::model::finance::snapshot::Snapshot;
+    use crate::model::finance::trade::transaction::{Status, Transaction};
+    use crate::model::finance::trade::Trade;
+    use crate::model::person::Person;
+
+    use super::{net_worth, Interval, Scope};
+
+    fn setup() -> (PooledConnection<SqliteConnectionManager>, i64, i64) {
+        let database = SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(database).unwrap();
+        let conn = pool.get().unwrap();
+
+        conn.execute_batch(&crate::model::initialize()).unwrap();
+
+        let person = Person::insert_one(
+            &conn,
+            &"test_user".to_string(),
+            &"test_password".to_string(),
+        )
+        .unwrap();
+        let owner = person.id();
+        let btc = Object::insert(
+            &conn,
+            owner,
+            "BTC".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let usdt = Object::insert(
+            &conn,
+            owner,
+            "USDT".to_string(),
+            None,
+            None,
+            Classification::default(),
+        )
+        .unwrap();
+        let trade = Trade::insert(&conn, owner, btc, usdt, None, None).unwrap();
+        let account = Account::insert(&conn, owner, "Exchange".to_string(), None, None).unwrap();
+
+        // Buy 1 BTC for 100 USDT on the 1st, priced 150 on the 2nd and 120 on the 3rd
+        Transaction::insert(
+            &conn,
+            trade,
+            account,
+            Decimal::new(1, 0).into(),
+            Some(Decimal::new(100, 0).into()),
+            None,
+            None,
+            false,
+            Status::Settled,
+            None,
+            None,
+            None,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()),
+        )
+        .unwrap();
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(150, 0).into(),
+            None,
+            Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+        Price::upsert(
+            &conn,
+            owner,
+            btc,
+            usdt,
+            Decimal::new(120, 0).into(),
+            None,
+            Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap(),
+        )
+        .unwrap();
+
+        (conn, owner, usdt)
+    }
+
+    #[test]
+    fn test_instants() {
+        let from = Utc.with_ymd_and_hms(2024, 1, 1, 10, 30, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap();
+
+        let days = Interval::Day.instants(from, to);
+        assert_eq!(
+            days,
+            vec![
+                Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap(),
+            ]
+        );
+
+        let hours = Interval::Hour.instants(from, from + chrono::TimeDelta::hours(2));
+        assert_eq!(hours.len(), 2);
+        assert_eq!(
+            hours[0],
+            Utc.with_ymd_and_hms(2024, 1, 1, 11, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_net_worth() {
+        let (conn, owner, usdt) = setup();
+        let instants = Interval::Day.instants(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap(),
+        );
+
+        let points = net_worth(&conn, owner, usdt, Scope::Person, &instants, false).unwrap();
+        let totals: Vec<Decimal> = points.iter().map(|point| point.total).collect();
+
+        // Nothing held yet, then 1 BTC worth 150 and 120 less the 100 USDT spent
+        assert_eq!(
+            totals,
+            vec![Decimal::ZERO, Decimal::new(50, 0), Decimal::new(20, 0)]
+        );
+        assert!(points.iter().all(|point| point.unpriced == 0));
+    }
+
+    #[test]
+    fn test_net_worth_reports_unpriced_before_first_price() {
+        let (conn, owner, usdt) = setup();
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 18, 0, 0).unwrap();
+
+        let points = net_worth(&conn, owner, usdt, Scope::Person, &[at], false).unwrap();
+
+        assert_eq!(points[0].total, Decimal::new(-100, 0));
+        assert_eq!(points[0].unpriced, 1);
+    }
+
+    #[test]
+    fn test_net_worth_of_object_scope() {
+        let (conn, owner, usdt) = setup();
+        let instants = Interval::Day.instants(
+            Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap(),
+        );
+
+        // Only the BTC holding, without the USDT spent on it
+        let points = net_worth(&conn, owner, usdt, Scope::Object(1), &instants, true).unwrap();
+        let totals: Vec<Decimal> = points.iter().map(|point| point.total).collect();
+        assert_eq!(totals, vec![Decimal::new(150, 0), Decimal::new(120, 0)]);
+
+        // Scoped series are never cached
+        let stored = Snapshot::select_by_owner_object_id_between(
+            &conn,
+            owner,
+            usdt,
+            instants[0],
+            instants[1],
+        )
+        .unwrap();
+        assert!(stored.is_empty());
+    }
+
+    #[test]
+    fn test_net_worth_caches_snapshots() {
+        let (conn, owner, usdt) = setup();
+        let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap();
+        let instants = Interval::Day.instants(from, to);
+
+        let first = net_worth(&conn, owner, usdt, Scope::Person, &instants, true).unwrap();
+        let stored =
+            Snapshot::select_by_owner_object_id_between(&conn, owner, usdt, from, to).unwrap();
+        assert_eq!(stored.len(), 3);
+
+        let second = net_worth(&conn, owner, usdt, Scope::Person, &instants, true).unwrap();
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::basis::Disposal;
+
+/// When a fiscal year begins, as a month and day that hold in every year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct FiscalYear {
+    month: u32,
+    day: u32,
+}
+
+impl FiscalYear {
+    /// The instants the fiscal year starting in `year` begins at and ends
+    /// before, in UTC.
+    pub fn bounds(&self, year: i32) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let start = NaiveDate::from_ymd_opt(year, self.month, self.day)?;
+        let end = NaiveDate::from_ymd_opt(year.checked_add(1)?, self.month, self.day)?;
+
+        Some((
+            Utc.from_utc_datetime(&start.and_hms_opt(0, 0, 0)?),
+            Utc.from_utc_datetime(&end.and_hms_opt(0, 0, 0)?),
+        ))
+    }
+}
+
+impl Default for FiscalYear {
+    fn default() -> Self {
+        Self { month: 1, day: 1 }
+    }
+}
+
+impl FromStr for FiscalYear {
+    type Err = String;
+
+    /// Parses `MM-DD`, refusing 29 February as it is missing most years.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("{} is not a month and day as MM-DD", s);
+
+        let (month, day) = s.split_once('-').ok_or_else(invalid)?;
+        let month = month.parse().map_err(|_| invalid())?;
+        let day = day.parse().map_err(|_| invalid())?;
+
+        NaiveDate::from_ymd_opt(2001, month, day).ok_or_else(invalid)?;
+
+        Ok(Self { month, day })
+    }
+}
+
+impl TryFrom<String> for FiscalYear {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<FiscalYear> for String {
+    fn from(value: FiscalYear) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for FiscalYear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}-{:02}", self.month, self.day)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Term {
+    Short,
+    Long,
+}
+
+impl Term {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Short => "short",
+            Self::Long => "long",
+        }
+    }
+}
+
+/// A closed lot, or part of one, disposed of within the fiscal year.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub object_id: i64,
+    pub quantity: Decimal,
+    pub acquired_at: Option<DateTime<Utc>>,
+    pub disposed_at: DateTime<Utc>,
+    pub proceeds: Option<Decimal>,
+    pub cost: Option<Decimal>,
+    pub gain: Option<Decimal>,
+    /// Whole days between acquiring and disposing.
+    pub holding_days: Option<i64>,
+    /// `None` when it is unknown when the lot was acquired.
+    pub term: Option<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// In the order disposed of.
+    pub rows: Vec<Row>,
+    pub proceeds: Decimal,
+    pub cost: Decimal,
+    pub short_term_gain: Decimal,
+    pub long_term_gain: Decimal,
+    /// Rows whose gain is unknown and missing from the sums.
+    pub unpriced: usize,
+}
+
+/// Collects the disposals from `start` up to `end`. A lot held for more than
+/// `long_term_days` whole days is long-term. Sums cover the rows with a known
+/// gain and term only.
+pub fn report(
+    disposals: &[Disposal],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    long_term_days: i64,
+) -> Report {
+    let rows: Vec<Row> = disposals
+        .iter()
+        .filter(|disposal| start <= disposal.disposed_at && disposal.disposed_at < end)
+        .map(|disposal| {
+            let holding_days = disposal
+                .acquired_at
+                .map(|acquired_at| (disposal.disposed_at - acquired_at).num_days());
+
+            Row {
+                object_id: disposal.object_id,
+                quantity: disposal.quantity,
+                acquired_at: disposal.acquired_at,
+                disposed_at: disposal.disposed_at,
+                proceeds: disposal.proceeds,
+                cost: disposal.cost,
+                gain: disposal.gain(),
+                holding_days,
+                term: holding_days.map(|days| {
+                    if days > long_term_days {
+                        Term::Long
+                    } else {
+                        Term::Short
+                    }
+                }),
+            }
+        })
+        .collect();
+
+    let mut report = Report {
+        start,
+        end,
+        rows: Vec::new(),
+        proceeds: Decimal::ZERO,
+        cost: Decimal::ZERO,
+        short_term_gain: Decimal::ZERO,
+        long_term_gain: Decimal::ZERO,
+        unpriced: 0,
+    };
+
+    for row in &rows {
+        match (row.proceeds, row.cost, row.gain, row.term) {
+            (Some(proceeds), Some(cost), Some(gain), Some(term)) => {
+                report.proceeds += proceeds;
+                report.cost += cost;
+                match term {
+                    Term::Short => report.short_term_gain += gain,
+                    Term::Long => report.long_term_gain += gain,
+                }
+            }
+            _ => report.unpriced += 1,
+        }
+    }
+    report.rows = rows;
+
+    report
+}
+
+/// Writes the rows of a report as CSV with a header, naming objects by
+/// `symbols`. Unknown values are left empty.
+pub fn to_csv(report: &Report, symbols: &HashMap<i64, String>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record([
+        "symbol",
+        "quantity",
+        "acquired_at",
+        "disposed_at",
+        "proceeds",
+        "cost",
+        "gain",
+        "holding_days",
+        "term",
+    ])?;
+
+    let text = |value: Option<String>| value.unwrap_or_default();
+
+    for row in &report.rows {
+        writer.write_record([
+            symbols.get(&row.object_id).cloned().unwrap_or_default(),
+            row.quantity.to_string(),
+            text(row.acquired_at.map(|at| at.to_rfc3339())),
+            row.disposed_at.to_rfc3339(),
+            text(row.proceeds.map(|value| value.to_string())),
+            text(row.cost.map(|value| value.to_string())),
+            text(row.gain.map(|value| value.to_string())),
+            text(row.holding_days.map(|days| days.to_string())),
+            text(row.term.map(|term| term.as_str().to_string())),
+        ])?;
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use crate::portfolio::basis::Disposal;
+
+    use super::{report, to_csv, FiscalYear, Term};
+
+    const BTC: i64 = 1;
+
+    fn disposal(
+        acquired: Option<(i32, u32, u32)>,
+        disposed: (i32, u32, u32),
+        gain: i64,
+    ) -> Disposal {
+        let at = |(year, month, day)| Utc.with_ymd_and_hms(year, month, day, 12, 0, 0).unwrap();
+
+        Disposal {
+            object_id: BTC,
+            acquired_at: acquired.map(at),
+            disposed_at: at(disposed),
+            quantity: Decimal::ONE,
+            cost: acquired.map(|_| Decimal::from(100)),
+            proceeds: Some(Decimal::from(100 + gain)),
+        }
+    }
+
+    #[test]
+    fn test_fiscal_year_bounds() {
+        let fiscal_year: FiscalYear = "04-06".parse().unwrap();
+        let (start, end) = fiscal_year.bounds(2023).unwrap();
+
+        assert_eq!(start, Utc.with_ymd_and_hms(2023, 4, 6, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2024, 4, 6, 0, 0, 0).unwrap());
+        assert_eq!(fiscal_year.to_string(), "04-06");
+
+        for source in ["02-29", "13-01", "0406", "04-31"] {
+            assert!(source.parse::<FiscalYear>().is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_report_splits_terms_within_the_year() {
+        let disposals = [
+            // Disposed of before the year
+            disposal(Some((2022, 1, 1)), (2023, 4, 5), 10),
+            disposal(Some((2023, 1, 1)), (2023, 6, 1), 20),
+            disposal(Some((2022, 1, 1)), (2023, 6, 1), 30),
+            // Exactly a year is still short-term
+            disposal(Some((2023, 1, 1)), (2024, 1, 1), -5),
+            disposal(None, (2024, 2, 1), 40),
+        ];
+        let (start, end) = "04-06".parse::<FiscalYear>().unwrap().bounds(2023).unwrap();
+
+        let report = report(&disposals, start, end, 365);
+
+        assert_eq!(report.rows.len(), 4);
+        assert_eq!(report.rows[0].term, Some(Term::Short));
+        assert_eq!(report.rows[1].term, Some(Term::Long));
+        assert_eq!(report.rows[2].holding_days, Some(365));
+        assert_eq!(report.rows[2].term, Some(Term::Short));
+        assert_eq!(report.rows[3].term, None);
+        assert_eq!(report.short_term_gain, Decimal::from(15));
+        assert_eq!(report.long_term_gain, Decimal::from(30));
+        assert_eq!(report.proceeds, Decimal::from(345));
+        assert_eq!(report.unpriced, 1);
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let disposals = [disposal(None, (2024, 2, 1), 40)];
+        let (start, end) = FiscalYear::default().bounds(2024).unwrap();
+        let report = report(&disposals, start, end, 365);
+        let symbols = HashMap::from([(BTC, "BTC".to_string())]);
+
+        let csv = String::from_utf8(to_csv(&report, &symbols).unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "symbol,quantity,acquired_at,disposed_at,proceeds,cost,gain,holding_days,term"
+        );
+        assert_eq!(lines[1], "BTC,1,,2024-02-01T12:00:00+00:00,140,,,,");
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::iter::Peekable;
+use std::slice::Iter;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::model::finance::price::Price;
+
+/// Exchange rates between objects, searchable for multi-hop conversions.
+///
+/// Every stored price `base → quote` is usable in both directions. An
+/// explicitly stored price always wins over the inverse of the opposite one.
+#[derive(Debug, Default)]
+pub struct PriceGraph {
+    edges: BTreeMap<i64, BTreeMap<i64, (Decimal, bool)>>,
+}
+
+/// How to convert one object into another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    /// Units of the target object per unit of the source object.
+    pub rate: Decimal,
+    /// Objects visited from source to target, both included.
+    pub path: Vec<i64>,
+}
+
+impl PriceGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_prices(prices: &[Price]) -> Self {
+        let mut graph = Self::new();
+
+        for price in prices {
+            graph.insert(
+                price.base_object_id,
+                price.quote_object_id,
+                price.price.value(),
+            );
+        }
+
+        graph
+    }
+
+    pub fn insert(&mut self, base_object_id: i64, quote_object_id: i64, price: Decimal) {
+        if base_object_id == quote_object_id || price <= Decimal::ZERO {
+            return;
+        }
+
+        self.edges
+            .entry(base_object_id)
+            .or_default()
+            .insert(quote_object_id, (price, true));
+
+        if let Some(inverse) = Decimal::ONE.checked_div(price) {
+            let edge = self
+                .edges
+                .entry(quote_object_id)
+                .or_default()
+                .entry(base_object_id)
+                .or_insert((inverse, false));
+
+            if !edge.1 {
+                *edge = (inverse, false);
+            }
+        }
+    }
+
+    /// Finds the conversion with the fewest hops. Ties are broken towards
+    /// lower object ids so the result is deterministic.
+    pub fn conversion(&self, from: i64, to: i64) -> Option<Conversion> {
+        if from == to {
+            return Some(Conversion {
+                rate: Decimal::ONE,
+                path: vec![from],
+            });
+        }
+
+        let mut previous: BTreeMap<i64, (i64, Decimal)> = BTreeMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(current) = queue.pop_front() {
+            let Some(neighbours) = self.edges.get(&current) else {
+                continue;
+            };
+
+            for (&next, &(rate, _)) in neighbours {
+                if next == from || previous.contains_key(&next) {
+                    continue;
+                }
+
+                previous.insert(next, (current, rate));
+
+                if next == to {
+                    return Self::unwind(from, to, &previous);
+                }
+
+                queue.push_back(next);
+            }
+        }
+
+        None
+    }
+
+    fn unwind(from: i64, to: i64, previous: &BTreeMap<i64, (i64, Decimal)>) -> Option<Conversion> {
+        let mut path = vec![to];
+        let mut rate = Decimal::ONE;
+        let mut current = to;
+
+        while current != from {
+            let (parent, step) = previous.get(&current)?;
+            rate = rate.checked_mul(*step)?;
+            path.push(*parent);
+            current = *parent;
+        }
+
+        path.reverse();
+
+        Some(Conversion { rate, path })
+    }
+}
+
+/// Walks prices in time order, so that each instant sees the latest price of
+/// every pair known at that time.
+pub struct Timeline<'a> {
+    prices: Peekable<Iter<'a, Price>>,
+    latest: BTreeMap<(i64, i64), Decimal>,
+    graph: PriceGraph,
+}
+
+impl<'a> Timeline<'a> {
+    /// `prices` must be in time order.
+    pub fn new(prices: &'a [Price]) -> Self {
+        Self {
+            prices: prices.iter().peekable(),
+            latest: BTreeMap::new(),
+            graph: PriceGraph::new(),
+        }
+    }
+
+    /// Advances to `at`, which must not be before the previous instant, and
+    /// returns the prices known by then.
+    pub fn graph_at(&mut self, at: DateTime<Utc>) -> &PriceGraph {
+        let mut changed = false;
+
+        while let Some(price) = self.prices.next_if(|price| price.occurrence_at <= at) {
+            self.latest.insert(
+                (price.base_object_id, price.quote_object_id),
+                price.price.value(),
+            );
+            changed = true;
+        }
+
+        if changed {
+            self.graph = PriceGraph::new();
+            for (&(base_object_id, quote_object_id), &price) in &self.latest {
+                self.graph.insert(base_object_id, quote_object_id, price);
+            }
+        }
+
+        &self.graph
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holding {
+    pub object_id: i64,
+    pub quantity: Decimal,
+    /// Units of the reporting object per unit of this object.
+    pub price: Decimal,
+    pub value: Decimal,
+    /// Share of the total net worth, `None` when the total is zero.
+    pub weight: Option<Decimal>,
+    pub path: Vec<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unpriced {
+    pub object_id: i64,
+    pub quantity: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Valuation {
+    pub object_id: i64,
+    pub holdings: Vec<Holding>,
+    pub unpriced: Vec<Unpriced>,
+    pub total: Decimal,
+}
+
+/// Values every balance in the reporting object `object_id`. Balances that
+/// cannot be converted are reported in `unpriced` rather than dropped.
+pub fn valuate(balances: &BTreeMap<i64, Decimal>, graph: &PriceGraph, object_id: i64) -> Valuation {
+    let mut holdings = Vec::new();
+    let mut unpriced = Vec::new();
+    let mut total = Decimal::ZERO;
+
+    for (&holding_object_id, &quantity) in balances {
+        let priced = graph
+            .conversion(holding_object_id, object_id)
+            .and_then(|conversion| {
+                let value = quantity.checked_mul(conversion.rate)?;
+                Some((conversion, value))
+            });
+
+        match priced {
+            Some((conversion, value)) => {
+                total += value;
+                holdings.push(Holding {
+                    object_id: holding_object_id,
+                    quantity,
+                    price: conversion.rate,
+                    value,
+                    weight: None,
+                    path: conversion.path,
+                });
+            }
+            None => unpriced.push(Unpriced {
+                object_id: holding_object_id,
+                quantity,
+            }),
+        }
+    }
+
+    if !total.is_zero() {
+        for holding in &mut holdings {
+            holding.weight = holding.value.checked_div(total);
+        }
+    }
+
+    Valuation {
+        object_id,
+        holdings,
+        unpriced,
+        total,
+    }
+}
+
+/// Holdings that share one key, such as an asset type or an exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group<K> {
+    pub key: K,
+    pub value: Decimal,
+    /// Share of the total net worth, `None` when the total is zero.
+    pub weight: Option<Decimal>,
+    pub object_ids: Vec<i64>,
+}
+
+/// Sums the holdings of a valuation up by `key`, ordered by key. Unpriced
+/// balances carry no value and are left out.
+pub fn group<K: Ord>(valuation: &Valuation, key: impl Fn(i64) -> K) -> Vec<Group<K>> {
+    let mut groups: BTreeMap<K, (Decimal, Vec<i64>)> = BTreeMap::new();
+
+    for holding in &valuation.holdings {
+        let (value, object_ids) = groups.entry(key(holding.object_id)).or_default();
+        *value += holding.value;
+        object_ids.push(holding.object_id);
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, (value, object_ids))| Group {
+            key,
+            value,
+            weight: if valuation.total.is_zero() {
+                None
+            } else {
+                value.checked_div(valuation.total)
+            },
+            object_ids,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rust_decimal::Decimal;
+
+    use super::{group, valuate, PriceGraph};
+
+    const BTC: i64 = 1;
+    const USDT: i64 = 2;
+    const USD: i64 = 3;
+    const EUR: i64 = 4;
+    const ART: i64 = 5;
+
+    fn graph() -> PriceGraph {
+        let mut graph = PriceGraph::new();
+        graph.insert(BTC, USDT, Decimal::new(40000, 0));
+        graph.insert(USDT, USD, Decimal::new(1, 0));
+        graph.insert(EUR, USD, Decimal::new(125, 2));
+        graph
+    }
+
+    #[test]
+    fn test_conversion_direct_and_inverse() {
+        let graph = graph();
+
+        let conversion = graph.conversion(BTC, USDT).unwrap();
+        assert_eq!(conversion.rate, Decimal::new(40000, 0));
+        assert_eq!(conversion.path, vec![BTC, USDT]);
+
+        let conversion = graph.conversion(USD, EUR).unwrap();
+        assert_eq!(conversion.rate, Decimal::new(8, 1));
+        assert_eq!(conversion.path, vec![USD, EUR]);
+    }
+
+    #[test]
+    fn test_conversion_triangulates() {
+        let graph = graph();
+
+        let conversion = graph.conversion(BTC, EUR).unwrap();
+        assert_eq!(conversion.rate, Decimal::new(32000, 0));
+        assert_eq!(conversion.path, vec![BTC, USDT, USD, EUR]);
+
+        assert!(graph.conversion(ART, USD).is_none());
+    }
+
+    #[test]
+    fn test_explicit_price_wins_over_inverse() {
+        let mut graph = PriceGraph::new();
+        graph.insert(USDT, USD, Decimal::new(1, 0));
+        graph.insert(USD, USDT, Decimal::new(99, 2));
+        graph.insert(USDT, USD, Decimal::new(1, 0));
+
+        assert_eq!(
+            graph.conversion(USD, USDT).unwrap().rate,
+            Decimal::new(99, 2)
+        );
+    }
+
+    #[test]
+    fn test_valuate() {
+        let graph = graph();
+        let balances = BTreeMap::from([
+            (BTC, Decimal::new(5, 1)),
+            (USDT, Decimal::new(10000, 0)),
+            (ART, Decimal::new(3, 0)),
+        ]);
+
+        let valuation = valuate(&balances, &graph, USD);
+
+        assert_eq!(valuation.total, Decimal::new(30000, 0));
+        assert_eq!(valuation.holdings.len(), 2);
+
+        let btc = &valuation.holdings[0];
+        assert_eq!(btc.object_id, BTC);
+        assert_eq!(btc.value, Decimal::new(20000, 0));
+        assert_eq!(btc.path, vec![BTC, USDT, USD]);
+        assert_eq!(btc.weight.unwrap().round_dp(4), Decimal::new(6667, 4));
+
+        let usdt = &valuation.holdings[1];
+        assert_eq!(usdt.value, Decimal::new(10000, 0));
+
+        // Holdings without a price path are reported, not dropped
+        assert_eq!(valuation.unpriced.len(), 1);
+        assert_eq!(valuation.unpriced[0].object_id, ART);
+        assert_eq!(valuation.unpriced[0].quantity, Decimal::new(3, 0));
+    }
+
+    #[test]
+    fn test_valuate_in_held_object() {
+        let graph = graph();
+        let balances = BTreeMap::from([(BTC, Decimal::new(2, 0))]);
+
+        let valuation = valuate(&balances, &graph, BTC);
+
+        assert_eq!(valuation.total, Decimal::new(2, 0));
+        assert_eq!(valuation.holdings[0].path, vec![BTC]);
+        assert_eq!(valuation.holdings[0].weight, Some(Decimal::ONE));
+    }
+
+    #[test]
+    fn test_group() {
+        let graph = graph();
+        let balances = BTreeMap::from([
+            (BTC, Decimal::new(5, 1)),
+            (USDT, Decimal::new(10000, 0)),
+            (EUR, Decimal::new(8000, 0)),
+            (ART, Decimal::new(3, 0)),
+        ]);
+
+        let valuation = valuate(&balances, &graph, USD);
+        let groups = group(&valuation, |object_id| object_id == BTC);
+
+        assert_eq!(groups.len(), 2);
+        assert!(!groups[0].key);
+        assert_eq!(groups[0].value, Decimal::new(20000, 0));
+        assert_eq!(groups[0].object_ids, vec![USDT, EUR]);
+        assert_eq!(groups[0].weight, Some(Decimal::new(5, 1)));
+        assert!(groups[1].key);
+        assert_eq!(groups[1].object_ids, vec![BTC]);
+    }
+}